@@ -198,6 +198,10 @@
         return clone(state.devices);
       }
 
+      if (cmd === "find_device_identity_matches") {
+        return [];
+      }
+
       if (cmd === "get_battery_info") {
         return clone(state.batteryById[args.id] ?? []);
       }
@@ -1,48 +1,314 @@
-use bluest::btuuid::descriptors::CHARACTERISTIC_USER_DESCRIPTION;
-use bluest::{Adapter, Characteristic, Device};
+use crate::ble_batch::{self, BatteryReadResult, ReadResult, SharedReads};
+use crate::ble_coalesce::{CoalesceOptions, CoalescingSink};
+use crate::ble_device_info::{self, DeviceMetadata};
+use crate::ble_identity::{self, DeviceFingerprint, IdentityMatch, RegisteredIdentity};
+use crate::ble_inspector::{self, GattInspection};
+use crate::ble_power_state::{PowerState, PowerStateFormat};
+use crate::ble_presence::{self, Presence, PresenceHub};
+use crate::ble_presentation::{PartPresentation, PresentationFormat};
+use crate::ble_reading::{Reading, ReadingFilter, ReadingFlag, ReadingPipeline};
+use crate::ble_reconnect::{self, ReconnectBackoff, ReconnectPolicy};
+use crate::ble_scheduler::{self, Priority};
+#[cfg(target_os = "linux")]
+use crate::bluez_battery;
+use crate::ble_watchdog::{DiagnosticKind, Liveness};
+use crate::ble_transport::{
+    self, Advertisement, BleAdapter, BleCharacteristic, BleDevice, BleTransport,
+};
+use crate::error::CommandError;
+use crate::history;
+use crate::power_supply_battery::{self, HidBattery};
+use bluest::btuuid::descriptors::{
+    CHARACTERISTIC_PRESENTATION_FORMAT, CHARACTERISTIC_USER_DESCRIPTION,
+};
 use futures_util::StreamExt;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, LazyLock};
 use tauri::{AppHandle, Emitter};
 use tokio::sync::{watch, Mutex};
 use tokio::task::JoinHandle;
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, sleep_until, Duration, Instant};
 use uuid::Uuid;
 
-const BATTERY_SERVICE_UUID: Uuid = Uuid::from_u128(0x0000180F_0000_1000_8000_00805F9B34FB);
-const BATTERY_LEVEL_UUID: Uuid = Uuid::from_u128(0x00002A19_0000_1000_8000_00805F9B34FB);
+pub(crate) const BATTERY_SERVICE_UUID: Uuid = Uuid::from_u128(0x0000180F_0000_1000_8000_00805F9B34FB);
+pub(crate) const BATTERY_LEVEL_UUID: Uuid = Uuid::from_u128(0x00002A19_0000_1000_8000_00805F9B34FB);
 const BATTERY_INFO_NOTIFICATION_EVENT: &str = "battery-info-notification";
 const BATTERY_MONITOR_STATUS_EVENT: &str = "battery-monitor-status";
+const BATTERY_DEVICE_SCAN_RESULT_EVENT: &str = "battery-device-scan-result";
+const BLUETOOTH_ADAPTER_STATUS_EVENT: &str = "bluetooth-adapter-status";
+const BATTERY_MONITOR_DIAGNOSTIC_EVENT: &str = "battery-monitor-diagnostic";
+/// How often a missing adapter is looked up again.
+const ADAPTER_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// How long a command waits for an adapter that is not on yet.
+const ADAPTER_STARTUP_WAIT: Duration = Duration::from_secs(3);
+const ADAPTER_STARTUP_POLL: Duration = Duration::from_millis(500);
+const DEFAULT_SCAN_SECS: u64 = 10;
+const MAX_SCAN_SECS: u64 = 60;
 
 #[derive(Serialize)]
 pub struct BleDeviceInfo {
     pub name: String,
     pub id: String,
+    /// Cached result of `get_device_metadata`, if it has run for this device.
+    pub metadata: Option<DeviceMetadata>,
 }
 
 #[derive(Serialize, Clone)]
 pub struct BatteryInfo {
     pub battery_level: Option<u8>,
     pub user_description: Option<String>,
+    /// Stable identity of the part within the device; see `battery_part_key`.
+    pub part_key: String,
+    /// From the Presentation Format descriptor, if the characteristic has one;
+    /// labels the part when there is no user description.
+    pub presentation: Option<PartPresentation>,
+    pub power_state: PowerState,
+    /// The level as the device sent it, before filtering; differs from
+    /// `battery_level` when a reading was rejected or smoothed.
+    pub raw_battery_level: Option<u8>,
+    /// Set when the reading was rejected, held back or jumped suspiciously.
+    pub reading_flag: Option<ReadingFlag>,
 }
 
+/// Parts of one device that changed together; see `ble_coalesce.rs`.
 #[derive(Serialize, Clone)]
 pub struct BatteryInfoNotificationEvent {
     pub id: String,
-    pub battery_info: BatteryInfo,
+    pub battery_infos: Vec<BatteryInfo>,
 }
 
 #[derive(Serialize, Clone)]
 pub struct BatteryMonitorStatusEvent {
     pub id: String,
     pub connected: bool,
+    /// Failed reconnection attempts since the device was last connected.
+    pub reconnect_attempt: u32,
+    /// When the connection watcher tries again, in milliseconds since the Unix
+    /// epoch. None while connected, outside the watcher, or after it gave up.
+    pub next_retry_at: Option<u64>,
+}
+
+impl BatteryMonitorStatusEvent {
+    pub fn new(id: String, connected: bool) -> Self {
+        Self {
+            id,
+            connected,
+            reconnect_attempt: 0,
+            next_retry_at: None,
+        }
+    }
+}
+
+/// Something the monitor of a device noticed and acted on by itself, for logs
+/// and bug reports.
+#[derive(Serialize, Clone, Debug)]
+pub struct BatteryMonitorDiagnosticEvent {
+    pub id: String,
+    pub kind: DiagnosticKind,
+    /// How long nothing had been heard from the device.
+    pub silent_secs: u64,
+    pub detail: String,
+}
+
+/// Where battery levels for a device come from, selected per device with
+/// `set_battery_source`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatterySource {
+    /// Battery Service characteristics read directly over GATT.
+    #[default]
+    Gatt,
+    /// `org.bluez.Battery1` over D-Bus (Linux only), for devices whose
+    /// Battery Service is claimed by BlueZ's battery plugin.
+    Bluez,
+    /// The kernel's HID battery under the power_supply class (Linux), which
+    /// also covers keyboards behind a dongle.
+    PowerSupply,
+}
+
+/// Timing, reading filter and event coalescing of the notification monitor,
+/// set per device with `set_monitor_options`. Parts that cannot notify are
+/// read on an interval, and notifying parts are re-read when they have been
+/// silent for a while.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct MonitorOptions {
+    pub poll_interval_secs: u64,
+    /// 0 disables verification reads.
+    pub verify_after_secs: u64,
+    /// Silence after which the watchdog probes the device and restarts the
+    /// session if the probe fails; 0 disables the watchdog.
+    pub stale_after_secs: u64,
+    pub reading_filter: ReadingFilter,
+    pub coalesce: CoalesceOptions,
+}
+
+impl Default for MonitorOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval_secs: 60,
+            verify_after_secs: 15 * 60,
+            stale_after_secs: 20 * 60,
+            reading_filter: ReadingFilter::default(),
+            coalesce: CoalesceOptions::default(),
+        }
+    }
+}
+
+impl MonitorOptions {
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval_secs)
+    }
+
+    fn verify_after(&self) -> Option<Duration> {
+        (self.verify_after_secs > 0).then(|| Duration::from_secs(self.verify_after_secs))
+    }
+
+    fn stale_after(&self) -> Option<Duration> {
+        (self.stale_after_secs > 0).then(|| Duration::from_secs(self.stale_after_secs))
+    }
+}
+
+/// A device seen advertising during an active scan.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ScannedDeviceInfo {
+    pub id: String,
+    pub name: Option<String>,
+    pub rssi: Option<i16>,
+    pub services: Vec<String>,
+    pub has_battery_service: bool,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BluetoothAdapterInfo {
+    pub id: String,
+    pub name: String,
+    /// Used for listing, scans and new monitors.
+    pub selected: bool,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdapterState {
+    Available,
+    PoweredOff,
+    /// The adapter is gone, e.g. an unplugged USB dongle.
+    Removed,
+}
+
+#[derive(Serialize, Clone)]
+pub struct BluetoothAdapterStatusEvent {
+    pub adapter_id: String,
+    pub state: AdapterState,
+}
+
+/// Destination for monitor events. The app emits them to the frontend through
+/// `AppHandle`; tests record them instead.
+pub(crate) trait BatteryEventSink: Send + Sync {
+    fn battery_info(&self, event: BatteryInfoNotificationEvent);
+    fn monitor_status(&self, event: BatteryMonitorStatusEvent);
+    fn adapter_status(&self, event: BluetoothAdapterStatusEvent);
+    fn diagnostic(&self, event: BatteryMonitorDiagnosticEvent);
+}
+
+impl BatteryEventSink for AppHandle {
+    fn battery_info(&self, event: BatteryInfoNotificationEvent) {
+        let _ = self.emit(BATTERY_INFO_NOTIFICATION_EVENT, event);
+    }
+
+    fn monitor_status(&self, event: BatteryMonitorStatusEvent) {
+        let _ = self.emit(BATTERY_MONITOR_STATUS_EVENT, event);
+    }
+
+    fn adapter_status(&self, event: BluetoothAdapterStatusEvent) {
+        let _ = self.emit(BLUETOOTH_ADAPTER_STATUS_EVENT, event);
+    }
+
+    fn diagnostic(&self, event: BatteryMonitorDiagnosticEvent) {
+        let _ = self.emit(BATTERY_MONITOR_DIAGNOSTIC_EVENT, event);
+    }
+}
+
+/// An event received by a `RecordingSink`.
+#[cfg(test)]
+pub(crate) enum SinkEvent {
+    BatteryInfo(BatteryInfoNotificationEvent),
+    MonitorStatus(BatteryMonitorStatusEvent),
+    AdapterStatus(BluetoothAdapterStatusEvent),
+    Diagnostic(BatteryMonitorDiagnosticEvent),
+}
+
+/// Test sink that turns every event into what a test checks with `record`
+/// and sends the results to the test.
+#[cfg(test)]
+pub(crate) struct RecordingSink<T> {
+    tx: tokio::sync::mpsc::UnboundedSender<T>,
+    record: fn(SinkEvent) -> Vec<T>,
+}
+
+#[cfg(test)]
+impl<T> RecordingSink<T> {
+    pub fn new(
+        tx: tokio::sync::mpsc::UnboundedSender<T>,
+        record: fn(SinkEvent) -> Vec<T>,
+    ) -> Self {
+        Self { tx, record }
+    }
+
+    fn send(&self, event: SinkEvent) {
+        for recorded in (self.record)(event) {
+            let _ = self.tx.send(recorded);
+        }
+    }
+}
+
+#[cfg(test)]
+impl<T: Send> BatteryEventSink for RecordingSink<T> {
+    fn battery_info(&self, event: BatteryInfoNotificationEvent) {
+        self.send(SinkEvent::BatteryInfo(event));
+    }
+
+    fn monitor_status(&self, event: BatteryMonitorStatusEvent) {
+        self.send(SinkEvent::MonitorStatus(event));
+    }
+
+    fn adapter_status(&self, event: BluetoothAdapterStatusEvent) {
+        self.send(SinkEvent::AdapterStatus(event));
+    }
+
+    fn diagnostic(&self, event: BatteryMonitorDiagnosticEvent) {
+        self.send(SinkEvent::Diagnostic(event));
+    }
+}
+
+#[derive(Clone)]
+struct PowerStateCharacteristic {
+    characteristic: Arc<dyn BleCharacteristic>,
+    format: PowerStateFormat,
 }
 
 #[derive(Clone)]
 struct BatteryCharacteristicContext {
-    characteristic: Characteristic,
+    characteristic: Arc<dyn BleCharacteristic>,
     user_description: Option<String>,
+    part_key: String,
+    presentation_format: Option<PresentationFormat>,
+    power_state: Option<PowerStateCharacteristic>,
+}
+
+impl BatteryCharacteristicContext {
+    fn battery_info(&self, reading: Reading, power_state: PowerState) -> BatteryInfo {
+        BatteryInfo {
+            battery_level: reading.level,
+            user_description: self.user_description.clone(),
+            part_key: self.part_key.clone(),
+            presentation: self.presentation_format.map(PartPresentation::from),
+            power_state,
+            raw_battery_level: reading.raw,
+            reading_flag: reading.flag,
+        }
+    }
 }
 
 #[derive(Default)]
@@ -71,25 +337,101 @@ impl MonitorConnectionState {
 }
 
 struct BatteryNotificationWorkerArgs {
-    app: AppHandle,
-    adapter: Adapter,
-    target_device: Device,
+    events: Arc<dyn BatteryEventSink>,
+    target_device: Arc<dyn BleDevice>,
     device_id: String,
     worker_id: usize,
     monitor_connection_state: Arc<Mutex<MonitorConnectionState>>,
     context: BatteryCharacteristicContext,
+    initial_info: BatteryInfo,
+    readings: Arc<ReadingPipeline>,
+    liveness: Arc<Liveness>,
+    verify_after: Option<Duration>,
+    stop_rx: watch::Receiver<bool>,
+}
+
+/// Reads every part that cannot notify on one interval.
+struct BatteryPollWorkerArgs {
+    events: Arc<dyn BatteryEventSink>,
+    target_device: Arc<dyn BleDevice>,
+    device_id: String,
+    worker_id: usize,
+    monitor_connection_state: Arc<Mutex<MonitorConnectionState>>,
+    parts: Vec<(BatteryCharacteristicContext, BatteryInfo)>,
+    readings: Arc<ReadingPipeline>,
+    liveness: Arc<Liveness>,
+    interval: Duration,
     stop_rx: watch::Receiver<bool>,
 }
 
 struct MonitorTask {
     stop_tx: watch::Sender<bool>,
     join_handles: Vec<JoinHandle<()>>,
+    /// Adapter a GATT monitor is bound to; None for the BlueZ and
+    /// power_supply sources, which do not go through an adapter.
+    adapter_id: Option<String>,
 }
 
 static MONITORS: LazyLock<Mutex<HashMap<String, MonitorTask>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
 
-fn bytes_to_hex(data: &[u8]) -> String {
+struct AdapterWatcher {
+    state_rx: watch::Receiver<AdapterState>,
+    stop_tx: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+/// One state watcher per adapter in use, shared by every monitor bound to it.
+static ADAPTER_WATCHERS: LazyLock<Mutex<HashMap<String, AdapterWatcher>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct PresenceWatcher {
+    hub: Arc<PresenceHub>,
+    stop_tx: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+/// One connected-device poller per adapter in use, shared by every monitor
+/// bound to it.
+static PRESENCE_WATCHERS: LazyLock<Mutex<HashMap<String, PresenceWatcher>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Serializes one-shot reads per device. The backend poller and a manual
+/// refresh can ask for the same device at once, and overlapping connects and
+/// reads on one device interfere with each other.
+static READ_LOCKS: LazyLock<Mutex<HashMap<String, Arc<Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// One-shot battery reads in progress by device id, joined by later requests
+/// for the same device.
+static BATTERY_READS: LazyLock<SharedReads<ReadResult>> = LazyLock::new(SharedReads::new);
+
+/// Reading filter state by device id, shared by the monitor and one-shot
+/// reads so a polled level is compared with the notified ones before it.
+static READING_PIPELINES: LazyLock<Mutex<HashMap<String, Arc<ReadingPipeline>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Non-default monitor options by device id.
+static MONITOR_OPTIONS: LazyLock<Mutex<HashMap<String, MonitorOptions>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Non-default reconnection policies by device id.
+static RECONNECT_POLICIES: LazyLock<Mutex<HashMap<String, ReconnectPolicy>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Non-default battery sources by device id.
+static BATTERY_SOURCES: LazyLock<Mutex<HashMap<String, BatterySource>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Adapter chosen for listing, scans and new monitors; None uses the
+/// transport's default adapter.
+static SELECTED_ADAPTER: LazyLock<Mutex<Option<String>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Stop signal of the scan in progress, if any. Only one scan runs at a time.
+static ACTIVE_SCAN: LazyLock<Mutex<Option<watch::Sender<bool>>>> =
+    LazyLock::new(|| Mutex::new(None));
+
+pub(crate) fn bytes_to_hex(data: &[u8]) -> String {
     data.iter()
         .map(|b| format!("{b:02X}"))
         .collect::<Vec<_>>()
@@ -103,34 +445,97 @@ fn sanitize_device_text(s: &str) -> String {
     s.trim_start_matches(['=', '+', '-', '@', '\t', '\r']).to_string()
 }
 
-async fn get_adapter() -> Result<Adapter, String> {
-    log::debug!("BLE I/O: requesting default adapter");
-    let adapter = Adapter::default()
+/// Open adapter `id`, or the transport's default adapter when `id` is None.
+async fn open_adapter(
+    transport: &dyn BleTransport,
+    id: Option<&str>,
+) -> Result<Arc<dyn BleAdapter>, CommandError> {
+    log::debug!("BLE I/O: requesting adapter id={id:?}");
+    let adapter = match id {
+        Some(id) => transport
+            .adapters()
+            .await
+            .into_iter()
+            .find(|adapter| adapter.id() == id),
+        None => transport.default_adapter().await,
+    }
+    .ok_or(CommandError::AdapterNotFound)?;
+    // Fail fast for an adapter its watcher knows to be off. With no watcher
+    // on it yet, ask the adapter itself.
+    let watched_state = ADAPTER_WATCHERS
+        .lock()
         .await
-        .ok_or("Bluetooth adapter not found")
-        .map_err(|e| e.to_string())?;
-    adapter.wait_available().await.map_err(|e| e.to_string())?;
-    log::debug!("BLE I/O: adapter is available");
+        .get(&adapter.id())
+        .map(|watcher| *watcher.state_rx.borrow());
+    let available = match watched_state {
+        Some(state) => state == AdapterState::Available,
+        None => wait_for_adapter_startup(adapter.as_ref()).await?,
+    };
+    if !available {
+        return Err(CommandError::AdapterUnavailable(
+            "Bluetooth is turned off".to_string(),
+        ));
+    }
+    log::debug!("BLE I/O: adapter is available id={}", adapter.id());
     Ok(adapter)
 }
 
-fn format_device_id_for_store(device: &Device) -> String {
-    device.id().to_string()
+/// Whether `adapter` is on, giving it `ADAPTER_STARTUP_WAIT` to come up.
+/// Right after launch the OS may still report its state as unknown (e.g.
+/// CoreBluetooth), which looks the same as off.
+async fn wait_for_adapter_startup(adapter: &dyn BleAdapter) -> Result<bool, CommandError> {
+    let deadline = Instant::now() + ADAPTER_STARTUP_WAIT;
+    loop {
+        if adapter.is_available().await? {
+            return Ok(true);
+        }
+        if Instant::now() >= deadline {
+            return Ok(false);
+        }
+        sleep(ADAPTER_STARTUP_POLL).await;
+    }
+}
+
+/// The adapter selected for listing, scans and new monitors.
+async fn get_adapter() -> Result<Arc<dyn BleAdapter>, CommandError> {
+    let selected = SELECTED_ADAPTER.lock().await.clone();
+    open_adapter(ble_transport::transport().as_ref(), selected.as_deref()).await
+}
+
+/// The adapter the monitor of `device_id` is bound to, or the selected one
+/// when it has none, so one-shot reads of a device bonded to a second adapter
+/// go through that adapter.
+async fn get_device_adapter(device_id: &str) -> Result<Arc<dyn BleAdapter>, CommandError> {
+    let bound = MONITORS
+        .lock()
+        .await
+        .get(device_id)
+        .and_then(|monitor| monitor.adapter_id.clone());
+    match bound {
+        Some(adapter_id) => {
+            open_adapter(ble_transport::transport().as_ref(), Some(&adapter_id)).await
+        }
+        None => get_adapter().await,
+    }
+}
+
+fn format_device_id_for_store(device: &dyn BleDevice) -> String {
+    device.id()
 }
 
-fn is_target_device(device: &Device, id: &str) -> bool {
-    device.id().to_string() == id
+fn is_target_device(device: &dyn BleDevice, id: &str) -> bool {
+    device.id() == id
 }
 
 #[inline]
 #[cfg_attr(target_os = "linux", allow(unused_variables))]
-async fn disconnect_device(adapter: &Adapter, device: &Device) {
+async fn disconnect_device(device: &dyn BleDevice) {
     // Do not call disconnect_device() on Linux because it causes OS-level disconnection.
     // See https://docs.rs/bluest/latest/bluest/struct.Adapter.html#method.disconnect_device
     #[cfg(not(target_os = "linux"))]
     {
-        let _ = adapter
-            .disconnect_device(device)
+        let _ = device
+            .disconnect()
             .await
             .map_err(|e| {
                 log::warn!(
@@ -143,53 +548,65 @@ async fn disconnect_device(adapter: &Adapter, device: &Device) {
     }
 }
 
-async fn get_target_device(adapter: &Adapter, id: &str) -> Result<Device, String> {
+async fn get_target_device(
+    adapter: &dyn BleAdapter,
+    id: &str,
+) -> Result<Arc<dyn BleDevice>, CommandError> {
     log::debug!("BLE I/O: searching target device id={id}");
     let devices = adapter
         .connected_devices_with_services(&[BATTERY_SERVICE_UUID, BATTERY_LEVEL_UUID])
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     let target = devices
         .iter()
-        .find(|device| is_target_device(device, id))
+        .find(|device| is_target_device(device.as_ref(), id))
         .cloned()
-        .ok_or_else(|| "Device not found".to_string())?;
+        .ok_or(CommandError::DeviceNotFound)?;
 
     let name = target
         .name()
         .unwrap_or_else(|_| "(unknown)".to_string());
     log::debug!(
         "BLE I/O: target device found id={} name={}",
-        format_device_id_for_store(&target),
+        format_device_id_for_store(target.as_ref()),
         name
     );
 
     Ok(target)
 }
 
+/// Identifies a Battery Level characteristic by its place in the GATT table,
+/// `<battery service instance>:<level within that service>`, so parts without
+/// a user description stay apart and a renamed description keeps its history.
+/// Sources outside GATT report the device's own battery, i.e. `0:0`.
+fn battery_part_key(service_index: usize, level_index: usize) -> String {
+    format!("{service_index}:{level_index}")
+}
+
 async fn get_battery_characteristic_contexts(
-    target_device: &Device,
-) -> Result<Vec<BatteryCharacteristicContext>, String> {
+    target_device: &dyn BleDevice,
+) -> Result<Vec<BatteryCharacteristicContext>, CommandError> {
     let mut contexts = Vec::new();
     log::debug!(
         "BLE I/O: discovering battery services for device id={}",
         format_device_id_for_store(target_device)
     );
-    let services = target_device.services().await.map_err(|e| e.to_string())?;
+    let services = target_device.services().await?;
 
-    for battery_service in services
+    for (service_index, battery_service) in services
         .iter()
         .filter(|service| service.uuid() == BATTERY_SERVICE_UUID)
+        .enumerate()
     {
         let characteristics = battery_service
             .characteristics()
-            .await
-            .map_err(|e| e.to_string())?;
+            .await?;
+        let service_contexts_start = contexts.len();
 
-        for battery_level_characteristic in characteristics
+        for (level_index, battery_level_characteristic) in characteristics
             .iter()
             .filter(|c| c.uuid() == BATTERY_LEVEL_UUID)
+            .enumerate()
         {
             log::debug!(
                 "BLE I/O: found battery level characteristic for device id={}",
@@ -198,8 +615,7 @@ async fn get_battery_characteristic_contexts(
             let mut user_description = None;
             let descriptors = battery_level_characteristic
                 .descriptors()
-                .await
-                .map_err(|e| e.to_string())?;
+                .await?;
 
             if let Some(user_description_descriptor) = descriptors
                 .iter()
@@ -207,8 +623,7 @@ async fn get_battery_characteristic_contexts(
             {
                 let desc_value = user_description_descriptor
                     .read()
-                    .await
-                    .map_err(|e| e.to_string())?;
+                    .await?;
                 log::debug!(
                     "BLE I/O: read user description bytes={} for device id={}",
                     bytes_to_hex(&desc_value),
@@ -222,19 +637,100 @@ async fn get_battery_characteristic_contexts(
                 }
             }
 
+            // Only a naming and ordering hint, so a failed read is not fatal.
+            let mut presentation_format = None;
+            if let Some(presentation_format_descriptor) = descriptors
+                .iter()
+                .find(|d| d.uuid() == CHARACTERISTIC_PRESENTATION_FORMAT)
+            {
+                match presentation_format_descriptor.read().await {
+                    Ok(value) => {
+                        log::debug!(
+                            "BLE I/O: read presentation format bytes={} for device id={}",
+                            bytes_to_hex(&value),
+                            format_device_id_for_store(target_device)
+                        );
+                        presentation_format = PresentationFormat::parse(&value);
+                    }
+                    Err(e) => {
+                        log::debug!("BLE I/O: presentation format read failed error={e}");
+                    }
+                }
+            }
+
             contexts.push(BatteryCharacteristicContext {
-                characteristic: battery_level_characteristic.clone(),
+                characteristic: Arc::clone(battery_level_characteristic),
                 user_description,
+                part_key: battery_part_key(service_index, level_index),
+                presentation_format,
+                power_state: None,
+            });
+        }
+
+        // The power state describes the battery of the device hosting the
+        // service, i.e. the first (central) level; levels relayed from split
+        // peripherals have no power state of their own.
+        let power_state = characteristics
+            .iter()
+            .filter_map(|c| PowerStateFormat::for_characteristic(c.uuid()).map(|f| (f, c)))
+            .min_by_key(|(format, _)| *format);
+        if let (Some(context), Some((format, characteristic))) =
+            (contexts.get_mut(service_contexts_start), power_state)
+        {
+            log::debug!(
+                "BLE I/O: found power state characteristic format={format:?} for device id={}",
+                format_device_id_for_store(target_device)
+            );
+            context.power_state = Some(PowerStateCharacteristic {
+                characteristic: Arc::clone(characteristic),
+                format,
             });
         }
     }
 
+    order_by_presentation(&mut contexts);
     Ok(contexts)
 }
 
+/// List the parts in the order their Presentation Format descriptions give
+/// (first, second, ...; left before right), when every part has one. Devices
+/// that describe only some parts keep discovery order.
+fn order_by_presentation(contexts: &mut [BatteryCharacteristicContext]) {
+    let hints: Option<Vec<u16>> = contexts
+        .iter()
+        .map(|c| c.presentation_format.and_then(|f| f.ordering_hint()))
+        .collect();
+    if hints.is_some() {
+        contexts.sort_by_key(|c| c.presentation_format.and_then(|f| f.ordering_hint()));
+    }
+}
+
+/// Power state is optional metadata, so a failed read degrades to `Unknown`
+/// instead of failing the battery reading.
+async fn read_power_state(context: &BatteryCharacteristicContext) -> PowerState {
+    let Some(power_state) = &context.power_state else {
+        return PowerState::Unknown;
+    };
+    match power_state.characteristic.read().await {
+        Ok(value) => {
+            let state = power_state.format.decode(&value);
+            log::debug!(
+                "BLE I/O: read response power_state bytes={} parsed={state:?}",
+                bytes_to_hex(&value)
+            );
+            state
+        }
+        Err(e) => {
+            log::debug!("BLE I/O: power state read failed error={e}");
+            PowerState::Unknown
+        }
+    }
+}
+
 async fn read_battery_infos_strict(
     contexts: &[BatteryCharacteristicContext],
-) -> Result<Vec<BatteryInfo>, String> {
+    readings: &ReadingPipeline,
+) -> Result<Vec<BatteryInfo>, CommandError> {
     let mut battery_infos = Vec::new();
 
     for context in contexts {
@@ -243,24 +739,25 @@ async fn read_battery_infos_strict(
         let value = context
             .characteristic
             .read()
-            .await
-            .map_err(|e| e.to_string())?;
+            .await?;
+        let reading = readings.accept(&context.part_key, &value);
         log::debug!(
-            "BLE I/O: read response battery_level descriptor={} bytes={} parsed={:?}",
+            "BLE I/O: read response battery_level descriptor={} bytes={} parsed={:?} flag={:?}",
             label,
             bytes_to_hex(&value),
-            value.first().copied()
+            reading.level,
+            reading.flag
         );
-        battery_infos.push(BatteryInfo {
-            battery_level: value.first().copied(),
-            user_description: context.user_description.clone(),
-        });
+        battery_infos.push(context.battery_info(reading, read_power_state(context).await));
     }
 
     Ok(battery_infos)
 }
 
-async fn read_battery_infos_best_effort(contexts: &[BatteryCharacteristicContext]) -> Vec<BatteryInfo> {
+async fn read_battery_infos_best_effort(
+    contexts: &[BatteryCharacteristicContext],
+    readings: &ReadingPipeline,
+) -> Vec<BatteryInfo> {
     let mut battery_infos = Vec::new();
 
     for context in contexts {
@@ -270,14 +767,18 @@ async fn read_battery_infos_best_effort(contexts: &[BatteryCharacteristicContext
             .characteristic
             .read()
             .await;
-        let battery_level = read_result.as_ref().ok().and_then(|value| value.first().copied());
+        let reading = match &read_result {
+            Ok(value) => readings.accept(&context.part_key, value),
+            Err(_) => Reading::missing(),
+        };
         match read_result {
             Ok(value) => {
                 log::debug!(
-                    "BLE I/O: best-effort read response descriptor={} bytes={} parsed={:?}",
+                    "BLE I/O: best-effort read response descriptor={} bytes={} parsed={:?} flag={:?}",
                     label,
                     bytes_to_hex(&value),
-                    battery_level
+                    reading.level,
+                    reading.flag
                 );
             }
             Err(e) => {
@@ -289,15 +790,111 @@ async fn read_battery_infos_best_effort(contexts: &[BatteryCharacteristicContext
             }
         }
 
-        battery_infos.push(BatteryInfo {
-            battery_level,
-            user_description: context.user_description.clone(),
-        });
+        battery_infos.push(context.battery_info(reading, read_power_state(context).await));
     }
 
     battery_infos
 }
 
+#[derive(Default)]
+struct ScanAccumulator {
+    devices: Vec<ScannedDeviceInfo>,
+}
+
+impl ScanAccumulator {
+    /// Merge an advertisement into the results. Returns the updated entry when
+    /// the device is new or its name or services changed; RSSI-only updates
+    /// are kept but not reported, since every advertisement carries one.
+    fn observe(&mut self, advertisement: &Advertisement) -> Option<ScannedDeviceInfo> {
+        let id = advertisement.device.id();
+        let name = advertisement
+            .local_name
+            .clone()
+            .or_else(|| advertisement.device.name().ok())
+            .filter(|name| !name.is_empty());
+
+        let (index, mut changed) = match self.devices.iter().position(|d| d.id == id) {
+            Some(index) => (index, false),
+            None => {
+                self.devices.push(ScannedDeviceInfo {
+                    id,
+                    name: None,
+                    rssi: None,
+                    services: Vec::new(),
+                    has_battery_service: false,
+                });
+                (self.devices.len() - 1, true)
+            }
+        };
+        let device = &mut self.devices[index];
+
+        if name.is_some() && device.name != name {
+            device.name = name;
+            changed = true;
+        }
+        for service in &advertisement.services {
+            let service = service.to_string();
+            if !device.services.contains(&service) {
+                device.services.push(service);
+                changed = true;
+            }
+        }
+        device.has_battery_service = advertisement.services.contains(&BATTERY_SERVICE_UUID)
+            || device.has_battery_service;
+        if advertisement.rssi.is_some() {
+            device.rssi = advertisement.rssi;
+        }
+
+        changed.then(|| device.clone())
+    }
+}
+
+/// Scan until `duration` elapses, a stop signal arrives or the scan stream
+/// ends, reporting each new or changed device through `on_update`.
+async fn scan_for_battery_devices(
+    adapter: &dyn BleAdapter,
+    duration: Duration,
+    mut stop_rx: watch::Receiver<bool>,
+    on_update: impl Fn(&ScannedDeviceInfo),
+) -> Result<Vec<ScannedDeviceInfo>, CommandError> {
+    log::debug!("BLE I/O: scan start duration={duration:?}");
+    let mut advertisements = adapter.scan(&[]).await?;
+    let mut accumulator = ScanAccumulator::default();
+    let deadline = sleep(duration);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            _ = &mut deadline => break,
+            changed = stop_rx.changed() => {
+                if changed.is_err() || *stop_rx.borrow() {
+                    log::debug!("BLE I/O: scan stopped by signal");
+                    break;
+                }
+            }
+            advertisement = advertisements.next() => {
+                let Some(advertisement) = advertisement else {
+                    log::debug!("BLE I/O: scan stream ended");
+                    break;
+                };
+                if let Some(info) = accumulator.observe(&advertisement) {
+                    log::debug!(
+                        "BLE I/O: scan result device_id={} name={:?} rssi={:?} battery_service={}",
+                        info.id,
+                        info.name,
+                        info.rssi,
+                        info.has_battery_service
+                    );
+                    on_update(&info);
+                }
+            }
+        }
+    }
+
+    log::debug!("BLE I/O: scan finished count={}", accumulator.devices.len());
+    Ok(accumulator.devices)
+}
+
 async fn wait_for_retry_or_stop(stop_rx: &mut watch::Receiver<bool>, duration: Duration) -> bool {
     tokio::select! {
         _ = sleep(duration) => false,
@@ -305,90 +902,343 @@ async fn wait_for_retry_or_stop(stop_rx: &mut watch::Receiver<bool>, duration: D
     }
 }
 
-async fn update_monitor_connection_state(
-    app: &AppHandle,
+/// Count a failed reconnection attempt and report it. Returns the delay
+/// before the next attempt, or None when the policy gave up.
+fn report_reconnect_attempt(
+    events: &Arc<dyn BatteryEventSink>,
     device_id: &str,
-    worker_id: usize,
-    connected: bool,
-    state: &Arc<Mutex<MonitorConnectionState>>,
-) {
-    let state_changed = {
-        state
-            .lock()
-            .await
-            .apply_worker_connection(worker_id, connected)
-    };
-
-    if let Some(next_connected) = state_changed {
-        let payload = BatteryMonitorStatusEvent {
-            id: device_id.to_string(),
-            connected: next_connected,
-        };
-        let _ = app.emit(BATTERY_MONITOR_STATUS_EVENT, payload);
+    backoff: &mut ReconnectBackoff,
+) -> Option<Duration> {
+    let delay = backoff.next_delay(Instant::now(), ble_reconnect::jitter_unit());
+    let mut status = BatteryMonitorStatusEvent::new(device_id.to_string(), false);
+    status.reconnect_attempt = backoff.attempt();
+    status.next_retry_at = delay.map(ble_reconnect::epoch_millis_after);
+    events.monitor_status(status);
+
+    match delay {
+        Some(delay) => log::debug!(
+            "BLE I/O: connection watcher retry {} in {delay:?} device_id={device_id}",
+            backoff.attempt()
+        ),
+        None => log::info!(
+            "BLE I/O: connection watcher giving up after {} attempts device_id={device_id}",
+            backoff.attempt()
+        ),
     }
+    delay
 }
 
-enum NotificationOutcome {
-    Emit(BatteryInfo),
-    Stop,
-}
-
-fn classify_notification_item(
-    item: Option<Result<Vec<u8>, bluest::Error>>,
-    user_description: &Option<String>,
-) -> NotificationOutcome {
-    match item {
-        Some(Ok(data)) => NotificationOutcome::Emit(BatteryInfo {
-            battery_level: data.first().copied(),
-            user_description: user_description.clone(),
-        }),
-        Some(Err(_)) | None => NotificationOutcome::Stop,
+/// Count and report a failed reconnection attempt, then wait out the policy's
+/// delay. Returns true when the watcher should end: a stop was requested or
+/// the policy gave up.
+async fn wait_for_reconnect_or_stop(
+    events: &Arc<dyn BatteryEventSink>,
+    device_id: &str,
+    backoff: &mut ReconnectBackoff,
+    stop_rx: &mut watch::Receiver<bool>,
+) -> bool {
+    match report_reconnect_attempt(events, device_id, backoff) {
+        Some(delay) => wait_for_retry_or_stop(stop_rx, delay).await,
+        None => true,
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum ConnectionWaitOutcome {
-    Proceed,
-    RetryOuter,
+/// Returns once a stop is requested or the stop sender is gone.
+async fn wait_for_stop(stop_rx: &mut watch::Receiver<bool>) {
+    let _ = stop_rx.wait_for(|stop| *stop).await;
 }
 
-fn classify_connection_wait_event(
-    event: Option<bluest::ConnectionEvent>,
-) -> ConnectionWaitOutcome {
-    match event {
-        Some(bluest::ConnectionEvent::Connected) => ConnectionWaitOutcome::Proceed,
-        Some(bluest::ConnectionEvent::Disconnected) | None => ConnectionWaitOutcome::RetryOuter,
+/// Returns once the adapter is not available. Never returns when nothing
+/// reports adapter state any more.
+async fn wait_for_adapter_loss(adapter_state: &mut watch::Receiver<AdapterState>) {
+    if adapter_state
+        .wait_for(|state| *state != AdapterState::Available)
+        .await
+        .is_err()
+    {
+        std::future::pending::<()>().await;
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum WorkerConnectionOutcome {
-    Continue,
-    Stop,
-}
-
-fn classify_worker_connection_event(
-    event: Option<bluest::ConnectionEvent>,
-) -> WorkerConnectionOutcome {
-    match event {
-        Some(bluest::ConnectionEvent::Connected) => WorkerConnectionOutcome::Continue,
-        Some(bluest::ConnectionEvent::Disconnected) | None => WorkerConnectionOutcome::Stop,
+/// Hold the watcher while its adapter is unavailable. Returns true when
+/// stopped instead.
+async fn wait_for_adapter_or_stop(
+    adapter_state: &mut watch::Receiver<AdapterState>,
+    stop_rx: &mut watch::Receiver<bool>,
+    device_id: &str,
+) -> bool {
+    let state = *adapter_state.borrow();
+    if state == AdapterState::Available {
+        return false;
+    }
+    log::info!("BLE I/O: adapter {state:?}, connection watcher paused device_id={device_id}");
+    tokio::select! {
+        // A closed channel means nothing reports adapter state any more, so
+        // carry on rather than wait forever.
+        _ = adapter_state.wait_for(|state| *state == AdapterState::Available) => {
+            log::info!("BLE I/O: adapter available, connection watcher resumed device_id={device_id}");
+            false
+        }
+        _ = wait_for_stop(stop_rx) => true,
     }
 }
 
-async fn battery_notification_worker(args: BatteryNotificationWorkerArgs) {
-    let BatteryNotificationWorkerArgs {
-        app,
-        adapter,
-        target_device,
-        device_id,
-        worker_id,
-        monitor_connection_state,
-        context,
-        mut stop_rx,
-    } = args;
-    // Subscribe to connection events
-    let conn_events_result = adapter.device_connection_events(&target_device).await;
+/// Follow the power state of `adapter_id` until stopped, publishing it on
+/// `state_tx` and emitting `bluetooth-adapter-status` on every change.
+async fn adapter_state_watcher(
+    events: Arc<dyn BatteryEventSink>,
+    transport: Arc<dyn BleTransport>,
+    adapter_id: String,
+    state_tx: watch::Sender<AdapterState>,
+    mut stop_rx: watch::Receiver<bool>,
+) {
+    log::debug!("BLE I/O: adapter watcher started adapter_id={adapter_id}");
+    let report = |state: AdapterState| {
+        let changed = state_tx.send_if_modified(|current| {
+            let changed = *current != state;
+            *current = state;
+            changed
+        });
+        if changed {
+            log::info!("BLE I/O: adapter state changed adapter_id={adapter_id} state={state:?}");
+            events.adapter_status(BluetoothAdapterStatusEvent {
+                adapter_id: adapter_id.clone(),
+                state,
+            });
+        }
+    };
+
+    'watch: loop {
+        if *stop_rx.borrow() {
+            break;
+        }
+
+        let adapter = transport
+            .adapters()
+            .await
+            .into_iter()
+            .find(|adapter| adapter.id() == adapter_id);
+        let Some(adapter) = adapter else {
+            report(AdapterState::Removed);
+            if wait_for_retry_or_stop(&mut stop_rx, ADAPTER_RETRY_INTERVAL).await {
+                break;
+            }
+            continue;
+        };
+
+        // Subscribe before reading the state so no change falls in between.
+        let mut adapter_events = match adapter.events().await {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!("BLE I/O: adapter events unavailable adapter_id={adapter_id}: {e}");
+                if wait_for_retry_or_stop(&mut stop_rx, ADAPTER_RETRY_INTERVAL).await {
+                    break;
+                }
+                continue;
+            }
+        };
+        match adapter.is_available().await {
+            Ok(true) => report(AdapterState::Available),
+            Ok(false) => report(AdapterState::PoweredOff),
+            Err(e) => {
+                log::warn!("BLE I/O: adapter state query failed adapter_id={adapter_id}: {e}");
+                report(AdapterState::PoweredOff);
+            }
+        }
+
+        loop {
+            tokio::select! {
+                changed = stop_rx.changed() => {
+                    if changed.is_err() || *stop_rx.borrow() {
+                        break 'watch;
+                    }
+                }
+                event = adapter_events.next() => match event {
+                    Some(Ok(bluest::AdapterEvent::Available)) => report(AdapterState::Available),
+                    Some(Ok(bluest::AdapterEvent::Unavailable)) => report(AdapterState::PoweredOff),
+                    Some(Err(e)) => {
+                        log::warn!("BLE I/O: adapter event error adapter_id={adapter_id}: {e}");
+                    }
+                    // The adapter went away. Look it up again after a while;
+                    // a stack that lists it right away must not be polled in
+                    // a tight loop.
+                    None => {
+                        report(AdapterState::Removed);
+                        if wait_for_retry_or_stop(&mut stop_rx, ADAPTER_RETRY_INTERVAL).await {
+                            break 'watch;
+                        }
+                        continue 'watch;
+                    }
+                },
+            }
+        }
+    }
+    log::debug!("BLE I/O: adapter watcher stopped adapter_id={adapter_id}");
+}
+
+/// State of `adapter_id`, starting its watcher on first use.
+async fn watch_adapter_state(
+    events: Arc<dyn BatteryEventSink>,
+    adapter_id: &str,
+) -> watch::Receiver<AdapterState> {
+    let mut watchers = ADAPTER_WATCHERS.lock().await;
+    if let Some(watcher) = watchers.get(adapter_id) {
+        return watcher.state_rx.clone();
+    }
+    let (state_tx, state_rx) = watch::channel(AdapterState::Available);
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let handle = tokio::spawn(adapter_state_watcher(
+        events,
+        ble_transport::transport(),
+        adapter_id.to_string(),
+        state_tx,
+        stop_rx,
+    ));
+    watchers.insert(
+        adapter_id.to_string(),
+        AdapterWatcher {
+            state_rx: state_rx.clone(),
+            stop_tx,
+            handle,
+        },
+    );
+    state_rx
+}
+
+/// Presence hub of `adapter`, starting its watcher on first use.
+async fn watch_presence(adapter: &Arc<dyn BleAdapter>) -> Arc<PresenceHub> {
+    let mut watchers = PRESENCE_WATCHERS.lock().await;
+    if let Some(watcher) = watchers.get(&adapter.id()) {
+        return watcher.hub.clone();
+    }
+    let hub = PresenceHub::new();
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let handle = tokio::spawn(ble_scheduler::with_priority(
+        Priority::BackgroundPoll,
+        ble_presence::presence_watcher(hub.clone(), adapter.clone(), stop_rx),
+    ));
+    watchers.insert(
+        adapter.id(),
+        PresenceWatcher {
+            hub: hub.clone(),
+            stop_tx,
+            handle,
+        },
+    );
+    hub
+}
+
+async fn update_monitor_connection_state(
+    events: &Arc<dyn BatteryEventSink>,
+    device_id: &str,
+    worker_id: usize,
+    connected: bool,
+    state: &Arc<Mutex<MonitorConnectionState>>,
+) {
+    let state_changed = {
+        state
+            .lock()
+            .await
+            .apply_worker_connection(worker_id, connected)
+    };
+
+    if let Some(next_connected) = state_changed {
+        events.monitor_status(BatteryMonitorStatusEvent::new(
+            device_id.to_string(),
+            next_connected,
+        ));
+    }
+}
+
+enum NotificationOutcome {
+    Emit(BatteryInfo),
+    /// The reading was rejected or held back; nothing to emit.
+    Skip(Reading),
+    Stop,
+}
+
+/// Notifications carry only the level; the rest of the part's info, power
+/// state included, stays as in `current`.
+fn classify_notification_item(
+    item: Option<Result<Vec<u8>, bluest::Error>>,
+    current: &BatteryInfo,
+    readings: &ReadingPipeline,
+) -> NotificationOutcome {
+    match item {
+        Some(Ok(data)) => {
+            let reading = readings.accept(&current.part_key, &data);
+            if reading.level.is_none() {
+                return NotificationOutcome::Skip(reading);
+            }
+            NotificationOutcome::Emit(BatteryInfo {
+                battery_level: reading.level,
+                raw_battery_level: reading.raw,
+                reading_flag: reading.flag,
+                ..current.clone()
+            })
+        }
+        Some(Err(_)) | None => NotificationOutcome::Stop,
+    }
+}
+
+/// Why a connection watcher session ended.
+enum SessionEnd {
+    /// Every worker finished, normally because the device disconnected.
+    WorkersDone,
+    Stopped,
+    AdapterLost,
+    /// The watchdog found the device silent and the probe read failed.
+    Stale(BatteryMonitorDiagnosticEvent),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ConnectionWaitOutcome {
+    Proceed,
+    RetryOuter,
+}
+
+fn classify_connection_wait_event(
+    event: Option<bluest::ConnectionEvent>,
+) -> ConnectionWaitOutcome {
+    match event {
+        Some(bluest::ConnectionEvent::Connected) => ConnectionWaitOutcome::Proceed,
+        Some(bluest::ConnectionEvent::Disconnected) | None => ConnectionWaitOutcome::RetryOuter,
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum WorkerConnectionOutcome {
+    Continue,
+    Stop,
+}
+
+fn classify_worker_connection_event(
+    event: Option<bluest::ConnectionEvent>,
+) -> WorkerConnectionOutcome {
+    match event {
+        Some(bluest::ConnectionEvent::Connected) => WorkerConnectionOutcome::Continue,
+        Some(bluest::ConnectionEvent::Disconnected) | None => WorkerConnectionOutcome::Stop,
+    }
+}
+
+async fn battery_notification_worker(args: BatteryNotificationWorkerArgs) {
+    let BatteryNotificationWorkerArgs {
+        events,
+        target_device,
+        device_id,
+        worker_id,
+        monitor_connection_state,
+        context,
+        initial_info,
+        readings,
+        liveness,
+        verify_after,
+        mut stop_rx,
+    } = args;
+    let mut current_info = initial_info;
+    let mut last_heard = Instant::now();
+    // Subscribe to connection events
+    let conn_events_result = target_device.connection_events().await;
     let mut conn_events = match conn_events_result {
         Ok(s) => Some(s),
         Err(e) => {
@@ -402,7 +1252,9 @@ async fn battery_notification_worker(args: BatteryNotificationWorkerArgs) {
         device_id,
         context.user_description.as_deref().unwrap_or("Central")
     );
-    let mut stream = match context.characteristic.notify().await {
+    let subscribe = context.characteristic.notify();
+    let subscribe = ble_scheduler::with_priority(Priority::NotificationSetup, subscribe);
+    let mut stream = match subscribe.await {
         Ok(stream) => stream,
         Err(e) => {
             log::warn!(
@@ -419,8 +1271,18 @@ async fn battery_notification_worker(args: BatteryNotificationWorkerArgs) {
         device_id,
         context.user_description.as_deref().unwrap_or("Central")
     );
+    let mut power_state_stream = match &context.power_state {
+        Some(power_state) => {
+            ble_scheduler::with_priority(
+                Priority::NotificationSetup,
+                subscribe_power_state(&device_id, power_state),
+            )
+            .await
+        }
+        None => None,
+    };
     update_monitor_connection_state(
-        &app,
+        &events,
         &device_id,
         worker_id,
         true,
@@ -433,7 +1295,7 @@ async fn battery_notification_worker(args: BatteryNotificationWorkerArgs) {
             changed = stop_rx.changed() => {
                 if changed.is_err() || *stop_rx.borrow() {
                     log::debug!("BLE I/O: notification worker stop event device_id={device_id}");
-                    disconnect_device(&adapter, &target_device).await;
+                    disconnect_device(target_device.as_ref()).await;
                     return;
                 }
             }
@@ -465,17 +1327,117 @@ async fn battery_notification_worker(args: BatteryNotificationWorkerArgs) {
                     }
                 }
 
-                match classify_notification_item(value, &context.user_description) {
+                match classify_notification_item(value, &current_info, &readings) {
                     NotificationOutcome::Emit(battery_info) => {
-                        let payload = BatteryInfoNotificationEvent {
+                        last_heard = Instant::now();
+                        liveness.heard();
+                        current_info = battery_info.clone();
+                        events.battery_info(BatteryInfoNotificationEvent {
                             id: device_id.clone(),
-                            battery_info,
-                        };
-                        let _ = app.emit(BATTERY_INFO_NOTIFICATION_EVENT, payload);
+                            battery_infos: vec![battery_info],
+                        });
+                    }
+                    NotificationOutcome::Skip(reading) => {
+                        // A rejected value still shows the stream is alive.
+                        last_heard = Instant::now();
+                        liveness.heard();
+                        log::warn!(
+                            "BLE I/O: notification not accepted device_id={} description={} raw={:?} flag={:?}",
+                            device_id,
+                            context.user_description.as_deref().unwrap_or("Central"),
+                            reading.raw,
+                            reading.flag
+                        );
                     }
                     NotificationOutcome::Stop => break,
                 }
             }
+            // Notifications can stop without the stream ending; re-read after
+            // a silent stretch so a missed change is still picked up.
+            _ = async {
+                match verify_after {
+                    Some(after) => sleep_until(last_heard + after).await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                last_heard = Instant::now();
+                log::debug!(
+                    "BLE I/O: verification read after silence device_id={} description={}",
+                    device_id,
+                    context.user_description.as_deref().unwrap_or("Central")
+                );
+                match context.characteristic.read().await {
+                    Ok(value) => {
+                        liveness.heard();
+                        let reading = readings.accept(&context.part_key, &value);
+                        if reading.level.is_none() {
+                            log::warn!(
+                                "BLE I/O: verification read not accepted device_id={} description={} raw={:?} flag={:?}",
+                                device_id,
+                                context.user_description.as_deref().unwrap_or("Central"),
+                                reading.raw,
+                                reading.flag
+                            );
+                        } else if reading.level != current_info.battery_level {
+                            log::warn!(
+                                "BLE I/O: verification read found a missed change device_id={} description={} parsed={:?}",
+                                device_id,
+                                context.user_description.as_deref().unwrap_or("Central"),
+                                reading.level
+                            );
+                            current_info.battery_level = reading.level;
+                            current_info.raw_battery_level = reading.raw;
+                            current_info.reading_flag = reading.flag;
+                            events.battery_info(BatteryInfoNotificationEvent {
+                                id: device_id.clone(),
+                                battery_infos: vec![current_info.clone()],
+                            });
+                        }
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "BLE I/O: verification read failed device_id={} description={}: {}",
+                            device_id,
+                            context.user_description.as_deref().unwrap_or("Central"),
+                            e
+                        );
+                        break;
+                    }
+                }
+            }
+            power_state_value = async {
+                match power_state_stream.as_mut() {
+                    Some(s) => s.next().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                let Some(Ok(data)) = power_state_value else {
+                    // Charging state is optional; keep the level stream running.
+                    log::warn!(
+                        "BLE I/O: power state notification stream ended device_id={device_id}"
+                    );
+                    power_state_stream = None;
+                    continue;
+                };
+                let Some(format) = context.power_state.as_ref().map(|p| p.format) else {
+                    continue;
+                };
+                liveness.heard();
+                let power_state = format.decode(&data);
+                log::debug!(
+                    "BLE I/O: power state notify event device_id={} bytes={} parsed={:?}",
+                    device_id,
+                    bytes_to_hex(&data),
+                    power_state
+                );
+                if power_state != current_info.power_state {
+                    current_info.power_state = power_state;
+                    events.battery_info(BatteryInfoNotificationEvent {
+                        id: device_id.clone(),
+                        battery_infos: vec![current_info.clone()],
+                    });
+                }
+            }
             // Detect disconnection
             conn_event = async {
                 match conn_events.as_mut() {
@@ -499,7 +1461,125 @@ async fn battery_notification_worker(args: BatteryNotificationWorkerArgs) {
     }
 
     update_monitor_connection_state(
-        &app,
+        &events,
+        &device_id,
+        worker_id,
+        false,
+        &monitor_connection_state,
+    )
+    .await;
+}
+
+/// Re-read polled parts, emitting the ones whose level or power state changed.
+/// A failed level read means the device is gone; a rejected level keeps the
+/// previous one.
+async fn poll_battery_parts(
+    events: &Arc<dyn BatteryEventSink>,
+    device_id: &str,
+    parts: &mut [(BatteryCharacteristicContext, BatteryInfo)],
+    readings: &ReadingPipeline,
+) -> Result<(), CommandError> {
+    for (context, current_info) in parts.iter_mut() {
+        let label = context.user_description.as_deref().unwrap_or("Central");
+        log::debug!("BLE I/O: poll read request device_id={device_id} description={label}");
+        let value = context
+            .characteristic
+            .read()
+            .await?;
+        let reading = readings.accept(&context.part_key, &value);
+        log::debug!(
+            "BLE I/O: poll read response device_id={device_id} description={label} bytes={} parsed={:?} flag={:?}",
+            bytes_to_hex(&value),
+            reading.level,
+            reading.flag
+        );
+        let power_state = read_power_state(context).await;
+        let battery_info = if reading.level.is_some() {
+            context.battery_info(reading, power_state)
+        } else {
+            BatteryInfo {
+                power_state,
+                ..current_info.clone()
+            }
+        };
+        if battery_info.battery_level != current_info.battery_level
+            || battery_info.power_state != current_info.power_state
+        {
+            *current_info = battery_info.clone();
+            events.battery_info(BatteryInfoNotificationEvent {
+                id: device_id.to_string(),
+                battery_infos: vec![battery_info],
+            });
+        }
+    }
+    Ok(())
+}
+
+async fn battery_poll_worker(args: BatteryPollWorkerArgs) {
+    let BatteryPollWorkerArgs {
+        events,
+        target_device,
+        device_id,
+        worker_id,
+        monitor_connection_state,
+        mut parts,
+        readings,
+        liveness,
+        interval,
+        mut stop_rx,
+    } = args;
+    let mut conn_events = match target_device.connection_events().await {
+        Ok(s) => Some(s),
+        Err(e) => {
+            log::warn!("BLE I/O: failed to subscribe to connection events device_id={device_id}: {e}");
+            None
+        }
+    };
+    log::debug!(
+        "BLE I/O: poll worker started device_id={device_id} parts={} interval={interval:?}",
+        parts.len()
+    );
+    update_monitor_connection_state(
+        &events,
+        &device_id,
+        worker_id,
+        true,
+        &monitor_connection_state,
+    )
+    .await;
+
+    loop {
+        tokio::select! {
+            changed = stop_rx.changed() => {
+                if changed.is_err() || *stop_rx.borrow() {
+                    log::debug!("BLE I/O: poll worker stop event device_id={device_id}");
+                    disconnect_device(target_device.as_ref()).await;
+                    return;
+                }
+            }
+            _ = sleep(interval) => {
+                if let Err(e) = poll_battery_parts(&events, &device_id, &mut parts, &readings).await {
+                    log::warn!("BLE I/O: poll read failed device_id={device_id}: {e}");
+                    break;
+                }
+                liveness.heard();
+            }
+            conn_event = async {
+                match conn_events.as_mut() {
+                    Some(s) => s.next().await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                if classify_worker_connection_event(conn_event) == WorkerConnectionOutcome::Stop {
+                    log::warn!("BLE I/O: device disconnected (connection event) device_id={device_id}");
+                    break;
+                }
+            }
+        }
+    }
+
+    update_monitor_connection_state(
+        &events,
         &device_id,
         worker_id,
         false,
@@ -508,13 +1588,81 @@ async fn battery_notification_worker(args: BatteryNotificationWorkerArgs) {
     .await;
 }
 
+/// Subscribe to power state changes when the characteristic supports it.
+/// Failures only cost live charging updates, so they are not fatal.
+async fn subscribe_power_state<'a>(
+    device_id: &str,
+    power_state: &'a PowerStateCharacteristic,
+) -> Option<ble_transport::NotifyStream<'a>> {
+    match power_state.characteristic.properties().await {
+        Ok(props) if props.notify || props.indicate => {}
+        _ => return None,
+    }
+    match power_state.characteristic.notify().await {
+        Ok(stream) => {
+            log::debug!("BLE I/O: power state subscribe response success device_id={device_id}");
+            Some(stream)
+        }
+        Err(e) => {
+            log::warn!("BLE I/O: power state subscribe failed device_id={device_id}: {e}");
+            None
+        }
+    }
+}
+
+/// Resolves when the session's device has been silent for `stale_after` and
+/// a probe read of `probe` fails or hangs. A successful probe counts as
+/// activity and the wait starts over. Never resolves when `stale_after` is
+/// None.
+async fn watch_for_stale_stream(
+    liveness: &Liveness,
+    stale_after: Option<Duration>,
+    probe: &BatteryCharacteristicContext,
+    device_id: &str,
+) -> BatteryMonitorDiagnosticEvent {
+    let Some(stale_after) = stale_after else {
+        return std::future::pending().await;
+    };
+    loop {
+        liveness.wait_until_stale(stale_after).await;
+        let silent_secs = liveness.last_heard().elapsed().as_secs();
+        log::debug!(
+            "BLE I/O: watchdog probe read request device_id={device_id} silent_secs={silent_secs}"
+        );
+        // The read is bounded by the transport's read timeout.
+        let read = probe.characteristic.read();
+        let detail = match ble_scheduler::with_priority(Priority::BackgroundPoll, read).await {
+            Ok(_) => {
+                log::debug!("BLE I/O: watchdog probe read response success device_id={device_id}");
+                liveness.heard();
+                continue;
+            }
+            Err(e) => format!("Probe read failed: {e}"),
+        };
+        return BatteryMonitorDiagnosticEvent {
+            id: device_id.to_string(),
+            kind: DiagnosticKind::StaleStreamRestarted,
+            silent_secs,
+            detail,
+        };
+    }
+}
+
 async fn battery_connection_watcher(
-    app: AppHandle,
-    adapter: Adapter,
+    events: Arc<dyn BatteryEventSink>,
+    presence: Arc<PresenceHub>,
     device_id: String,
+    options: MonitorOptions,
+    reconnect: ReconnectPolicy,
+    mut adapter_state: watch::Receiver<AdapterState>,
     mut stop_rx: watch::Receiver<bool>,
 ) {
     log::debug!("BLE I/O: connection watcher started device_id={device_id}");
+    let mut backoff = ReconnectBackoff::new(reconnect);
+    // Kept across reconnects so a spike right after one is still caught.
+    let readings = reading_pipeline(&device_id, options.reading_filter).await;
+    let events: Arc<dyn BatteryEventSink> =
+        CoalescingSink::spawn(events, &device_id, options.coalesce);
 
     'outer: loop {
         if *stop_rx.borrow() {
@@ -529,45 +1677,45 @@ async fn battery_connection_watcher(
         // and the whole system sluggish — especially when multiple disconnected
         // devices each run their own scan concurrently.
         // ZMK keyboards reconnect through OS-level BLE bonding, so we only need
-        // to check whether the device has appeared in the connected list.
+        // to check whether the device has appeared in the connected list. The
+        // adapter's presence watcher runs that query once for all devices.
         log::debug!("BLE I/O: connection watcher polling for device device_id={device_id}");
+        let mut poll_at = Instant::now();
         let target_device = loop {
-            match adapter
-                .connected_devices_with_services(&[BATTERY_SERVICE_UUID, BATTERY_LEVEL_UUID])
-                .await
-            {
-                Ok(devices) => {
-                    if let Some(device) = devices.into_iter().find(|d| is_target_device(d, &device_id)) {
-                        log::debug!("BLE I/O: connection watcher found target device device_id={device_id}");
-                        break device;
-                    }
-                }
-                Err(e) => {
-                    log::warn!("BLE I/O: connection watcher query failed device_id={device_id}: {e}");
+            if wait_for_adapter_or_stop(&mut adapter_state, &mut stop_rx, &device_id).await {
+                return;
+            }
+            match presence.find(&device_id, poll_at, &mut stop_rx).await {
+                Presence::Connected(device) => {
+                    log::debug!("BLE I/O: connection watcher found target device device_id={device_id}");
+                    break device;
                 }
+                Presence::Stopped => return,
+                Presence::Missing => {}
             }
-            if wait_for_retry_or_stop(&mut stop_rx, Duration::from_secs(5)).await {
+            let Some(delay) = report_reconnect_attempt(&events, &device_id, &mut backoff) else {
                 return;
-            }
+            };
+            poll_at = Instant::now() + delay;
         };
 
         log::debug!("BLE I/O: connection watcher calling connect_device device_id={device_id}");
         // On macOS, bluest connection should be Established before subscribing to device_connection_events().
         // See https://docs.rs/bluest/latest/bluest/struct.Adapter.html#method.device_connection_events
-        if let Err(e) = adapter.connect_device(&target_device).await {
+        if let Err(e) = target_device.connect().await {
             log::warn!("BLE I/O: connection watcher connect_device failed device_id={device_id}: {e}");
-            if wait_for_retry_or_stop(&mut stop_rx, Duration::from_secs(2)).await {
+            if wait_for_reconnect_or_stop(&events, &device_id, &mut backoff, &mut stop_rx).await {
                 return;
             }
             continue 'outer;
         }
 
-        let mut conn_events = match adapter.device_connection_events(&target_device).await {
+        let mut conn_events = match target_device.connection_events().await {
             Ok(s) => s,
             Err(e) => {
                 log::warn!("BLE I/O: connection watcher failed to subscribe to connection events device_id={device_id}: {e}");
-                if wait_for_retry_or_stop(&mut stop_rx, Duration::from_secs(2)).await {
-                    disconnect_device(&adapter, &target_device).await;
+                if wait_for_reconnect_or_stop(&events, &device_id, &mut backoff, &mut stop_rx).await {
+                    disconnect_device(target_device.as_ref()).await;
                     return;
                 }
                 continue 'outer;
@@ -575,11 +1723,10 @@ async fn battery_connection_watcher(
         };
 
         // Check whether the device is already connected (returned in the connected-first batch).
-        let already_connected = adapter
-            .connected_devices_with_services(&[BATTERY_SERVICE_UUID, BATTERY_LEVEL_UUID])
-            .await
-            .map(|devs| devs.iter().any(|d| is_target_device(d, &device_id)))
-            .unwrap_or(false);
+        let already_connected = matches!(
+            presence.find(&device_id, Instant::now(), &mut stop_rx).await,
+            Presence::Connected(_)
+        );
 
         if !already_connected {
             // Wait for ConnectionEvent::Connected.
@@ -588,7 +1735,7 @@ async fn battery_connection_watcher(
                 tokio::select! {
                     changed = stop_rx.changed() => {
                         if changed.is_err() || *stop_rx.borrow() {
-                            disconnect_device(&adapter, &target_device).await;
+                            disconnect_device(target_device.as_ref()).await;
                             return;
                         }
                     }
@@ -608,8 +1755,8 @@ async fn battery_connection_watcher(
                         match classify_connection_wait_event(event) {
                             ConnectionWaitOutcome::Proceed => break,
                             ConnectionWaitOutcome::RetryOuter => {
-                                if wait_for_retry_or_stop(&mut stop_rx, Duration::from_secs(2)).await {
-                                    disconnect_device(&adapter, &target_device).await;
+                                if wait_for_reconnect_or_stop(&events, &device_id, &mut backoff, &mut stop_rx).await {
+                                    disconnect_device(target_device.as_ref()).await;
                                     return;
                                 }
                                 continue 'outer;
@@ -622,89 +1769,160 @@ async fn battery_connection_watcher(
             log::debug!("BLE I/O: connection watcher device already connected device_id={device_id}");
         }
 
-        let contexts = match get_battery_characteristic_contexts(&target_device).await {
+        let contexts = match get_battery_characteristic_contexts(target_device.as_ref()).await {
             Ok(c) => c,
             Err(e) => {
                 log::warn!("BLE I/O: connection watcher failed to get characteristics device_id={device_id}: {e}");
-                if wait_for_retry_or_stop(&mut stop_rx, Duration::from_secs(2)).await {
-                    disconnect_device(&adapter, &target_device).await;
+                if wait_for_reconnect_or_stop(&events, &device_id, &mut backoff, &mut stop_rx).await {
+                    disconnect_device(target_device.as_ref()).await;
                     return;
                 }
                 continue 'outer;
             }
         };
 
-        let mut notify_contexts = Vec::new();
-        for context in contexts.iter().cloned() {
+        // Parts that can notify get a subscription; the rest are polled.
+        let mut notify_indices = Vec::new();
+        let mut poll_indices = Vec::new();
+        for (index, context) in contexts.iter().enumerate() {
             match context.characteristic.properties().await {
-                Ok(props) if props.notify || props.indicate => notify_contexts.push(context),
-                _ => {}
+                Ok(props) if props.notify || props.indicate => notify_indices.push(index),
+                _ => poll_indices.push(index),
             }
         }
 
-        if notify_contexts.is_empty() {
-            log::warn!("BLE I/O: connection watcher no notify characteristics device_id={device_id}");
-            if wait_for_retry_or_stop(&mut stop_rx, Duration::from_secs(5)).await {
-                disconnect_device(&adapter, &target_device).await;
+        if contexts.is_empty() {
+            log::warn!("BLE I/O: connection watcher no battery characteristics device_id={device_id}");
+            if wait_for_reconnect_or_stop(&events, &device_id, &mut backoff, &mut stop_rx).await {
+                disconnect_device(target_device.as_ref()).await;
                 return;
             }
             continue 'outer;
         }
 
-        let _ = app.emit(
-            BATTERY_MONITOR_STATUS_EVENT,
-            BatteryMonitorStatusEvent {
-                id: device_id.clone(),
-                connected: true,
-            },
-        );
+        backoff.reset();
+        events.monitor_status(BatteryMonitorStatusEvent::new(device_id.clone(), true));
 
-        // Send initial battery readings to the frontend.
-        let initial_infos = read_battery_infos_best_effort(&contexts).await;
-        for info in &initial_infos {
-            let _ = app.emit(
-                BATTERY_INFO_NOTIFICATION_EVENT,
-                BatteryInfoNotificationEvent {
-                    id: device_id.clone(),
-                    battery_info: info.clone(),
-                },
-            );
-        }
+        // Send initial battery readings to the frontend. A held reading waits
+        // for the next one to confirm it.
+        let initial_infos = read_battery_infos_best_effort(&contexts, &readings).await;
+        events.battery_info(BatteryInfoNotificationEvent {
+            id: device_id.clone(),
+            battery_infos: initial_infos
+                .iter()
+                .filter(|info| info.reading_flag != Some(ReadingFlag::Held))
+                .cloned()
+                .collect(),
+        });
 
         log::debug!(
-            "BLE I/O: connection watcher starting {} workers device_id={device_id}",
-            notify_contexts.len()
+            "BLE I/O: connection watcher starting {} notify workers, polling {} parts device_id={device_id}",
+            notify_indices.len(),
+            poll_indices.len()
         );
 
         let monitor_connection_state = Arc::new(Mutex::new(MonitorConnectionState::default()));
+        let liveness = Arc::new(Liveness::new());
+        // Workers stop with the monitor or when the adapter goes away.
+        let (session_stop_tx, session_stop_rx) = watch::channel(false);
         let mut sub_handles = Vec::new();
+        let poll_worker_id = notify_indices.len();
 
-        for (worker_id, context) in notify_contexts.into_iter().enumerate() {
-            let app_c = app.clone();
-            let adapter_c = adapter.clone();
+        for (worker_id, index) in notify_indices.into_iter().enumerate() {
+            let context = contexts[index].clone();
+            let initial_info = initial_infos[index].clone();
+            let events_c = events.clone();
             let device_c = target_device.clone();
             let id_c = device_id.clone();
-            let stop_rx_c = stop_rx.clone();
+            let stop_rx_c = session_stop_rx.clone();
             let state_c = monitor_connection_state.clone();
+            let readings_c = readings.clone();
+            let liveness_c = liveness.clone();
+
+            // Verification and power state reads are background work; the
+            // worker raises the priority of its subscriptions itself.
+            let worker = battery_notification_worker(BatteryNotificationWorkerArgs {
+                events: events_c,
+                target_device: device_c,
+                device_id: id_c,
+                worker_id,
+                monitor_connection_state: state_c,
+                context,
+                initial_info,
+                readings: readings_c,
+                liveness: liveness_c,
+                verify_after: options.verify_after(),
+                stop_rx: stop_rx_c,
+            });
+            sub_handles.push(tokio::spawn(ble_scheduler::with_priority(
+                Priority::BackgroundPoll,
+                worker,
+            )));
+        }
 
-            sub_handles.push(tokio::spawn(async move {
-                battery_notification_worker(BatteryNotificationWorkerArgs {
-                    app: app_c,
-                    adapter: adapter_c,
-                    target_device: device_c,
-                    device_id: id_c,
-                    worker_id,
-                    monitor_connection_state: state_c,
-                    context,
-                    stop_rx: stop_rx_c,
-                })
-                .await;
-            }));
+        if !poll_indices.is_empty() {
+            let parts = poll_indices
+                .into_iter()
+                .map(|index| (contexts[index].clone(), initial_infos[index].clone()))
+                .collect();
+            let worker = battery_poll_worker(BatteryPollWorkerArgs {
+                events: events.clone(),
+                target_device: target_device.clone(),
+                device_id: device_id.clone(),
+                worker_id: poll_worker_id,
+                monitor_connection_state: monitor_connection_state.clone(),
+                parts,
+                readings: readings.clone(),
+                liveness: liveness.clone(),
+                interval: options.poll_interval(),
+                stop_rx: session_stop_rx,
+            });
+            sub_handles.push(tokio::spawn(ble_scheduler::with_priority(
+                Priority::BackgroundPoll,
+                worker,
+            )));
+        }
+
+        // Wait for all sub-workers to finish (disconnection), the stop signal,
+        // the adapter going away or the watchdog giving up on a silent device.
+        let mut workers = futures_util::future::join_all(sub_handles);
+        let end = tokio::select! {
+            _ = &mut workers => SessionEnd::WorkersDone,
+            _ = wait_for_stop(&mut stop_rx) => SessionEnd::Stopped,
+            _ = wait_for_adapter_loss(&mut adapter_state) => SessionEnd::AdapterLost,
+            diagnostic = watch_for_stale_stream(
+                &liveness,
+                options.stale_after(),
+                &contexts[0],
+                &device_id,
+            ) => SessionEnd::Stale(diagnostic),
+        };
+        if !matches!(end, SessionEnd::WorkersDone) {
+            let _ = session_stop_tx.send(true);
+            workers.await;
         }
 
-        // Wait for all sub-workers to finish (disconnection or stop signal).
-        for h in sub_handles {
-            let _ = h.await;
+        if let SessionEnd::Stale(diagnostic) = &end {
+            log::warn!(
+                "BLE I/O: connection watcher restarting silent session device_id={device_id}: {}",
+                diagnostic.detail
+            );
+            events.diagnostic(diagnostic.clone());
+        }
+        if matches!(end, SessionEnd::AdapterLost | SessionEnd::Stale(_)) {
+            // Stopped workers do not report; say disconnected unless they
+            // already did.
+            let reported_disconnected = {
+                let state = monitor_connection_state.lock().await;
+                state.ever_connected && !state.is_connected
+            };
+            if !reported_disconnected {
+                events.monitor_status(BatteryMonitorStatusEvent::new(device_id.clone(), false));
+            }
+        }
+        if matches!(end, SessionEnd::AdapterLost) {
+            log::info!("BLE I/O: adapter lost, connection watcher ending session device_id={device_id}");
+            continue 'outer;
         }
 
         let never_connected = {
@@ -715,23 +1933,17 @@ async fn battery_connection_watcher(
             log::warn!(
                 "BLE I/O: no notification worker connected this session, reporting disconnected device_id={device_id}"
             );
-            let _ = app.emit(
-                BATTERY_MONITOR_STATUS_EVENT,
-                BatteryMonitorStatusEvent {
-                    id: device_id.clone(),
-                    connected: false,
-                },
-            );
+            events.monitor_status(BatteryMonitorStatusEvent::new(device_id.clone(), false));
         }
 
         log::debug!("BLE I/O: connection watcher all workers finished, restarting device_id={device_id}");
 
         if *stop_rx.borrow() {
-            disconnect_device(&adapter, &target_device).await;
+            disconnect_device(target_device.as_ref()).await;
             return;
         }
-        if wait_for_retry_or_stop(&mut stop_rx, Duration::from_secs(2)).await {
-            disconnect_device(&adapter, &target_device).await;
+        if wait_for_reconnect_or_stop(&events, &device_id, &mut backoff, &mut stop_rx).await {
+            disconnect_device(target_device.as_ref()).await;
             return;
         }
     }
@@ -759,6 +1971,34 @@ pub async fn stop_all_battery_monitors() {
         log::debug!("BLE I/O: monitor stopped device_id={id}");
     }
 
+    let presence_watchers: Vec<(String, PresenceWatcher)> =
+        PRESENCE_WATCHERS.lock().await.drain().collect();
+    for (id, watcher) in presence_watchers {
+        let _ = watcher.stop_tx.send(true);
+        let abort_handle = watcher.handle.abort_handle();
+        if tokio::time::timeout(Duration::from_secs(10), watcher.handle)
+            .await
+            .is_err()
+        {
+            log::warn!("BLE I/O: presence watcher did not stop in time, aborting adapter_id={id}");
+            abort_handle.abort();
+        }
+    }
+
+    let adapter_watchers: Vec<(String, AdapterWatcher)> =
+        ADAPTER_WATCHERS.lock().await.drain().collect();
+    for (id, watcher) in adapter_watchers {
+        let _ = watcher.stop_tx.send(true);
+        let abort_handle = watcher.handle.abort_handle();
+        if tokio::time::timeout(Duration::from_secs(10), watcher.handle)
+            .await
+            .is_err()
+        {
+            log::warn!("BLE I/O: adapter watcher did not stop in time, aborting adapter_id={id}");
+            abort_handle.abort();
+        }
+    }
+
     log::debug!("BLE I/O: all monitors stopped");
 }
 
@@ -780,15 +2020,60 @@ async fn stop_battery_notification_monitor_internal(id: &str) {
     }
 }
 
+async fn list_adapters(
+    transport: &dyn BleTransport,
+    selected: Option<&str>,
+) -> Vec<BluetoothAdapterInfo> {
+    transport
+        .adapters()
+        .await
+        .iter()
+        .enumerate()
+        .map(|(index, adapter)| BluetoothAdapterInfo {
+            id: adapter.id(),
+            name: adapter.name(),
+            selected: selected.map_or(index == 0, |id| id == adapter.id()),
+        })
+        .collect()
+}
+
+/// Adapters the app can use, the default one first.
+#[tauri::command]
+pub async fn list_bluetooth_adapters() -> Vec<BluetoothAdapterInfo> {
+    let selected = SELECTED_ADAPTER.lock().await.clone();
+    list_adapters(ble_transport::transport().as_ref(), selected.as_deref()).await
+}
+
+/// Use adapter `id` (None for the default adapter) for listing, scans and
+/// monitors started from now on. Running monitors stay on the adapter they
+/// were started on until they are stopped. An adapter that is not present
+/// yet, like an unplugged dongle, is used once it appears.
+#[tauri::command]
+pub async fn select_bluetooth_adapter(app: AppHandle, id: Option<String>) {
+    log::debug!("BLE I/O: adapter selected id={id:?}");
+    *SELECTED_ADAPTER.lock().await = id.clone();
+    // Report the state of the adapter new monitors will use, even before any
+    // monitor starts.
+    let adapter_id = match id {
+        Some(id) => Some(id),
+        None => ble_transport::transport()
+            .default_adapter()
+            .await
+            .map(|adapter| adapter.id()),
+    };
+    if let Some(adapter_id) = adapter_id {
+        watch_adapter_state(Arc::new(app), &adapter_id).await;
+    }
+}
+
 #[tauri::command]
-pub async fn list_battery_devices() -> Result<Vec<BleDeviceInfo>, String> {
+pub async fn list_battery_devices() -> Result<Vec<BleDeviceInfo>, CommandError> {
     let adapter = get_adapter().await?;
 
     log::debug!("BLE I/O: list connected battery devices request");
     let devices = adapter
         .connected_devices_with_services(&[BATTERY_SERVICE_UUID, BATTERY_LEVEL_UUID])
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     let mut result = Vec::new();
 
@@ -797,274 +2082,1058 @@ pub async fn list_battery_devices() -> Result<Vec<BleDeviceInfo>, String> {
             Ok(n) => n.to_string(),
             Err(_) => continue,
         };
-        let id = format_device_id_for_store(&device);
-        result.push(BleDeviceInfo { name, id });
+        let id = format_device_id_for_store(device.as_ref());
+        let metadata = ble_device_info::cached_metadata(&id).await;
+        result.push(BleDeviceInfo { name, id, metadata });
     }
     log::debug!("BLE I/O: list connected battery devices response count={}", result.len());
 
     Ok(result)
 }
 
-#[tauri::command]
-pub async fn get_battery_info(id: String) -> Result<Vec<BatteryInfo>, String> {
-    let adapter = get_adapter().await?;
-    let target_device = get_target_device(&adapter, &id).await?;
+async fn read_battery_info_from_adapter(
+    adapter: &dyn BleAdapter,
+    id: &str,
+    readings: &ReadingPipeline,
+) -> Result<Vec<BatteryInfo>, CommandError> {
+    let target_device = get_target_device(adapter, id).await?;
 
     log::debug!("BLE I/O: connect request (polling) device_id={id}");
-    adapter
-        .connect_device(&target_device)
-        .await
-        .map_err(|e| e.to_string())?;
+    target_device.connect().await?;
     log::debug!("BLE I/O: connect response success (polling) device_id={id}");
 
-    let contexts = get_battery_characteristic_contexts(&target_device).await?;
-    let battery_infos = read_battery_infos_strict(&contexts).await?;
+    // Disconnect whether or not the levels could be read.
+    let battery_infos = async {
+        let contexts = get_battery_characteristic_contexts(target_device.as_ref()).await?;
+        read_battery_infos_strict(&contexts, readings).await
+    }
+    .await;
 
     log::debug!("BLE I/O: disconnect request (polling) device_id={id}");
-    disconnect_device(&adapter, &target_device).await;
+    disconnect_device(target_device.as_ref()).await;
     log::debug!("BLE I/O: disconnect response success (polling) device_id={id}");
 
-    Ok(battery_infos)
+    battery_infos
 }
 
+async fn read_device_metadata_from_adapter(
+    adapter: &dyn BleAdapter,
+    id: &str,
+) -> Result<DeviceMetadata, CommandError> {
+    let target_device = get_target_device(adapter, id).await?;
+
+    log::debug!("BLE I/O: connect request (metadata) device_id={id}");
+    target_device.connect().await?;
+    log::debug!("BLE I/O: connect response success (metadata) device_id={id}");
+
+    let metadata = ble_device_info::read_device_metadata(target_device.as_ref()).await;
+
+    log::debug!("BLE I/O: disconnect request (metadata) device_id={id}");
+    disconnect_device(target_device.as_ref()).await;
+    log::debug!("BLE I/O: disconnect response success (metadata) device_id={id}");
+
+    metadata
+}
+
+/// Device Information and Appearance of a connected device. Served from the
+/// per-device cache unless `refresh` is set (e.g. after a firmware update).
 #[tauri::command]
-pub async fn start_battery_notification_monitor(
-    app: AppHandle,
+pub async fn get_device_metadata(
     id: String,
-) -> Result<Vec<BatteryInfo>, String> {
-    log::debug!("BLE I/O: start notification monitor request device_id={}", id);
-    let adapter = get_adapter().await?;
+    refresh: Option<bool>,
+) -> Result<DeviceMetadata, CommandError> {
+    if !refresh.unwrap_or(false) {
+        if let Some(metadata) = ble_device_info::cached_metadata(&id).await {
+            log::debug!("BLE I/O: metadata served from cache device_id={id}");
+            return Ok(metadata);
+        }
+    }
 
-    stop_battery_notification_monitor_internal(&id).await;
+    let adapter = get_device_adapter(&id).await?;
+    let metadata = read_device_metadata_from_adapter(adapter.as_ref(), &id).await?;
+    ble_device_info::cache_metadata(&id, metadata.clone()).await;
+    Ok(metadata)
+}
 
-    let (stop_tx, stop_rx) = watch::channel(false);
-    let initial_battery_infos;
+/// Dump the full GATT tree of a connected device, with values read and
+/// decoded where possible, for attaching to bug reports.
+#[tauri::command]
+pub async fn inspect_gatt_device(id: String) -> Result<GattInspection, CommandError> {
+    let read_lock = READ_LOCKS.lock().await.entry(id.clone()).or_default().clone();
+    let _guard = read_lock.lock().await;
 
-    // Try to read initial battery info if the device is currently connected.
-    match get_target_device(&adapter, &id).await {
-        Ok(target_device) => {
-            log::debug!("BLE I/O: connect request (notification) device_id={id}");
-            adapter
-                .connect_device(&target_device)
-                .await
-                .map_err(|e| e.to_string())?;
-            log::debug!("BLE I/O: connect response success (notification) device_id={id}");
+    let adapter = get_device_adapter(&id).await?;
+    let target_device = get_target_device(adapter.as_ref(), &id).await?;
 
-            let contexts = get_battery_characteristic_contexts(&target_device).await?;
-            if contexts.is_empty() {
-                return Err("Battery level characteristic not found".to_string());
-            }
+    log::debug!("BLE I/O: connect request (inspect) device_id={id}");
+    target_device.connect().await?;
+    log::debug!("BLE I/O: connect response success (inspect) device_id={id}");
 
-            initial_battery_infos = read_battery_infos_best_effort(&contexts).await;
+    let inspection = ble_inspector::inspect_device(target_device.as_ref()).await;
 
-            // Verify that at least one characteristic supports notifications.
-            let mut has_notify = false;
-            for context in contexts.iter() {
-                if let Ok(props) = context.characteristic.properties().await {
-                    if props.notify || props.indicate {
-                        has_notify = true;
-                        break;
-                    }
-                }
-            }
+    log::debug!("BLE I/O: disconnect request (inspect) device_id={id}");
+    disconnect_device(target_device.as_ref()).await;
+    log::debug!("BLE I/O: disconnect response success (inspect) device_id={id}");
 
-            if !has_notify {
-                return Err(
-                    "Battery level notification is not supported by this device".to_string(),
-                );
-            }
-        }
-        Err(e) => {
-            log::info!(
-                "BLE I/O: device not found at startup, connection watcher will discover it device_id={id}: {e}"
-            );
-            initial_battery_infos = vec![];
-        }
-    }
+    inspection
+}
 
-    // Always use the connection watcher so that reconnections after a power-off
-    // cycle obtain a fresh Device handle instead of reusing a potentially stale
-    // one.
-    let app_c = app.clone();
-    let adapter_c = adapter.clone();
-    let id_c = id.clone();
-    let stop_rx_c = stop_rx.clone();
+/// Fingerprint of a connected device. Metadata comes from the cache when it
+/// was read before.
+async fn read_fingerprint_from_device(
+    device: &dyn BleDevice,
+) -> Result<DeviceFingerprint, CommandError> {
+    let id = format_device_id_for_store(device);
+    let name = device.name()?;
 
-    let join_handles = vec![tokio::spawn(async move {
-        battery_connection_watcher(app_c, adapter_c, id_c, stop_rx_c).await;
-    })];
+    log::debug!("BLE I/O: connect request (fingerprint) device_id={id}");
+    device.connect().await?;
+    log::debug!("BLE I/O: connect response success (fingerprint) device_id={id}");
 
-    {
-        let mut monitors = MONITORS.lock().await;
-        monitors.insert(id, MonitorTask { stop_tx, join_handles });
-    }
+    // Disconnect whether or not the device could be fingerprinted.
+    let fingerprint = read_fingerprint_from_connected_device(device, &id, name).await;
 
-    log::debug!("BLE I/O: start notification monitor response success");
+    log::debug!("BLE I/O: disconnect request (fingerprint) device_id={id}");
+    disconnect_device(device).await;
+    log::debug!("BLE I/O: disconnect response success (fingerprint) device_id={id}");
 
-    Ok(initial_battery_infos)
+    fingerprint
 }
 
-#[tauri::command]
-pub async fn stop_battery_notification_monitor(id: String) -> Result<(), String> {
-    log::debug!("BLE I/O: stop notification monitor request device_id={id}");
-    stop_battery_notification_monitor_internal(&id).await;
-    log::debug!("BLE I/O: stop notification monitor response success device_id={id}");
-    Ok(())
+async fn read_fingerprint_from_connected_device(
+    device: &dyn BleDevice,
+    id: &str,
+    name: String,
+) -> Result<DeviceFingerprint, CommandError> {
+    let contexts = get_battery_characteristic_contexts(device).await?;
+    let metadata = match ble_device_info::cached_metadata(id).await {
+        Some(metadata) => metadata,
+        None => {
+            let metadata = ble_device_info::read_device_metadata(device).await?;
+            ble_device_info::cache_metadata(id, metadata.clone()).await;
+            metadata
+        }
+    };
+
+    let layout = contexts.into_iter().map(|c| c.user_description).collect();
+    Ok(DeviceFingerprint::new(name, &metadata, layout))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::time::Duration;
-    use tokio::sync::watch;
+/// Fingerprint the connected battery devices on `adapter` that are not
+/// registered and match them against the registered ones. Devices that
+/// cannot be fingerprinted are skipped.
+async fn find_identity_matches_on_adapter(
+    adapter: &dyn BleAdapter,
+    registered: &[RegisteredIdentity],
+) -> Result<Vec<IdentityMatch>, CommandError> {
+    let devices = adapter
+        .connected_devices_with_services(&[BATTERY_SERVICE_UUID, BATTERY_LEVEL_UUID])
+        .await?;
 
-    #[test]
-    fn first_worker_connect_flips_aggregate_to_connected() {
-        let mut state = MonitorConnectionState::default();
-        assert_eq!(state.apply_worker_connection(0, true), Some(true));
-        assert!(state.is_connected);
+    let mut seen = Vec::new();
+    for device in devices {
+        let id = format_device_id_for_store(device.as_ref());
+        if registered.iter().any(|r| r.id == id) {
+            continue;
+        }
+        match read_fingerprint_from_device(device.as_ref()).await {
+            Ok(fingerprint) => seen.push((id, fingerprint)),
+            Err(e) => log::debug!("BLE I/O: fingerprint failed device_id={id}: {e}"),
+        }
     }
+    Ok(ble_identity::find_matches(&seen, registered))
+}
 
-    #[test]
-    fn second_worker_connect_does_not_re_emit() {
-        let mut state = MonitorConnectionState::default();
-        state.apply_worker_connection(0, true);
-        assert_eq!(state.apply_worker_connection(1, true), None);
-    }
+/// Fingerprint of a connected device, to keep with its registration.
+#[tauri::command]
+pub async fn get_device_fingerprint(id: String) -> Result<DeviceFingerprint, CommandError> {
+    let adapter = get_device_adapter(&id).await?;
+    let device = get_target_device(adapter.as_ref(), &id).await?;
+    read_fingerprint_from_device(device.as_ref()).await
+}
 
-    #[test]
-    fn partial_disconnect_keeps_connected() {
-        let mut state = MonitorConnectionState::default();
-        state.apply_worker_connection(0, true);
-        state.apply_worker_connection(1, true);
-        assert_eq!(state.apply_worker_connection(0, false), None);
-        assert!(state.is_connected);
-    }
+/// Connected devices that are not registered but look like a registered one,
+/// e.g. a keyboard that was re-paired and came back under a new id.
+#[tauri::command]
+pub async fn find_device_identity_matches(
+    registered: Vec<RegisteredIdentity>,
+) -> Result<Vec<IdentityMatch>, CommandError> {
+    let adapter = get_adapter().await?;
+    find_identity_matches_on_adapter(adapter.as_ref(), &registered).await
+}
 
-    #[test]
-    fn last_worker_disconnect_flips_to_disconnected() {
-        let mut state = MonitorConnectionState::default();
-        state.apply_worker_connection(0, true);
-        state.apply_worker_connection(1, true);
-        state.apply_worker_connection(0, false);
-        assert_eq!(state.apply_worker_connection(1, false), Some(false));
-        assert!(!state.is_connected);
+/// Move a per-device setting stored under `from` to `to`.
+async fn move_device_setting<T>(settings: &Mutex<HashMap<String, T>>, from: &str, to: &str) {
+    let mut settings = settings.lock().await;
+    if let Some(value) = settings.remove(from) {
+        settings.insert(to.to_string(), value);
     }
+}
 
-    #[test]
-    fn disconnect_of_unknown_worker_is_noop() {
-        let mut state = MonitorConnectionState::default();
-        assert_eq!(state.apply_worker_connection(7, false), None);
-        assert!(!state.is_connected);
+/// Carry a registered device over to the new id it came back under: its
+/// battery history, settings, reading filter state, cached metadata and
+/// running monitor. Returns
+/// the monitor's initial snapshot when one was moved.
+#[tauri::command]
+pub async fn link_device_identity(
+    app: AppHandle,
+    from_id: String,
+    from_name: String,
+    to_id: String,
+    to_name: String,
+) -> Result<Option<Vec<BatteryInfo>>, CommandError> {
+    if from_id == to_id {
+        return Ok(None);
     }
+    log::info!("BLE I/O: linking device identity from_id={from_id} to_id={to_id}");
 
-    #[test]
-    fn reconnect_after_full_disconnect_emits_again() {
-        let mut state = MonitorConnectionState::default();
-        state.apply_worker_connection(0, true);
-        state.apply_worker_connection(0, false);
-        assert_eq!(state.apply_worker_connection(0, true), Some(true));
-    }
+    // The only step that can fail goes first, so a failed link leaves the
+    // device as it was.
+    history::migrate_battery_history(&app, &from_name, &from_id, &to_name, &to_id)?;
 
-    #[test]
-    fn ever_connected_starts_false_and_latches_on_connect() {
-        let mut state = MonitorConnectionState::default();
-        assert!(!state.ever_connected);
-        state.apply_worker_connection(0, true);
-        assert!(state.ever_connected);
-        state.apply_worker_connection(0, false);
-        assert!(state.ever_connected);
+    let was_monitored = MONITORS.lock().await.contains_key(&from_id);
+    if was_monitored {
+        stop_battery_notification_monitor_internal(&from_id).await;
     }
 
-    #[test]
-    fn ever_connected_stays_false_when_only_disconnects_applied() {
-        let mut state = MonitorConnectionState::default();
-        state.apply_worker_connection(0, false);
-        assert!(!state.ever_connected);
-    }
+    move_device_setting(&MONITOR_OPTIONS, &from_id, &to_id).await;
+    move_device_setting(&RECONNECT_POLICIES, &from_id, &to_id).await;
+    move_device_setting(&BATTERY_SOURCES, &from_id, &to_id).await;
+    move_device_setting(&READING_PIPELINES, &from_id, &to_id).await;
+    ble_device_info::move_cached_metadata(&from_id, &to_id).await;
 
-    #[test]
-    fn bytes_to_hex_formats_uppercase_space_separated() {
-        assert_eq!(bytes_to_hex(&[0x00, 0xAB, 0x05]), "00 AB 05");
-        assert_eq!(bytes_to_hex(&[]), "");
+    if !was_monitored {
+        return Ok(None);
     }
+    start_battery_notification_monitor(app, to_id).await.map(Some)
+}
 
-    #[test]
-    fn sanitize_device_text_passes_normal_names() {
-        assert_eq!(sanitize_device_text("Central"), "Central");
-        assert_eq!(sanitize_device_text("Peripheral"), "Peripheral");
-        assert_eq!(sanitize_device_text("Left half"), "Left half");
-    }
+/// Actively scan for advertising devices, including keyboards that are bonded
+/// but connected to another host. Opt-in and time-boxed: the connection
+/// watcher keeps using the cheap connected-device query (see
+/// `battery_connection_watcher`). Results are emitted as
+/// `battery-device-scan-result` events as they arrive and returned at the end.
+#[tauri::command]
+pub async fn scan_battery_devices(
+    app: AppHandle,
+    timeout_secs: Option<u64>,
+) -> Result<Vec<ScannedDeviceInfo>, CommandError> {
+    let stop_rx = {
+        let mut active_scan = ACTIVE_SCAN.lock().await;
+        if active_scan.is_some() {
+            return Err(CommandError::Busy(
+                "A Bluetooth scan is already running".to_string(),
+            ));
+        }
+        let (stop_tx, stop_rx) = watch::channel(false);
+        *active_scan = Some(stop_tx);
+        stop_rx
+    };
 
-    #[test]
-    fn sanitize_device_text_strips_leading_triggers() {
-        assert_eq!(sanitize_device_text("=X"), "X");
-        assert_eq!(sanitize_device_text("+X"), "X");
-        assert_eq!(sanitize_device_text("-X"), "X");
-        assert_eq!(sanitize_device_text("@X"), "X");
-        assert_eq!(sanitize_device_text("\tX"), "X");
-        assert_eq!(sanitize_device_text("=+-@X"), "X");
-        assert_eq!(sanitize_device_text("=cmd()"), "cmd()");
-    }
+    let duration = Duration::from_secs(
+        timeout_secs
+            .unwrap_or(DEFAULT_SCAN_SECS)
+            .clamp(1, MAX_SCAN_SECS),
+    );
+    let result = match get_adapter().await {
+        Ok(adapter) => {
+            scan_for_battery_devices(adapter.as_ref(), duration, stop_rx, |info| {
+                let _ = app.emit(BATTERY_DEVICE_SCAN_RESULT_EVENT, info.clone());
+            })
+            .await
+        }
+        Err(e) => Err(e),
+    };
 
-    #[test]
-    fn sanitize_device_text_preserves_interior_chars() {
-        assert_eq!(sanitize_device_text("Left-half"), "Left-half");
-        assert_eq!(sanitize_device_text("A=B"), "A=B");
+    ACTIVE_SCAN.lock().await.take();
+    result
+}
+
+#[tauri::command]
+pub async fn stop_battery_device_scan() {
+    if let Some(stop_tx) = ACTIVE_SCAN.lock().await.as_ref() {
+        log::debug!("BLE I/O: sending stop signal to scan");
+        let _ = stop_tx.send(true);
     }
+}
 
-    #[test]
-    fn notification_with_data_emits_first_byte() {
-        let user_description = Some("Central".to_string());
-        let outcome =
-            classify_notification_item(Some(Ok(vec![87, 1, 2])), &user_description);
+async fn monitor_options(id: &str) -> MonitorOptions {
+    MONITOR_OPTIONS
+        .lock()
+        .await
+        .get(id)
+        .copied()
+        .unwrap_or_default()
+}
 
-        match outcome {
-            NotificationOutcome::Emit(info) => {
-                assert_eq!(info.battery_level, Some(87));
-                assert_eq!(info.user_description, user_description);
-            }
-            NotificationOutcome::Stop => panic!("notification data should emit battery info"),
+/// Filter state of `id`, started afresh when `filter` changed.
+async fn reading_pipeline(id: &str, filter: ReadingFilter) -> Arc<ReadingPipeline> {
+    let mut pipelines = READING_PIPELINES.lock().await;
+    match pipelines.get(id) {
+        Some(pipeline) if pipeline.filter() == filter => pipeline.clone(),
+        _ => {
+            let pipeline = Arc::new(ReadingPipeline::new(filter));
+            pipelines.insert(id.to_string(), pipeline.clone());
+            pipeline
         }
     }
+}
 
-    #[test]
-    fn notification_with_empty_data_emits_none_level() {
-        let user_description = Some("Peripheral".to_string());
-        let outcome = classify_notification_item(Some(Ok(vec![])), &user_description);
-
-        match outcome {
-            NotificationOutcome::Emit(info) => {
-                assert_eq!(info.battery_level, None);
-                assert_eq!(info.user_description, user_description);
-            }
-            NotificationOutcome::Stop => panic!("empty notification data should emit battery info"),
-        }
+/// Set the poll interval, verification delay, reading filter and event
+/// coalescing of the notification monitor for `id`. Takes effect on the next
+/// monitor start.
+#[tauri::command]
+pub async fn set_monitor_options(
+    id: String,
+    options: MonitorOptions,
+) -> Result<(), CommandError> {
+    if options.poll_interval_secs == 0 {
+        return Err(CommandError::InvalidArgument(
+            "Poll interval must be at least one second".to_string(),
+        ));
     }
+    options.reading_filter.validate()?;
+    options.coalesce.validate()?;
+
+    log::debug!("BLE I/O: monitor options set device_id={id} options={options:?}");
+    let mut all_options = MONITOR_OPTIONS.lock().await;
+    if options == MonitorOptions::default() {
+        all_options.remove(&id);
+    } else {
+        all_options.insert(id, options);
+    }
+    Ok(())
+}
 
-    #[test]
-    fn notification_error_stops_worker() {
-        let outcome = classify_notification_item(
-            Some(Err(bluest::error::ErrorKind::Other.into())),
-            &None,
-        );
+async fn reconnect_policy(id: &str) -> ReconnectPolicy {
+    RECONNECT_POLICIES
+        .lock()
+        .await
+        .get(id)
+        .copied()
+        .unwrap_or_default()
+}
 
-        assert!(matches!(outcome, NotificationOutcome::Stop));
+/// Set how the connection watcher of `id` retries while the device is away.
+/// Takes effect on the next monitor start.
+#[tauri::command]
+pub async fn set_reconnect_policy(
+    id: String,
+    policy: ReconnectPolicy,
+) -> Result<(), CommandError> {
+    policy.validate()?;
+
+    log::debug!("BLE I/O: reconnect policy set device_id={id} policy={policy:?}");
+    let mut policies = RECONNECT_POLICIES.lock().await;
+    if policy == ReconnectPolicy::default() {
+        policies.remove(&id);
+    } else {
+        policies.insert(id, policy);
     }
+    Ok(())
+}
 
-    #[test]
-    fn notification_stream_end_stops_worker() {
-        let outcome = classify_notification_item(None, &None);
+#[cfg(not(target_os = "linux"))]
+fn bluez_unsupported() -> CommandError {
+    CommandError::Unsupported("The BlueZ battery source is only available on Linux".to_string())
+}
 
-        assert!(matches!(outcome, NotificationOutcome::Stop));
-    }
+async fn battery_source(id: &str) -> BatterySource {
+    BATTERY_SOURCES
+        .lock()
+        .await
+        .get(id)
+        .copied()
+        .unwrap_or_default()
+}
 
-    #[test]
-    fn connection_wait_connected_proceeds() {
-        assert_eq!(
-            classify_connection_wait_event(Some(bluest::ConnectionEvent::Connected)),
-            ConnectionWaitOutcome::Proceed
-        );
+/// Choose where the battery level of `id` is read from. Takes effect on the
+/// next read or monitor start.
+#[tauri::command]
+pub async fn set_battery_source(id: String, source: BatterySource) -> Result<(), CommandError> {
+    #[cfg(not(target_os = "linux"))]
+    if source == BatterySource::Bluez {
+        return Err(bluez_unsupported());
+    }
+
+    log::debug!("BLE I/O: battery source set device_id={id} source={source:?}");
+    let mut sources = BATTERY_SOURCES.lock().await;
+    if source == BatterySource::default() {
+        sources.remove(&id);
+    } else {
+        sources.insert(id, source);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn bluez_battery_info(level: u8) -> BatteryInfo {
+    BatteryInfo {
+        battery_level: Some(level),
+        user_description: None,
+        part_key: battery_part_key(0, 0),
+        presentation: None,
+        power_state: PowerState::Unknown,
+        raw_battery_level: Some(level),
+        reading_flag: None,
+    }
+}
+
+/// Turns readings from a battery source outside GATT into the same monitor
+/// events the GATT workers emit. `None` means the source lost the device.
+struct ExternalBatteryForwarder {
+    events: Arc<dyn BatteryEventSink>,
+    device_id: String,
+    connected: bool,
+}
+
+impl ExternalBatteryForwarder {
+    fn new(events: Arc<dyn BatteryEventSink>, device_id: &str) -> Self {
+        Self {
+            events,
+            device_id: device_id.to_string(),
+            connected: false,
+        }
+    }
+
+    fn update(&mut self, battery_info: Option<BatteryInfo>) {
+        if battery_info.is_some() != self.connected {
+            self.connected = battery_info.is_some();
+            self.events.monitor_status(BatteryMonitorStatusEvent::new(
+                self.device_id.clone(),
+                self.connected,
+            ));
+        }
+        if let Some(battery_info) = battery_info {
+            self.events.battery_info(BatteryInfoNotificationEvent {
+                id: self.device_id.clone(),
+                battery_infos: vec![battery_info],
+            });
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn bluez_battery_watcher(
+    events: Arc<dyn BatteryEventSink>,
+    connection: zbus::Connection,
+    device_id: String,
+    stop_rx: watch::Receiver<bool>,
+) {
+    let mut forwarder = ExternalBatteryForwarder::new(events, &device_id);
+    bluez_battery::watch_percentage(&connection, &device_id, stop_rx, |level| {
+        forwarder.update(level.map(bluez_battery_info));
+    })
+    .await;
+}
+
+fn power_supply_battery_info(battery: &HidBattery) -> BatteryInfo {
+    BatteryInfo {
+        battery_level: battery.battery_level,
+        user_description: None,
+        part_key: battery_part_key(0, 0),
+        presentation: None,
+        power_state: battery.power_state,
+        raw_battery_level: battery.battery_level,
+        reading_flag: None,
+    }
+}
+
+async fn power_supply_battery_watcher(
+    events: Arc<dyn BatteryEventSink>,
+    root: std::path::PathBuf,
+    device_id: String,
+    stop_rx: watch::Receiver<bool>,
+) {
+    let mut forwarder = ExternalBatteryForwarder::new(events, &device_id);
+    power_supply_battery::watch_battery(
+        &root,
+        &device_id,
+        power_supply_battery::POLL_INTERVAL,
+        stop_rx,
+        |battery| forwarder.update(battery.map(power_supply_battery_info)),
+    )
+    .await;
+}
+
+async fn start_power_supply_battery_monitor(
+    events: Arc<dyn BatteryEventSink>,
+    id: String,
+) -> Result<Vec<BatteryInfo>, CommandError> {
+    stop_battery_notification_monitor_internal(&id).await;
+
+    let root = power_supply_battery::power_supply_root();
+    let initial_battery_infos = match power_supply_battery::read_battery(&root, &id) {
+        Ok(battery) => vec![power_supply_battery_info(&battery)],
+        Err(e) => {
+            log::info!("power_supply: no battery at monitor start, watcher will wait for it device_id={id}: {e}");
+            vec![]
+        }
+    };
+
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let id_c = id.clone();
+    let join_handles = vec![tokio::spawn(async move {
+        power_supply_battery_watcher(events, root, id_c, stop_rx).await;
+    })];
+    MONITORS
+        .lock()
+        .await
+        .insert(
+            id,
+            MonitorTask {
+                stop_tx,
+                join_handles,
+                adapter_id: None,
+            },
+        );
+
+    Ok(initial_battery_infos)
+}
+
+/// HID batteries the kernel currently exposes, for matching against
+/// registered devices. Empty where the power_supply class does not exist.
+#[tauri::command]
+pub async fn list_hid_batteries() -> Vec<HidBattery> {
+    power_supply_battery::enumerate(&power_supply_battery::power_supply_root())
+}
+
+#[cfg(target_os = "linux")]
+async fn start_bluez_battery_monitor(
+    events: Arc<dyn BatteryEventSink>,
+    id: String,
+) -> Result<Vec<BatteryInfo>, CommandError> {
+    stop_battery_notification_monitor_internal(&id).await;
+
+    let connection = bluez_battery::system_bus().await?;
+    let initial_battery_infos = match bluez_battery::read_percentage(&connection, &id).await {
+        Ok(level) => vec![bluez_battery_info(level)],
+        Err(e) => {
+            log::info!("BlueZ: no battery at monitor start, watcher will wait for it device_id={id}: {e}");
+            vec![]
+        }
+    };
+
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let id_c = id.clone();
+    let join_handles = vec![tokio::spawn(async move {
+        bluez_battery_watcher(events, connection, id_c, stop_rx).await;
+    })];
+    MONITORS
+        .lock()
+        .await
+        .insert(
+            id,
+            MonitorTask {
+                stop_tx,
+                join_handles,
+                adapter_id: None,
+            },
+        );
+
+    Ok(initial_battery_infos)
+}
+
+#[tauri::command]
+pub async fn get_battery_info(id: String) -> Result<Vec<BatteryInfo>, CommandError> {
+    let read = BATTERY_READS.run(&id, read_battery_info(id.clone()));
+    ble_scheduler::with_priority(Priority::UserRefresh, read).await
+}
+
+/// `get_battery_info` for the backend poller, queued behind user refreshes and
+/// monitor setup. Joins a read of the device already in progress either way,
+/// and a user refresh joining this one raises it to its own priority.
+pub(crate) async fn poll_battery_info(id: String) -> ReadResult {
+    let read = BATTERY_READS.run(&id, read_battery_info(id.clone()));
+    ble_scheduler::with_priority(Priority::BackgroundPoll, read).await
+}
+
+/// Read several devices in one call, each once and in request order. Reads
+/// run concurrently while their GATT operations take turns on each adapter,
+/// and each device reports its own infos or error.
+#[tauri::command]
+pub async fn get_battery_info_many(ids: Vec<String>) -> Vec<BatteryReadResult> {
+    let reads = ble_batch::unique_ids(ids).into_iter().map(|id| async move {
+        let result = get_battery_info(id.clone()).await;
+        BatteryReadResult::new(id, result)
+    });
+    futures_util::future::join_all(reads).await
+}
+
+async fn read_battery_info(id: String) -> ReadResult {
+    let read_lock = READ_LOCKS.lock().await.entry(id.clone()).or_default().clone();
+    let _guard = read_lock.lock().await;
+    match battery_source(&id).await {
+        BatterySource::Gatt => {}
+        #[cfg(target_os = "linux")]
+        BatterySource::Bluez => {
+            let connection = bluez_battery::system_bus().await?;
+            let level = bluez_battery::read_percentage(&connection, &id).await?;
+            return Ok(vec![bluez_battery_info(level)]);
+        }
+        #[cfg(not(target_os = "linux"))]
+        BatterySource::Bluez => {
+            return Err(bluez_unsupported())
+        }
+        BatterySource::PowerSupply => {
+            let root = power_supply_battery::power_supply_root();
+            let battery = power_supply_battery::read_battery(&root, &id)?;
+            return Ok(vec![power_supply_battery_info(&battery)]);
+        }
+    }
+
+    let adapter = get_device_adapter(&id).await?;
+    let readings = reading_pipeline(&id, monitor_options(&id).await.reading_filter).await;
+    read_battery_info_from_adapter(adapter.as_ref(), &id, &readings).await
+}
+
+/// Initial infos of a device a monitor starts on while it is connected.
+async fn read_monitor_startup_infos(
+    target_device: &dyn BleDevice,
+    id: &str,
+) -> Result<Vec<BatteryInfo>, CommandError> {
+    log::debug!("BLE I/O: connect request (notification) device_id={id}");
+    target_device.connect().await?;
+    log::debug!("BLE I/O: connect response success (notification) device_id={id}");
+
+    let contexts = get_battery_characteristic_contexts(target_device).await?;
+    if contexts.is_empty() {
+        return Err(CommandError::CharacteristicNotFound(
+            "Battery Level (0x2A19)".to_string(),
+        ));
+    }
+
+    // The watcher filters with its own state; this read has nothing before it
+    // to compare with.
+    let readings = ReadingPipeline::new(monitor_options(id).await.reading_filter);
+    Ok(read_battery_infos_best_effort(&contexts, &readings).await)
+}
+
+#[tauri::command]
+pub async fn start_battery_notification_monitor(
+    app: AppHandle,
+    id: String,
+) -> Result<Vec<BatteryInfo>, CommandError> {
+    log::debug!("BLE I/O: start notification monitor request device_id={}", id);
+    match battery_source(&id).await {
+        BatterySource::Gatt => {}
+        #[cfg(target_os = "linux")]
+        BatterySource::Bluez => return start_bluez_battery_monitor(Arc::new(app), id).await,
+        #[cfg(not(target_os = "linux"))]
+        BatterySource::Bluez => {
+            return Err(bluez_unsupported())
+        }
+        BatterySource::PowerSupply => {
+            return start_power_supply_battery_monitor(Arc::new(app), id).await
+        }
+    }
+
+    let adapter = get_device_adapter(&id).await?;
+
+    stop_battery_notification_monitor_internal(&id).await;
+
+    let (stop_tx, stop_rx) = watch::channel(false);
+
+    // Try to read initial battery info if the device is currently connected.
+    let initial_battery_infos = match get_target_device(adapter.as_ref(), &id).await {
+        Ok(target_device) => {
+            ble_scheduler::with_priority(
+                Priority::NotificationSetup,
+                read_monitor_startup_infos(target_device.as_ref(), &id),
+            )
+            .await?
+        }
+        Err(e) => {
+            log::info!(
+                "BLE I/O: device not found at startup, connection watcher will discover it device_id={id}: {e}"
+            );
+            vec![]
+        }
+    };
+
+    // Always use the connection watcher so that reconnections after a power-off
+    // cycle obtain a fresh Device handle instead of reusing a potentially stale
+    // one.
+    let events: Arc<dyn BatteryEventSink> = Arc::new(app);
+    let id_c = id.clone();
+    let stop_rx_c = stop_rx.clone();
+    let options = monitor_options(&id).await;
+    let reconnect = reconnect_policy(&id).await;
+    let adapter_id = adapter.id();
+    let adapter_state = watch_adapter_state(events.clone(), &adapter_id).await;
+    let presence = watch_presence(&adapter).await;
+
+    // The watcher connects and subscribes; its workers lower their own
+    // priority.
+    let join_handles = vec![tokio::spawn(ble_scheduler::with_priority(
+        Priority::NotificationSetup,
+        battery_connection_watcher(
+            events,
+            presence,
+            id_c,
+            options,
+            reconnect,
+            adapter_state,
+            stop_rx_c,
+        ),
+    ))];
+
+    {
+        let mut monitors = MONITORS.lock().await;
+        monitors.insert(
+            id,
+            MonitorTask {
+                stop_tx,
+                join_handles,
+                adapter_id: Some(adapter_id),
+            },
+        );
+    }
+
+    log::debug!("BLE I/O: start notification monitor response success");
+
+    Ok(initial_battery_infos)
+}
+
+#[tauri::command]
+pub async fn stop_battery_notification_monitor(id: String) -> Result<(), CommandError> {
+    log::debug!("BLE I/O: stop notification monitor request device_id={id}");
+    stop_battery_notification_monitor_internal(&id).await;
+    log::debug!("BLE I/O: stop notification monitor response success device_id={id}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble_identity::IdentityConfidence;
+    use crate::ble_power_state::{
+        battery_level_status_value, BATTERY_LEVEL_STATUS_UUID, BATTERY_POWER_STATE_UUID,
+    };
+    use crate::ble_simulated::{SimAction, SimulatedAdapter, SimulatedKeyboard, SimulatedTransport};
+    use crate::ble_trace;
+    use crate::ble_transport::{AdapterEventStream, AdvertisementStream, BleResult};
+    use std::time::Duration;
+    use tokio::sync::{mpsc, watch};
+
+    #[derive(Debug, PartialEq)]
+    enum RecordedEvent {
+        Level(Option<String>, Option<u8>),
+        /// Sent in addition to `Level` when the power state is known.
+        Power(Option<u8>, PowerState),
+        Connected(bool),
+        /// A status event scheduling reconnection attempt `.0`; `.1` is false
+        /// when the watcher gave up instead.
+        Retry(u32, bool),
+        Adapter(AdapterState),
+        Diagnostic(DiagnosticKind),
+    }
+
+    fn record(event: SinkEvent) -> Vec<RecordedEvent> {
+        match event {
+            SinkEvent::BatteryInfo(event) => event
+                .battery_infos
+                .into_iter()
+                .flat_map(|info| {
+                    let power = (info.power_state != PowerState::Unknown)
+                        .then_some(RecordedEvent::Power(info.battery_level, info.power_state));
+                    power.into_iter().chain([RecordedEvent::Level(
+                        info.user_description,
+                        info.battery_level,
+                    )])
+                })
+                .collect(),
+            SinkEvent::MonitorStatus(event) if event.reconnect_attempt > 0 => vec![
+                RecordedEvent::Retry(event.reconnect_attempt, event.next_retry_at.is_some()),
+            ],
+            SinkEvent::MonitorStatus(event) => vec![RecordedEvent::Connected(event.connected)],
+            SinkEvent::AdapterStatus(event) => vec![RecordedEvent::Adapter(event.state)],
+            SinkEvent::Diagnostic(event) => vec![RecordedEvent::Diagnostic(event.kind)],
+        }
+    }
+
+    /// A connection watcher and the state and presence watchers of its adapter.
+    struct WatcherHarness {
+        adapter: SimulatedAdapter,
+        events: mpsc::UnboundedReceiver<RecordedEvent>,
+        stop_tx: watch::Sender<bool>,
+        handle: JoinHandle<()>,
+        adapter_handle: JoinHandle<()>,
+        presence_handle: JoinHandle<()>,
+    }
+
+    impl WatcherHarness {
+        fn start(keyboard: SimulatedKeyboard) -> Self {
+            Self::start_with_options(keyboard, MonitorOptions::default())
+        }
+
+        fn start_with_options(keyboard: SimulatedKeyboard, options: MonitorOptions) -> Self {
+            Self::start_with(keyboard, options, ReconnectPolicy::default())
+        }
+
+        fn start_with(
+            keyboard: SimulatedKeyboard,
+            options: MonitorOptions,
+            reconnect: ReconnectPolicy,
+        ) -> Self {
+            let device_id = keyboard.id.clone();
+            let adapter = SimulatedAdapter::new(vec![keyboard]);
+            let (tx, events) = mpsc::unbounded_channel();
+            let sink: Arc<dyn BatteryEventSink> = Arc::new(RecordingSink::new(tx, record));
+            let (stop_tx, stop_rx) = watch::channel(false);
+            let (state_tx, state_rx) = watch::channel(AdapterState::Available);
+            let adapter_handle = tokio::spawn(adapter_state_watcher(
+                sink.clone(),
+                Arc::new(SimulatedTransport::new(adapter.clone())),
+                adapter.id(),
+                state_tx,
+                stop_rx.clone(),
+            ));
+            let presence = PresenceHub::new();
+            let presence_handle = tokio::spawn(ble_presence::presence_watcher(
+                presence.clone(),
+                Arc::new(adapter.clone()),
+                stop_rx.clone(),
+            ));
+            let handle = tokio::spawn(battery_connection_watcher(
+                sink,
+                presence,
+                device_id,
+                options,
+                reconnect,
+                state_rx,
+                stop_rx,
+            ));
+            Self {
+                adapter,
+                events,
+                stop_tx,
+                handle,
+                adapter_handle,
+                presence_handle,
+            }
+        }
+
+        /// Skip events until `expected` arrives; panics if the watcher goes quiet.
+        async fn expect(&mut self, expected: RecordedEvent) {
+            loop {
+                let event = tokio::time::timeout(Duration::from_secs(60), self.events.recv())
+                    .await
+                    .expect("watcher produced no event")
+                    .expect("event channel closed");
+                if event == expected {
+                    return;
+                }
+            }
+        }
+
+        /// Like `expect`, for events whose relative order is not fixed.
+        async fn expect_all(&mut self, mut expected: Vec<RecordedEvent>) {
+            while !expected.is_empty() {
+                let event = tokio::time::timeout(Duration::from_secs(60), self.events.recv())
+                    .await
+                    .expect("watcher produced no event")
+                    .expect("event channel closed");
+                expected.retain(|e| *e != event);
+            }
+        }
+
+        /// Events received so far, without retry reports.
+        fn drain_without_retries(&mut self) -> Vec<RecordedEvent> {
+            std::iter::from_fn(|| self.events.try_recv().ok())
+                .filter(|event| !matches!(event, RecordedEvent::Retry(..)))
+                .collect()
+        }
+
+        /// Let every spawned worker reach its next await point.
+        async fn settle(&self) {
+            sleep(Duration::from_millis(10)).await;
+        }
+
+        async fn stop(self) {
+            self.stop_tx.send(true).unwrap();
+            for handle in [self.handle, self.adapter_handle, self.presence_handle] {
+                tokio::time::timeout(Duration::from_secs(10), handle)
+                    .await
+                    .expect("watcher did not stop")
+                    .expect("watcher panicked");
+            }
+        }
+    }
+
+    fn peripheral() -> Option<String> {
+        Some("Peripheral 0".to_string())
+    }
+
+    #[test]
+    fn first_worker_connect_flips_aggregate_to_connected() {
+        let mut state = MonitorConnectionState::default();
+        assert_eq!(state.apply_worker_connection(0, true), Some(true));
+        assert!(state.is_connected);
+    }
+
+    #[test]
+    fn second_worker_connect_does_not_re_emit() {
+        let mut state = MonitorConnectionState::default();
+        state.apply_worker_connection(0, true);
+        assert_eq!(state.apply_worker_connection(1, true), None);
+    }
+
+    #[test]
+    fn partial_disconnect_keeps_connected() {
+        let mut state = MonitorConnectionState::default();
+        state.apply_worker_connection(0, true);
+        state.apply_worker_connection(1, true);
+        assert_eq!(state.apply_worker_connection(0, false), None);
+        assert!(state.is_connected);
+    }
+
+    #[test]
+    fn last_worker_disconnect_flips_to_disconnected() {
+        let mut state = MonitorConnectionState::default();
+        state.apply_worker_connection(0, true);
+        state.apply_worker_connection(1, true);
+        state.apply_worker_connection(0, false);
+        assert_eq!(state.apply_worker_connection(1, false), Some(false));
+        assert!(!state.is_connected);
+    }
+
+    #[test]
+    fn disconnect_of_unknown_worker_is_noop() {
+        let mut state = MonitorConnectionState::default();
+        assert_eq!(state.apply_worker_connection(7, false), None);
+        assert!(!state.is_connected);
+    }
+
+    #[test]
+    fn reconnect_after_full_disconnect_emits_again() {
+        let mut state = MonitorConnectionState::default();
+        state.apply_worker_connection(0, true);
+        state.apply_worker_connection(0, false);
+        assert_eq!(state.apply_worker_connection(0, true), Some(true));
+    }
+
+    #[test]
+    fn ever_connected_starts_false_and_latches_on_connect() {
+        let mut state = MonitorConnectionState::default();
+        assert!(!state.ever_connected);
+        state.apply_worker_connection(0, true);
+        assert!(state.ever_connected);
+        state.apply_worker_connection(0, false);
+        assert!(state.ever_connected);
+    }
+
+    #[test]
+    fn ever_connected_stays_false_when_only_disconnects_applied() {
+        let mut state = MonitorConnectionState::default();
+        state.apply_worker_connection(0, false);
+        assert!(!state.ever_connected);
+    }
+
+    #[test]
+    fn bytes_to_hex_formats_uppercase_space_separated() {
+        assert_eq!(bytes_to_hex(&[0x00, 0xAB, 0x05]), "00 AB 05");
+        assert_eq!(bytes_to_hex(&[]), "");
+    }
+
+    #[test]
+    fn sanitize_device_text_passes_normal_names() {
+        assert_eq!(sanitize_device_text("Central"), "Central");
+        assert_eq!(sanitize_device_text("Peripheral"), "Peripheral");
+        assert_eq!(sanitize_device_text("Left half"), "Left half");
+    }
+
+    #[test]
+    fn sanitize_device_text_strips_leading_triggers() {
+        assert_eq!(sanitize_device_text("=X"), "X");
+        assert_eq!(sanitize_device_text("+X"), "X");
+        assert_eq!(sanitize_device_text("-X"), "X");
+        assert_eq!(sanitize_device_text("@X"), "X");
+        assert_eq!(sanitize_device_text("\tX"), "X");
+        assert_eq!(sanitize_device_text("=+-@X"), "X");
+        assert_eq!(sanitize_device_text("=cmd()"), "cmd()");
+    }
+
+    #[test]
+    fn sanitize_device_text_preserves_interior_chars() {
+        assert_eq!(sanitize_device_text("Left-half"), "Left-half");
+        assert_eq!(sanitize_device_text("A=B"), "A=B");
+    }
+
+    fn part_info(user_description: Option<&str>, part_key: &str) -> BatteryInfo {
+        BatteryInfo {
+            battery_level: Some(50),
+            user_description: user_description.map(str::to_string),
+            part_key: part_key.to_string(),
+            presentation: None,
+            power_state: PowerState::Charging,
+            raw_battery_level: Some(50),
+            reading_flag: None,
+        }
+    }
+
+    fn readings() -> ReadingPipeline {
+        ReadingPipeline::new(ReadingFilter::default())
+    }
+
+    #[test]
+    fn notification_with_data_emits_first_byte() {
+        let current = part_info(Some("Central"), "0:0");
+        let outcome = classify_notification_item(Some(Ok(vec![87, 1, 2])), &current, &readings());
+
+        match outcome {
+            NotificationOutcome::Emit(info) => {
+                assert_eq!(info.battery_level, Some(87));
+                assert_eq!(info.raw_battery_level, Some(87));
+                assert_eq!(info.user_description, current.user_description);
+                assert_eq!(info.part_key, "0:0");
+                assert_eq!(info.power_state, PowerState::Charging);
+            }
+            _ => panic!("notification data should emit battery info"),
+        }
+    }
+
+    #[test]
+    fn notification_with_empty_or_out_of_range_data_is_skipped() {
+        let current = part_info(Some("Peripheral"), "0:1");
+        let readings = readings();
+
+        let empty = classify_notification_item(Some(Ok(vec![])), &current, &readings);
+        assert!(matches!(
+            empty,
+            NotificationOutcome::Skip(Reading { flag: Some(ReadingFlag::Empty), .. })
+        ));
+        let out_of_range = classify_notification_item(Some(Ok(vec![0xFF])), &current, &readings);
+        assert!(matches!(
+            out_of_range,
+            NotificationOutcome::Skip(Reading { raw: Some(0xFF), .. })
+        ));
+    }
+
+    #[test]
+    fn notification_error_stops_worker() {
+        let outcome = classify_notification_item(
+            Some(Err(bluest::error::ErrorKind::Other.into())),
+            &part_info(None, "0:0"),
+            &readings(),
+        );
+
+        assert!(matches!(outcome, NotificationOutcome::Stop));
+    }
+
+    #[test]
+    fn notification_stream_end_stops_worker() {
+        let outcome = classify_notification_item(None, &part_info(None, "0:0"), &readings());
+
+        assert!(matches!(outcome, NotificationOutcome::Stop));
+    }
+
+    #[test]
+    fn connection_wait_connected_proceeds() {
+        assert_eq!(
+            classify_connection_wait_event(Some(bluest::ConnectionEvent::Connected)),
+            ConnectionWaitOutcome::Proceed
+        );
     }
 
     #[test]
@@ -1075,74 +3144,1092 @@ mod tests {
         );
     }
 
-    #[test]
-    fn connection_wait_stream_end_retries() {
+    #[test]
+    fn connection_wait_stream_end_retries() {
+        assert_eq!(
+            classify_connection_wait_event(None),
+            ConnectionWaitOutcome::RetryOuter
+        );
+    }
+
+    #[test]
+    fn connection_worker_connected_continues() {
+        assert_eq!(
+            classify_worker_connection_event(Some(bluest::ConnectionEvent::Connected)),
+            WorkerConnectionOutcome::Continue
+        );
+    }
+
+    #[test]
+    fn connection_worker_disconnected_stops() {
+        assert_eq!(
+            classify_worker_connection_event(Some(bluest::ConnectionEvent::Disconnected)),
+            WorkerConnectionOutcome::Stop
+        );
+    }
+
+    #[test]
+    fn connection_worker_stream_end_stops() {
+        assert_eq!(
+            classify_worker_connection_event(None),
+            WorkerConnectionOutcome::Stop
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_for_retry_elapses_returns_false() {
+        let (_tx, mut rx) = watch::channel(false);
+        let result = wait_for_retry_or_stop(&mut rx, Duration::from_secs(5)).await;
+        assert!(!result);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_for_retry_stop_signal_returns_true() {
+        let (tx, mut rx) = watch::channel(false);
+        let handle = tokio::spawn(async move {
+            wait_for_retry_or_stop(&mut rx, Duration::from_secs(60)).await
+        });
+        tx.send(true).unwrap();
+        assert!(handle.await.unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_for_retry_sender_dropped_returns_true() {
+        let (tx, mut rx) = watch::channel(false);
+        let handle = tokio::spawn(async move {
+            wait_for_retry_or_stop(&mut rx, Duration::from_secs(60)).await
+        });
+        drop(tx);
+        assert!(handle.await.unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_for_retry_false_signal_returns_false_immediately() {
+        let (tx, mut rx) = watch::channel(false);
+        let handle = tokio::spawn(async move {
+            wait_for_retry_or_stop(&mut rx, Duration::from_secs(60)).await
+        });
+        tx.send(false).unwrap();
+        tokio::task::yield_now().await;
+        assert!(handle.is_finished());
+        assert!(!handle.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn read_battery_info_reads_every_part_of_simulated_keyboard() {
+        let adapter = SimulatedAdapter::new(vec![SimulatedKeyboard::split("kbd-1", "Corne", 80, 70)]);
+
+        let infos = read_battery_info_from_adapter(&adapter, "kbd-1", &readings())
+            .await
+            .expect("read");
+
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].user_description, None);
+        assert_eq!(infos[0].battery_level, Some(80));
+        assert_eq!(infos[1].user_description, peripheral());
+        assert_eq!(infos[1].battery_level, Some(70));
+    }
+
+    #[tokio::test]
+    async fn polled_spikes_go_through_the_reading_filter() {
+        let adapter =
+            SimulatedAdapter::new(vec![SimulatedKeyboard::new("kbd-1", "Corne").part(None, 80)]);
+        let readings = ReadingPipeline::new(ReadingFilter {
+            debounce: true,
+            ..ReadingFilter::default()
+        });
+
+        let mut polled = Vec::new();
+        for level in [80, 3, 79] {
+            adapter.apply(&SimAction::SetLevel {
+                device: "kbd-1".to_string(),
+                part: 0,
+                level,
+            });
+            let infos = read_battery_info_from_adapter(&adapter, "kbd-1", &readings)
+                .await
+                .expect("read");
+            polled.push((infos[0].battery_level, infos[0].reading_flag));
+        }
+        assert_eq!(
+            polled,
+            [(Some(80), None), (None, Some(ReadingFlag::Held)), (Some(79), None)]
+        );
+    }
+
+    #[tokio::test]
+    async fn monitor_and_one_shot_reads_share_a_device_filter() {
+        let filter = ReadingFilter::default();
+        let first = reading_pipeline("kbd-shared-filter", filter).await;
+        assert!(Arc::ptr_eq(&first, &reading_pipeline("kbd-shared-filter", filter).await));
+
+        let debounced = ReadingFilter {
+            debounce: true,
+            ..filter
+        };
+        let changed = reading_pipeline("kbd-shared-filter", debounced).await;
+        assert!(!Arc::ptr_eq(&first, &changed));
+        assert_eq!(changed.filter(), debounced);
+    }
+
+    #[tokio::test]
+    async fn parts_without_descriptions_get_distinct_part_keys() {
+        let keyboard = SimulatedKeyboard::new("kbd-1", "Corne")
+            .part(None, 80)
+            .part(None, 70);
+        let adapter = SimulatedAdapter::new(vec![keyboard]);
+
+        let infos = read_battery_info_from_adapter(&adapter, "kbd-1", &readings())
+            .await
+            .expect("read");
+
+        let keys: Vec<&str> = infos.iter().map(|info| info.part_key.as_str()).collect();
+        assert_eq!(keys, vec!["0:0", "0:1"]);
+        assert!(infos.iter().all(|info| info.user_description.is_none()));
+    }
+
+    #[tokio::test]
+    async fn presentation_format_names_and_orders_parts() {
+        let keyboard = SimulatedKeyboard::new("kbd-1", "Corne")
+            .part(None, 80)
+            .with_presentation_format(PresentationFormat::battery_level(0x010E))
+            .part(None, 70)
+            .with_presentation_format(PresentationFormat::battery_level(0x010D));
+        let adapter = SimulatedAdapter::new(vec![keyboard]);
+
+        let infos = read_battery_info_from_adapter(&adapter, "kbd-1", &readings())
+            .await
+            .expect("read");
+
+        let parts: Vec<(&str, Option<&str>, Option<u8>)> = infos
+            .iter()
+            .map(|info| {
+                let name = info.presentation.as_ref().and_then(|p| p.description_name.as_deref());
+                (info.part_key.as_str(), name, info.battery_level)
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            vec![("0:1", Some("left"), Some(70)), ("0:0", Some("right"), Some(80))]
+        );
+    }
+
+    #[tokio::test]
+    async fn partial_presentation_formats_keep_discovery_order() {
+        let keyboard = SimulatedKeyboard::new("kbd-1", "Corne")
+            .part(None, 80)
+            .part(Some("Peripheral 0"), 70)
+            .with_presentation_format(PresentationFormat::battery_level(0x0001));
+        let adapter = SimulatedAdapter::new(vec![keyboard]);
+
+        let infos = read_battery_info_from_adapter(&adapter, "kbd-1", &readings())
+            .await
+            .expect("read");
+
+        assert_eq!(infos[0].part_key, "0:0");
+        assert!(infos[0].presentation.is_none());
+        assert_eq!(infos[1].user_description, peripheral());
+        assert_eq!(
+            infos[1].presentation.as_ref().map(|p| p.description),
+            Some(0x0001)
+        );
+    }
+
+    fn two_adapter_transport() -> SimulatedTransport {
+        SimulatedTransport::with_adapters(vec![
+            SimulatedAdapter::new(vec![SimulatedKeyboard::split("kbd-1", "Corne", 80, 70)]),
+            SimulatedAdapter::new(vec![SimulatedKeyboard::split("kbd-2", "Lily58", 60, 50)])
+                .with_id("dongle"),
+        ])
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dropped_read_gives_the_adapter_back_to_other_devices() {
+        let adapter = SimulatedAdapter::new(vec![
+            SimulatedKeyboard::new("kbd-1", "Corne").part(None, 80),
+            SimulatedKeyboard::new("kbd-2", "Lily58").part(None, 70),
+        ])
+        .with_id("stalled-read");
+        adapter.apply(&SimAction::StallReads {
+            device: "kbd-1".to_string(),
+            part: 0,
+            stall: true,
+        });
+        let transport = ble_scheduler::scheduled(Arc::new(SimulatedTransport::new(adapter)));
+        let reads = Arc::new(SharedReads::new());
+        let read = |id: &'static str| {
+            let transport = transport.clone();
+            async move {
+                let adapter = open_adapter(transport.as_ref(), Some("stalled-read")).await?;
+                read_battery_info_from_adapter(adapter.as_ref(), id, &readings()).await
+            }
+        };
+
+        // The poller stops while its read holds the adapter's only turn.
+        let poll = tokio::spawn({
+            let reads = reads.clone();
+            let read = read("kbd-1");
+            async move { reads.run("kbd-1", read).await }
+        });
+        sleep(Duration::from_secs(1)).await;
+        poll.abort();
+        let _ = poll.await;
+
+        let infos = tokio::time::timeout(
+            Duration::from_secs(10),
+            reads.run("kbd-2", read("kbd-2")),
+        )
+        .await
+        .expect("adapter still held by the dropped read")
+        .expect("read");
+        assert_eq!(infos[0].battery_level, Some(70));
+    }
+
+    #[tokio::test]
+    async fn open_adapter_uses_default_or_selected_adapter() {
+        let transport = two_adapter_transport();
+
+        let default = open_adapter(&transport, None).await.expect("default adapter");
+        assert_eq!(default.id(), "sim0");
+        let dongle = open_adapter(&transport, Some("dongle")).await.expect("dongle");
+        let infos = read_battery_info_from_adapter(dongle.as_ref(), "kbd-2", &readings())
+            .await
+            .expect("read through dongle");
+        assert_eq!(infos[0].battery_level, Some(60));
+        assert!(read_battery_info_from_adapter(dongle.as_ref(), "kbd-1", &readings())
+            .await
+            .is_err());
+
+        assert_eq!(
+            open_adapter(&transport, Some("hci9")).await.err(),
+            Some(CommandError::AdapterNotFound)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn open_adapter_gives_up_on_bluetooth_that_stays_off() {
+        let adapter =
+            SimulatedAdapter::new(vec![SimulatedKeyboard::split("kbd-1", "Corne", 80, 70)])
+                .with_id("off-without-watcher");
+        adapter.apply(&SimAction::SetPowered { powered: false });
+        let transport = SimulatedTransport::new(adapter);
+
+        let started = Instant::now();
+        let error = open_adapter(&transport, Some("off-without-watcher"))
+            .await
+            .err()
+            .expect("adapter is off");
+        assert_eq!(error.code(), "adapter_unavailable");
+        assert_eq!(started.elapsed(), ADAPTER_STARTUP_WAIT);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn open_adapter_waits_for_bluetooth_that_is_starting() {
+        let adapter = SimulatedAdapter::new(Vec::new()).with_id("starting-without-watcher");
+        adapter.apply(&SimAction::SetPowered { powered: false });
+        let transport = SimulatedTransport::new(adapter.clone());
+        tokio::spawn(async move {
+            sleep(Duration::from_secs(1)).await;
+            adapter.apply(&SimAction::SetPowered { powered: true });
+        });
+
+        let opened = open_adapter(&transport, Some("starting-without-watcher"))
+            .await
+            .expect("adapter came up");
+        assert_eq!(opened.id(), "starting-without-watcher");
+    }
+
+    #[tokio::test]
+    async fn list_adapters_marks_selected_adapter() {
+        let transport = two_adapter_transport();
+        let selected = |adapters: Vec<BluetoothAdapterInfo>| -> Vec<String> {
+            adapters
+                .into_iter()
+                .filter(|adapter| adapter.selected)
+                .map(|adapter| adapter.id)
+                .collect()
+        };
+
+        let adapters = list_adapters(&transport, None).await;
         assert_eq!(
-            classify_connection_wait_event(None),
-            ConnectionWaitOutcome::RetryOuter
+            adapters.iter().map(|a| a.id.as_str()).collect::<Vec<_>>(),
+            vec!["sim0", "dongle"]
+        );
+        assert_eq!(selected(adapters), vec!["sim0"]);
+        assert_eq!(
+            selected(list_adapters(&transport, Some("dongle")).await),
+            vec!["dongle"]
         );
+        assert!(selected(list_adapters(&transport, Some("unplugged")).await).is_empty());
     }
 
-    #[test]
-    fn connection_worker_connected_continues() {
+    #[tokio::test]
+    async fn read_battery_info_fails_for_unknown_or_disconnected_device() {
+        let adapter = SimulatedAdapter::new(vec![
+            SimulatedKeyboard::split("kbd-1", "Corne", 80, 70).disconnected(),
+        ]);
+
+        for id in ["kbd-1", "missing"] {
+            let Err(error) = read_battery_info_from_adapter(&adapter, id, &readings()).await else {
+                panic!("read succeeded for {id}");
+            };
+            assert_eq!(error, CommandError::DeviceNotFound);
+            assert!(error.retryable());
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_reports_initial_levels_and_notifications() {
+        let mut harness = WatcherHarness::start(SimulatedKeyboard::split("kbd-1", "Corne", 80, 70));
+
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(80))).await;
+        harness.expect(RecordedEvent::Level(peripheral(), Some(70))).await;
+        harness.settle().await;
+
+        harness.adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 1,
+            level: 69,
+        });
+        harness.expect(RecordedEvent::Level(peripheral(), Some(69))).await;
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_recovers_after_disconnect_and_reconnect() {
+        let mut harness = WatcherHarness::start(SimulatedKeyboard::split("kbd-1", "Corne", 80, 70));
+        harness.expect(RecordedEvent::Level(peripheral(), Some(70))).await;
+        harness.settle().await;
+
+        harness.adapter.apply(&SimAction::Disconnect {
+            device: "kbd-1".to_string(),
+        });
+        harness.expect(RecordedEvent::Connected(false)).await;
+
+        harness.adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 0,
+            level: 55,
+        });
+        harness.adapter.apply(&SimAction::Connect {
+            device: "kbd-1".to_string(),
+        });
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(55))).await;
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_waits_for_device_that_starts_disconnected() {
+        let mut harness = WatcherHarness::start(
+            SimulatedKeyboard::split("kbd-1", "Corne", 80, 70).disconnected(),
+        );
+        sleep(Duration::from_secs(30)).await;
+        assert!(harness.drain_without_retries().is_empty());
+
+        harness.adapter.apply(&SimAction::Connect {
+            device: "kbd-1".to_string(),
+        });
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(80))).await;
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_follows_a_replayed_trace() {
+        let world = ble_trace::replay_world(
+            &ble_trace::parse_trace(include_str!("../../docs/ble-trace.example.jsonl"))
+                .expect("parse example trace"),
+        );
+        let keyboard = world.keyboards.into_iter().next().expect("keyboard");
+        let mut harness = WatcherHarness::start(keyboard);
+        let adapter = harness.adapter.clone();
+        let script = tokio::spawn(async move { adapter.run_script(&world.script).await });
+
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(80))).await;
+        harness.expect(RecordedEvent::Level(peripheral(), Some(70))).await;
+        harness.expect(RecordedEvent::Level(peripheral(), Some(69))).await;
+        harness.expect(RecordedEvent::Connected(false)).await;
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(75))).await;
+
+        script.await.expect("script panicked");
+        harness.stop().await;
+    }
+
+    fn doubling_policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay_ms: 1_000,
+            factor: 2.0,
+            jitter: 0.0,
+            max_delay_ms: 4_000,
+            give_up_after_secs: 0,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_backs_off_and_reports_attempts() {
+        let mut harness = WatcherHarness::start_with(
+            SimulatedKeyboard::new("kbd-1", "Single").part(None, 90).disconnected(),
+            MonitorOptions::default(),
+            doubling_policy(),
+        );
+        let started = Instant::now();
+        let mut attempt_times = Vec::new();
+        for attempt in 1..=5 {
+            harness.expect(RecordedEvent::Retry(attempt, true)).await;
+            attempt_times.push(started.elapsed().as_secs());
+        }
+        assert_eq!(attempt_times, vec![0, 1, 3, 7, 11]);
+
+        harness.adapter.apply(&SimAction::Connect {
+            device: "kbd-1".to_string(),
+        });
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(90))).await;
+        harness.settle().await;
+
+        // A new streak starts from the first attempt again.
+        harness.adapter.apply(&SimAction::Disconnect {
+            device: "kbd-1".to_string(),
+        });
+        harness.expect(RecordedEvent::Connected(false)).await;
+        harness.expect(RecordedEvent::Retry(1, true)).await;
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_gives_up_after_policy_limit() {
+        let mut harness = WatcherHarness::start_with(
+            SimulatedKeyboard::new("kbd-1", "Single").part(None, 90).disconnected(),
+            MonitorOptions::default(),
+            ReconnectPolicy {
+                give_up_after_secs: 10,
+                ..doubling_policy()
+            },
+        );
+        harness.expect(RecordedEvent::Retry(5, false)).await;
+        harness.settle().await;
+        assert!(harness.handle.is_finished());
+
+        // Coming back later does not wake a watcher that gave up.
+        harness.adapter.apply(&SimAction::Connect {
+            device: "kbd-1".to_string(),
+        });
+        sleep(Duration::from_secs(30)).await;
+        assert!(harness.drain_without_retries().is_empty());
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_pauses_while_adapter_is_off_and_resumes() {
+        let mut harness = WatcherHarness::start(SimulatedKeyboard::split("kbd-1", "Corne", 80, 70));
+        harness.expect(RecordedEvent::Level(peripheral(), Some(70))).await;
+        harness.settle().await;
+
+        harness.adapter.apply(&SimAction::SetPowered { powered: false });
+        harness
+            .expect_all(vec![
+                RecordedEvent::Adapter(AdapterState::PoweredOff),
+                RecordedEvent::Connected(false),
+            ])
+            .await;
+        sleep(Duration::from_secs(30)).await;
+        assert!(harness.drain_without_retries().is_empty());
+
+        harness.adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 0,
+            level: 55,
+        });
+        harness.adapter.apply(&SimAction::SetPowered { powered: true });
+        harness
+            .expect(RecordedEvent::Adapter(AdapterState::Available))
+            .await;
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(55))).await;
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_resumes_when_removed_adapter_returns() {
+        let mut harness = WatcherHarness::start(
+            SimulatedKeyboard::new("kbd-1", "Single").part(None, 90),
+        );
+        harness.expect(RecordedEvent::Level(None, Some(90))).await;
+        harness.settle().await;
+
+        harness.adapter.apply(&SimAction::RemoveAdapter);
+        harness
+            .expect_all(vec![
+                RecordedEvent::Adapter(AdapterState::Removed),
+                RecordedEvent::Connected(false),
+            ])
+            .await;
+
+        harness.adapter.apply(&SimAction::RestoreAdapter);
+        harness
+            .expect(RecordedEvent::Adapter(AdapterState::Available))
+            .await;
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(90))).await;
+
+        harness.stop().await;
+    }
+
+    /// An adapter that stays listed but whose event stream ends at once, the
+    /// way the OS default adapter behaves when its stack goes away.
+    struct EndingEventsTransport {
+        adapter: SimulatedAdapter,
+        subscriptions: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    struct EndingEventsAdapter(EndingEventsTransport);
+
+    #[async_trait::async_trait]
+    impl BleTransport for EndingEventsTransport {
+        async fn default_adapter(&self) -> Option<Arc<dyn BleAdapter>> {
+            Some(Arc::new(EndingEventsAdapter(EndingEventsTransport {
+                adapter: self.adapter.clone(),
+                subscriptions: self.subscriptions.clone(),
+            })))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl BleAdapter for EndingEventsAdapter {
+        fn id(&self) -> String {
+            self.0.adapter.id()
+        }
+
+        fn name(&self) -> String {
+            self.0.adapter.name()
+        }
+
+        async fn wait_available(&self) -> BleResult<()> {
+            self.0.adapter.wait_available().await
+        }
+
+        async fn is_available(&self) -> BleResult<bool> {
+            self.0.adapter.is_available().await
+        }
+
+        async fn events(&self) -> BleResult<AdapterEventStream<'_>> {
+            self.0
+                .subscriptions
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(futures_util::stream::empty().boxed())
+        }
+
+        async fn connected_devices_with_services(
+            &self,
+            services: &[Uuid],
+        ) -> BleResult<Vec<Arc<dyn BleDevice>>> {
+            self.0.adapter.connected_devices_with_services(services).await
+        }
+
+        async fn scan<'a>(&'a self, services: &'a [Uuid]) -> BleResult<AdvertisementStream<'a>> {
+            self.0.adapter.scan(services).await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn adapter_watcher_waits_before_looking_up_an_adapter_whose_events_ended() {
+        let adapter = SimulatedAdapter::new(Vec::new());
+        let subscriptions = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let transport = EndingEventsTransport {
+            adapter: adapter.clone(),
+            subscriptions: subscriptions.clone(),
+        };
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let (state_tx, _state_rx) = watch::channel(AdapterState::Available);
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let handle = tokio::spawn(adapter_state_watcher(
+            Arc::new(RecordingSink::new(tx, record)),
+            Arc::new(transport),
+            adapter.id(),
+            state_tx,
+            stop_rx,
+        ));
+
         assert_eq!(
-            classify_worker_connection_event(Some(bluest::ConnectionEvent::Connected)),
-            WorkerConnectionOutcome::Continue
+            events.recv().await,
+            Some(RecordedEvent::Adapter(AdapterState::Removed))
         );
+        sleep(Duration::from_secs(12)).await;
+        // One subscription at the start and one per retry interval.
+        assert_eq!(subscriptions.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        stop_tx.send(true).unwrap();
+        handle.await.expect("adapter watcher panicked");
     }
 
-    #[test]
-    fn connection_worker_disconnected_stops() {
+    #[tokio::test(start_paused = true)]
+    async fn watcher_restarts_workers_after_notify_stream_error() {
+        let mut harness = WatcherHarness::start(
+            SimulatedKeyboard::new("kbd-1", "Single").part(None, 90),
+        );
+        harness.expect(RecordedEvent::Level(None, Some(90))).await;
+        harness.settle().await;
+
+        harness.adapter.apply(&SimAction::FailNotify {
+            device: "kbd-1".to_string(),
+            part: 0,
+        });
+        harness.expect(RecordedEvent::Connected(false)).await;
+
+        // The watcher starts a fresh session and re-reads the level.
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(90))).await;
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_only_subscribes_to_notifying_parts() {
+        let mut harness = WatcherHarness::start(
+            SimulatedKeyboard::new("kbd-1", "Corne")
+                .part(None, 80)
+                .part(Some("Peripheral 0"), 70)
+                .without_notify(),
+        );
+        harness.expect(RecordedEvent::Level(peripheral(), Some(70))).await;
+        harness.settle().await;
+
+        harness.adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 1,
+            level: 60,
+        });
+        harness.adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 0,
+            level: 79,
+        });
+        let next = loop {
+            match harness.events.recv().await.expect("event") {
+                RecordedEvent::Level(description, level) => break (description, level),
+                _ => continue,
+            }
+        };
+        assert_eq!(next, (None, Some(79)));
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_polls_parts_that_cannot_notify() {
+        let options = MonitorOptions {
+            poll_interval_secs: 30,
+            ..MonitorOptions::default()
+        };
+        let mut harness = WatcherHarness::start_with_options(
+            SimulatedKeyboard::new("kbd-1", "Corne")
+                .part(None, 80)
+                .part(Some("Peripheral 0"), 70)
+                .without_notify(),
+            options,
+        );
+        harness.expect(RecordedEvent::Level(peripheral(), Some(70))).await;
+        harness.settle().await;
+
+        harness.adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 1,
+            level: 65,
+        });
+        let started = Instant::now();
+        harness.expect(RecordedEvent::Level(peripheral(), Some(65))).await;
+        assert!(started.elapsed() >= Duration::from_secs(25));
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_monitors_device_without_any_notify_support() {
+        let options = MonitorOptions {
+            poll_interval_secs: 30,
+            ..MonitorOptions::default()
+        };
+        let mut harness = WatcherHarness::start_with_options(
+            SimulatedKeyboard::new("kbd-1", "Corne")
+                .part(None, 80)
+                .without_notify(),
+            options,
+        );
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(80))).await;
+        harness.settle().await;
+
+        harness.adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 0,
+            level: 79,
+        });
+        harness.expect(RecordedEvent::Level(None, Some(79))).await;
+
+        harness.adapter.apply(&SimAction::Disconnect {
+            device: "kbd-1".to_string(),
+        });
+        harness.expect(RecordedEvent::Connected(false)).await;
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_verifies_level_after_notifications_go_silent() {
+        let options = MonitorOptions {
+            verify_after_secs: 40,
+            ..MonitorOptions::default()
+        };
+        let mut harness = WatcherHarness::start_with_options(
+            SimulatedKeyboard::new("kbd-1", "Corne").part(None, 80),
+            options,
+        );
+        harness.expect(RecordedEvent::Level(None, Some(80))).await;
+        harness.settle().await;
+
+        harness.adapter.apply(&SimAction::MuteNotify {
+            device: "kbd-1".to_string(),
+            part: 0,
+            mute: true,
+        });
+        harness.adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 0,
+            level: 72,
+        });
+        let started = Instant::now();
+        harness.expect(RecordedEvent::Level(None, Some(72))).await;
+        assert!(started.elapsed() >= Duration::from_secs(35));
+
+        // An unchanged verification read emits nothing.
+        sleep(Duration::from_secs(45)).await;
+        assert!(harness.events.try_recv().is_err());
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watcher_drops_out_of_range_levels_and_debounced_spikes() {
+        let options = MonitorOptions {
+            reading_filter: ReadingFilter {
+                debounce: true,
+                ..ReadingFilter::default()
+            },
+            ..MonitorOptions::default()
+        };
+        let mut harness = WatcherHarness::start_with_options(
+            SimulatedKeyboard::new("kbd-1", "Corne").part(None, 80),
+            options,
+        );
+        harness.expect(RecordedEvent::Level(None, Some(80))).await;
+        harness.settle().await;
+
+        for level in [0xFF, 3, 79] {
+            harness.adapter.apply(&SimAction::SetLevel {
+                device: "kbd-1".to_string(),
+                part: 0,
+                level,
+            });
+        }
+        let next = loop {
+            match harness.events.recv().await.expect("event") {
+                RecordedEvent::Level(description, level) => break (description, level),
+                _ => continue,
+            }
+        };
+        assert_eq!(next, (None, Some(79)));
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watchdog_restarts_a_session_whose_stream_went_silent() {
+        let options = MonitorOptions {
+            verify_after_secs: 0,
+            stale_after_secs: 60,
+            ..MonitorOptions::default()
+        };
+        let mut harness = WatcherHarness::start_with_options(
+            SimulatedKeyboard::new("kbd-1", "Corne").part(None, 80),
+            options,
+        );
+        harness.expect(RecordedEvent::Level(None, Some(80))).await;
+        harness.settle().await;
+        // The worker reports its subscription as connected.
+        harness.drain_without_retries();
+
+        // A quiet device that still answers the probe read is left alone.
+        sleep(Duration::from_secs(90)).await;
+        assert!(harness.drain_without_retries().is_empty());
+
+        harness.adapter.apply(&SimAction::MuteNotify {
+            device: "kbd-1".to_string(),
+            part: 0,
+            mute: true,
+        });
+        harness.adapter.apply(&SimAction::FailReads {
+            device: "kbd-1".to_string(),
+            part: 0,
+            count: 1,
+        });
+        harness
+            .expect(RecordedEvent::Diagnostic(DiagnosticKind::StaleStreamRestarted))
+            .await;
+        harness.expect(RecordedEvent::Connected(false)).await;
+        harness.expect(RecordedEvent::Connected(true)).await;
+        harness.expect(RecordedEvent::Level(None, Some(80))).await;
+
+        harness.stop().await;
+    }
+
+    #[tokio::test]
+    async fn monitor_options_reject_zero_poll_interval() {
+        let options = MonitorOptions {
+            poll_interval_secs: 0,
+            ..MonitorOptions::default()
+        };
         assert_eq!(
-            classify_worker_connection_event(Some(bluest::ConnectionEvent::Disconnected)),
-            WorkerConnectionOutcome::Stop
+            set_monitor_options("kbd-options".to_string(), options)
+                .await
+                .unwrap_err()
+                .code(),
+            "invalid_argument"
         );
+        assert_eq!(
+            monitor_options("kbd-options").await,
+            MonitorOptions::default()
+        );
+    }
+
+    fn advertisement(
+        adapter: &SimulatedAdapter,
+        id: &str,
+        name: Option<&str>,
+        services: Vec<Uuid>,
+        rssi: i16,
+    ) -> Advertisement {
+        Advertisement {
+            device: adapter.device_handle(id),
+            local_name: name.map(str::to_string),
+            services,
+            rssi: Some(rssi),
+        }
     }
 
     #[test]
-    fn connection_worker_stream_end_stops() {
+    fn scan_accumulator_reports_new_and_changed_devices_only() {
+        let adapter = SimulatedAdapter::new(vec![SimulatedKeyboard::new("kbd-1", "Corne")]);
+        let mut accumulator = ScanAccumulator::default();
+
+        let first = accumulator
+            .observe(&advertisement(&adapter, "kbd-1", None, vec![], -70))
+            .expect("first sighting is reported");
+        assert_eq!(first.name.as_deref(), Some("Corne"));
+        assert!(!first.has_battery_service);
+
         assert_eq!(
-            classify_worker_connection_event(None),
-            WorkerConnectionOutcome::Stop
+            accumulator.observe(&advertisement(&adapter, "kbd-1", None, vec![], -50)),
+            None
         );
+
+        let updated = accumulator
+            .observe(&advertisement(
+                &adapter,
+                "kbd-1",
+                Some("Corne"),
+                vec![BATTERY_SERVICE_UUID],
+                -55,
+            ))
+            .expect("new service is reported");
+        assert!(updated.has_battery_service);
+        assert_eq!(updated.rssi, Some(-55));
+        assert_eq!(updated.services, vec![BATTERY_SERVICE_UUID.to_string()]);
+
+        // A later packet without the service list keeps what was learned.
+        accumulator.observe(&advertisement(&adapter, "kbd-1", None, vec![], -60));
+        assert_eq!(accumulator.devices.len(), 1);
+        assert!(accumulator.devices[0].has_battery_service);
+        assert_eq!(accumulator.devices[0].rssi, Some(-60));
     }
 
     #[tokio::test(start_paused = true)]
-    async fn wait_for_retry_elapses_returns_false() {
-        let (_tx, mut rx) = watch::channel(false);
-        let result = wait_for_retry_or_stop(&mut rx, Duration::from_secs(5)).await;
-        assert!(!result);
+    async fn scan_finds_only_advertising_keyboards_within_time_box() {
+        let adapter = SimulatedAdapter::new(vec![
+            SimulatedKeyboard::new("kbd-1", "Corne").part(None, 80),
+            SimulatedKeyboard::new("kbd-2", "Lily58")
+                .part(None, 60)
+                .disconnected(),
+        ]);
+        let (_stop_tx, stop_rx) = watch::channel(false);
+        let reported = std::sync::Mutex::new(Vec::new());
+
+        let devices = scan_for_battery_devices(&adapter, Duration::from_secs(5), stop_rx, |info| {
+            reported.lock().unwrap().push(info.id.clone())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].id, "kbd-2");
+        assert_eq!(devices[0].name.as_deref(), Some("Lily58"));
+        assert!(devices[0].has_battery_service);
+        assert_eq!(*reported.lock().unwrap(), vec!["kbd-2".to_string()]);
     }
 
     #[tokio::test(start_paused = true)]
-    async fn wait_for_retry_stop_signal_returns_true() {
-        let (tx, mut rx) = watch::channel(false);
-        let handle = tokio::spawn(async move {
-            wait_for_retry_or_stop(&mut rx, Duration::from_secs(60)).await
-        });
-        tx.send(true).unwrap();
-        assert!(handle.await.unwrap());
+    async fn scan_ends_early_on_stop_signal() {
+        let adapter = SimulatedAdapter::new(vec![SimulatedKeyboard::new("kbd-1", "Corne")
+            .part(None, 80)
+            .disconnected()]);
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let started = tokio::time::Instant::now();
+
+        let scan = scan_for_battery_devices(&adapter, Duration::from_secs(60), stop_rx, |_| {});
+        let stop = async {
+            sleep(Duration::from_secs(1)).await;
+            stop_tx.send(true).unwrap();
+        };
+        let (devices, ()) = tokio::join!(scan, stop);
+
+        assert_eq!(devices.unwrap().len(), 1);
+        assert!(started.elapsed() < Duration::from_secs(60));
+    }
+
+    fn charging_keyboard(state: PowerState) -> SimulatedKeyboard {
+        SimulatedKeyboard::split("kbd-1", "Corne", 80, 70).notifying_characteristic(
+            BATTERY_SERVICE_UUID,
+            BATTERY_LEVEL_STATUS_UUID,
+            &battery_level_status_value(state),
+        )
+    }
+
+    #[tokio::test]
+    async fn read_battery_info_attaches_power_state_to_central_only() {
+        let adapter = SimulatedAdapter::new(vec![charging_keyboard(PowerState::Charging)]);
+
+        let infos = read_battery_info_from_adapter(&adapter, "kbd-1", &readings())
+            .await
+            .expect("read");
+
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].power_state, PowerState::Charging);
+        assert_eq!(infos[1].power_state, PowerState::Unknown);
+    }
+
+    #[tokio::test]
+    async fn read_battery_info_falls_back_to_legacy_power_state() {
+        let adapter = SimulatedAdapter::new(vec![SimulatedKeyboard::new("kbd-1", "Corne")
+            .part(None, 80)
+            .characteristic(BATTERY_SERVICE_UUID, BATTERY_POWER_STATE_UUID, &[0b10_10_11_11])]);
+
+        let infos = read_battery_info_from_adapter(&adapter, "kbd-1", &readings())
+            .await
+            .expect("read");
+
+        assert_eq!(infos[0].power_state, PowerState::Discharging);
+    }
+
+    #[tokio::test]
+    async fn re_paired_keyboard_matches_its_registration() {
+        let adapter =
+            SimulatedAdapter::new(vec![SimulatedKeyboard::split("kbd-paired", "Corne", 80, 70)]);
+        let device = get_target_device(&adapter, "kbd-paired").await.expect("device");
+        let fingerprint = read_fingerprint_from_device(device.as_ref())
+            .await
+            .expect("fingerprint");
+        assert_eq!(fingerprint.name, "Corne");
+        assert_eq!(fingerprint.battery_layout, vec![None, peripheral()]);
+
+        // After re-pairing the same keyboard shows up under another id.
+        let adapter = SimulatedAdapter::new(vec![
+            SimulatedKeyboard::split("kbd-re-paired", "Corne", 60, 50),
+            SimulatedKeyboard::new("kbd-unrelated", "Lily58").part(None, 90),
+        ]);
+        let registered = [RegisteredIdentity {
+            id: "kbd-paired".to_string(),
+            fingerprint,
+        }];
+        let matches = find_identity_matches_on_adapter(&adapter, &registered)
+            .await
+            .expect("matches");
+
+        assert_eq!(
+            matches,
+            vec![IdentityMatch {
+                device_id: "kbd-re-paired".to_string(),
+                registered_id: "kbd-paired".to_string(),
+                confidence: IdentityConfidence::NameAndLayout,
+            }]
+        );
     }
 
     #[tokio::test(start_paused = true)]
-    async fn wait_for_retry_sender_dropped_returns_true() {
-        let (tx, mut rx) = watch::channel(false);
-        let handle = tokio::spawn(async move {
-            wait_for_retry_or_stop(&mut rx, Duration::from_secs(60)).await
+    async fn watcher_reports_power_state_changes_with_last_level() {
+        let mut harness = WatcherHarness::start(charging_keyboard(PowerState::Discharging));
+        harness
+            .expect(RecordedEvent::Power(Some(80), PowerState::Discharging))
+            .await;
+        harness.settle().await;
+
+        harness.adapter.apply(&SimAction::SetValue {
+            device: "kbd-1".to_string(),
+            uuid: BATTERY_LEVEL_STATUS_UUID,
+            value: battery_level_status_value(PowerState::Charging),
         });
-        drop(tx);
-        assert!(handle.await.unwrap());
+        harness
+            .expect(RecordedEvent::Power(Some(80), PowerState::Charging))
+            .await;
+
+        // Level notifications keep the latest power state.
+        harness.adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 0,
+            level: 81,
+        });
+        harness
+            .expect(RecordedEvent::Power(Some(81), PowerState::Charging))
+            .await;
+
+        harness.stop().await;
     }
 
     #[tokio::test(start_paused = true)]
-    async fn wait_for_retry_false_signal_returns_false_immediately() {
-        let (tx, mut rx) = watch::channel(false);
-        let handle = tokio::spawn(async move {
-            wait_for_retry_or_stop(&mut rx, Duration::from_secs(60)).await
-        });
-        tx.send(false).unwrap();
-        tokio::task::yield_now().await;
-        assert!(handle.is_finished());
-        assert!(!handle.await.unwrap());
+    async fn power_supply_watcher_reports_connection_and_levels() {
+        let root = tempfile::tempdir().expect("create temp dir");
+        let supply = root.path().join("hid-aa:bb:cc:dd:ee:ff-battery");
+        std::fs::create_dir_all(&supply).unwrap();
+        std::fs::write(supply.join("capacity"), "64\n").unwrap();
+        std::fs::write(supply.join("status"), "Charging\n").unwrap();
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let handle = tokio::spawn(power_supply_battery_watcher(
+            Arc::new(RecordingSink::new(tx, record)),
+            root.path().to_path_buf(),
+            "AA:BB:CC:DD:EE:FF".to_string(),
+            stop_rx,
+        ));
+
+        assert_eq!(events.recv().await, Some(RecordedEvent::Connected(true)));
+        assert_eq!(
+            events.recv().await,
+            Some(RecordedEvent::Power(Some(64), PowerState::Charging))
+        );
+        assert_eq!(events.recv().await, Some(RecordedEvent::Level(None, Some(64))));
+
+        std::fs::remove_dir_all(&supply).unwrap();
+        assert_eq!(events.recv().await, Some(RecordedEvent::Connected(false)));
+
+        stop_tx.send(true).unwrap();
+        handle.await.expect("watcher panicked");
     }
 }
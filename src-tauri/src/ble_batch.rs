@@ -0,0 +1,233 @@
+//! Sharing of one-shot battery reads.
+//!
+//! `get_battery_info_many` reads every registered device in one round trip,
+//! and the backend poller or a manual refresh may ask for the same devices
+//! while it runs. A read of a device that is already being read joins that
+//! read instead of starting a second connect.
+//!
+//! A shared read runs as its own task at the highest priority among its
+//! callers, and is aborted once all of them were dropped, so an abandoned
+//! read gives back its scheduler turn and read lock right away.
+
+use crate::ble::BatteryInfo;
+use crate::ble_scheduler::{self, SharedPriority};
+use crate::error::CommandError;
+use futures_util::future::{BoxFuture, Shared};
+use futures_util::FutureExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::task::AbortHandle;
+
+pub(crate) type ReadResult = Result<Vec<BatteryInfo>, CommandError>;
+
+/// Result of one device in a batch read: its infos or why reading it failed.
+#[derive(Serialize, Clone)]
+pub struct BatteryReadResult {
+    pub id: String,
+    pub battery_infos: Option<Vec<BatteryInfo>>,
+    pub error: Option<CommandError>,
+}
+
+impl BatteryReadResult {
+    pub fn new(id: String, result: ReadResult) -> Self {
+        match result {
+            Ok(battery_infos) => Self {
+                id,
+                battery_infos: Some(battery_infos),
+                error: None,
+            },
+            Err(error) => Self {
+                id,
+                battery_infos: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// Ids in request order, each once.
+pub(crate) fn unique_ids(ids: Vec<String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::with_capacity(ids.len());
+    for id in ids {
+        if !unique.contains(&id) {
+            unique.push(id);
+        }
+    }
+    unique
+}
+
+/// Result of a read task; None when it panicked.
+type SharedResult<T> = Shared<BoxFuture<'static, Option<T>>>;
+
+struct InFlight<T: Clone> {
+    result: SharedResult<T>,
+    priority: SharedPriority,
+    task: AbortHandle,
+    callers: usize,
+}
+
+/// Reads in progress by key. Callers asking for a key that is being read
+/// share the result of that read.
+pub(crate) struct SharedReads<T: Clone> {
+    in_flight: Mutex<HashMap<String, InFlight<T>>>,
+}
+
+impl<T: Clone + Send + Sync + 'static> SharedReads<T> {
+    pub fn new() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Runs `read` unless a read of `key` is already in progress, and returns
+    /// the result of whichever read ran. Joining a read raises it to the
+    /// caller's priority.
+    pub async fn run<F>(&self, key: &str, read: F) -> T
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let priority = ble_scheduler::current_priority();
+        let result = {
+            let mut in_flight = self.lock();
+            let entry = in_flight.entry(key.to_string()).or_insert_with(|| {
+                let shared_priority = SharedPriority::new(priority);
+                let task = tokio::spawn(ble_scheduler::with_shared_priority(
+                    shared_priority.clone(),
+                    read,
+                ));
+                InFlight {
+                    task: task.abort_handle(),
+                    result: task.map(Result::ok).boxed().shared(),
+                    priority: shared_priority,
+                    callers: 0,
+                }
+            });
+            entry.priority.raise(priority);
+            entry.callers += 1;
+            entry.result.clone()
+        };
+
+        let _caller = Caller {
+            reads: self,
+            key,
+            result: &result,
+        };
+        result.clone().await.expect("shared read panicked")
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, InFlight<T>>> {
+        self.in_flight.lock().unwrap_or_else(|p| p.into_inner())
+    }
+}
+
+/// One caller of a shared read. The last one to go, done or dropped, removes
+/// the read and aborts it if it is still running.
+struct Caller<'a, T: Clone + Send + Sync + 'static> {
+    reads: &'a SharedReads<T>,
+    key: &'a str,
+    result: &'a SharedResult<T>,
+}
+
+impl<T: Clone + Send + Sync + 'static> Drop for Caller<'_, T> {
+    fn drop(&mut self) {
+        let mut in_flight = self.reads.lock();
+        let Some(entry) = in_flight.get_mut(self.key) else {
+            return;
+        };
+        if !entry.result.ptr_eq(self.result) {
+            return;
+        }
+        entry.callers -= 1;
+        if entry.callers == 0 {
+            entry.task.abort();
+            in_flight.remove(self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble_scheduler::Priority;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::time::{sleep, Duration};
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_reads_of_one_key_share_a_single_read() {
+        let reads = SharedReads::new();
+        let started = Arc::new(AtomicUsize::new(0));
+        let read = |value: u8| {
+            let started = started.clone();
+            async move {
+                started.fetch_add(1, Ordering::SeqCst);
+                sleep(Duration::from_secs(1)).await;
+                value
+            }
+        };
+
+        let (first, second) =
+            tokio::join!(reads.run("kbd-1", read(1)), reads.run("kbd-1", read(2)));
+        assert_eq!((first, second), (1, 1));
+        assert_eq!(started.load(Ordering::SeqCst), 1);
+
+        // Once done, the next read of the key starts afresh.
+        assert_eq!(reads.run("kbd-1", read(3)).await, 3);
+        assert_eq!(started.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_read_is_aborted_once_all_its_callers_are_dropped() {
+        let reads = Arc::new(SharedReads::new());
+        let finished = Arc::new(AtomicUsize::new(0));
+        let read = {
+            let finished = finished.clone();
+            async move {
+                sleep(Duration::from_secs(1)).await;
+                finished.fetch_add(1, Ordering::SeqCst);
+                1
+            }
+        };
+
+        let caller = tokio::spawn({
+            let reads = reads.clone();
+            async move { reads.run("kbd-1", read).await }
+        });
+        sleep(Duration::from_millis(100)).await;
+        caller.abort();
+        let _ = caller.await;
+
+        sleep(Duration::from_secs(2)).await;
+        assert_eq!(finished.load(Ordering::SeqCst), 0);
+        assert_eq!(reads.run("kbd-1", async { 2 }).await, 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn joining_a_read_raises_its_priority() {
+        let reads = SharedReads::new();
+        let read = async {
+            sleep(Duration::from_secs(1)).await;
+            ble_scheduler::current_priority()
+        };
+        let background =
+            ble_scheduler::with_priority(Priority::BackgroundPoll, reads.run("kbd-1", read));
+        let user = async {
+            sleep(Duration::from_millis(100)).await;
+            reads.run("kbd-1", async { Priority::BackgroundPoll }).await
+        };
+
+        let (first, second) = tokio::join!(background, user);
+        assert_eq!(
+            (first, second),
+            (Priority::UserRefresh, Priority::UserRefresh)
+        );
+    }
+
+    #[test]
+    fn duplicate_ids_are_read_once_in_request_order() {
+        let ids = ["b", "a", "b", "c", "a"].map(String::from).to_vec();
+        assert_eq!(unique_ids(ids), ["b", "a", "c"]);
+    }
+}
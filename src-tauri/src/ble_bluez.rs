@@ -0,0 +1,291 @@
+//! Bluetooth adapters other than the default one, on Linux.
+//!
+//! bluest only opens BlueZ's default adapter, so `BluestTransport` lists the
+//! others (a USB dongle next to the built-in radio, say) through this module.
+//! It talks to BlueZ with bluer, the library bluest itself uses on Linux, and
+//! reports devices by Bluetooth address the same way, so a keyboard keeps its
+//! registration whichever adapter it is read through.
+
+use crate::ble_transport::{
+    AdapterEventStream, Advertisement, AdvertisementStream, BleAdapter, BleCharacteristic,
+    BleDescriptor, BleDevice, BleResult, BleService, ConnectionEventStream, NotifyStream,
+};
+use async_trait::async_trait;
+use bluer::{AdapterProperty, DeviceProperty};
+use bluest::error::ErrorKind;
+use bluest::{AdapterEvent, CharacteristicProperties, ConnectionEvent};
+use futures_util::StreamExt;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use uuid::Uuid;
+
+/// One D-Bus connection to BlueZ for every lookup, opened on first use.
+static SESSION: OnceCell<bluer::Session> = OnceCell::const_new();
+
+/// The BlueZ adapters besides the default one, by name (`hci1`, ...).
+pub(crate) async fn secondary_adapters() -> Vec<Arc<dyn BleAdapter>> {
+    match list_secondary_adapters().await {
+        Ok(adapters) => adapters,
+        Err(e) => {
+            log::warn!("BLE I/O: listing BlueZ adapters failed: {e}");
+            vec![]
+        }
+    }
+}
+
+async fn list_secondary_adapters() -> bluer::Result<Vec<Arc<dyn BleAdapter>>> {
+    let session = SESSION.get_or_try_init(bluer::Session::new).await?;
+    let default = session.default_adapter().await?;
+    let mut adapters = Vec::new();
+    for name in session.adapter_names().await? {
+        if name == default.name() {
+            continue;
+        }
+        let adapter = session.adapter(&name)?;
+        let address = adapter.address().await?;
+        adapters.push(Arc::new(BluezAdapter {
+            label: format!("{name} ({address})"),
+            adapter,
+        }) as Arc<dyn BleAdapter>);
+    }
+    Ok(adapters)
+}
+
+struct BluezAdapter {
+    adapter: bluer::Adapter,
+    label: String,
+}
+
+#[async_trait]
+impl BleAdapter for BluezAdapter {
+    fn id(&self) -> String {
+        self.adapter.name().to_string()
+    }
+
+    fn name(&self) -> String {
+        self.label.clone()
+    }
+
+    async fn wait_available(&self) -> BleResult<()> {
+        let mut events = self.events().await?;
+        while !self.adapter.is_powered().await? {
+            match events.next().await {
+                Some(Ok(AdapterEvent::Available)) => break,
+                Some(Ok(AdapterEvent::Unavailable)) => {}
+                Some(Err(e)) => return Err(e),
+                // The adapter was removed.
+                None => return Err(ErrorKind::AdapterUnavailable.into()),
+            }
+        }
+        Ok(())
+    }
+
+    async fn is_available(&self) -> BleResult<bool> {
+        Ok(self.adapter.is_powered().await?)
+    }
+
+    async fn events(&self) -> BleResult<AdapterEventStream<'_>> {
+        let events = self.adapter.events().await?;
+        Ok(events
+            .filter_map(|event| async move {
+                match event {
+                    bluer::AdapterEvent::PropertyChanged(AdapterProperty::Powered(true)) => {
+                        Some(Ok(AdapterEvent::Available))
+                    }
+                    bluer::AdapterEvent::PropertyChanged(AdapterProperty::Powered(false)) => {
+                        Some(Ok(AdapterEvent::Unavailable))
+                    }
+                    _ => None,
+                }
+            })
+            .boxed())
+    }
+
+    async fn connected_devices_with_services(
+        &self,
+        services: &[Uuid],
+    ) -> BleResult<Vec<Arc<dyn BleDevice>>> {
+        let mut devices = Vec::new();
+        for address in self.adapter.device_addresses().await? {
+            let device = self.adapter.device(address)?;
+            if !device.is_connected().await.unwrap_or(false) {
+                continue;
+            }
+            for service in device.services().await? {
+                if services.contains(&service.uuid().await?) {
+                    devices.push(BluezDevice::open(device).await?);
+                    break;
+                }
+            }
+        }
+        Ok(devices)
+    }
+
+    async fn scan<'a>(&'a self, services: &'a [Uuid]) -> BleResult<AdvertisementStream<'a>> {
+        let discovered = self.adapter.discover_devices().await?;
+        Ok(discovered
+            .filter_map(move |event| async move {
+                let bluer::AdapterEvent::DeviceAdded(address) = event else {
+                    return None;
+                };
+                let device = self.adapter.device(address).ok()?;
+                if device.is_connected().await.unwrap_or(false) {
+                    return None;
+                }
+                let uuids: Vec<Uuid> = device
+                    .uuids()
+                    .await
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect();
+                if !services.is_empty() && !uuids.iter().any(|uuid| services.contains(uuid)) {
+                    return None;
+                }
+                let rssi = device.rssi().await.ok().flatten();
+                let device = BluezDevice::open(device).await.ok()?;
+                let local_name = device.name().ok().filter(|name| !name.is_empty());
+                Some(Advertisement {
+                    device,
+                    local_name,
+                    services: uuids,
+                    rssi,
+                })
+            })
+            .boxed())
+    }
+}
+
+struct BluezDevice {
+    device: bluer::Device,
+    /// BlueZ's alias, which falls back to the address for unnamed devices.
+    name: String,
+}
+
+impl BluezDevice {
+    async fn open(device: bluer::Device) -> BleResult<Arc<dyn BleDevice>> {
+        let name = device.alias().await?;
+        Ok(Arc::new(Self { device, name }))
+    }
+}
+
+#[async_trait]
+impl BleDevice for BluezDevice {
+    fn id(&self) -> String {
+        self.device.address().to_string()
+    }
+
+    fn name(&self) -> BleResult<String> {
+        Ok(self.name.clone())
+    }
+
+    async fn connect(&self) -> BleResult<()> {
+        Ok(self.device.connect().await?)
+    }
+
+    async fn disconnect(&self) -> BleResult<()> {
+        Ok(self.device.disconnect().await?)
+    }
+
+    async fn connection_events(&self) -> BleResult<ConnectionEventStream<'_>> {
+        let events = self.device.events().await?;
+        Ok(events
+            .filter_map(|event| async move {
+                match event {
+                    bluer::DeviceEvent::PropertyChanged(DeviceProperty::Connected(true)) => {
+                        Some(ConnectionEvent::Connected)
+                    }
+                    bluer::DeviceEvent::PropertyChanged(DeviceProperty::Connected(false)) => {
+                        Some(ConnectionEvent::Disconnected)
+                    }
+                    _ => None,
+                }
+            })
+            .boxed())
+    }
+
+    async fn services(&self) -> BleResult<Vec<Arc<dyn BleService>>> {
+        let mut services = Vec::new();
+        for service in self.device.services().await? {
+            let uuid = service.uuid().await?;
+            services.push(Arc::new(BluezService { service, uuid }) as Arc<dyn BleService>);
+        }
+        Ok(services)
+    }
+}
+
+struct BluezService {
+    service: bluer::gatt::remote::Service,
+    uuid: Uuid,
+}
+
+#[async_trait]
+impl BleService for BluezService {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    async fn characteristics(&self) -> BleResult<Vec<Arc<dyn BleCharacteristic>>> {
+        let mut characteristics = Vec::new();
+        for characteristic in self.service.characteristics().await? {
+            let uuid = characteristic.uuid().await?;
+            characteristics.push(Arc::new(BluezCharacteristic {
+                characteristic,
+                uuid,
+            }) as Arc<dyn BleCharacteristic>);
+        }
+        Ok(characteristics)
+    }
+}
+
+struct BluezCharacteristic {
+    characteristic: bluer::gatt::remote::Characteristic,
+    uuid: Uuid,
+}
+
+#[async_trait]
+impl BleCharacteristic for BluezCharacteristic {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    async fn properties(&self) -> BleResult<CharacteristicProperties> {
+        Ok(self.characteristic.flags().await?.into())
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        Ok(self.characteristic.read().await?)
+    }
+
+    async fn notify(&self) -> BleResult<NotifyStream<'_>> {
+        let stream = self.characteristic.notify().await?;
+        Ok(stream.map(Ok).boxed())
+    }
+
+    async fn descriptors(&self) -> BleResult<Vec<Arc<dyn BleDescriptor>>> {
+        let mut descriptors = Vec::new();
+        for descriptor in self.characteristic.descriptors().await? {
+            let uuid = descriptor.uuid().await?;
+            descriptors
+                .push(Arc::new(BluezDescriptor { descriptor, uuid }) as Arc<dyn BleDescriptor>);
+        }
+        Ok(descriptors)
+    }
+}
+
+struct BluezDescriptor {
+    descriptor: bluer::gatt::remote::Descriptor,
+    uuid: Uuid,
+}
+
+#[async_trait]
+impl BleDescriptor for BluezDescriptor {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        Ok(self.descriptor.read().await?)
+    }
+}
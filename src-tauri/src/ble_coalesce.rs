@@ -0,0 +1,340 @@
+//! Coalescing of battery info events per device.
+//!
+//! Some boards notify on every ADC sample, and each event costs the frontend a
+//! history write and possibly a tray redraw. The connection watcher sends its
+//! events through a `CoalescingSink`, which drops parts whose level and power
+//! state did not change, gathers parts that change together into one event,
+//! and emits at most one event per `min_interval_ms`. A change after a quiet
+//! stretch still goes out as soon as the short batch window closes.
+
+use crate::ble::{
+    BatteryEventSink, BatteryInfo, BatteryInfoNotificationEvent, BatteryMonitorDiagnosticEvent,
+    BatteryMonitorStatusEvent, BluetoothAdapterStatusEvent,
+};
+use crate::error::CommandError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{sleep_until, Duration, Instant};
+
+const MAX_BATCH_WINDOW_MS: u64 = 1_000;
+const MAX_MIN_INTERVAL_MS: u64 = 60_000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct CoalesceOptions {
+    /// How long a change waits for the device's other parts to change too.
+    pub batch_window_ms: u64,
+    /// Least time between two events of one device; 0 disables the limit.
+    pub min_interval_ms: u64,
+}
+
+impl Default for CoalesceOptions {
+    fn default() -> Self {
+        Self {
+            batch_window_ms: 50,
+            min_interval_ms: 1_000,
+        }
+    }
+}
+
+impl CoalesceOptions {
+    pub fn validate(&self) -> Result<(), CommandError> {
+        if self.batch_window_ms > MAX_BATCH_WINDOW_MS {
+            return Err(CommandError::InvalidArgument(format!(
+                "Batch window must be at most {MAX_BATCH_WINDOW_MS} ms"
+            )));
+        }
+        if self.min_interval_ms > MAX_MIN_INTERVAL_MS {
+            return Err(CommandError::InvalidArgument(format!(
+                "Minimum interval must be at most {MAX_MIN_INTERVAL_MS} ms"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Pending changes of one device and what was last emitted for it.
+struct Coalescer {
+    options: CoalesceOptions,
+    /// Last emitted info by part key.
+    emitted: HashMap<String, BatteryInfo>,
+    /// Latest info of each changed part, in the order the parts first changed.
+    pending: Vec<BatteryInfo>,
+    last_emit: Option<Instant>,
+    flush_at: Option<Instant>,
+}
+
+impl Coalescer {
+    fn new(options: CoalesceOptions) -> Self {
+        Self {
+            options,
+            emitted: HashMap::new(),
+            pending: Vec::new(),
+            last_emit: None,
+            flush_at: None,
+        }
+    }
+
+    fn push(&mut self, info: BatteryInfo, now: Instant) {
+        self.pending.retain(|p| p.part_key != info.part_key);
+        let unchanged = self.emitted.get(&info.part_key).is_some_and(|emitted| {
+            emitted.battery_level == info.battery_level && emitted.power_state == info.power_state
+        });
+        if !unchanged {
+            self.pending.push(info);
+        }
+
+        if self.pending.is_empty() {
+            self.flush_at = None;
+        } else if self.flush_at.is_none() {
+            let batched = now + Duration::from_millis(self.options.batch_window_ms);
+            let limited = self
+                .last_emit
+                .map(|at| at + Duration::from_millis(self.options.min_interval_ms));
+            self.flush_at = Some(limited.map_or(batched, |limited| limited.max(batched)));
+        }
+    }
+
+    fn take(&mut self, now: Instant) -> Vec<BatteryInfo> {
+        self.flush_at = None;
+        let infos = std::mem::take(&mut self.pending);
+        if !infos.is_empty() {
+            self.last_emit = Some(now);
+            for info in &infos {
+                self.emitted.insert(info.part_key.clone(), info.clone());
+            }
+        }
+        infos
+    }
+
+    /// After a disconnect, the first readings of the next connection are sent
+    /// even when they match the last ones.
+    fn forget_emitted(&mut self) {
+        self.emitted.clear();
+    }
+}
+
+enum Input {
+    Info(BatteryInfoNotificationEvent),
+    Status(BatteryMonitorStatusEvent),
+}
+
+/// Sink that coalesces the battery info events of one device before passing
+/// them on. Status events are passed on in order, after any pending infos;
+/// adapter and diagnostic events are passed on at once.
+pub(crate) struct CoalescingSink {
+    inner: Arc<dyn BatteryEventSink>,
+    tx: mpsc::UnboundedSender<Input>,
+}
+
+impl CoalescingSink {
+    /// Starts the task that emits coalesced events; it flushes and ends once
+    /// the sink is dropped.
+    pub fn spawn(
+        inner: Arc<dyn BatteryEventSink>,
+        device_id: &str,
+        options: CoalesceOptions,
+    ) -> Arc<Self> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run_coalescer(
+            inner.clone(),
+            device_id.to_string(),
+            options,
+            rx,
+        ));
+        Arc::new(Self { inner, tx })
+    }
+}
+
+impl BatteryEventSink for CoalescingSink {
+    fn battery_info(&self, event: BatteryInfoNotificationEvent) {
+        let _ = self.tx.send(Input::Info(event));
+    }
+
+    fn monitor_status(&self, event: BatteryMonitorStatusEvent) {
+        let _ = self.tx.send(Input::Status(event));
+    }
+
+    fn adapter_status(&self, event: BluetoothAdapterStatusEvent) {
+        self.inner.adapter_status(event);
+    }
+
+    fn diagnostic(&self, event: BatteryMonitorDiagnosticEvent) {
+        self.inner.diagnostic(event);
+    }
+}
+
+async fn run_coalescer(
+    inner: Arc<dyn BatteryEventSink>,
+    device_id: String,
+    options: CoalesceOptions,
+    mut rx: mpsc::UnboundedReceiver<Input>,
+) {
+    let mut coalescer = Coalescer::new(options);
+    let flush = |coalescer: &mut Coalescer| {
+        let battery_infos = coalescer.take(Instant::now());
+        if !battery_infos.is_empty() {
+            inner.battery_info(BatteryInfoNotificationEvent {
+                id: device_id.clone(),
+                battery_infos,
+            });
+        }
+    };
+
+    loop {
+        let flush_at = coalescer.flush_at;
+        tokio::select! {
+            input = rx.recv() => match input {
+                Some(Input::Info(event)) => {
+                    let now = Instant::now();
+                    for info in event.battery_infos {
+                        coalescer.push(info, now);
+                    }
+                }
+                Some(Input::Status(event)) => {
+                    flush(&mut coalescer);
+                    if !event.connected {
+                        coalescer.forget_emitted();
+                    }
+                    inner.monitor_status(event);
+                }
+                None => {
+                    flush(&mut coalescer);
+                    return;
+                }
+            },
+            _ = async {
+                match flush_at {
+                    Some(at) => sleep_until(at).await,
+                    None => std::future::pending().await,
+                }
+            } => flush(&mut coalescer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble::{RecordingSink, SinkEvent};
+    use crate::ble_power_state::PowerState;
+    use tokio::time::sleep;
+
+    fn info(part_key: &str, level: u8) -> BatteryInfo {
+        BatteryInfo {
+            battery_level: Some(level),
+            user_description: None,
+            part_key: part_key.to_string(),
+            presentation: None,
+            power_state: PowerState::Unknown,
+            raw_battery_level: Some(level),
+            reading_flag: None,
+        }
+    }
+
+    /// Each emitted event as (part key, level) pairs.
+    type Batch = Vec<(String, Option<u8>)>;
+
+    /// A status event shows up as an empty batch.
+    fn record(event: SinkEvent) -> Vec<Batch> {
+        match event {
+            SinkEvent::BatteryInfo(event) => vec![event
+                .battery_infos
+                .into_iter()
+                .map(|info| (info.part_key, info.battery_level))
+                .collect()],
+            SinkEvent::MonitorStatus(_) => vec![Vec::new()],
+            SinkEvent::AdapterStatus(_) | SinkEvent::Diagnostic(_) => vec![],
+        }
+    }
+
+    fn start() -> (Arc<CoalescingSink>, mpsc::UnboundedReceiver<Batch>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let sink = CoalescingSink::spawn(
+            Arc::new(RecordingSink::new(tx, record)),
+            "kbd-1",
+            CoalesceOptions::default(),
+        );
+        (sink, rx)
+    }
+
+    fn send(sink: &CoalescingSink, infos: Vec<BatteryInfo>) {
+        sink.battery_info(BatteryInfoNotificationEvent {
+            id: "kbd-1".to_string(),
+            battery_infos: infos,
+        });
+    }
+
+    fn batch(parts: &[(&str, u8)]) -> Batch {
+        parts
+            .iter()
+            .map(|&(key, level)| (key.to_string(), Some(level)))
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn parts_changing_together_share_one_event() {
+        let (sink, mut rx) = start();
+        let started = Instant::now();
+        send(&sink, vec![info("0:0", 80)]);
+        sleep(Duration::from_millis(10)).await;
+        send(&sink, vec![info("0:1", 70)]);
+
+        assert_eq!(rx.recv().await, Some(batch(&[("0:0", 80), ("0:1", 70)])));
+        assert_eq!(started.elapsed(), Duration::from_millis(50));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unchanged_levels_are_dropped() {
+        let (sink, mut rx) = start();
+        send(&sink, vec![info("0:0", 80)]);
+        assert_eq!(rx.recv().await, Some(batch(&[("0:0", 80)])));
+
+        sleep(Duration::from_secs(5)).await;
+        send(&sink, vec![info("0:0", 80)]);
+        // A change that is reverted before it went out is dropped as well.
+        send(&sink, vec![info("0:0", 79)]);
+        send(&sink, vec![info("0:0", 80)]);
+        sleep(Duration::from_secs(5)).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn emit_rate_is_limited_to_latest_values() {
+        let (sink, mut rx) = start();
+        let started = Instant::now();
+        send(&sink, vec![info("0:0", 80)]);
+        assert_eq!(rx.recv().await, Some(batch(&[("0:0", 80)])));
+
+        for level in [79, 78, 77] {
+            sleep(Duration::from_millis(100)).await;
+            send(&sink, vec![info("0:0", level)]);
+        }
+        assert_eq!(rx.recv().await, Some(batch(&[("0:0", 77)])));
+        assert_eq!(started.elapsed(), Duration::from_millis(1_050));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn disconnect_flushes_pending_and_resends_after_reconnect() {
+        let (sink, mut rx) = start();
+        send(&sink, vec![info("0:0", 80)]);
+        sink.monitor_status(BatteryMonitorStatusEvent::new("kbd-1".to_string(), false));
+        assert_eq!(rx.recv().await, Some(batch(&[("0:0", 80)])));
+        assert_eq!(rx.recv().await, Some(Vec::new()));
+
+        send(&sink, vec![info("0:0", 80)]);
+        assert_eq!(rx.recv().await, Some(batch(&[("0:0", 80)])));
+    }
+
+    #[test]
+    fn oversized_windows_are_refused() {
+        let options = CoalesceOptions {
+            batch_window_ms: 5_000,
+            ..CoalesceOptions::default()
+        };
+        assert!(options.validate().is_err());
+        assert!(CoalesceOptions::default().validate().is_ok());
+    }
+}
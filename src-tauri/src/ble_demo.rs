@@ -0,0 +1,501 @@
+//! Demo mode: serve virtual ZMK keyboards from a JSON file instead of the OS
+//! Bluetooth stack.
+//!
+//! Enabled with `--demo-devices <path>` or the `ZMK_BATTERY_CENTER_DEMO_DEVICES`
+//! environment variable. The file describes each keyboard's parts, their drain
+//! rate, charge windows and scheduled disconnects; a driver task advances the
+//! simulated world on a timer so the tray, charts and notifications behave as
+//! they would with real hardware.
+
+use crate::ble_simulated::{SimAction, SimulatedAdapter, SimulatedKeyboard, SimulatedTransport};
+use crate::ble_transport;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::time::{interval, Duration, Instant, MissedTickBehavior};
+
+const DEMO_DEVICES_ENV: &str = "ZMK_BATTERY_CENTER_DEMO_DEVICES";
+const DEMO_DEVICES_FLAG: &str = "--demo-devices";
+
+fn default_time_scale() -> f64 {
+    1.0
+}
+
+fn default_tick_secs() -> u64 {
+    5
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DemoConfig {
+    /// Simulated seconds per real second, to fast-forward drain curves.
+    #[serde(default = "default_time_scale")]
+    time_scale: f64,
+    /// How often (in real seconds) the driver recomputes levels.
+    #[serde(default = "default_tick_secs")]
+    tick_secs: u64,
+    keyboards: Vec<DemoKeyboard>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DemoKeyboard {
+    id: String,
+    name: String,
+    #[serde(default = "default_true")]
+    connected: bool,
+    parts: Vec<DemoPart>,
+    #[serde(default)]
+    events: Vec<DemoEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DemoPart {
+    #[serde(default)]
+    user_description: Option<String>,
+    level: u8,
+    /// Percentage points lost per simulated hour.
+    #[serde(default)]
+    drain_per_hour: f64,
+    #[serde(default = "default_true")]
+    notify: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum DemoEvent {
+    Disconnect {
+        at_secs: u64,
+    },
+    Connect {
+        at_secs: u64,
+    },
+    /// Charge `part` at `per_hour` percentage points per hour for `duration_secs`.
+    Charge {
+        at_secs: u64,
+        part: usize,
+        duration_secs: u64,
+        per_hour: f64,
+    },
+}
+
+fn parse_demo_config(json: &str) -> Result<DemoConfig, String> {
+    let config: DemoConfig =
+        serde_json::from_str(json).map_err(|e| format!("Invalid demo devices file: {e}"))?;
+
+    if config.time_scale <= 0.0 {
+        return Err("Invalid demo devices file: time_scale must be positive".to_string());
+    }
+    if config.tick_secs == 0 {
+        return Err("Invalid demo devices file: tick_secs must be at least 1".to_string());
+    }
+
+    let mut ids = HashSet::new();
+    for keyboard in &config.keyboards {
+        if !ids.insert(keyboard.id.as_str()) {
+            return Err(format!(
+                "Invalid demo devices file: duplicate keyboard id {}",
+                keyboard.id
+            ));
+        }
+        if keyboard.parts.is_empty() {
+            return Err(format!(
+                "Invalid demo devices file: keyboard {} has no parts",
+                keyboard.id
+            ));
+        }
+        if keyboard.parts.iter().any(|part| part.level > 100) {
+            return Err(format!(
+                "Invalid demo devices file: keyboard {} has a level above 100",
+                keyboard.id
+            ));
+        }
+        for event in &keyboard.events {
+            if let DemoEvent::Charge { part, .. } = event {
+                if *part >= keyboard.parts.len() {
+                    return Err(format!(
+                        "Invalid demo devices file: keyboard {} charges unknown part {}",
+                        keyboard.id, part
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+/// Level of `part` after `elapsed_secs` simulated seconds: linear drain,
+/// interrupted by charge windows, clamped to 0..=100.
+fn part_level_at(keyboard: &DemoKeyboard, part: usize, elapsed_secs: f64) -> u8 {
+    let definition = &keyboard.parts[part];
+    let mut windows: Vec<(f64, f64, f64)> = keyboard
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            DemoEvent::Charge {
+                at_secs,
+                part: charged,
+                duration_secs,
+                per_hour,
+            } if *charged == part => Some((
+                *at_secs as f64,
+                (*at_secs + *duration_secs) as f64,
+                *per_hour,
+            )),
+            _ => None,
+        })
+        .collect();
+    windows.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let drain =
+        |level: f64, secs: f64| (level - definition.drain_per_hour * secs / 3600.0).max(0.0);
+
+    let mut level = f64::from(definition.level);
+    let mut t = 0.0;
+    for (start, end, per_hour) in windows {
+        if start >= elapsed_secs {
+            break;
+        }
+        let start = start.max(t);
+        level = drain(level, start - t);
+        let end = end.min(elapsed_secs).max(start);
+        level = (level + per_hour * (end - start) / 3600.0).min(100.0);
+        t = end;
+    }
+    level = drain(level, (elapsed_secs - t).max(0.0));
+
+    level.round().clamp(0.0, 100.0) as u8
+}
+
+fn keyboard_connected_at(keyboard: &DemoKeyboard, elapsed_secs: f64) -> bool {
+    let mut changes: Vec<(u64, bool)> = keyboard
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            DemoEvent::Disconnect { at_secs } => Some((*at_secs, false)),
+            DemoEvent::Connect { at_secs } => Some((*at_secs, true)),
+            DemoEvent::Charge { .. } => None,
+        })
+        .collect();
+    changes.sort_by_key(|(at_secs, _)| *at_secs);
+
+    changes
+        .into_iter()
+        .take_while(|(at_secs, _)| (*at_secs as f64) <= elapsed_secs)
+        .last()
+        .map(|(_, connected)| connected)
+        .unwrap_or(keyboard.connected)
+}
+
+struct DemoDriver {
+    config: DemoConfig,
+    adapter: SimulatedAdapter,
+    connected: Vec<bool>,
+    levels: Vec<Vec<u8>>,
+}
+
+impl DemoDriver {
+    fn new(config: DemoConfig) -> Self {
+        let mut keyboards = Vec::new();
+        let mut connected = Vec::new();
+        let mut levels = Vec::new();
+
+        for keyboard in &config.keyboards {
+            let mut simulated = SimulatedKeyboard::new(&keyboard.id, &keyboard.name);
+            for part in &keyboard.parts {
+                simulated = simulated.part(part.user_description.as_deref(), part.level);
+                if !part.notify {
+                    simulated = simulated.without_notify();
+                }
+            }
+            if !keyboard.connected {
+                simulated = simulated.disconnected();
+            }
+            keyboards.push(simulated);
+            connected.push(keyboard.connected);
+            levels.push(keyboard.parts.iter().map(|part| part.level).collect());
+        }
+
+        let mut driver = Self {
+            config,
+            adapter: SimulatedAdapter::new(keyboards),
+            connected,
+            levels,
+        };
+        driver.advance_to(0.0);
+        driver
+    }
+
+    /// Push every level and connection change up to `elapsed_secs` into the
+    /// simulated adapter. Levels are updated before a reconnect so the monitor
+    /// reads fresh values when it picks the keyboard up again.
+    fn advance_to(&mut self, elapsed_secs: f64) {
+        for (index, keyboard) in self.config.keyboards.iter().enumerate() {
+            let connected = keyboard_connected_at(keyboard, elapsed_secs);
+            if !connected && self.connected[index] {
+                self.adapter.apply(&SimAction::Disconnect {
+                    device: keyboard.id.clone(),
+                });
+            }
+
+            for part in 0..keyboard.parts.len() {
+                let level = part_level_at(keyboard, part, elapsed_secs);
+                if level != self.levels[index][part] {
+                    self.levels[index][part] = level;
+                    self.adapter.apply(&SimAction::SetLevel {
+                        device: keyboard.id.clone(),
+                        part,
+                        level,
+                    });
+                }
+            }
+
+            if connected && !self.connected[index] {
+                self.adapter.apply(&SimAction::Connect {
+                    device: keyboard.id.clone(),
+                });
+            }
+            self.connected[index] = connected;
+        }
+    }
+
+    async fn run(mut self) {
+        let start = Instant::now();
+        let mut ticker = interval(Duration::from_secs(self.config.tick_secs));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let elapsed_secs = start.elapsed().as_secs_f64() * self.config.time_scale;
+            self.advance_to(elapsed_secs);
+        }
+    }
+}
+
+/// Value of `--flag <path>` or `--flag=<path>` in `args`, falling back to a
+/// non-empty `env_value`. Shared with the trace flags in `ble_trace.rs`.
+pub(crate) fn path_from_args(
+    args: &[String],
+    flag: &str,
+    env_value: Option<&str>,
+) -> Option<PathBuf> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == flag {
+            if let Some(path) = iter.next() {
+                return Some(PathBuf::from(path));
+            }
+        } else if let Some(path) = arg.strip_prefix(flag).and_then(|s| s.strip_prefix('=')) {
+            return Some(PathBuf::from(path));
+        }
+    }
+
+    env_value
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+fn demo_devices_path_from(args: &[String], env_value: Option<&str>) -> Option<PathBuf> {
+    path_from_args(args, DEMO_DEVICES_FLAG, env_value)
+}
+
+/// Path of the demo devices file from the command line or environment, if demo
+/// mode was requested. The command-line flag wins over the environment.
+pub fn demo_devices_path() -> Option<PathBuf> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let env_value = std::env::var(DEMO_DEVICES_ENV).ok();
+    demo_devices_path_from(&args, env_value.as_deref())
+}
+
+/// Load the demo devices file, route all BLE commands to the virtual keyboards
+/// and start the driver task. Must run before the first BLE command.
+pub fn start(path: &Path) -> Result<(), String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read demo devices file {}: {e}", path.display()))?;
+    let config = parse_demo_config(&json)?;
+    log::info!(
+        "BLE demo: serving {} virtual keyboards from {}",
+        config.keyboards.len(),
+        path.display()
+    );
+
+    let driver = DemoDriver::new(config);
+    ble_transport::install_transport(Arc::new(SimulatedTransport::new(driver.adapter.clone())))?;
+    tauri::async_runtime::spawn(driver.run());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble_transport::BleAdapter;
+
+    const SAMPLE: &str = r#"{
+        "time_scale": 60,
+        "keyboards": [
+            {
+                "id": "demo-corne",
+                "name": "Demo Corne",
+                "parts": [
+                    { "user_description": "Central", "level": 90, "drain_per_hour": 10 },
+                    { "user_description": "Peripheral 0", "level": 80, "drain_per_hour": 20 }
+                ],
+                "events": [
+                    { "type": "disconnect", "at_secs": 1800 },
+                    { "type": "connect", "at_secs": 3600 },
+                    { "type": "charge", "at_secs": 7200, "part": 1, "duration_secs": 1800, "per_hour": 100 }
+                ]
+            },
+            {
+                "id": "demo-single",
+                "name": "Demo Single",
+                "connected": false,
+                "parts": [{ "level": 50 }]
+            }
+        ]
+    }"#;
+
+    fn sample() -> DemoConfig {
+        parse_demo_config(SAMPLE).expect("parse sample")
+    }
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    async fn connected_ids(adapter: &SimulatedAdapter) -> Vec<String> {
+        adapter
+            .connected_devices_with_services(&[])
+            .await
+            .expect("list")
+            .iter()
+            .map(|d| d.id())
+            .collect()
+    }
+
+    #[test]
+    fn example_file_in_docs_parses() {
+        let config = parse_demo_config(include_str!("../../docs/demo-devices.example.json"))
+            .expect("parse docs example");
+        assert_eq!(config.keyboards.len(), 3);
+    }
+
+    #[test]
+    fn parse_applies_defaults() {
+        let config = sample();
+        assert_eq!(config.time_scale, 60.0);
+        assert_eq!(config.tick_secs, 5);
+        let single = &config.keyboards[1];
+        assert!(!single.connected);
+        assert_eq!(single.parts[0].user_description, None);
+        assert_eq!(single.parts[0].drain_per_hour, 0.0);
+        assert!(single.parts[0].notify);
+    }
+
+    #[test]
+    fn parse_rejects_invalid_files() {
+        assert!(parse_demo_config("not json").is_err());
+        assert!(parse_demo_config(r#"{"keyboards": [], "unknown": 1}"#).is_err());
+        assert!(
+            parse_demo_config(r#"{"keyboards": [{"id": "a", "name": "A", "parts": []}]}"#).is_err()
+        );
+        assert!(parse_demo_config(
+            r#"{"keyboards": [{"id": "a", "name": "A", "parts": [{"level": 101}]}]}"#
+        )
+        .is_err());
+        assert!(parse_demo_config(
+            r#"{"keyboards": [
+                {"id": "a", "name": "A", "parts": [{"level": 1}]},
+                {"id": "a", "name": "B", "parts": [{"level": 1}]}
+            ]}"#
+        )
+        .is_err());
+        assert!(parse_demo_config(
+            r#"{"keyboards": [{"id": "a", "name": "A", "parts": [{"level": 1}],
+                "events": [{"type": "charge", "at_secs": 0, "part": 3, "duration_secs": 1, "per_hour": 1}]}]}"#
+        )
+        .is_err());
+        assert!(parse_demo_config(r#"{"time_scale": 0, "keyboards": []}"#).is_err());
+    }
+
+    #[test]
+    fn levels_drain_linearly_and_charge_in_windows() {
+        let config = sample();
+        let corne = &config.keyboards[0];
+        assert_eq!(part_level_at(corne, 0, 0.0), 90);
+        assert_eq!(part_level_at(corne, 0, 3600.0), 80);
+        assert_eq!(part_level_at(corne, 1, 3600.0), 60);
+        // 80 - 40 by the charge start, +50 during the window, -10 afterwards.
+        assert_eq!(part_level_at(corne, 1, 7200.0), 40);
+        assert_eq!(part_level_at(corne, 1, 9000.0), 90);
+        assert_eq!(part_level_at(corne, 1, 10800.0), 80);
+        assert_eq!(part_level_at(corne, 0, 100.0 * 3600.0), 0);
+    }
+
+    #[test]
+    fn charge_is_capped_at_full() {
+        let config = parse_demo_config(
+            r#"{"keyboards": [{"id": "a", "name": "A", "parts": [{"level": 95}],
+                "events": [{"type": "charge", "at_secs": 0, "part": 0, "duration_secs": 3600, "per_hour": 100}]}]}"#,
+        )
+        .expect("parse");
+        assert_eq!(part_level_at(&config.keyboards[0], 0, 3600.0), 100);
+    }
+
+    #[test]
+    fn scheduled_disconnects_follow_event_times() {
+        let config = sample();
+        let corne = &config.keyboards[0];
+        assert!(keyboard_connected_at(corne, 0.0));
+        assert!(!keyboard_connected_at(corne, 1800.0));
+        assert!(!keyboard_connected_at(corne, 3599.0));
+        assert!(keyboard_connected_at(corne, 3600.0));
+        assert!(!keyboard_connected_at(&config.keyboards[1], 1e9));
+    }
+
+    #[tokio::test]
+    async fn driver_pushes_changes_into_simulated_adapter() {
+        let mut driver = DemoDriver::new(sample());
+        assert_eq!(
+            connected_ids(&driver.adapter).await,
+            vec!["demo-corne".to_string()]
+        );
+
+        driver.advance_to(1800.0);
+        assert!(connected_ids(&driver.adapter).await.is_empty());
+
+        driver.advance_to(3600.0);
+        assert_eq!(
+            connected_ids(&driver.adapter).await,
+            vec!["demo-corne".to_string()]
+        );
+        assert_eq!(driver.levels[0], vec![80, 60]);
+    }
+
+    #[test]
+    fn cli_flag_takes_precedence_over_env() {
+        assert_eq!(
+            demo_devices_path_from(&args(&["--demo-devices", "a.json"]), Some("b.json")),
+            Some(PathBuf::from("a.json"))
+        );
+        assert_eq!(
+            demo_devices_path_from(&args(&["--demo-devices=c.json"]), None),
+            Some(PathBuf::from("c.json"))
+        );
+        assert_eq!(
+            demo_devices_path_from(&args(&["--other"]), Some("b.json")),
+            Some(PathBuf::from("b.json"))
+        );
+        assert_eq!(demo_devices_path_from(&args(&[]), Some("")), None);
+        assert_eq!(
+            demo_devices_path_from(&args(&["--demo-devices"]), None),
+            None
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Device metadata from the Device Information Service (0x180A) and the GAP
+//! Appearance characteristic.
+//!
+//! These values only change with a firmware update, so they are read on request
+//! and cached per device id. Some stacks hide the Generic Access service (BlueZ
+//! and CoreBluetooth do); the appearance is then simply left empty.
+
+use crate::ble_transport::BleDevice;
+use crate::error::CommandError;
+use bluest::btuuid::{characteristics, services};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+const HID_APPEARANCE_CATEGORY: u16 = 0x00F;
+
+/// Device type derived from the GAP Appearance value.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceKind {
+    Keyboard,
+    Mouse,
+    Joystick,
+    Gamepad,
+    DigitizerTablet,
+    Touchpad,
+    PresentationRemote,
+    /// Generic HID or a HID subcategory without a dedicated kind.
+    OtherHid,
+    Other,
+}
+
+impl DeviceKind {
+    /// The appearance packs the category in bits 15..6 and the subcategory in
+    /// bits 5..0 (Bluetooth Assigned Numbers, section 2.6).
+    pub fn from_appearance(appearance: u16) -> Self {
+        if appearance >> 6 != HID_APPEARANCE_CATEGORY {
+            return Self::Other;
+        }
+        match appearance & 0x3F {
+            0x01 => Self::Keyboard,
+            0x02 => Self::Mouse,
+            0x03 => Self::Joystick,
+            0x04 => Self::Gamepad,
+            0x05 => Self::DigitizerTablet,
+            0x09 => Self::Touchpad,
+            0x0A => Self::PresentationRemote,
+            _ => Self::OtherHid,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default, PartialEq)]
+pub struct DeviceMetadata {
+    pub manufacturer: Option<String>,
+    pub model_number: Option<String>,
+    pub serial_number: Option<String>,
+    pub hardware_revision: Option<String>,
+    pub firmware_revision: Option<String>,
+    pub software_revision: Option<String>,
+    pub appearance: Option<u16>,
+    pub device_kind: Option<DeviceKind>,
+}
+
+impl DeviceMetadata {
+    fn apply(&mut self, characteristic: Uuid, value: &[u8]) {
+        match characteristic {
+            characteristics::MANUFACTURER_NAME_STRING => self.manufacturer = decode_string(value),
+            characteristics::MODEL_NUMBER_STRING => self.model_number = decode_string(value),
+            characteristics::SERIAL_NUMBER_STRING => self.serial_number = decode_string(value),
+            characteristics::HARDWARE_REVISION_STRING => {
+                self.hardware_revision = decode_string(value)
+            }
+            characteristics::FIRMWARE_REVISION_STRING => {
+                self.firmware_revision = decode_string(value)
+            }
+            characteristics::SOFTWARE_REVISION_STRING => {
+                self.software_revision = decode_string(value)
+            }
+            characteristics::APPEARANCE => {
+                if let [low, high, ..] = value {
+                    let appearance = u16::from_le_bytes([*low, *high]);
+                    self.appearance = Some(appearance);
+                    self.device_kind = Some(DeviceKind::from_appearance(appearance));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// DIS strings are UTF-8 without a terminator, but some firmware pads them
+/// with NULs.
+pub(crate) fn decode_string(value: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(value);
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Read every metadata characteristic the device exposes. Individual read
+/// failures leave the field empty; only service discovery errors fail.
+pub(crate) async fn read_device_metadata(
+    device: &dyn BleDevice,
+) -> Result<DeviceMetadata, CommandError> {
+    let device_id = device.id();
+    log::debug!("BLE I/O: discovering metadata services for device id={device_id}");
+    let services = device.services().await?;
+    let mut metadata = DeviceMetadata::default();
+
+    for service in services.iter().filter(|service| {
+        service.uuid() == services::DEVICE_INFORMATION || service.uuid() == services::GENERIC_ACCESS
+    }) {
+        let characteristics = match service.characteristics().await {
+            Ok(characteristics) => characteristics,
+            Err(e) => {
+                log::debug!(
+                    "BLE I/O: metadata characteristics failed service={} device_id={device_id}: {e}",
+                    service.uuid()
+                );
+                continue;
+            }
+        };
+
+        for characteristic in &characteristics {
+            let uuid = characteristic.uuid();
+            match characteristic.read().await {
+                Ok(value) => {
+                    log::debug!(
+                        "BLE I/O: read metadata characteristic={uuid} bytes={} device_id={device_id}",
+                        value.len()
+                    );
+                    metadata.apply(uuid, &value);
+                }
+                Err(e) => log::debug!(
+                    "BLE I/O: metadata read failed characteristic={uuid} device_id={device_id}: {e}"
+                ),
+            }
+        }
+    }
+
+    Ok(metadata)
+}
+
+static METADATA_CACHE: LazyLock<Mutex<HashMap<String, DeviceMetadata>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub(crate) async fn cached_metadata(device_id: &str) -> Option<DeviceMetadata> {
+    METADATA_CACHE.lock().await.get(device_id).cloned()
+}
+
+pub(crate) async fn cache_metadata(device_id: &str, metadata: DeviceMetadata) {
+    METADATA_CACHE
+        .lock()
+        .await
+        .insert(device_id.to_string(), metadata);
+}
+
+/// Keep the cached metadata of a device that now has the id `to`.
+pub(crate) async fn move_cached_metadata(from: &str, to: &str) {
+    let mut cache = METADATA_CACHE.lock().await;
+    if let Some(metadata) = cache.remove(from) {
+        cache.insert(to.to_string(), metadata);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble_simulated::{SimAction, SimulatedAdapter, SimulatedKeyboard};
+
+    fn zmk_keyboard(id: &str) -> SimulatedKeyboard {
+        SimulatedKeyboard::new(id, "Corne")
+            .part(None, 80)
+            .characteristic(
+                services::DEVICE_INFORMATION,
+                characteristics::MANUFACTURER_NAME_STRING,
+                b"ZMK Project",
+            )
+            .characteristic(
+                services::DEVICE_INFORMATION,
+                characteristics::MODEL_NUMBER_STRING,
+                b"Corne\0\0",
+            )
+            .characteristic(
+                services::DEVICE_INFORMATION,
+                characteristics::FIRMWARE_REVISION_STRING,
+                b"v0.3.0-4-gabc1234",
+            )
+            .characteristic(
+                services::GENERIC_ACCESS,
+                characteristics::APPEARANCE,
+                &0x03C1u16.to_le_bytes(),
+            )
+    }
+
+    #[test]
+    fn appearance_maps_hid_subcategories() {
+        assert_eq!(DeviceKind::from_appearance(0x03C1), DeviceKind::Keyboard);
+        assert_eq!(DeviceKind::from_appearance(0x03C2), DeviceKind::Mouse);
+        assert_eq!(DeviceKind::from_appearance(0x03C9), DeviceKind::Touchpad);
+        assert_eq!(DeviceKind::from_appearance(0x03C0), DeviceKind::OtherHid);
+        assert_eq!(DeviceKind::from_appearance(0x0000), DeviceKind::Other);
+        assert_eq!(DeviceKind::from_appearance(0x0040), DeviceKind::Other);
+    }
+
+    #[test]
+    fn decode_string_trims_padding_and_rejects_empty() {
+        assert_eq!(decode_string(b" v1.2\0\0"), Some("v1.2".to_string()));
+        assert_eq!(decode_string(b"\0\0"), None);
+        assert_eq!(decode_string(b""), None);
+    }
+
+    #[test]
+    fn short_appearance_value_is_ignored() {
+        let mut metadata = DeviceMetadata::default();
+        metadata.apply(characteristics::APPEARANCE, &[0xC1]);
+        assert_eq!(metadata.appearance, None);
+        assert_eq!(metadata.device_kind, None);
+    }
+
+    #[tokio::test]
+    async fn reads_device_information_and_appearance() {
+        let adapter = SimulatedAdapter::new(vec![zmk_keyboard("kbd-1")]);
+        let metadata = read_device_metadata(adapter.device_handle("kbd-1").as_ref())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            metadata,
+            DeviceMetadata {
+                manufacturer: Some("ZMK Project".to_string()),
+                model_number: Some("Corne".to_string()),
+                firmware_revision: Some("v0.3.0-4-gabc1234".to_string()),
+                appearance: Some(0x03C1),
+                device_kind: Some(DeviceKind::Keyboard),
+                ..DeviceMetadata::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn device_without_metadata_services_yields_empty_metadata() {
+        let adapter =
+            SimulatedAdapter::new(vec![SimulatedKeyboard::new("kbd-1", "Corne").part(None, 80)]);
+        let metadata = read_device_metadata(adapter.device_handle("kbd-1").as_ref())
+            .await
+            .unwrap();
+        assert_eq!(metadata, DeviceMetadata::default());
+    }
+
+    #[tokio::test]
+    async fn disconnected_device_fails() {
+        let adapter = SimulatedAdapter::new(vec![zmk_keyboard("kbd-1")]);
+        adapter.apply(&SimAction::Disconnect {
+            device: "kbd-1".to_string(),
+        });
+        assert!(
+            read_device_metadata(adapter.device_handle("kbd-1").as_ref())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Recognizing a registered device that came back under a new id.
+//!
+//! Devices are stored by the id the OS gives them, and that id changes when a
+//! keyboard is re-paired or the stack rotates its address. A fingerprint of
+//! what the device says about itself (name, Device Information strings and
+//! the layout of its Battery Level characteristics) lets the frontend offer
+//! to link the new id to the registered device instead of adding a stranger.
+
+use crate::ble_device_info::DeviceMetadata;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DeviceFingerprint {
+    pub name: String,
+    pub manufacturer: Option<String>,
+    pub model_number: Option<String>,
+    pub serial_number: Option<String>,
+    /// User descriptions of the Battery Level characteristics in discovery
+    /// order; a split keyboard lists its central and each peripheral.
+    pub battery_layout: Vec<Option<String>>,
+}
+
+impl DeviceFingerprint {
+    pub fn new(
+        name: String,
+        metadata: &DeviceMetadata,
+        battery_layout: Vec<Option<String>>,
+    ) -> Self {
+        Self {
+            name,
+            manufacturer: metadata.manufacturer.clone(),
+            model_number: metadata.model_number.clone(),
+            serial_number: metadata.serial_number.clone(),
+            battery_layout,
+        }
+    }
+
+    /// How sure we are that `self` and `other` are the same device, if at all.
+    fn confidence(&self, other: &Self) -> Option<IdentityConfidence> {
+        if self.manufacturer != other.manufacturer || self.model_number != other.model_number {
+            return None;
+        }
+        match (&self.serial_number, &other.serial_number) {
+            (Some(a), Some(b)) if a == b => Some(IdentityConfidence::SerialNumber),
+            // Different serials, or only one side publishes one: not the same
+            // device whatever the name says.
+            (Some(_), _) | (_, Some(_)) => None,
+            // ZMK publishes no serial by default. Two boards built from the
+            // same shield look alike here, so this is only offered, never
+            // applied on its own.
+            (None, None) => (!self.battery_layout.is_empty()
+                && self.name == other.name
+                && self.battery_layout == other.battery_layout)
+                .then_some(IdentityConfidence::NameAndLayout),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityConfidence {
+    /// Same name and battery layout, no serial number on either side.
+    NameAndLayout,
+    SerialNumber,
+}
+
+/// A device the frontend has registered, with the fingerprint taken when it
+/// was last seen.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RegisteredIdentity {
+    pub id: String,
+    pub fingerprint: DeviceFingerprint,
+}
+
+/// `device_id` is not registered but looks like `registered_id`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct IdentityMatch {
+    pub device_id: String,
+    pub registered_id: String,
+    pub confidence: IdentityConfidence,
+}
+
+/// Pair unregistered devices in `seen` with the registered device they look
+/// like. A device that matches several registered ones equally well, or a
+/// registered one claimed by several devices, is left out rather than
+/// guessed.
+pub(crate) fn find_matches(
+    seen: &[(String, DeviceFingerprint)],
+    registered: &[RegisteredIdentity],
+) -> Vec<IdentityMatch> {
+    let registered_ids: HashSet<&str> = registered.iter().map(|r| r.id.as_str()).collect();
+    let mut matches: Vec<IdentityMatch> = Vec::new();
+
+    for (device_id, fingerprint) in seen {
+        if registered_ids.contains(device_id.as_str()) {
+            continue;
+        }
+        let candidates: Vec<(IdentityConfidence, &str)> = registered
+            .iter()
+            .filter_map(|r| {
+                r.fingerprint
+                    .confidence(fingerprint)
+                    .map(|confidence| (confidence, r.id.as_str()))
+            })
+            .collect();
+        let Some(best) = candidates.iter().map(|(confidence, _)| *confidence).max() else {
+            continue;
+        };
+        let mut best_ids = candidates.iter().filter(|(c, _)| *c == best);
+        let (Some((_, registered_id)), None) = (best_ids.next(), best_ids.next()) else {
+            log::debug!("BLE I/O: ambiguous identity match skipped device_id={device_id}");
+            continue;
+        };
+        matches.push(IdentityMatch {
+            device_id: device_id.clone(),
+            registered_id: registered_id.to_string(),
+            confidence: best,
+        });
+    }
+
+    let claimed: Vec<String> = matches.iter().map(|m| m.registered_id.clone()).collect();
+    matches.retain(|m| claimed.iter().filter(|id| **id == m.registered_id).count() == 1);
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corne(serial: Option<&str>) -> DeviceFingerprint {
+        DeviceFingerprint {
+            name: "Corne".to_string(),
+            manufacturer: Some("ZMK Project".to_string()),
+            model_number: Some("Corne".to_string()),
+            serial_number: serial.map(str::to_string),
+            battery_layout: vec![None, Some("Peripheral 0".to_string())],
+        }
+    }
+
+    fn registered(id: &str, fingerprint: DeviceFingerprint) -> RegisteredIdentity {
+        RegisteredIdentity {
+            id: id.to_string(),
+            fingerprint,
+        }
+    }
+
+    #[test]
+    fn serial_number_links_a_renamed_device() {
+        let seen = DeviceFingerprint {
+            name: "Corne (work)".to_string(),
+            ..corne(Some("A1B2"))
+        };
+        let matches = find_matches(
+            &[("new".to_string(), seen)],
+            &[registered("old", corne(Some("A1B2")))],
+        );
+        assert_eq!(
+            matches,
+            vec![IdentityMatch {
+                device_id: "new".to_string(),
+                registered_id: "old".to_string(),
+                confidence: IdentityConfidence::SerialNumber,
+            }]
+        );
+    }
+
+    #[test]
+    fn different_serial_numbers_never_match() {
+        let matches = find_matches(
+            &[("new".to_string(), corne(Some("A1B2")))],
+            &[registered("old", corne(Some("C3D4")))],
+        );
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn name_and_layout_match_without_serial_numbers() {
+        let matches = find_matches(
+            &[("new".to_string(), corne(None))],
+            &[registered("old", corne(None))],
+        );
+        assert_eq!(matches[0].confidence, IdentityConfidence::NameAndLayout);
+
+        let other_layout = DeviceFingerprint {
+            battery_layout: vec![None],
+            ..corne(None)
+        };
+        assert!(find_matches(
+            &[("new".to_string(), other_layout)],
+            &[registered("old", corne(None))]
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn registered_devices_are_not_matched_again() {
+        let matches = find_matches(
+            &[("old".to_string(), corne(None))],
+            &[registered("old", corne(None))],
+        );
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn ambiguous_matches_are_left_out() {
+        // Two identical boards registered: the new id could be either.
+        let matches = find_matches(
+            &[("new".to_string(), corne(None))],
+            &[
+                registered("left", corne(None)),
+                registered("right", corne(None)),
+            ],
+        );
+        assert!(matches.is_empty());
+
+        // Two new ids that both look like the one registered board.
+        let matches = find_matches(
+            &[
+                ("new-1".to_string(), corne(None)),
+                ("new-2".to_string(), corne(None)),
+            ],
+            &[registered("old", corne(None))],
+        );
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn serial_number_on_one_side_only_does_not_match() {
+        let matches = find_matches(
+            &[("new".to_string(), corne(Some("A1B2")))],
+            &[
+                registered("serial", corne(Some("A1B2"))),
+                registered("lookalike", corne(None)),
+            ],
+        );
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].registered_id, "serial");
+    }
+}
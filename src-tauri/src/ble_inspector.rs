@@ -0,0 +1,412 @@
+//! GATT dump of a connected device for bug reports.
+//!
+//! Walks every service, characteristic and descriptor, reads what can be
+//! read and decodes the values this app knows about. Failures are recorded on
+//! the node they happened on, so a device with one broken characteristic
+//! still produces a useful dump.
+
+use crate::ble::bytes_to_hex;
+use crate::ble_device_info::{self, DeviceKind};
+use crate::ble_power_state::PowerStateFormat;
+use crate::ble_presentation::PresentationFormat;
+use crate::ble_transport::{BleCharacteristic, BleDescriptor, BleDevice, BleService};
+use crate::error::CommandError;
+use bluest::btuuid::{characteristics, descriptors, BluetoothUuidExt};
+use bluest::CharacteristicProperties;
+use serde::Serialize;
+use uuid::Uuid;
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct GattValue {
+    /// Raw bytes, uppercase and space separated.
+    pub hex: String,
+    /// Human-readable form for values this app understands.
+    pub decoded: Option<String>,
+}
+
+impl GattValue {
+    fn new(value: &[u8], decoded: Option<String>) -> Self {
+        Self {
+            hex: bytes_to_hex(value),
+            decoded,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GattDescriptorInfo {
+    pub uuid: String,
+    pub name: Option<&'static str>,
+    pub value: Option<GattValue>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GattCharacteristicInfo {
+    pub uuid: String,
+    pub name: Option<&'static str>,
+    pub properties: Vec<&'static str>,
+    /// Only read when the characteristic is readable.
+    pub value: Option<GattValue>,
+    pub error: Option<String>,
+    /// From the User Description descriptor (0x2901), if any.
+    pub user_description: Option<String>,
+    pub presentation_format: Option<PresentationFormat>,
+    pub descriptors: Vec<GattDescriptorInfo>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GattServiceInfo {
+    pub uuid: String,
+    pub name: Option<&'static str>,
+    pub characteristics: Vec<GattCharacteristicInfo>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GattInspection {
+    pub app_version: &'static str,
+    pub device_id: String,
+    pub name: Option<String>,
+    pub services: Vec<GattServiceInfo>,
+}
+
+/// Assigned-number names of the attributes that matter for battery
+/// reporting and HID keyboards. Others are listed by UUID only.
+fn known_name(uuid: Uuid) -> Option<&'static str> {
+    Some(match uuid.try_to_u16()? {
+        0x1800 => "Generic Access",
+        0x1801 => "Generic Attribute",
+        0x180A => "Device Information",
+        0x180F => "Battery",
+        0x1812 => "Human Interface Device",
+        0x2A00 => "Device Name",
+        0x2A01 => "Appearance",
+        0x2A04 => "Peripheral Preferred Connection Parameters",
+        0x2A05 => "Service Changed",
+        0x2A19 => "Battery Level",
+        0x2A1A => "Battery Power State",
+        0x2A22 => "Boot Keyboard Input Report",
+        0x2A24 => "Model Number String",
+        0x2A25 => "Serial Number String",
+        0x2A26 => "Firmware Revision String",
+        0x2A27 => "Hardware Revision String",
+        0x2A28 => "Software Revision String",
+        0x2A29 => "Manufacturer Name String",
+        0x2A32 => "Boot Keyboard Output Report",
+        0x2A4A => "HID Information",
+        0x2A4B => "Report Map",
+        0x2A4C => "HID Control Point",
+        0x2A4D => "Report",
+        0x2A4E => "Protocol Mode",
+        0x2A50 => "PnP ID",
+        0x2AA6 => "Central Address Resolution",
+        0x2BED => "Battery Level Status",
+        0x2900 => "Characteristic Extended Properties",
+        0x2901 => "Characteristic User Description",
+        0x2902 => "Client Characteristic Configuration",
+        0x2904 => "Characteristic Presentation Format",
+        0x2908 => "Report Reference",
+        _ => return None,
+    })
+}
+
+fn property_names(properties: CharacteristicProperties) -> Vec<&'static str> {
+    [
+        (properties.broadcast, "broadcast"),
+        (properties.read, "read"),
+        (properties.write_without_response, "write_without_response"),
+        (properties.write, "write"),
+        (properties.notify, "notify"),
+        (properties.indicate, "indicate"),
+        (
+            properties.authenticated_signed_writes,
+            "authenticated_signed_writes",
+        ),
+        (properties.extended_properties, "extended_properties"),
+        (properties.reliable_write, "reliable_write"),
+        (properties.writable_auxiliaries, "writable_auxiliaries"),
+    ]
+    .into_iter()
+    .filter_map(|(set, name)| set.then_some(name))
+    .collect()
+}
+
+fn decode_characteristic(uuid: Uuid, value: &[u8]) -> Option<String> {
+    if let Some(format) = PowerStateFormat::for_characteristic(uuid) {
+        return Some(format.decode(value).as_str().to_string());
+    }
+    match uuid {
+        characteristics::BATTERY_LEVEL => value.first().map(|level| format!("{level}%")),
+        characteristics::APPEARANCE => match *value {
+            [low, high, ..] => {
+                let appearance = u16::from_le_bytes([low, high]);
+                let kind = DeviceKind::from_appearance(appearance);
+                Some(format!("{appearance:#06X} ({kind:?})"))
+            }
+            _ => None,
+        },
+        characteristics::PNP_ID => match *value {
+            [source, vendor_lo, vendor_hi, product_lo, product_hi, version_lo, version_hi, ..] => {
+                Some(format!(
+                    "source {source} vendor {:#06X} product {:#06X} version {:#06X}",
+                    u16::from_le_bytes([vendor_lo, vendor_hi]),
+                    u16::from_le_bytes([product_lo, product_hi]),
+                    u16::from_le_bytes([version_lo, version_hi]),
+                ))
+            }
+            _ => None,
+        },
+        characteristics::DEVICE_NAME
+        | characteristics::MANUFACTURER_NAME_STRING
+        | characteristics::MODEL_NUMBER_STRING
+        | characteristics::SERIAL_NUMBER_STRING
+        | characteristics::HARDWARE_REVISION_STRING
+        | characteristics::FIRMWARE_REVISION_STRING
+        | characteristics::SOFTWARE_REVISION_STRING => ble_device_info::decode_string(value),
+        _ => None,
+    }
+}
+
+fn decode_descriptor(uuid: Uuid, value: &[u8]) -> Option<String> {
+    match uuid {
+        descriptors::CHARACTERISTIC_USER_DESCRIPTION => ble_device_info::decode_string(value),
+        descriptors::CLIENT_CHARACTERISTIC_CONFIGURATION => value.first().map(|bits| {
+            format!(
+                "notifications {}, indications {}",
+                if bits & 0b01 != 0 { "on" } else { "off" },
+                if bits & 0b10 != 0 { "on" } else { "off" },
+            )
+        }),
+        descriptors::CHARACTERISTIC_PRESENTATION_FORMAT => {
+            PresentationFormat::parse(value).map(|f| {
+                let decoded = format!(
+                    "format {:#04X} exponent {} unit {:#06X} namespace {:#04X} description {:#06X}",
+                    f.format, f.exponent, f.unit, f.namespace, f.description
+                );
+                match f.description_name() {
+                    Some(name) => format!("{decoded} ({name})"),
+                    None => decoded,
+                }
+            })
+        }
+        descriptors::REPORT_REFERENCE => match *value {
+            [id, kind, ..] => {
+                let kind = match kind {
+                    1 => "input",
+                    2 => "output",
+                    3 => "feature",
+                    _ => "unknown",
+                };
+                Some(format!("report {id} ({kind})"))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The descriptor's node and its raw value, if it could be read.
+async fn inspect_descriptor(
+    descriptor: &dyn BleDescriptor,
+) -> (GattDescriptorInfo, Option<Vec<u8>>) {
+    let uuid = descriptor.uuid();
+    let mut info = GattDescriptorInfo {
+        uuid: uuid.to_string(),
+        name: known_name(uuid),
+        value: None,
+        error: None,
+    };
+    match descriptor.read().await {
+        Ok(value) => {
+            info.value = Some(GattValue::new(&value, decode_descriptor(uuid, &value)));
+            (info, Some(value))
+        }
+        Err(e) => {
+            info.error = Some(e.to_string());
+            (info, None)
+        }
+    }
+}
+
+async fn inspect_characteristic(characteristic: &dyn BleCharacteristic) -> GattCharacteristicInfo {
+    let uuid = characteristic.uuid();
+    let mut info = GattCharacteristicInfo {
+        uuid: uuid.to_string(),
+        name: known_name(uuid),
+        properties: Vec::new(),
+        value: None,
+        error: None,
+        user_description: None,
+        presentation_format: None,
+        descriptors: Vec::new(),
+    };
+
+    // Some stacks fail to report properties; try reading anyway then.
+    let readable = match characteristic.properties().await {
+        Ok(properties) => {
+            info.properties = property_names(properties);
+            properties.read
+        }
+        Err(e) => {
+            info.error = Some(e.to_string());
+            true
+        }
+    };
+    if readable {
+        match characteristic.read().await {
+            Ok(value) => {
+                info.value = Some(GattValue::new(&value, decode_characteristic(uuid, &value)))
+            }
+            Err(e) => info.error = Some(e.to_string()),
+        }
+    }
+
+    match characteristic.descriptors().await {
+        Ok(found) => {
+            for descriptor in &found {
+                let (descriptor_info, value) = inspect_descriptor(descriptor.as_ref()).await;
+                match (descriptor.uuid(), value) {
+                    (descriptors::CHARACTERISTIC_USER_DESCRIPTION, Some(value)) => {
+                        info.user_description = ble_device_info::decode_string(&value);
+                    }
+                    (descriptors::CHARACTERISTIC_PRESENTATION_FORMAT, Some(value)) => {
+                        info.presentation_format = PresentationFormat::parse(&value);
+                    }
+                    _ => {}
+                }
+                info.descriptors.push(descriptor_info);
+            }
+        }
+        Err(e) => info.error = Some(e.to_string()),
+    }
+    info
+}
+
+async fn inspect_service(service: &dyn BleService) -> GattServiceInfo {
+    let uuid = service.uuid();
+    let mut info = GattServiceInfo {
+        uuid: uuid.to_string(),
+        name: known_name(uuid),
+        characteristics: Vec::new(),
+        error: None,
+    };
+    match service.characteristics().await {
+        Ok(characteristics) => {
+            for characteristic in &characteristics {
+                info.characteristics
+                    .push(inspect_characteristic(characteristic.as_ref()).await);
+            }
+        }
+        Err(e) => info.error = Some(e.to_string()),
+    }
+    info
+}
+
+/// Dump the GATT tree of a connected device. Only service discovery errors
+/// fail; everything below is recorded on its node.
+pub(crate) async fn inspect_device(device: &dyn BleDevice) -> Result<GattInspection, CommandError> {
+    let device_id = device.id();
+    log::debug!("BLE I/O: inspecting GATT tree device_id={device_id}");
+    let services = device.services().await?;
+    let mut inspection = GattInspection {
+        app_version: env!("CARGO_PKG_VERSION"),
+        device_id,
+        name: device.name().ok(),
+        services: Vec::with_capacity(services.len()),
+    };
+    for service in &services {
+        inspection
+            .services
+            .push(inspect_service(service.as_ref()).await);
+    }
+    Ok(inspection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble_simulated::{SimulatedAdapter, SimulatedKeyboard};
+    use bluest::btuuid::services;
+
+    #[test]
+    fn known_values_are_decoded() {
+        assert_eq!(
+            decode_characteristic(characteristics::BATTERY_LEVEL, &[85]).as_deref(),
+            Some("85%")
+        );
+        assert_eq!(
+            decode_characteristic(characteristics::APPEARANCE, &0x03C1u16.to_le_bytes())
+                .as_deref(),
+            Some("0x03C1 (Keyboard)")
+        );
+        assert_eq!(
+            decode_characteristic(characteristics::MODEL_NUMBER_STRING, b"Corne\0").as_deref(),
+            Some("Corne")
+        );
+        assert_eq!(
+            decode_descriptor(descriptors::CLIENT_CHARACTERISTIC_CONFIGURATION, &[0x01, 0x00])
+                .as_deref(),
+            Some("notifications on, indications off")
+        );
+        assert_eq!(
+            decode_descriptor(descriptors::REPORT_REFERENCE, &[0x01, 0x01]).as_deref(),
+            Some("report 1 (input)")
+        );
+        assert_eq!(decode_characteristic(Uuid::nil(), &[0x01]), None);
+    }
+
+    #[tokio::test]
+    async fn inspection_lists_services_characteristics_and_descriptors() {
+        let adapter = SimulatedAdapter::new(vec![SimulatedKeyboard::split("kbd-1", "Corne", 80, 70)
+            .characteristic(
+                services::DEVICE_INFORMATION,
+                characteristics::MODEL_NUMBER_STRING,
+                b"Corne",
+            )]);
+        let device = adapter.device_handle("kbd-1");
+        device.connect().await.expect("connect");
+
+        let inspection = inspect_device(device.as_ref()).await.expect("inspect");
+
+        assert_eq!(inspection.name.as_deref(), Some("Corne"));
+        let battery = inspection
+            .services
+            .iter()
+            .find(|s| s.name == Some("Battery"))
+            .expect("battery service");
+        assert_eq!(battery.characteristics.len(), 2);
+        let central = &battery.characteristics[0];
+        assert_eq!(central.name, Some("Battery Level"));
+        assert_eq!(central.properties, vec!["read", "notify"]);
+        assert_eq!(
+            central.value.as_ref().and_then(|v| v.decoded.as_deref()),
+            Some("80%")
+        );
+        let peripheral = &battery.characteristics[1];
+        assert_eq!(peripheral.value.as_ref().map(|v| v.hex.as_str()), Some("46"));
+        assert_eq!(peripheral.user_description.as_deref(), Some("Peripheral 0"));
+        assert_eq!(
+            peripheral.descriptors[0].name,
+            Some("Characteristic User Description")
+        );
+
+        let info = inspection
+            .services
+            .iter()
+            .find(|s| s.name == Some("Device Information"))
+            .expect("device information service");
+        assert_eq!(
+            info.characteristics[0]
+                .value
+                .as_ref()
+                .and_then(|v| v.decoded.as_deref()),
+            Some("Corne")
+        );
+
+        // The dump is what gets attached to bug reports.
+        let json = serde_json::to_value(&inspection).expect("serialize");
+        assert_eq!(json["device_id"], "kbd-1");
+        assert_eq!(json["services"][0]["uuid"], battery.uuid);
+    }
+}
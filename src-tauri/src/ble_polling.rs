@@ -0,0 +1,629 @@
+//! Backend-owned polling for devices in polling mode.
+//!
+//! Every polled device gets a task that reads through `get_battery_info` and
+//! reports through the same events as the notification monitors, so polling
+//! keeps running while the webview is hidden or throttled. The configured
+//! interval is the shortest one used: it stretches while the level holds or
+//! drains slowly, and failed reads back off.
+
+use crate::ble::{
+    self, BatteryEventSink, BatteryInfo, BatteryInfoNotificationEvent, BatteryMonitorStatusEvent,
+};
+use crate::ble_power_state::PowerState;
+use crate::ble_reading::ReadingFlag;
+use crate::error::CommandError;
+use futures_util::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use tauri::AppHandle;
+use tokio::sync::{watch, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration, Instant};
+
+/// Longest interval as a multiple of the configured one.
+const MAX_STRETCH: u32 = 8;
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+/// A device that was not known to be disconnected gets this many quick
+/// attempts before it is reported as disconnected.
+const ATTEMPTS_BEFORE_DISCONNECT: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_ERROR_BACKOFF: Duration = Duration::from_secs(15 * 60);
+
+type BatteryReader = Arc<
+    dyn Fn(String) -> BoxFuture<'static, Result<Vec<BatteryInfo>, CommandError>> + Send + Sync,
+>;
+
+#[derive(Debug, PartialEq)]
+struct NextPoll {
+    delay: Duration,
+    /// The new connection state, when this read changed it.
+    connected: Option<bool>,
+}
+
+/// Interval and connection bookkeeping for one polled device.
+struct PollSchedule {
+    base: Duration,
+    connected: Option<bool>,
+    failures: u32,
+    /// The lowest part level and when it was first read at that value.
+    level_since: Option<(u8, Instant)>,
+}
+
+impl PollSchedule {
+    fn new(base: Duration) -> Self {
+        Self {
+            base: base.max(MIN_INTERVAL),
+            connected: None,
+            failures: 0,
+            level_since: None,
+        }
+    }
+
+    fn set_connected(&mut self, connected: bool) -> Option<bool> {
+        (self.connected != Some(connected)).then(|| {
+            self.connected = Some(connected);
+            connected
+        })
+    }
+
+    /// Poll again after about the time one percent took to drain, estimated
+    /// from the last level change. A level that holds counts as draining no
+    /// faster than the time it has held, so stable devices back off
+    /// geometrically. Charging or levelless devices stay on the base interval.
+    fn after_success(&mut self, infos: &[BatteryInfo], now: Instant) -> NextPoll {
+        self.failures = 0;
+        let connected = self.set_connected(true);
+        let charging = infos
+            .iter()
+            .any(|info| info.power_state == PowerState::Charging);
+        let level = infos.iter().filter_map(|info| info.battery_level).min();
+
+        let estimate = match (level, self.level_since) {
+            (Some(level), Some((since_level, since))) if !charging && level == since_level => {
+                now - since
+            }
+            (Some(level), Some((since_level, since))) if !charging && level < since_level => {
+                self.level_since = Some((level, now));
+                (now - since) / u32::from(since_level - level)
+            }
+            _ => {
+                self.level_since = level.filter(|_| !charging).map(|level| (level, now));
+                self.base
+            }
+        };
+
+        NextPoll {
+            delay: estimate.clamp(self.base, self.base * MAX_STRETCH),
+            connected,
+        }
+    }
+
+    /// Retry quickly while a connected device may just have missed one read,
+    /// then report it disconnected and double the delay per failure.
+    fn after_failure(&mut self) -> NextPoll {
+        self.failures += 1;
+        self.level_since = None;
+        if self.connected != Some(false) && self.failures < ATTEMPTS_BEFORE_DISCONNECT {
+            return NextPoll {
+                delay: RETRY_DELAY,
+                connected: None,
+            };
+        }
+
+        let connected = self.set_connected(false);
+        let doublings = self
+            .failures
+            .saturating_sub(ATTEMPTS_BEFORE_DISCONNECT)
+            .min(16);
+        NextPoll {
+            delay: (self.base * 2u32.pow(doublings)).min(MAX_ERROR_BACKOFF.max(self.base)),
+            connected,
+        }
+    }
+}
+
+async fn poll_loop(
+    events: Arc<dyn BatteryEventSink>,
+    read: BatteryReader,
+    device_id: String,
+    base: Duration,
+    mut stop_rx: watch::Receiver<bool>,
+) {
+    log::debug!("BLE I/O: poller started device_id={device_id} interval={base:?}");
+    let mut schedule = PollSchedule::new(base);
+    loop {
+        let result = tokio::select! {
+            result = read(device_id.clone()) => result,
+            _ = stop_rx.wait_for(|stop| *stop) => break,
+        };
+
+        let next = match result {
+            Ok(infos) => {
+                let next = schedule.after_success(&infos, Instant::now());
+                if let Some(connected) = next.connected {
+                    events.monitor_status(BatteryMonitorStatusEvent::new(
+                        device_id.clone(),
+                        connected,
+                    ));
+                }
+                // Like the monitors, a held reading waits for the next one
+                // to confirm it.
+                let battery_infos: Vec<BatteryInfo> = infos
+                    .into_iter()
+                    .filter(|info| info.reading_flag != Some(ReadingFlag::Held))
+                    .collect();
+                if !battery_infos.is_empty() {
+                    events.battery_info(BatteryInfoNotificationEvent {
+                        id: device_id.clone(),
+                        battery_infos,
+                    });
+                }
+                next
+            }
+            Err(e) => {
+                log::debug!("BLE I/O: poll failed device_id={device_id}: {e}");
+                let next = schedule.after_failure();
+                if let Some(connected) = next.connected {
+                    events.monitor_status(BatteryMonitorStatusEvent::new(
+                        device_id.clone(),
+                        connected,
+                    ));
+                }
+                next
+            }
+        };
+
+        log::debug!(
+            "BLE I/O: next poll in {:?} device_id={device_id}",
+            next.delay
+        );
+        tokio::select! {
+            _ = sleep(next.delay) => {}
+            _ = stop_rx.wait_for(|stop| *stop) => break,
+        }
+    }
+    log::debug!("BLE I/O: poller stopped device_id={device_id}");
+}
+
+struct Poller {
+    interval: Duration,
+    stop_tx: watch::Sender<bool>,
+    handle: JoinHandle<()>,
+}
+
+static POLLERS: LazyLock<Mutex<HashMap<String, Poller>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+async fn stop_poller(id: &str, poller: Poller) {
+    let _ = poller.stop_tx.send(true);
+    let abort_handle = poller.handle.abort_handle();
+    if tokio::time::timeout(Duration::from_secs(10), poller.handle)
+        .await
+        .is_err()
+    {
+        log::warn!("BLE I/O: poller did not stop in time, aborting device_id={id}");
+        abort_handle.abort();
+    }
+}
+
+/// Make `pollers` poll exactly `ids` at `interval`. Pollers already running
+/// with that interval keep their schedule.
+async fn reconcile_pollers(
+    pollers: &Mutex<HashMap<String, Poller>>,
+    events: Arc<dyn BatteryEventSink>,
+    read: BatteryReader,
+    ids: Vec<String>,
+    interval: Duration,
+) {
+    let stopped: Vec<(String, Poller)> = {
+        let mut pollers = pollers.lock().await;
+        let stale: Vec<String> = pollers
+            .iter()
+            .filter(|(id, poller)| !ids.contains(id) || poller.interval != interval)
+            .map(|(id, _)| id.clone())
+            .collect();
+        let stopped = stale
+            .into_iter()
+            .filter_map(|id| pollers.remove_entry(&id))
+            .collect();
+
+        for id in ids {
+            if pollers.contains_key(&id) {
+                continue;
+            }
+            let (stop_tx, stop_rx) = watch::channel(false);
+            let handle = tokio::spawn(poll_loop(
+                events.clone(),
+                read.clone(),
+                id.clone(),
+                interval,
+                stop_rx,
+            ));
+            pollers.insert(
+                id,
+                Poller {
+                    interval,
+                    stop_tx,
+                    handle,
+                },
+            );
+        }
+        stopped
+    };
+
+    for (id, poller) in stopped {
+        stop_poller(&id, poller).await;
+    }
+}
+
+/// Poll exactly the devices in `ids`, at most every `interval_ms`. Results
+/// arrive as `battery-info-notification` and `battery-monitor-status` events.
+#[tauri::command]
+pub async fn set_battery_polling(app: AppHandle, ids: Vec<String>, interval_ms: u64) {
+    log::debug!(
+        "BLE I/O: set polled devices count={} interval_ms={interval_ms}",
+        ids.len()
+    );
+    let read: BatteryReader = Arc::new(|id| Box::pin(ble::poll_battery_info(id)));
+    reconcile_pollers(
+        &POLLERS,
+        Arc::new(app),
+        read,
+        ids,
+        Duration::from_millis(interval_ms),
+    )
+    .await;
+}
+
+#[tauri::command]
+pub async fn stop_all_battery_polling() {
+    let all: Vec<(String, Poller)> = POLLERS.lock().await.drain().collect();
+    log::debug!("BLE I/O: stopping all pollers count={}", all.len());
+    for (id, poller) in all {
+        stop_poller(&id, poller).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble::{RecordingSink, SinkEvent};
+    use std::collections::VecDeque;
+    use std::sync::Mutex as StdMutex;
+    use tokio::sync::mpsc;
+
+    const BASE: Duration = Duration::from_secs(60);
+
+    fn info(level: u8, power_state: PowerState) -> BatteryInfo {
+        BatteryInfo {
+            battery_level: Some(level),
+            user_description: None,
+            part_key: "0:0".to_string(),
+            presentation: None,
+            power_state,
+            raw_battery_level: Some(level),
+            reading_flag: None,
+        }
+    }
+
+    fn level(level: u8) -> Vec<BatteryInfo> {
+        vec![info(level, PowerState::Discharging)]
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Level(String, Option<u8>),
+        Connected(String, bool),
+    }
+
+    fn record(event: SinkEvent) -> Vec<Event> {
+        match event {
+            SinkEvent::BatteryInfo(event) => event
+                .battery_infos
+                .iter()
+                .map(|info| Event::Level(event.id.clone(), info.battery_level))
+                .collect(),
+            SinkEvent::MonitorStatus(event) => vec![Event::Connected(event.id, event.connected)],
+            SinkEvent::AdapterStatus(_) | SinkEvent::Diagnostic(_) => vec![],
+        }
+    }
+
+    type ReadResult = Result<Vec<BatteryInfo>, CommandError>;
+
+    /// Replays scripted results per device and counts the reads.
+    #[derive(Clone, Default)]
+    struct ScriptedReader {
+        results: Arc<StdMutex<HashMap<String, VecDeque<ReadResult>>>>,
+        reads: Arc<StdMutex<Vec<(String, Instant)>>>,
+    }
+
+    impl ScriptedReader {
+        fn script(&self, id: &str, results: Vec<ReadResult>) {
+            self.results
+                .lock()
+                .unwrap()
+                .insert(id.to_string(), results.into());
+        }
+
+        fn reader(&self) -> BatteryReader {
+            let this = self.clone();
+            Arc::new(move |id| {
+                this.reads
+                    .lock()
+                    .unwrap()
+                    .push((id.clone(), Instant::now()));
+                let result = this
+                    .results
+                    .lock()
+                    .unwrap()
+                    .get_mut(&id)
+                    .and_then(|results| results.pop_front())
+                    .unwrap_or_else(|| Err(CommandError::Io("script exhausted".to_string())));
+                Box::pin(async move { result })
+            })
+        }
+
+        fn read_offsets(&self, id: &str, start: Instant) -> Vec<Duration> {
+            self.reads
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(read_id, _)| read_id == id)
+                .map(|(_, at)| *at - start)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn stable_level_stretches_interval_up_to_limit() {
+        let start = Instant::now();
+        let mut schedule = PollSchedule::new(BASE);
+
+        let first = schedule.after_success(&level(80), start);
+        assert_eq!(first.delay, BASE);
+        assert_eq!(first.connected, Some(true));
+
+        let mut now = start;
+        let mut delays = Vec::new();
+        for _ in 0..6 {
+            let delay = delays.last().copied().unwrap_or(BASE);
+            now += delay;
+            let next = schedule.after_success(&level(80), now);
+            assert_eq!(next.connected, None);
+            delays.push(next.delay);
+        }
+        assert_eq!(
+            delays,
+            vec![BASE, BASE * 2, BASE * 4, BASE * 8, BASE * 8, BASE * 8]
+        );
+    }
+
+    #[test]
+    fn drain_rate_sets_interval() {
+        let start = Instant::now();
+        let mut schedule = PollSchedule::new(BASE);
+        schedule.after_success(&level(80), start);
+
+        // 2% in 10 minutes: about 5 minutes per percent.
+        let next = schedule.after_success(&level(78), start + BASE * 10);
+        assert_eq!(next.delay, BASE * 5);
+
+        // 3% in one minute is faster than the base interval allows.
+        let next = schedule.after_success(&level(75), start + BASE * 11);
+        assert_eq!(next.delay, BASE);
+    }
+
+    #[test]
+    fn charging_and_rising_levels_use_base_interval() {
+        let start = Instant::now();
+        let mut schedule = PollSchedule::new(BASE);
+        schedule.after_success(&level(80), start);
+        schedule.after_success(&level(80), start + BASE * 4);
+
+        let charging = vec![info(80, PowerState::Charging)];
+        assert_eq!(
+            schedule.after_success(&charging, start + BASE * 8).delay,
+            BASE
+        );
+        assert_eq!(
+            schedule.after_success(&level(85), start + BASE * 9).delay,
+            BASE
+        );
+        // The level after charging starts a fresh estimate.
+        assert_eq!(
+            schedule.after_success(&level(85), start + BASE * 10).delay,
+            BASE
+        );
+    }
+
+    #[test]
+    fn split_keyboard_follows_lowest_part() {
+        let start = Instant::now();
+        let mut schedule = PollSchedule::new(BASE);
+        let parts = |central, peripheral| {
+            vec![
+                info(central, PowerState::Unknown),
+                info(peripheral, PowerState::Unknown),
+            ]
+        };
+        schedule.after_success(&parts(90, 60), start);
+        // The central dropping does not matter while the peripheral is lower.
+        let next = schedule.after_success(&parts(85, 60), start + BASE * 2);
+        assert_eq!(next.delay, BASE * 2);
+    }
+
+    #[test]
+    fn failures_retry_then_back_off() {
+        let mut schedule = PollSchedule::new(BASE);
+        schedule.after_success(&level(80), Instant::now());
+
+        let retries: Vec<NextPoll> = (0..2).map(|_| schedule.after_failure()).collect();
+        assert!(retries
+            .iter()
+            .all(|next| next.delay == RETRY_DELAY && next.connected.is_none()));
+
+        assert_eq!(
+            schedule.after_failure(),
+            NextPoll {
+                delay: BASE,
+                connected: Some(false),
+            }
+        );
+        let delays: Vec<Duration> = (0..6).map(|_| schedule.after_failure().delay).collect();
+        assert_eq!(
+            delays,
+            vec![
+                BASE * 2,
+                BASE * 4,
+                BASE * 8,
+                MAX_ERROR_BACKOFF,
+                MAX_ERROR_BACKOFF,
+                MAX_ERROR_BACKOFF
+            ]
+        );
+
+        let next = schedule.after_success(&level(79), Instant::now());
+        assert_eq!(next.connected, Some(true));
+        assert_eq!(next.delay, BASE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn poller_emits_readings_and_connection_changes() {
+        let scripted = ScriptedReader::default();
+        scripted.script(
+            "kbd-1",
+            vec![
+                Ok(level(80)),
+                Err(CommandError::DeviceNotFound),
+                Err(CommandError::DeviceNotFound),
+                Err(CommandError::DeviceNotFound),
+                Ok(level(79)),
+            ],
+        );
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let start = Instant::now();
+        let handle = tokio::spawn(poll_loop(
+            Arc::new(RecordingSink::new(tx, record)),
+            scripted.reader(),
+            "kbd-1".to_string(),
+            BASE,
+            stop_rx,
+        ));
+
+        let id = || "kbd-1".to_string();
+        for expected in [
+            Event::Connected(id(), true),
+            Event::Level(id(), Some(80)),
+            Event::Connected(id(), false),
+            Event::Connected(id(), true),
+            Event::Level(id(), Some(79)),
+        ] {
+            assert_eq!(events.recv().await, Some(expected));
+        }
+        assert_eq!(
+            scripted.read_offsets("kbd-1", start),
+            vec![
+                Duration::ZERO,
+                BASE,
+                BASE + RETRY_DELAY,
+                BASE + RETRY_DELAY * 2,
+                BASE * 2 + RETRY_DELAY * 2,
+            ]
+        );
+
+        stop_tx.send(true).unwrap();
+        handle.await.expect("poller panicked");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn poller_leaves_held_readings_out_of_events() {
+        let held = BatteryInfo {
+            battery_level: None,
+            reading_flag: Some(ReadingFlag::Held),
+            ..info(3, PowerState::Discharging)
+        };
+        let scripted = ScriptedReader::default();
+        scripted.script("kbd-1", vec![Ok(level(80)), Ok(vec![held]), Ok(level(79))]);
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let handle = tokio::spawn(poll_loop(
+            Arc::new(RecordingSink::new(tx, record)),
+            scripted.reader(),
+            "kbd-1".to_string(),
+            BASE,
+            stop_rx,
+        ));
+
+        let id = || "kbd-1".to_string();
+        for expected in [
+            Event::Connected(id(), true),
+            Event::Level(id(), Some(80)),
+            Event::Level(id(), Some(79)),
+        ] {
+            assert_eq!(events.recv().await, Some(expected));
+        }
+
+        stop_tx.send(true).unwrap();
+        handle.await.expect("poller panicked");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reconcile_starts_stops_and_keeps_pollers() {
+        let scripted = ScriptedReader::default();
+        scripted.script("kbd-1", vec![Ok(level(80)); 10]);
+        scripted.script("kbd-2", vec![Ok(level(50)); 10]);
+        let pollers = Mutex::new(HashMap::new());
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let sink: Arc<dyn BatteryEventSink> = Arc::new(RecordingSink::new(tx, record));
+        let start = Instant::now();
+
+        reconcile_pollers(
+            &pollers,
+            sink.clone(),
+            scripted.reader(),
+            vec!["kbd-1".to_string(), "kbd-2".to_string()],
+            BASE,
+        )
+        .await;
+        sleep(BASE / 2).await;
+
+        // kbd-1 keeps its schedule, kbd-2 stops.
+        reconcile_pollers(
+            &pollers,
+            sink.clone(),
+            scripted.reader(),
+            vec!["kbd-1".to_string()],
+            BASE,
+        )
+        .await;
+        assert_eq!(
+            pollers.lock().await.keys().cloned().collect::<Vec<_>>(),
+            vec!["kbd-1".to_string()]
+        );
+        sleep(BASE * 2).await;
+
+        assert_eq!(
+            scripted.read_offsets("kbd-1", start),
+            vec![Duration::ZERO, BASE, BASE * 2]
+        );
+        assert_eq!(scripted.read_offsets("kbd-2", start), vec![Duration::ZERO]);
+
+        // A new interval restarts the poller with an immediate read.
+        reconcile_pollers(
+            &pollers,
+            sink,
+            scripted.reader(),
+            vec!["kbd-1".to_string()],
+            BASE * 2,
+        )
+        .await;
+        sleep(Duration::from_millis(1)).await;
+        assert_eq!(scripted.read_offsets("kbd-1", start).len(), 4);
+
+        for (id, poller) in pollers.lock().await.drain() {
+            stop_poller(&id, poller).await;
+        }
+        while events.try_recv().is_ok() {}
+    }
+}
@@ -0,0 +1,197 @@
+//! Charging state from the Battery Service.
+//!
+//! Battery Service 1.1 publishes it in Battery Level Status (0x2BED); older
+//! firmware may instead expose the deprecated Battery Power State (0x2A1A).
+//! Both are reduced to the coarse `PowerState` the UI and history need.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub(crate) const BATTERY_LEVEL_STATUS_UUID: Uuid =
+    Uuid::from_u128(0x00002BED_0000_1000_8000_00805F9B34FB);
+pub(crate) const BATTERY_POWER_STATE_UUID: Uuid =
+    Uuid::from_u128(0x00002A1A_0000_1000_8000_00805F9B34FB);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerState {
+    Charging,
+    Discharging,
+    /// On external power but not charging, typically because it is full.
+    ExternalPower,
+    #[default]
+    Unknown,
+}
+
+impl PowerState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Charging => "charging",
+            Self::Discharging => "discharging",
+            Self::ExternalPower => "external_power",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// Inverse of `as_str`; anything unrecognized (including an empty history
+    /// column) is `Unknown`.
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "charging" => Self::Charging,
+            "discharging" => Self::Discharging,
+            "external_power" => Self::ExternalPower,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// Which characteristic a power state is read from. Ordered by preference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum PowerStateFormat {
+    LevelStatus,
+    LegacyPowerState,
+}
+
+impl PowerStateFormat {
+    pub fn for_characteristic(uuid: Uuid) -> Option<Self> {
+        match uuid {
+            BATTERY_LEVEL_STATUS_UUID => Some(Self::LevelStatus),
+            BATTERY_POWER_STATE_UUID => Some(Self::LegacyPowerState),
+            _ => None,
+        }
+    }
+
+    pub fn decode(self, value: &[u8]) -> PowerState {
+        match self {
+            Self::LevelStatus => decode_battery_level_status(value),
+            Self::LegacyPowerState => decode_battery_power_state(value),
+        }
+    }
+}
+
+/// Battery Level Status: flags (u8) followed by the Power State field (u16 LE).
+/// Bits 1-2 and 3-4 of Power State report wired and wireless external power,
+/// bits 5-6 the charge state (1 charging, 2 discharging active, 3 discharging
+/// inactive).
+fn decode_battery_level_status(value: &[u8]) -> PowerState {
+    let [_flags, low, high, ..] = value else {
+        return PowerState::Unknown;
+    };
+    let power_state = u16::from_le_bytes([*low, *high]);
+    let wired = (power_state >> 1) & 0b11;
+    let wireless = (power_state >> 3) & 0b11;
+    let charge_state = (power_state >> 5) & 0b11;
+    match charge_state {
+        1 => PowerState::Charging,
+        _ if wired == 1 || wireless == 1 => PowerState::ExternalPower,
+        2 | 3 => PowerState::Discharging,
+        _ => PowerState::Unknown,
+    }
+}
+
+/// Battery Power State: one byte of 2-bit fields (present, discharging,
+/// charging, level), where 2 means "no" and 3 means "yes".
+fn decode_battery_power_state(value: &[u8]) -> PowerState {
+    let [state, ..] = value else {
+        return PowerState::Unknown;
+    };
+    let discharging = (state >> 2) & 0b11;
+    let charging = (state >> 4) & 0b11;
+    match (charging, discharging) {
+        (3, _) => PowerState::Charging,
+        (_, 3) => PowerState::Discharging,
+        (2, 2) => PowerState::ExternalPower,
+        _ => PowerState::Unknown,
+    }
+}
+
+/// Encode a Battery Level Status value for the simulated backend.
+#[cfg(test)]
+pub(crate) fn battery_level_status_value(state: PowerState) -> Vec<u8> {
+    let power_state: u16 = match state {
+        PowerState::Charging => 0b01 << 5 | 0b01 << 1 | 1,
+        PowerState::Discharging => 0b10 << 5 | 1,
+        PowerState::ExternalPower => 0b11 << 5 | 0b01 << 1 | 1,
+        PowerState::Unknown => 0,
+    };
+    let [low, high] = power_state.to_le_bytes();
+    vec![0, low, high]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_status_decodes_charge_state_and_external_power() {
+        for state in [
+            PowerState::Charging,
+            PowerState::Discharging,
+            PowerState::ExternalPower,
+            PowerState::Unknown,
+        ] {
+            assert_eq!(
+                PowerStateFormat::LevelStatus.decode(&battery_level_status_value(state)),
+                state
+            );
+        }
+        // Wireless charger connected, charge state unknown.
+        let wireless = (0b01u16 << 3 | 1).to_le_bytes();
+        assert_eq!(
+            decode_battery_level_status(&[0, wireless[0], wireless[1]]),
+            PowerState::ExternalPower
+        );
+    }
+
+    #[test]
+    fn level_status_ignores_optional_trailing_fields() {
+        // Flags announce identifier and battery level after Power State.
+        let mut value = battery_level_status_value(PowerState::Charging);
+        value[0] = 0b011;
+        value.extend_from_slice(&[0x01, 0x00, 55]);
+        assert_eq!(decode_battery_level_status(&value), PowerState::Charging);
+    }
+
+    #[test]
+    fn short_values_are_unknown() {
+        assert_eq!(decode_battery_level_status(&[0, 0x20]), PowerState::Unknown);
+        assert_eq!(decode_battery_power_state(&[]), PowerState::Unknown);
+    }
+
+    #[test]
+    fn legacy_power_state_decodes_charging_fields() {
+        // present=3, discharging=2, charging=3, level=2
+        assert_eq!(decode_battery_power_state(&[0b10_11_10_11]), PowerState::Charging);
+        // present=3, discharging=3, charging=2, level=2
+        assert_eq!(decode_battery_power_state(&[0b10_10_11_11]), PowerState::Discharging);
+        // present=3, discharging=2, charging=2
+        assert_eq!(decode_battery_power_state(&[0b10_10_10_11]), PowerState::ExternalPower);
+        assert_eq!(decode_battery_power_state(&[0]), PowerState::Unknown);
+    }
+
+    #[test]
+    fn history_text_round_trips() {
+        for state in [
+            PowerState::Charging,
+            PowerState::Discharging,
+            PowerState::ExternalPower,
+            PowerState::Unknown,
+        ] {
+            assert_eq!(PowerState::parse(state.as_str()), state);
+        }
+        assert_eq!(PowerState::parse(""), PowerState::Unknown);
+    }
+
+    #[test]
+    fn level_status_is_preferred_over_legacy_characteristic() {
+        assert_eq!(
+            PowerStateFormat::for_characteristic(BATTERY_LEVEL_STATUS_UUID),
+            Some(PowerStateFormat::LevelStatus)
+        );
+        assert!(PowerStateFormat::LevelStatus < PowerStateFormat::LegacyPowerState);
+        assert_eq!(
+            PowerStateFormat::for_characteristic(crate::ble::BATTERY_LEVEL_UUID),
+            None
+        );
+    }
+}
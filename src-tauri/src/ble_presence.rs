@@ -0,0 +1,288 @@
+//! One connected-device query per adapter, shared by every connection watcher
+//! on it.
+//!
+//! Watchers tell the presence watcher when they next want to know whether
+//! their device is connected. It queries `connected_devices_with_services`
+//! once at the earliest such time and hands the connected set to all of them,
+//! so a device that appears is picked up by its watcher on whichever poll sees
+//! it first, and ten registered keyboards cost the OS one query, not ten.
+
+use crate::ble::{BATTERY_LEVEL_UUID, BATTERY_SERVICE_UUID};
+use crate::ble_transport::{BleAdapter, BleDevice};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{watch, Notify};
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+
+/// How long a failed query waits before it is repeated.
+const FAILED_POLL_RETRY: Duration = Duration::from_secs(2);
+
+/// Result of one query: connected devices by id.
+type ConnectedDevices = Arc<HashMap<String, Arc<dyn BleDevice>>>;
+
+pub(crate) enum Presence {
+    Connected(Arc<dyn BleDevice>),
+    /// A poll at or after the requested time did not list the device.
+    Missing,
+    Stopped,
+}
+
+pub(crate) struct PresenceHub {
+    connected_tx: watch::Sender<ConnectedDevices>,
+    /// Earliest time each waiting watcher wants a poll, by device id.
+    wanted: Mutex<HashMap<String, Instant>>,
+    wake: Notify,
+}
+
+impl PresenceHub {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            connected_tx: watch::Sender::new(Arc::default()),
+            wanted: Mutex::default(),
+            wake: Notify::new(),
+        })
+    }
+
+    fn wanted(&self) -> std::sync::MutexGuard<'_, HashMap<String, Instant>> {
+        self.wanted.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    /// Wait for the first poll that lists `device_id`, or report it missing
+    /// once a poll at or after `poll_at` did not. Only polls that finish
+    /// after this call count, so the answer is never older than one query.
+    pub async fn find(
+        &self,
+        device_id: &str,
+        poll_at: Instant,
+        stop_rx: &mut watch::Receiver<bool>,
+    ) -> Presence {
+        let mut connected_rx = self.connected_tx.subscribe();
+        self.wanted().insert(device_id.to_string(), poll_at);
+        self.wake.notify_one();
+
+        let presence = loop {
+            tokio::select! {
+                changed = connected_rx.changed() => {
+                    if changed.is_err() {
+                        break Presence::Stopped;
+                    }
+                    if let Some(device) = connected_rx.borrow_and_update().get(device_id) {
+                        break Presence::Connected(device.clone());
+                    }
+                    if Instant::now() >= poll_at {
+                        break Presence::Missing;
+                    }
+                }
+                _ = stop_rx.wait_for(|stop| *stop) => break Presence::Stopped,
+            }
+        };
+        // Let the presence watcher drop a poll nobody needs any more.
+        self.wanted().remove(device_id);
+        self.wake.notify_one();
+        presence
+    }
+
+    fn next_poll(&self) -> Option<Instant> {
+        self.wanted().values().min().copied()
+    }
+}
+
+/// Poll `adapter` whenever a watcher asks, until stopped, logging devices as
+/// they appear and disappear.
+pub(crate) async fn presence_watcher(
+    hub: Arc<PresenceHub>,
+    adapter: Arc<dyn BleAdapter>,
+    mut stop_rx: watch::Receiver<bool>,
+) {
+    let adapter_id = adapter.id();
+    log::debug!("BLE I/O: presence watcher started adapter_id={adapter_id}");
+    loop {
+        let next_poll = hub.next_poll();
+        tokio::select! {
+            _ = stop_rx.wait_for(|stop| *stop) => break,
+            // A watcher asked; look at the wanted times again.
+            _ = hub.wake.notified() => continue,
+            _ = async {
+                match next_poll {
+                    Some(at) => sleep_until(at).await,
+                    None => std::future::pending().await,
+                }
+            } => {}
+        }
+
+        let started = Instant::now();
+        log::debug!("BLE I/O: presence poll adapter_id={adapter_id}");
+        let connected: HashMap<String, Arc<dyn BleDevice>> = match adapter
+            .connected_devices_with_services(&[BATTERY_SERVICE_UUID, BATTERY_LEVEL_UUID])
+            .await
+        {
+            Ok(devices) => devices.into_iter().map(|d| (d.id(), d)).collect(),
+            Err(e) => {
+                // A failed query says nothing about the devices, so it
+                // answers no request; they wait for one that succeeds.
+                log::warn!("BLE I/O: presence poll failed adapter_id={adapter_id}: {e}");
+                tokio::select! {
+                    _ = stop_rx.wait_for(|stop| *stop) => break,
+                    _ = sleep(FAILED_POLL_RETRY) => continue,
+                }
+            }
+        };
+
+        let previous = hub.connected_tx.borrow().clone();
+        for id in connected.keys().filter(|id| !previous.contains_key(*id)) {
+            log::debug!("BLE I/O: device appeared adapter_id={adapter_id} device_id={id}");
+        }
+        for id in previous.keys().filter(|id| !connected.contains_key(*id)) {
+            log::debug!("BLE I/O: device disappeared adapter_id={adapter_id} device_id={id}");
+        }
+
+        // This poll answers every request that was due when it started.
+        hub.wanted().retain(|_, at| *at > started);
+        hub.connected_tx.send_replace(Arc::new(connected));
+    }
+    log::debug!("BLE I/O: presence watcher stopped adapter_id={adapter_id}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble_simulated::{SimAction, SimulatedAdapter, SimulatedKeyboard};
+    use tokio::task::JoinHandle;
+    use tokio::time::{sleep, Duration};
+
+    struct Harness {
+        adapter: SimulatedAdapter,
+        hub: Arc<PresenceHub>,
+        stop_tx: watch::Sender<bool>,
+        handle: JoinHandle<()>,
+    }
+
+    impl Harness {
+        fn start(keyboards: Vec<SimulatedKeyboard>) -> Self {
+            let adapter = SimulatedAdapter::new(keyboards);
+            let hub = PresenceHub::new();
+            let (stop_tx, stop_rx) = watch::channel(false);
+            let handle = tokio::spawn(presence_watcher(
+                hub.clone(),
+                Arc::new(adapter.clone()),
+                stop_rx,
+            ));
+            Self {
+                adapter,
+                hub,
+                stop_tx,
+                handle,
+            }
+        }
+
+        /// `find` on its own task, as each connection watcher would call it.
+        fn find_later(&self, device_id: &str, after: Duration) -> JoinHandle<bool> {
+            let hub = self.hub.clone();
+            let device_id = device_id.to_string();
+            let mut stop_rx = self.stop_tx.subscribe();
+            tokio::spawn(async move {
+                let poll_at = Instant::now() + after;
+                matches!(
+                    hub.find(&device_id, poll_at, &mut stop_rx).await,
+                    Presence::Connected(_)
+                )
+            })
+        }
+
+        async fn stop(self) {
+            self.stop_tx.send(true).unwrap();
+            self.handle.await.expect("presence watcher panicked");
+        }
+    }
+
+    fn keyboards(count: usize) -> Vec<SimulatedKeyboard> {
+        (0..count)
+            .map(|i| {
+                SimulatedKeyboard::new(&format!("kbd-{i}"), "Board")
+                    .part(None, 80)
+                    .disconnected()
+            })
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn one_query_answers_every_waiting_device() {
+        let harness = Harness::start(keyboards(10));
+        let finds: Vec<_> = (0..10)
+            .map(|i| harness.find_later(&format!("kbd-{i}"), Duration::from_secs(5)))
+            .collect();
+        for find in finds {
+            assert!(!find.await.unwrap());
+        }
+        assert_eq!(harness.adapter.connected_queries(), 1);
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn device_is_found_on_a_poll_requested_by_another() {
+        let harness = Harness::start(keyboards(2));
+        let slow = harness.find_later("kbd-0", Duration::from_secs(600));
+        sleep(Duration::from_secs(1)).await;
+        harness.adapter.apply(&SimAction::Connect {
+            device: "kbd-0".to_string(),
+        });
+
+        let fast = harness.find_later("kbd-1", Duration::from_secs(5));
+        assert!(!fast.await.unwrap());
+        sleep(Duration::from_millis(10)).await;
+        assert!(slow.is_finished());
+        assert!(slow.await.unwrap());
+        assert_eq!(harness.adapter.connected_queries(), 1);
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_poll_does_not_report_devices_missing() {
+        let keyboard = SimulatedKeyboard::new("kbd-0", "Board").part(None, 80);
+        let harness = Harness::start(vec![keyboard]);
+        harness.adapter.apply(&SimAction::SetPowered { powered: false });
+        let find = harness.find_later("kbd-0", Duration::from_secs(5));
+
+        sleep(Duration::from_secs(30)).await;
+        assert!(!find.is_finished());
+        assert!(harness.adapter.connected_queries() > 1);
+
+        harness.adapter.apply(&SimAction::SetPowered { powered: true });
+        assert!(find.await.unwrap());
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_hub_does_not_poll() {
+        let harness = Harness::start(keyboards(3));
+        sleep(Duration::from_secs(3600)).await;
+        assert_eq!(harness.adapter.connected_queries(), 0);
+
+        harness.stop().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stopped_find_withdraws_its_request() {
+        let harness = Harness::start(keyboards(1));
+        let (stop_tx, mut stop_rx) = watch::channel(false);
+        let hub = harness.hub.clone();
+        let find = tokio::spawn(async move {
+            let poll_at = Instant::now() + Duration::from_secs(60);
+            matches!(
+                hub.find("kbd-0", poll_at, &mut stop_rx).await,
+                Presence::Stopped
+            )
+        });
+        sleep(Duration::from_secs(1)).await;
+        stop_tx.send(true).unwrap();
+        assert!(find.await.unwrap());
+
+        sleep(Duration::from_secs(120)).await;
+        assert_eq!(harness.adapter.connected_queries(), 0);
+
+        harness.stop().await;
+    }
+}
@@ -0,0 +1,203 @@
+//! Characteristic Presentation Format descriptor (0x2904).
+//!
+//! Devices with several batteries often tell them apart by the namespace and
+//! description fields of this descriptor ("left", "right", "first",
+//! "second", ...) rather than by a User Description. The battery monitor uses
+//! them as a fallback label and to order the parts; the GATT inspector shows
+//! the whole descriptor.
+
+use serde::Serialize;
+
+/// Namespace of the descriptions assigned by the Bluetooth SIG.
+pub const BLUETOOTH_SIG_NAMESPACE: u8 = 0x01;
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct PresentationFormat {
+    pub format: u8,
+    pub exponent: i8,
+    pub unit: u16,
+    /// 1 is the Bluetooth SIG namespace.
+    pub namespace: u8,
+    pub description: u16,
+}
+
+impl PresentationFormat {
+    pub fn parse(value: &[u8]) -> Option<Self> {
+        let [format, exponent, unit_lo, unit_hi, namespace, desc_lo, desc_hi, ..] = *value else {
+            return None;
+        };
+        Some(Self {
+            format,
+            exponent: exponent as i8,
+            unit: u16::from_le_bytes([unit_lo, unit_hi]),
+            namespace,
+            description: u16::from_le_bytes([desc_lo, desc_hi]),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn battery_level(description: u16) -> Self {
+        Self {
+            format: 0x04,
+            exponent: 0,
+            unit: 0x27AD,
+            namespace: BLUETOOTH_SIG_NAMESPACE,
+            description,
+        }
+    }
+
+    pub fn to_bytes(self) -> Vec<u8> {
+        let [unit_lo, unit_hi] = self.unit.to_le_bytes();
+        let [desc_lo, desc_hi] = self.description.to_le_bytes();
+        vec![
+            self.format,
+            self.exponent as u8,
+            unit_lo,
+            unit_hi,
+            self.namespace,
+            desc_lo,
+            desc_hi,
+        ]
+    }
+
+    /// The description's name in the Bluetooth SIG namespace, e.g. "left" or
+    /// "second". None for other namespaces and unassigned descriptions.
+    pub fn description_name(&self) -> Option<String> {
+        if self.namespace != BLUETOOTH_SIG_NAMESPACE {
+            return None;
+        }
+        match self.description {
+            0x0001..=0x00FF => Some(ordinal(self.description)),
+            0x0100..=0x0110 => Some(
+                [
+                    "front",
+                    "back",
+                    "top",
+                    "bottom",
+                    "upper",
+                    "lower",
+                    "main",
+                    "backup",
+                    "auxiliary",
+                    "supplementary",
+                    "flash",
+                    "inside",
+                    "outside",
+                    "left",
+                    "right",
+                    "internal",
+                    "external",
+                ][usize::from(self.description - 0x0100)]
+                .to_string(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Where the part goes among its siblings. The SIG assigns ordinals
+    /// first and pairs such as left/right and front/back in reading order,
+    /// so the description itself sorts; "unknown" and other namespaces give
+    /// no hint.
+    pub fn ordering_hint(&self) -> Option<u16> {
+        (self.namespace == BLUETOOTH_SIG_NAMESPACE && self.description != 0)
+            .then_some(self.description)
+    }
+}
+
+fn ordinal(n: u16) -> String {
+    const WORDS: [&str; 10] = [
+        "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+        "tenth",
+    ];
+    if let Some(word) = WORDS.get(usize::from(n) - 1) {
+        return word.to_string();
+    }
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+/// What a Battery Level characteristic's Presentation Format says about the
+/// part, as carried in `BatteryInfo`.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct PartPresentation {
+    pub namespace: u8,
+    pub description: u16,
+    /// See [`PresentationFormat::description_name`].
+    pub description_name: Option<String>,
+}
+
+impl From<PresentationFormat> for PartPresentation {
+    fn from(format: PresentationFormat) -> Self {
+        Self {
+            namespace: format.namespace,
+            description: format.description,
+            description_name: format.description_name(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presentation_format_parses_fields() {
+        let format = PresentationFormat::parse(&[0x04, 0xFE, 0xAD, 0x27, 0x01, 0x06, 0x01])
+            .expect("seven bytes");
+        assert_eq!(
+            format,
+            PresentationFormat {
+                format: 0x04,
+                exponent: -2,
+                unit: 0x27AD,
+                namespace: 0x01,
+                description: 0x0106,
+            }
+        );
+        assert_eq!(PresentationFormat::parse(&format.to_bytes()), Some(format));
+        assert_eq!(PresentationFormat::parse(&[0x04, 0x00, 0xAD]), None);
+    }
+
+    #[test]
+    fn sig_descriptions_are_named() {
+        let name = |description| PresentationFormat::battery_level(description).description_name();
+        assert_eq!(name(0x0001).as_deref(), Some("first"));
+        assert_eq!(name(0x0002).as_deref(), Some("second"));
+        assert_eq!(name(0x000B).as_deref(), Some("11th"));
+        assert_eq!(name(0x0016).as_deref(), Some("22nd"));
+        assert_eq!(name(0x0071).as_deref(), Some("113th"));
+        assert_eq!(name(0x010D).as_deref(), Some("left"));
+        assert_eq!(name(0x010E).as_deref(), Some("right"));
+        assert_eq!(name(0x0110).as_deref(), Some("external"));
+        assert_eq!(name(0x0000), None);
+        assert_eq!(name(0x0111), None);
+    }
+
+    #[test]
+    fn other_namespaces_have_no_name_or_order() {
+        let format = PresentationFormat {
+            namespace: 0x02,
+            ..PresentationFormat::battery_level(0x010D)
+        };
+        assert_eq!(format.description_name(), None);
+        assert_eq!(format.ordering_hint(), None);
+        assert_eq!(
+            PresentationFormat::battery_level(0x0000).ordering_hint(),
+            None
+        );
+    }
+
+    #[test]
+    fn ordering_hint_puts_left_before_right_and_ordinals_first() {
+        let hint = |description| PresentationFormat::battery_level(description).ordering_hint();
+        assert!(hint(0x010D) < hint(0x010E));
+        assert!(hint(0x0002) < hint(0x0100));
+        assert!(hint(0x0001) < hint(0x0002));
+    }
+}
@@ -0,0 +1,300 @@
+//! Validation and glitch filtering of Battery Level readings.
+//!
+//! Every level the monitor reads or is notified of goes through here before
+//! it reaches events, and through them the UI, tray and history. Empty
+//! payloads and levels above 100 are rejected. A level that moved further
+//! than `jump_threshold` from the last accepted one is flagged; with
+//! `debounce` it is held back until the next reading confirms it, which drops
+//! the one-off spikes ZMK peripherals are known to report. A median over the
+//! last few readings can smooth jitter on top. The filter is set per device as
+//! part of `MonitorOptions`.
+
+use crate::error::CommandError;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+const MAX_MEDIAN_WINDOW: u8 = 9;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct ReadingFilter {
+    /// Change in points between two readings that is flagged as suspicious;
+    /// 0 disables jump detection.
+    pub jump_threshold: u8,
+    /// Hold a suspicious jump back until the next reading confirms it.
+    pub debounce: bool,
+    /// Report the median of this many recent readings; 1 reports each as is.
+    pub median_window: u8,
+}
+
+impl Default for ReadingFilter {
+    fn default() -> Self {
+        Self {
+            jump_threshold: 30,
+            debounce: false,
+            median_window: 1,
+        }
+    }
+}
+
+impl ReadingFilter {
+    pub fn validate(&self) -> Result<(), CommandError> {
+        if self.jump_threshold > 100 {
+            return Err(CommandError::InvalidArgument(
+                "Jump threshold must be between 0 and 100".to_string(),
+            ));
+        }
+        if !(1..=MAX_MEDIAN_WINDOW).contains(&self.median_window) {
+            return Err(CommandError::InvalidArgument(format!(
+                "Median window must be between 1 and {MAX_MEDIAN_WINDOW}"
+            )));
+        }
+        if self.debounce && self.jump_threshold == 0 {
+            return Err(CommandError::InvalidArgument(
+                "Debounce needs a jump threshold".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Why a reading was rejected, held back or needs a second look.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingFlag {
+    /// The payload had no bytes.
+    Empty,
+    /// The level was above 100.
+    OutOfRange,
+    /// Accepted, but far from the previous level.
+    SuspiciousJump,
+    /// A suspicious jump held back until the next reading confirms it.
+    Held,
+}
+
+/// One reading of a part: the level to report, if any, and the raw one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Reading {
+    /// None when the reading was rejected or held back.
+    pub level: Option<u8>,
+    pub raw: Option<u8>,
+    pub flag: Option<ReadingFlag>,
+}
+
+impl Reading {
+    /// Range check only, for one-off reads with nothing to compare against.
+    pub fn checked(payload: &[u8]) -> Self {
+        match payload.first().copied() {
+            None => Self {
+                level: None,
+                raw: None,
+                flag: Some(ReadingFlag::Empty),
+            },
+            Some(raw) if raw > 100 => Self {
+                level: None,
+                raw: Some(raw),
+                flag: Some(ReadingFlag::OutOfRange),
+            },
+            Some(raw) => Self {
+                level: Some(raw),
+                raw: Some(raw),
+                flag: None,
+            },
+        }
+    }
+
+    /// No reading at all, e.g. after a failed read.
+    pub fn missing() -> Self {
+        Self {
+            level: None,
+            raw: None,
+            flag: None,
+        }
+    }
+}
+
+/// Filter state of one part.
+#[derive(Default)]
+struct PartFilter {
+    last_accepted: Option<u8>,
+    /// A suspicious level waiting for confirmation.
+    held: Option<u8>,
+    recent: VecDeque<u8>,
+}
+
+impl PartFilter {
+    fn accept(&mut self, filter: &ReadingFilter, payload: &[u8]) -> Reading {
+        let checked = Reading::checked(payload);
+        let Some(raw) = checked.level else {
+            return checked;
+        };
+
+        let jumped = match self.last_accepted {
+            Some(last) if filter.jump_threshold > 0 => raw.abs_diff(last) > filter.jump_threshold,
+            _ => false,
+        };
+        let held = self.held.take();
+        let mut flag = None;
+        if jumped {
+            // Confirmed when the reading before also jumped and this one is
+            // close to it.
+            let confirmed = held.is_some_and(|h| h.abs_diff(raw) <= filter.jump_threshold);
+            if filter.debounce && !confirmed {
+                self.held = Some(raw);
+                return Reading {
+                    level: None,
+                    raw: Some(raw),
+                    flag: Some(ReadingFlag::Held),
+                };
+            }
+            flag = Some(ReadingFlag::SuspiciousJump);
+            // A confirmed step (e.g. a battery swap) restarts the median
+            // rather than being averaged away.
+            self.recent.clear();
+        }
+
+        self.last_accepted = Some(raw);
+        self.recent.push_back(raw);
+        while self.recent.len() > usize::from(filter.median_window) {
+            self.recent.pop_front();
+        }
+        let mut sorted: Vec<u8> = self.recent.iter().copied().collect();
+        sorted.sort_unstable();
+        Reading {
+            level: Some(sorted[(sorted.len() - 1) / 2]),
+            raw: Some(raw),
+            flag,
+        }
+    }
+}
+
+/// Readings of every part of one device, by part key. Shared by the
+/// connection watcher and one-shot reads and kept across reconnects, so the
+/// first reading after one is still compared with the last one before it.
+pub(crate) struct ReadingPipeline {
+    filter: ReadingFilter,
+    parts: Mutex<HashMap<String, PartFilter>>,
+}
+
+impl ReadingPipeline {
+    pub fn new(filter: ReadingFilter) -> Self {
+        Self {
+            filter,
+            parts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn filter(&self) -> ReadingFilter {
+        self.filter
+    }
+
+    pub fn accept(&self, part_key: &str, payload: &[u8]) -> Reading {
+        let mut parts = self.parts.lock().unwrap_or_else(|p| p.into_inner());
+        parts
+            .entry(part_key.to_string())
+            .or_default()
+            .accept(&self.filter, payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(filter: ReadingFilter, payloads: &[&[u8]]) -> Vec<Reading> {
+        let pipeline = ReadingPipeline::new(filter);
+        payloads
+            .iter()
+            .map(|payload| pipeline.accept("0:0", payload))
+            .collect()
+    }
+
+    #[test]
+    fn empty_and_out_of_range_payloads_are_rejected() {
+        assert_eq!(
+            Reading::checked(&[]),
+            Reading {
+                level: None,
+                raw: None,
+                flag: Some(ReadingFlag::Empty),
+            }
+        );
+        assert_eq!(
+            Reading::checked(&[0xFF]),
+            Reading {
+                level: None,
+                raw: Some(0xFF),
+                flag: Some(ReadingFlag::OutOfRange),
+            }
+        );
+        assert_eq!(Reading::checked(&[100, 7]).level, Some(100));
+    }
+
+    #[test]
+    fn rejected_readings_do_not_move_the_baseline() {
+        let readings = levels(ReadingFilter::default(), &[&[80], &[200], &[], &[79]]);
+        assert_eq!(readings[1].level, None);
+        assert_eq!(readings[2].level, None);
+        assert_eq!(readings[3].level, Some(79));
+        assert_eq!(readings[3].flag, None);
+    }
+
+    #[test]
+    fn jumps_are_flagged_without_debounce() {
+        let readings = levels(ReadingFilter::default(), &[&[80], &[5], &[80]]);
+        assert_eq!(readings[1].level, Some(5));
+        assert_eq!(readings[1].flag, Some(ReadingFlag::SuspiciousJump));
+        assert_eq!(readings[2].flag, Some(ReadingFlag::SuspiciousJump));
+    }
+
+    #[test]
+    fn debounce_drops_a_one_off_spike_and_confirms_a_real_step() {
+        let filter = ReadingFilter {
+            debounce: true,
+            ..ReadingFilter::default()
+        };
+        let readings = levels(filter, &[&[80], &[0], &[79], &[20], &[21]]);
+        let reported: Vec<Option<u8>> = readings.iter().map(|r| r.level).collect();
+        assert_eq!(reported, vec![Some(80), None, Some(79), None, Some(21)]);
+        assert_eq!(readings[1].flag, Some(ReadingFlag::Held));
+        assert_eq!(readings[1].raw, Some(0));
+        assert_eq!(readings[4].flag, Some(ReadingFlag::SuspiciousJump));
+    }
+
+    #[test]
+    fn median_smooths_jitter() {
+        let filter = ReadingFilter {
+            median_window: 3,
+            ..ReadingFilter::default()
+        };
+        let readings = levels(filter, &[&[80], &[79], &[60], &[78]]);
+        let reported: Vec<Option<u8>> = readings.iter().map(|r| r.level).collect();
+        assert_eq!(reported, vec![Some(80), Some(79), Some(79), Some(78)]);
+        assert_eq!(readings[2].raw, Some(60));
+    }
+
+    #[test]
+    fn parts_are_filtered_separately() {
+        let pipeline = ReadingPipeline::new(ReadingFilter::default());
+        pipeline.accept("0:0", &[90]);
+        let other = pipeline.accept("0:1", &[10]);
+        assert_eq!(other.flag, None);
+    }
+
+    #[test]
+    fn invalid_filters_are_refused() {
+        let median = ReadingFilter {
+            median_window: 0,
+            ..ReadingFilter::default()
+        };
+        assert!(median.validate().is_err());
+        let debounce = ReadingFilter {
+            debounce: true,
+            jump_threshold: 0,
+            ..ReadingFilter::default()
+        };
+        assert!(debounce.validate().is_err());
+        assert!(ReadingFilter::default().validate().is_ok());
+    }
+}
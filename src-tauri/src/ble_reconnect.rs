@@ -0,0 +1,258 @@
+//! Reconnection backoff of the connection watcher.
+//!
+//! While a monitored device is away, the watcher retries on a schedule set per
+//! device with `set_reconnect_policy`. The default keeps the fixed five-second
+//! retry; a growing delay lets registered but rarely used keyboards stop
+//! waking the radio, and a give-up time ends the watcher altogether.
+
+use crate::error::CommandError;
+use serde::{Deserialize, Serialize};
+use std::hash::BuildHasher;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
+
+const MIN_DELAY_MS: u64 = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    pub initial_delay_ms: u64,
+    /// The delay is multiplied by this after every failed attempt; 1 keeps it
+    /// fixed.
+    pub factor: f64,
+    /// Share of the delay, 0 to 1, randomly added or removed so devices that
+    /// went away together do not retry in lockstep.
+    pub jitter: f64,
+    pub max_delay_ms: u64,
+    /// Stop retrying after this long without a connection; 0 retries forever.
+    pub give_up_after_secs: u64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 5_000,
+            factor: 1.0,
+            jitter: 0.0,
+            max_delay_ms: 5 * 60 * 1000,
+            give_up_after_secs: 0,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    pub fn validate(&self) -> Result<(), CommandError> {
+        if self.initial_delay_ms < MIN_DELAY_MS {
+            return Err(CommandError::InvalidArgument(format!(
+                "Initial delay must be at least {MIN_DELAY_MS} ms"
+            )));
+        }
+        if self.max_delay_ms < self.initial_delay_ms {
+            return Err(CommandError::InvalidArgument(
+                "Maximum delay must not be below the initial delay".to_string(),
+            ));
+        }
+        if !self.factor.is_finite() || self.factor < 1.0 {
+            return Err(CommandError::InvalidArgument(
+                "Backoff factor must be at least 1".to_string(),
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(CommandError::InvalidArgument(
+                "Jitter must be between 0 and 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Delay before attempt `attempt` (1-based). `unit` in [0, 1) picks the
+    /// jitter, 0.5 being none.
+    fn delay(&self, attempt: u32, unit: f64) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(64) as i32;
+        let max = self.max_delay_ms as f64;
+        let base = (self.initial_delay_ms as f64 * self.factor.powi(exponent)).min(max);
+        let jittered = base * (1.0 + self.jitter * (2.0 * unit - 1.0));
+        Duration::from_millis(jittered.clamp(MIN_DELAY_MS as f64, max) as u64)
+    }
+
+    fn give_up_after(&self) -> Option<Duration> {
+        (self.give_up_after_secs > 0).then(|| Duration::from_secs(self.give_up_after_secs))
+    }
+}
+
+/// Attempt count of one device since it was last connected.
+pub(crate) struct ReconnectBackoff {
+    policy: ReconnectPolicy,
+    attempt: u32,
+    /// When the first attempt of this streak failed.
+    since: Option<Instant>,
+}
+
+impl ReconnectBackoff {
+    pub fn new(policy: ReconnectPolicy) -> Self {
+        Self {
+            policy,
+            attempt: 0,
+            since: None,
+        }
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Start over after the device connected.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+        self.since = None;
+    }
+
+    /// Count a failed attempt and return the delay before the next one, or
+    /// None once the policy gives up.
+    pub fn next_delay(&mut self, now: Instant, unit: f64) -> Option<Duration> {
+        self.attempt = self.attempt.saturating_add(1);
+        let since = *self.since.get_or_insert(now);
+        if let Some(give_up_after) = self.policy.give_up_after() {
+            if now.duration_since(since) >= give_up_after {
+                return None;
+            }
+        }
+        Some(self.policy.delay(self.attempt, unit))
+    }
+}
+
+/// A number in [0, 1) for jitter. Not suitable for anything but spreading
+/// retries.
+pub(crate) fn jitter_unit() -> f64 {
+    let hash = std::collections::hash_map::RandomState::new().hash_one(SystemTime::now());
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Wall-clock time `delay` from now in milliseconds since the Unix epoch, for
+/// the frontend.
+pub(crate) fn epoch_millis_after(delay: Duration) -> u64 {
+    (SystemTime::now() + delay)
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay_ms: 1_000,
+            factor: 2.0,
+            jitter: 0.0,
+            max_delay_ms: 10_000,
+            give_up_after_secs: 0,
+        }
+    }
+
+    #[test]
+    fn default_policy_retries_every_five_seconds_forever() {
+        let mut backoff = ReconnectBackoff::new(ReconnectPolicy::default());
+        let start = Instant::now();
+        for attempt in 1..=100u32 {
+            let now = start + Duration::from_secs(5 * u64::from(attempt));
+            assert_eq!(backoff.next_delay(now, 0.5), Some(Duration::from_secs(5)));
+        }
+        assert_eq!(backoff.attempt(), 100);
+    }
+
+    #[test]
+    fn delay_grows_by_factor_up_to_max() {
+        let mut backoff = ReconnectBackoff::new(policy());
+        let now = Instant::now();
+        let delays: Vec<u64> = (0..6)
+            .map(|_| backoff.next_delay(now, 0.5).unwrap().as_millis() as u64)
+            .collect();
+        assert_eq!(delays, vec![1_000, 2_000, 4_000, 8_000, 10_000, 10_000]);
+    }
+
+    #[test]
+    fn jitter_stays_within_its_share_of_the_delay() {
+        let policy = ReconnectPolicy {
+            jitter: 0.25,
+            ..policy()
+        };
+        assert_eq!(policy.delay(1, 0.0), Duration::from_millis(750));
+        assert_eq!(policy.delay(1, 0.5), Duration::from_millis(1_000));
+        assert!(policy.delay(1, 0.999_999) <= Duration::from_millis(1_250));
+        // Jitter never pushes past the maximum.
+        assert_eq!(policy.delay(10, 0.999_999), Duration::from_millis(10_000));
+    }
+
+    #[test]
+    fn gives_up_once_the_streak_is_long_enough() {
+        let mut backoff = ReconnectBackoff::new(ReconnectPolicy {
+            give_up_after_secs: 30,
+            ..policy()
+        });
+        let start = Instant::now();
+        assert!(backoff.next_delay(start, 0.5).is_some());
+        assert!(backoff
+            .next_delay(start + Duration::from_secs(29), 0.5)
+            .is_some());
+        assert_eq!(
+            backoff.next_delay(start + Duration::from_secs(30), 0.5),
+            None
+        );
+    }
+
+    #[test]
+    fn reset_starts_a_new_streak() {
+        let mut backoff = ReconnectBackoff::new(ReconnectPolicy {
+            give_up_after_secs: 30,
+            ..policy()
+        });
+        let start = Instant::now();
+        backoff.next_delay(start, 0.5);
+        backoff.next_delay(start + Duration::from_secs(10), 0.5);
+        backoff.reset();
+        assert_eq!(backoff.attempt(), 0);
+        assert_eq!(
+            backoff.next_delay(start + Duration::from_secs(40), 0.5),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        for invalid in [
+            ReconnectPolicy {
+                initial_delay_ms: 0,
+                ..policy()
+            },
+            ReconnectPolicy {
+                factor: 0.5,
+                ..policy()
+            },
+            ReconnectPolicy {
+                factor: f64::NAN,
+                ..policy()
+            },
+            ReconnectPolicy {
+                jitter: 1.5,
+                ..policy()
+            },
+            ReconnectPolicy {
+                max_delay_ms: 500,
+                ..policy()
+            },
+        ] {
+            assert_eq!(invalid.validate().unwrap_err().code(), "invalid_argument");
+        }
+        assert!(policy().validate().is_ok());
+        assert!(ReconnectPolicy::default().validate().is_ok());
+    }
+
+    #[test]
+    fn jitter_unit_is_in_range() {
+        for _ in 0..100 {
+            let unit = jitter_unit();
+            assert!((0.0..1.0).contains(&unit));
+        }
+    }
+}
@@ -0,0 +1,722 @@
+//! Per-adapter queue of GATT operations.
+//!
+//! Polling reads, monitor startups, descriptor reads and presence queries run
+//! on independent tasks, and WinRT and BlueZ both misbehave when one adapter
+//! gets overlapping GATT requests. `scheduled` wraps the transport so every
+//! connect, discovery, read and subscription first takes a turn on its
+//! adapter; `MAX_ACTIVE_PER_ADAPTER` of them run at once.
+//!
+//! Waiting operations are served by priority, then by device, the one served
+//! least recently first, so a device with many parts cannot hold the others
+//! back. The priority comes from the surrounding `with_priority` scope;
+//! operations outside any scope come from commands and count as user
+//! refreshes. A scope's priority can be raised while it runs, which moves
+//! its queued operations up too. Queue wait times per priority are kept for
+//! `get_gatt_scheduler_metrics`.
+
+use crate::ble_transport::{
+    AdapterEventStream, AdvertisementStream, BleAdapter, BleCharacteristic, BleDescriptor,
+    BleDevice, BleResult, BleService, BleTransport, ConnectionEventStream, NotifyStream,
+};
+use async_trait::async_trait;
+use bluest::CharacteristicProperties;
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::sync::oneshot;
+use tokio::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Operations one adapter runs at once.
+const MAX_ACTIVE_PER_ADAPTER: usize = 1;
+
+/// Waits longer than this are logged.
+const SLOW_WAIT: Duration = Duration::from_secs(2);
+
+/// Who an operation is for; earlier variants go first.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// A read or query the user is waiting for.
+    UserRefresh,
+    /// Connecting and subscribing for a notification monitor.
+    NotificationSetup,
+    /// Backend polling, verification reads and presence queries.
+    BackgroundPoll,
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [
+        Priority::UserRefresh,
+        Priority::NotificationSetup,
+        Priority::BackgroundPoll,
+    ];
+}
+
+/// Priority of one scope's operations, shared with the queue so raising it
+/// also moves operations that are already waiting.
+#[derive(Clone)]
+pub(crate) struct SharedPriority(Arc<AtomicU8>);
+
+impl SharedPriority {
+    pub fn new(priority: Priority) -> Self {
+        Self(Arc::new(AtomicU8::new(priority as u8)))
+    }
+
+    pub fn get(&self) -> Priority {
+        Priority::ALL[usize::from(self.0.load(Ordering::Relaxed))]
+    }
+
+    /// Moves the operations up to `priority`; never lowers it.
+    pub fn raise(&self, priority: Priority) {
+        self.0.fetch_min(priority as u8, Ordering::Relaxed);
+    }
+}
+
+impl From<Priority> for SharedPriority {
+    fn from(priority: Priority) -> Self {
+        Self::new(priority)
+    }
+}
+
+tokio::task_local! {
+    static PRIORITY: SharedPriority;
+}
+
+/// Runs `future` with its GATT operations queued at `priority`. Tasks it
+/// spawns do not inherit the priority.
+pub(crate) async fn with_priority<F: Future>(priority: Priority, future: F) -> F::Output {
+    with_shared_priority(priority.into(), future).await
+}
+
+/// `with_priority` with a priority the caller can raise later.
+pub(crate) async fn with_shared_priority<F: Future>(
+    priority: SharedPriority,
+    future: F,
+) -> F::Output {
+    PRIORITY.scope(priority, future).await
+}
+
+/// Priority of the enclosing scope right now.
+pub(crate) fn current_priority() -> Priority {
+    scope_priority().get()
+}
+
+fn scope_priority() -> SharedPriority {
+    PRIORITY
+        .try_with(SharedPriority::clone)
+        .unwrap_or_else(|_| Priority::UserRefresh.into())
+}
+
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WaitStats {
+    pub operations: u64,
+    pub total_wait_ms: u64,
+    pub max_wait_ms: u64,
+}
+
+impl WaitStats {
+    fn record(&mut self, wait: Duration) {
+        let wait_ms = u64::try_from(wait.as_millis()).unwrap_or(u64::MAX);
+        self.operations += 1;
+        self.total_wait_ms = self.total_wait_ms.saturating_add(wait_ms);
+        self.max_wait_ms = self.max_wait_ms.max(wait_ms);
+    }
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct AdapterQueueMetrics {
+    pub adapter_id: String,
+    /// Operations waiting for their turn right now.
+    pub queued: usize,
+    /// Queue wait of the operations that got their turn, by priority.
+    pub waits: BTreeMap<Priority, WaitStats>,
+}
+
+struct Waiter {
+    seq: u64,
+    priority: SharedPriority,
+    device_id: String,
+    enqueued: Instant,
+    turn_tx: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct AdapterQueue {
+    active: usize,
+    waiting: Vec<Waiter>,
+    next_seq: u64,
+    /// Turn number each device was last served at.
+    last_served: HashMap<String, u64>,
+    turns: u64,
+    waits: BTreeMap<Priority, WaitStats>,
+}
+
+impl AdapterQueue {
+    fn serve(&mut self, device_id: &str, priority: Priority, wait: Duration) {
+        self.active += 1;
+        self.turns += 1;
+        self.last_served.insert(device_id.to_string(), self.turns);
+        self.waits.entry(priority).or_default().record(wait);
+    }
+
+    /// Hands free turns to the waiters that come next.
+    fn grant(&mut self, max_active: usize, adapter_id: &str) {
+        while self.active < max_active {
+            let Some(index) = (0..self.waiting.len()).min_by_key(|&i| {
+                let waiter = &self.waiting[i];
+                let last_served = self.last_served.get(&waiter.device_id).copied();
+                (waiter.priority.get(), last_served.unwrap_or(0), waiter.seq)
+            }) else {
+                return;
+            };
+            let waiter = self.waiting.remove(index);
+            let priority = waiter.priority.get();
+            let wait = waiter.enqueued.elapsed();
+            if wait >= SLOW_WAIT {
+                log::debug!(
+                    "BLE I/O: GATT operation waited {wait:?} adapter_id={adapter_id} device_id={} priority={priority:?}",
+                    waiter.device_id
+                );
+            }
+            self.serve(&waiter.device_id, priority, wait);
+            // A waiter that went away gives its turn back when its guard drops.
+            let _ = waiter.turn_tx.send(());
+        }
+    }
+}
+
+pub(crate) struct GattScheduler {
+    max_active: usize,
+    queues: Mutex<HashMap<String, AdapterQueue>>,
+}
+
+impl GattScheduler {
+    pub fn new(max_active: usize) -> Self {
+        Self {
+            max_active,
+            queues: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, AdapterQueue>> {
+        self.queues.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    /// Waits for a turn on `adapter_id`, which lasts until the returned turn
+    /// is dropped.
+    pub async fn turn(
+        self: &Arc<Self>,
+        adapter_id: &str,
+        device_id: &str,
+        priority: impl Into<SharedPriority>,
+    ) -> Turn {
+        let priority = priority.into();
+        let (seq, turn_rx) = {
+            let mut queues = self.lock();
+            let queue = queues.entry(adapter_id.to_string()).or_default();
+            if queue.active < self.max_active && queue.waiting.is_empty() {
+                queue.serve(device_id, priority.get(), Duration::ZERO);
+                return self.granted(adapter_id);
+            }
+            let seq = queue.next_seq;
+            queue.next_seq += 1;
+            let (turn_tx, turn_rx) = oneshot::channel();
+            queue.waiting.push(Waiter {
+                seq,
+                priority,
+                device_id: device_id.to_string(),
+                enqueued: Instant::now(),
+                turn_tx,
+            });
+            (seq, turn_rx)
+        };
+
+        let mut waiting = WaitGuard {
+            scheduler: self,
+            adapter_id,
+            seq,
+            done: false,
+        };
+        let _ = turn_rx.await;
+        waiting.done = true;
+        self.granted(adapter_id)
+    }
+
+    fn granted(self: &Arc<Self>, adapter_id: &str) -> Turn {
+        Turn {
+            scheduler: self.clone(),
+            adapter_id: adapter_id.to_string(),
+        }
+    }
+
+    fn release(&self, adapter_id: &str) {
+        let mut queues = self.lock();
+        if let Some(queue) = queues.get_mut(adapter_id) {
+            queue.active -= 1;
+            queue.grant(self.max_active, adapter_id);
+        }
+    }
+
+    pub fn metrics(&self) -> Vec<AdapterQueueMetrics> {
+        let mut metrics: Vec<AdapterQueueMetrics> = self
+            .lock()
+            .iter()
+            .map(|(adapter_id, queue)| AdapterQueueMetrics {
+                adapter_id: adapter_id.clone(),
+                queued: queue.waiting.len(),
+                waits: queue.waits.clone(),
+            })
+            .collect();
+        metrics.sort_by(|a, b| a.adapter_id.cmp(&b.adapter_id));
+        metrics
+    }
+}
+
+/// Leaves the queue when a waiting operation is dropped, or gives back the
+/// turn it was granted in the meantime.
+struct WaitGuard<'a> {
+    scheduler: &'a GattScheduler,
+    adapter_id: &'a str,
+    seq: u64,
+    done: bool,
+}
+
+impl Drop for WaitGuard<'_> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let mut queues = self.scheduler.lock();
+        let Some(queue) = queues.get_mut(self.adapter_id) else {
+            return;
+        };
+        let before = queue.waiting.len();
+        queue.waiting.retain(|waiter| waiter.seq != self.seq);
+        if queue.waiting.len() == before {
+            queue.active -= 1;
+            queue.grant(self.scheduler.max_active, self.adapter_id);
+        }
+    }
+}
+
+/// One operation's turn on its adapter.
+pub(crate) struct Turn {
+    scheduler: Arc<GattScheduler>,
+    adapter_id: String,
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        self.scheduler.release(&self.adapter_id);
+    }
+}
+
+static SCHEDULER: LazyLock<Arc<GattScheduler>> =
+    LazyLock::new(|| Arc::new(GattScheduler::new(MAX_ACTIVE_PER_ADAPTER)));
+
+/// Queue wait times of every adapter used so far.
+#[tauri::command]
+pub async fn get_gatt_scheduler_metrics() -> Vec<AdapterQueueMetrics> {
+    SCHEDULER.metrics()
+}
+
+/// `transport` with every GATT operation queued on its adapter.
+pub(crate) fn scheduled(transport: Arc<dyn BleTransport>) -> Arc<dyn BleTransport> {
+    Arc::new(ScheduledTransport {
+        inner: transport,
+        scheduler: SCHEDULER.clone(),
+    })
+}
+
+/// Where an operation is queued: its adapter and the device it is for.
+#[derive(Clone)]
+struct Slot {
+    scheduler: Arc<GattScheduler>,
+    adapter_id: String,
+    device_id: String,
+}
+
+impl Slot {
+    async fn run<T>(&self, operation: impl Future<Output = BleResult<T>>) -> BleResult<T> {
+        let _turn = self
+            .scheduler
+            .turn(&self.adapter_id, &self.device_id, scope_priority())
+            .await;
+        operation.await
+    }
+}
+
+struct ScheduledTransport {
+    inner: Arc<dyn BleTransport>,
+    scheduler: Arc<GattScheduler>,
+}
+
+impl ScheduledTransport {
+    fn adapter(&self, adapter: Arc<dyn BleAdapter>) -> Arc<dyn BleAdapter> {
+        Arc::new(ScheduledAdapter {
+            inner: adapter,
+            scheduler: self.scheduler.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl BleTransport for ScheduledTransport {
+    async fn default_adapter(&self) -> Option<Arc<dyn BleAdapter>> {
+        let adapter = self.inner.default_adapter().await?;
+        Some(self.adapter(adapter))
+    }
+
+    async fn adapters(&self) -> Vec<Arc<dyn BleAdapter>> {
+        self.inner
+            .adapters()
+            .await
+            .into_iter()
+            .map(|adapter| self.adapter(adapter))
+            .collect()
+    }
+}
+
+struct ScheduledAdapter {
+    inner: Arc<dyn BleAdapter>,
+    scheduler: Arc<GattScheduler>,
+}
+
+impl ScheduledAdapter {
+    fn device(&self, device: Arc<dyn BleDevice>) -> Arc<dyn BleDevice> {
+        let slot = Slot {
+            scheduler: self.scheduler.clone(),
+            adapter_id: self.inner.id(),
+            device_id: device.id(),
+        };
+        Arc::new(ScheduledDevice {
+            inner: device,
+            slot,
+        })
+    }
+}
+
+/// Adapter state, events and scans are not GATT operations and are passed
+/// straight through; only the connected-device query is queued.
+#[async_trait]
+impl BleAdapter for ScheduledAdapter {
+    fn id(&self) -> String {
+        self.inner.id()
+    }
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn wait_available(&self) -> BleResult<()> {
+        self.inner.wait_available().await
+    }
+
+    async fn is_available(&self) -> BleResult<bool> {
+        self.inner.is_available().await
+    }
+
+    async fn events(&self) -> BleResult<AdapterEventStream<'_>> {
+        self.inner.events().await
+    }
+
+    async fn connected_devices_with_services(
+        &self,
+        services: &[Uuid],
+    ) -> BleResult<Vec<Arc<dyn BleDevice>>> {
+        // Queries are not for one device; they take turns as a device of
+        // their own.
+        let slot = Slot {
+            scheduler: self.scheduler.clone(),
+            adapter_id: self.inner.id(),
+            device_id: String::new(),
+        };
+        let devices = slot
+            .run(self.inner.connected_devices_with_services(services))
+            .await?;
+        Ok(devices
+            .into_iter()
+            .map(|device| self.device(device))
+            .collect())
+    }
+
+    async fn scan<'a>(&'a self, services: &'a [Uuid]) -> BleResult<AdvertisementStream<'a>> {
+        let advertisements = self.inner.scan(services).await?;
+        Ok(advertisements
+            .map(|mut advertisement| {
+                advertisement.device = self.device(advertisement.device);
+                advertisement
+            })
+            .boxed())
+    }
+}
+
+struct ScheduledDevice {
+    inner: Arc<dyn BleDevice>,
+    slot: Slot,
+}
+
+#[async_trait]
+impl BleDevice for ScheduledDevice {
+    fn id(&self) -> String {
+        self.inner.id()
+    }
+
+    fn name(&self) -> BleResult<String> {
+        self.inner.name()
+    }
+
+    async fn connect(&self) -> BleResult<()> {
+        self.slot.run(self.inner.connect()).await
+    }
+
+    async fn disconnect(&self) -> BleResult<()> {
+        self.slot.run(self.inner.disconnect()).await
+    }
+
+    async fn connection_events(&self) -> BleResult<ConnectionEventStream<'_>> {
+        self.inner.connection_events().await
+    }
+
+    async fn services(&self) -> BleResult<Vec<Arc<dyn BleService>>> {
+        let services = self.slot.run(self.inner.services()).await?;
+        Ok(services
+            .into_iter()
+            .map(|service| {
+                Arc::new(ScheduledService {
+                    inner: service,
+                    slot: self.slot.clone(),
+                }) as Arc<dyn BleService>
+            })
+            .collect())
+    }
+}
+
+struct ScheduledService {
+    inner: Arc<dyn BleService>,
+    slot: Slot,
+}
+
+#[async_trait]
+impl BleService for ScheduledService {
+    fn uuid(&self) -> Uuid {
+        self.inner.uuid()
+    }
+
+    async fn characteristics(&self) -> BleResult<Vec<Arc<dyn BleCharacteristic>>> {
+        let characteristics = self.slot.run(self.inner.characteristics()).await?;
+        Ok(characteristics
+            .into_iter()
+            .map(|characteristic| {
+                Arc::new(ScheduledCharacteristic {
+                    inner: characteristic,
+                    slot: self.slot.clone(),
+                }) as Arc<dyn BleCharacteristic>
+            })
+            .collect())
+    }
+}
+
+struct ScheduledCharacteristic {
+    inner: Arc<dyn BleCharacteristic>,
+    slot: Slot,
+}
+
+#[async_trait]
+impl BleCharacteristic for ScheduledCharacteristic {
+    fn uuid(&self) -> Uuid {
+        self.inner.uuid()
+    }
+
+    async fn properties(&self) -> BleResult<CharacteristicProperties> {
+        self.inner.properties().await
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        self.slot.run(self.inner.read()).await
+    }
+
+    /// Only subscribing takes a turn; notifications then arrive on their own.
+    async fn notify(&self) -> BleResult<NotifyStream<'_>> {
+        self.slot.run(self.inner.notify()).await
+    }
+
+    async fn descriptors(&self) -> BleResult<Vec<Arc<dyn BleDescriptor>>> {
+        let descriptors = self.slot.run(self.inner.descriptors()).await?;
+        Ok(descriptors
+            .into_iter()
+            .map(|descriptor| {
+                Arc::new(ScheduledDescriptor {
+                    inner: descriptor,
+                    slot: self.slot.clone(),
+                }) as Arc<dyn BleDescriptor>
+            })
+            .collect())
+    }
+}
+
+struct ScheduledDescriptor {
+    inner: Arc<dyn BleDescriptor>,
+    slot: Slot,
+}
+
+#[async_trait]
+impl BleDescriptor for ScheduledDescriptor {
+    fn uuid(&self) -> Uuid {
+        self.inner.uuid()
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        self.slot.run(self.inner.read()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    use tokio::time::sleep;
+
+    /// Queues `(device, priority)` operations behind one that holds the
+    /// adapter for a second, and returns the order they ran in.
+    async fn run_order(scheduler: Arc<GattScheduler>, queued: &[(&str, Priority)]) -> Vec<String> {
+        let busy = scheduler
+            .turn("hci0", "busy", Priority::BackgroundPoll)
+            .await;
+        let (order_tx, mut order_rx) = mpsc::unbounded_channel();
+        for (index, &(device_id, priority)) in queued.iter().enumerate() {
+            let scheduler = scheduler.clone();
+            let order_tx = order_tx.clone();
+            let device_id = device_id.to_string();
+            tokio::spawn(async move {
+                let _turn = scheduler.turn("hci0", &device_id, priority).await;
+                let _ = order_tx.send(format!("{device_id}#{index}"));
+                sleep(Duration::from_millis(10)).await;
+            });
+        }
+        drop(order_tx);
+        sleep(Duration::from_secs(1)).await;
+        drop(busy);
+
+        let mut order = Vec::new();
+        while let Some(entry) = order_rx.recv().await {
+            order.push(entry);
+        }
+        order
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn higher_priorities_go_first() {
+        let order = run_order(
+            Arc::new(GattScheduler::new(1)),
+            &[
+                ("kbd-1", Priority::BackgroundPoll),
+                ("kbd-2", Priority::NotificationSetup),
+                ("kbd-3", Priority::UserRefresh),
+            ],
+        )
+        .await;
+        assert_eq!(order, ["kbd-3#2", "kbd-2#1", "kbd-1#0"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn devices_take_turns_within_a_priority() {
+        let poll = Priority::BackgroundPoll;
+        let order = run_order(
+            Arc::new(GattScheduler::new(1)),
+            &[
+                ("kbd-1", poll),
+                ("kbd-1", poll),
+                ("kbd-1", poll),
+                ("kbd-2", poll),
+            ],
+        )
+        .await;
+        assert_eq!(order, ["kbd-1#0", "kbd-2#3", "kbd-1#1", "kbd-1#2"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_times_are_recorded_and_abandoned_waits_leave_the_queue() {
+        let scheduler = Arc::new(GattScheduler::new(1));
+        let busy = scheduler
+            .turn("hci0", "kbd-1", Priority::BackgroundPoll)
+            .await;
+
+        let abandoned = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move {
+                let _turn = scheduler.turn("hci0", "kbd-2", Priority::UserRefresh).await;
+            }
+        });
+        let waiting = tokio::spawn({
+            let scheduler = scheduler.clone();
+            async move {
+                let _turn = scheduler.turn("hci0", "kbd-3", Priority::UserRefresh).await;
+            }
+        });
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(scheduler.metrics()[0].queued, 2);
+        abandoned.abort();
+        let _ = abandoned.await;
+        assert_eq!(scheduler.metrics()[0].queued, 1);
+
+        sleep(Duration::from_millis(400)).await;
+        drop(busy);
+        waiting.await.expect("waiting operation");
+
+        let metrics = scheduler.metrics();
+        assert_eq!(metrics[0].queued, 0);
+        assert_eq!(
+            metrics[0].waits[&Priority::UserRefresh],
+            WaitStats {
+                operations: 1,
+                total_wait_ms: 500,
+                max_wait_ms: 500,
+            }
+        );
+        assert_eq!(metrics[0].waits[&Priority::BackgroundPoll].operations, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn raising_a_priority_moves_waiting_operations_up() {
+        let scheduler = Arc::new(GattScheduler::new(1));
+        let busy = scheduler
+            .turn("hci0", "busy", Priority::BackgroundPoll)
+            .await;
+        let raised = SharedPriority::new(Priority::BackgroundPoll);
+        let (order_tx, mut order_rx) = mpsc::unbounded_channel();
+        for (device_id, priority) in [
+            ("kbd-1", SharedPriority::new(Priority::NotificationSetup)),
+            ("kbd-2", raised.clone()),
+        ] {
+            let scheduler = scheduler.clone();
+            let order_tx = order_tx.clone();
+            tokio::spawn(async move {
+                let _turn = scheduler.turn("hci0", device_id, priority).await;
+                let _ = order_tx.send(device_id);
+            });
+        }
+        drop(order_tx);
+        sleep(Duration::from_millis(100)).await;
+        raised.raise(Priority::UserRefresh);
+        raised.raise(Priority::BackgroundPoll);
+        drop(busy);
+
+        let mut order = Vec::new();
+        while let Some(device_id) = order_rx.recv().await {
+            order.push(device_id);
+        }
+        assert_eq!(order, ["kbd-2", "kbd-1"]);
+    }
+
+    #[tokio::test]
+    async fn priority_comes_from_the_enclosing_scope() {
+        assert_eq!(current_priority(), Priority::UserRefresh);
+        let scoped = with_priority(Priority::BackgroundPoll, async { current_priority() }).await;
+        assert_eq!(scoped, Priority::BackgroundPoll);
+    }
+}
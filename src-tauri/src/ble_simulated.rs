@@ -0,0 +1,1106 @@
+//! In-process simulated BLE backend with virtual ZMK keyboards.
+//!
+//! Each keyboard exposes one Battery Level characteristic per part, with the
+//! part name published through the User Description descriptor the same way
+//! ZMK split keyboards do (optionally with a Presentation Format descriptor
+//! as well), plus any fixed read-only characteristics (Device Information
+//! strings, GAP Appearance, ...) added to its definition.
+//!
+//! Levels, disconnects, reconnects and notify failures are driven either
+//! directly through `SimulatedAdapter::apply` or by a timed script, so the
+//! monitor code in `ble.rs` can be exercised without hardware. The adapter
+//! itself can be powered off or unplugged the same way.
+
+use crate::ble::{BATTERY_LEVEL_UUID, BATTERY_SERVICE_UUID};
+use crate::ble_presentation::PresentationFormat;
+use crate::ble_transport::{
+    AdapterEventStream, Advertisement, AdvertisementStream, BleAdapter, BleCharacteristic,
+    BleDescriptor, BleDevice, BleResult, BleService, BleTransport, ConnectionEventStream,
+    NotifyStream,
+};
+use async_trait::async_trait;
+use bluest::btuuid::descriptors::{
+    CHARACTERISTIC_PRESENTATION_FORMAT, CHARACTERISTIC_USER_DESCRIPTION,
+};
+use bluest::error::ErrorKind;
+use bluest::{AdapterEvent, CharacteristicProperties, ConnectionEvent};
+use futures_util::StreamExt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration};
+use uuid::Uuid;
+
+const SIMULATED_RSSI: i16 = -60;
+/// How often a stalled read checks whether it was released.
+const STALL_RECHECK: Duration = Duration::from_millis(100);
+
+/// Definition of one battery part of a virtual keyboard.
+#[derive(Debug, Clone)]
+pub struct SimulatedPart {
+    pub user_description: Option<String>,
+    pub level: u8,
+    pub notify: bool,
+    pub presentation_format: Option<PresentationFormat>,
+}
+
+/// A characteristic other than Battery Level. Its value only changes through
+/// `SimAction::SetValue`.
+#[derive(Debug, Clone)]
+pub struct SimulatedStaticCharacteristic {
+    pub service: Uuid,
+    pub uuid: Uuid,
+    pub value: Vec<u8>,
+    pub notify: bool,
+}
+
+/// Definition of a virtual keyboard.
+#[derive(Debug, Clone)]
+pub struct SimulatedKeyboard {
+    pub id: String,
+    pub name: String,
+    pub connected: bool,
+    pub parts: Vec<SimulatedPart>,
+    pub static_characteristics: Vec<SimulatedStaticCharacteristic>,
+}
+
+impl SimulatedKeyboard {
+    pub fn new(id: &str, name: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            connected: true,
+            parts: Vec::new(),
+            static_characteristics: Vec::new(),
+        }
+    }
+
+    /// A split keyboard with a central and one peripheral, both notifying.
+    #[cfg(test)]
+    pub fn split(id: &str, name: &str, central: u8, peripheral: u8) -> Self {
+        Self::new(id, name)
+            .part(None, central)
+            .part(Some("Peripheral 0"), peripheral)
+    }
+
+    pub fn part(mut self, user_description: Option<&str>, level: u8) -> Self {
+        self.parts.push(SimulatedPart {
+            user_description: user_description.map(str::to_string),
+            level,
+            notify: true,
+            presentation_format: None,
+        });
+        self
+    }
+
+    /// Gives the most recently added part a Presentation Format descriptor.
+    pub fn with_presentation_format(mut self, format: PresentationFormat) -> Self {
+        if let Some(part) = self.parts.last_mut() {
+            part.presentation_format = Some(format);
+        }
+        self
+    }
+
+    /// Marks the most recently added part as read-only (no notify/indicate).
+    pub fn without_notify(mut self) -> Self {
+        if let Some(part) = self.parts.last_mut() {
+            part.notify = false;
+        }
+        self
+    }
+
+    pub fn disconnected(mut self) -> Self {
+        self.connected = false;
+        self
+    }
+
+    /// Adds a read-only characteristic to `service`. Services are exposed
+    /// after the Battery Service in the order they are first used.
+    pub fn characteristic(mut self, service: Uuid, uuid: Uuid, value: &[u8]) -> Self {
+        self.static_characteristics.push(SimulatedStaticCharacteristic {
+            service,
+            uuid,
+            value: value.to_vec(),
+            notify: false,
+        });
+        self
+    }
+
+    /// Like `characteristic`, but it also notifies on `SimAction::SetValue`.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn notifying_characteristic(self, service: Uuid, uuid: Uuid, value: &[u8]) -> Self {
+        let mut keyboard = self.characteristic(service, uuid, value);
+        if let Some(characteristic) = keyboard.static_characteristics.last_mut() {
+            characteristic.notify = true;
+        }
+        keyboard
+    }
+}
+
+/// One scripted change to the simulated world. The failure injections are
+/// driven from tests and from replayed traces (`ble_trace.rs`).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(test), allow(dead_code))]
+pub enum SimAction {
+    SetLevel {
+        device: String,
+        part: usize,
+        level: u8,
+    },
+    Disconnect {
+        device: String,
+    },
+    Connect {
+        device: String,
+    },
+    /// Push an error into every open notify stream of the part.
+    FailNotify {
+        device: String,
+        part: usize,
+    },
+    /// Make the next `count` reads of the part fail.
+    FailReads {
+        device: String,
+        part: usize,
+        count: u32,
+    },
+    /// Leave reads of the part pending until cleared, like a wedged stack.
+    StallReads {
+        device: String,
+        part: usize,
+        stall: bool,
+    },
+    /// Make notify subscriptions of the part fail until cleared.
+    RejectSubscribe {
+        device: String,
+        part: usize,
+        reject: bool,
+    },
+    /// Keep the part's notify streams open but stop delivering level changes
+    /// until cleared, like firmware that silently stops notifying.
+    MuteNotify {
+        device: String,
+        part: usize,
+        mute: bool,
+    },
+    /// Replace the value of the first non-Battery-Level characteristic with
+    /// `uuid`, notifying subscribers.
+    SetValue {
+        device: String,
+        uuid: Uuid,
+        value: Vec<u8>,
+    },
+    /// Power the adapter off or on. While it is off every device operation
+    /// fails and open streams end; bonded keyboards come back on power-up.
+    SetPowered {
+        powered: bool,
+    },
+    /// Unplug the adapter: it disappears from the transport and its event
+    /// streams end.
+    RemoveAdapter,
+    /// Plug a removed adapter back in, powered on.
+    RestoreAdapter,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimStep {
+    /// Delay after the previous step.
+    pub after: Duration,
+    pub action: SimAction,
+}
+
+struct PartState {
+    definition: SimulatedPart,
+    failing_reads: u32,
+    stalled_reads: bool,
+    reject_subscribe: bool,
+    muted: bool,
+    subscribers: Vec<mpsc::UnboundedSender<BleResult<Vec<u8>>>>,
+}
+
+struct StaticCharacteristicState {
+    definition: SimulatedStaticCharacteristic,
+    subscribers: Vec<mpsc::UnboundedSender<BleResult<Vec<u8>>>>,
+}
+
+struct KeyboardState {
+    id: String,
+    name: String,
+    connected: bool,
+    parts: Vec<PartState>,
+    static_characteristics: Vec<StaticCharacteristicState>,
+    connection_subscribers: Vec<mpsc::UnboundedSender<ConnectionEvent>>,
+}
+
+impl KeyboardState {
+    /// End every open notify stream and tell connection subscribers the link
+    /// is gone.
+    fn drop_links(&mut self) {
+        // Dropping the senders ends every open notify stream.
+        for part in self.parts.iter_mut() {
+            part.subscribers.clear();
+        }
+        for characteristic in self.static_characteristics.iter_mut() {
+            characteristic.subscribers.clear();
+        }
+        self.connection_subscribers
+            .retain(|tx| tx.send(ConnectionEvent::Disconnected).is_ok());
+    }
+}
+
+#[derive(Default)]
+struct SimState {
+    keyboards: Vec<KeyboardState>,
+    powered_off: bool,
+    removed: bool,
+    adapter_subscribers: Vec<mpsc::UnboundedSender<BleResult<AdapterEvent>>>,
+    #[cfg(test)]
+    connected_queries: usize,
+}
+
+impl SimState {
+    fn available(&self) -> BleResult<()> {
+        if self.powered_off || self.removed {
+            return Err(ErrorKind::AdapterUnavailable.into());
+        }
+        Ok(())
+    }
+
+    fn keyboard(&self, id: &str) -> BleResult<&KeyboardState> {
+        self.available()?;
+        self.keyboards
+            .iter()
+            .find(|k| k.id == id)
+            .ok_or_else(|| ErrorKind::NotFound.into())
+    }
+
+    fn keyboard_mut(&mut self, id: &str) -> Option<&mut KeyboardState> {
+        self.keyboards.iter_mut().find(|k| k.id == id)
+    }
+
+    fn connected_part(&self, id: &str, part: usize) -> BleResult<&PartState> {
+        let keyboard = self.keyboard(id)?;
+        if !keyboard.connected {
+            return Err(ErrorKind::NotConnected.into());
+        }
+        keyboard
+            .parts
+            .get(part)
+            .ok_or_else(|| ErrorKind::NotFound.into())
+    }
+
+    fn connected_keyboard(&self, id: &str) -> BleResult<&KeyboardState> {
+        let keyboard = self.keyboard(id)?;
+        if !keyboard.connected {
+            return Err(ErrorKind::NotConnected.into());
+        }
+        Ok(keyboard)
+    }
+}
+
+/// Simulated adapter shared by every handle it hands out. Cloning is cheap and
+/// clones observe the same keyboards.
+#[derive(Clone)]
+pub struct SimulatedAdapter {
+    id: String,
+    state: Arc<Mutex<SimState>>,
+}
+
+impl Default for SimulatedAdapter {
+    fn default() -> Self {
+        Self {
+            id: "sim0".to_string(),
+            state: Arc::default(),
+        }
+    }
+}
+
+impl SimulatedAdapter {
+    pub fn new(keyboards: Vec<SimulatedKeyboard>) -> Self {
+        let adapter = Self::default();
+        for keyboard in keyboards {
+            adapter.add_keyboard(keyboard);
+        }
+        adapter
+    }
+
+    /// Give the adapter another id, e.g. to stand in for a USB dongle next to
+    /// the built-in radio.
+    pub fn with_id(mut self, id: &str) -> Self {
+        self.id = id.to_string();
+        self
+    }
+
+    pub fn add_keyboard(&self, keyboard: SimulatedKeyboard) {
+        let mut state = self.lock();
+        state.keyboards.push(KeyboardState {
+            id: keyboard.id,
+            name: keyboard.name,
+            connected: keyboard.connected,
+            parts: keyboard
+                .parts
+                .into_iter()
+                .map(|definition| PartState {
+                    definition,
+                    failing_reads: 0,
+                    stalled_reads: false,
+                    reject_subscribe: false,
+                    muted: false,
+                    subscribers: Vec::new(),
+                })
+                .collect(),
+            static_characteristics: keyboard
+                .static_characteristics
+                .into_iter()
+                .map(|definition| StaticCharacteristicState {
+                    definition,
+                    subscribers: Vec::new(),
+                })
+                .collect(),
+            connection_subscribers: Vec::new(),
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, SimState> {
+        self.state.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    pub fn apply(&self, action: &SimAction) {
+        let mut state = self.lock();
+        match action {
+            SimAction::SetLevel {
+                device,
+                part,
+                level,
+            } => {
+                let Some(keyboard) = state.keyboard_mut(device) else {
+                    return;
+                };
+                let connected = keyboard.connected;
+                if let Some(part) = keyboard.parts.get_mut(*part) {
+                    part.definition.level = *level;
+                    if connected && !part.muted {
+                        part.subscribers
+                            .retain(|tx| tx.send(Ok(vec![*level])).is_ok());
+                    }
+                }
+            }
+            SimAction::Disconnect { device } => {
+                let Some(keyboard) = state.keyboard_mut(device) else {
+                    return;
+                };
+                keyboard.connected = false;
+                keyboard.drop_links();
+            }
+            SimAction::Connect { device } => {
+                let Some(keyboard) = state.keyboard_mut(device) else {
+                    return;
+                };
+                keyboard.connected = true;
+                keyboard
+                    .connection_subscribers
+                    .retain(|tx| tx.send(ConnectionEvent::Connected).is_ok());
+            }
+            SimAction::FailNotify { device, part } => {
+                if let Some(part) = state
+                    .keyboard_mut(device)
+                    .and_then(|k| k.parts.get_mut(*part))
+                {
+                    part.subscribers
+                        .retain(|tx| tx.send(Err(ErrorKind::Other.into())).is_ok());
+                }
+            }
+            SimAction::FailReads {
+                device,
+                part,
+                count,
+            } => {
+                if let Some(part) = state
+                    .keyboard_mut(device)
+                    .and_then(|k| k.parts.get_mut(*part))
+                {
+                    part.failing_reads = *count;
+                }
+            }
+            SimAction::StallReads {
+                device,
+                part,
+                stall,
+            } => {
+                if let Some(part) = state
+                    .keyboard_mut(device)
+                    .and_then(|k| k.parts.get_mut(*part))
+                {
+                    part.stalled_reads = *stall;
+                }
+            }
+            SimAction::RejectSubscribe {
+                device,
+                part,
+                reject,
+            } => {
+                if let Some(part) = state
+                    .keyboard_mut(device)
+                    .and_then(|k| k.parts.get_mut(*part))
+                {
+                    part.reject_subscribe = *reject;
+                }
+            }
+            SimAction::MuteNotify { device, part, mute } => {
+                if let Some(part) = state
+                    .keyboard_mut(device)
+                    .and_then(|k| k.parts.get_mut(*part))
+                {
+                    part.muted = *mute;
+                }
+            }
+            SimAction::SetValue {
+                device,
+                uuid,
+                value,
+            } => {
+                let Some(keyboard) = state.keyboard_mut(device) else {
+                    return;
+                };
+                let connected = keyboard.connected;
+                if let Some(characteristic) = keyboard
+                    .static_characteristics
+                    .iter_mut()
+                    .find(|c| c.definition.uuid == *uuid)
+                {
+                    characteristic.definition.value = value.clone();
+                    if connected {
+                        characteristic
+                            .subscribers
+                            .retain(|tx| tx.send(Ok(value.clone())).is_ok());
+                    }
+                }
+            }
+            SimAction::SetPowered { powered } => {
+                if state.powered_off != *powered {
+                    return;
+                }
+                state.powered_off = !*powered;
+                if !*powered {
+                    for keyboard in state.keyboards.iter_mut() {
+                        keyboard.drop_links();
+                    }
+                }
+                let event = if *powered {
+                    AdapterEvent::Available
+                } else {
+                    AdapterEvent::Unavailable
+                };
+                state
+                    .adapter_subscribers
+                    .retain(|tx| tx.send(Ok(event)).is_ok());
+            }
+            SimAction::RemoveAdapter => {
+                state.removed = true;
+                for keyboard in state.keyboards.iter_mut() {
+                    keyboard.drop_links();
+                }
+                state.adapter_subscribers.clear();
+            }
+            SimAction::RestoreAdapter => {
+                state.removed = false;
+                state.powered_off = false;
+            }
+        }
+    }
+
+    /// How many times the connected devices were queried.
+    #[cfg(test)]
+    pub fn connected_queries(&self) -> usize {
+        self.lock().connected_queries
+    }
+
+    fn is_present(&self) -> bool {
+        !self.lock().removed
+    }
+
+    /// Apply each step after its delay, in order.
+    pub async fn run_script(&self, steps: &[SimStep]) {
+        for step in steps {
+            sleep(step.after).await;
+            log::debug!("BLE sim: applying {:?}", step.action);
+            self.apply(&step.action);
+        }
+    }
+
+    pub(crate) fn device_handle(&self, id: &str) -> Arc<dyn BleDevice> {
+        Arc::new(SimulatedDevice {
+            adapter: self.clone(),
+            id: id.to_string(),
+        })
+    }
+}
+
+fn receiver_stream<T: Send + 'static>(
+    rx: mpsc::UnboundedReceiver<T>,
+) -> futures_util::stream::BoxStream<'static, T> {
+    futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    })
+    .boxed()
+}
+
+/// Transport over one or more simulated adapters; the first is the default.
+/// Removed adapters are left out until restored.
+pub struct SimulatedTransport {
+    adapters: Vec<SimulatedAdapter>,
+}
+
+impl SimulatedTransport {
+    pub fn new(adapter: SimulatedAdapter) -> Self {
+        Self {
+            adapters: vec![adapter],
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_adapters(adapters: Vec<SimulatedAdapter>) -> Self {
+        Self { adapters }
+    }
+}
+
+#[async_trait]
+impl BleTransport for SimulatedTransport {
+    async fn default_adapter(&self) -> Option<Arc<dyn BleAdapter>> {
+        self.adapters
+            .iter()
+            .find(|adapter| adapter.is_present())
+            .map(|adapter| Arc::new(adapter.clone()) as Arc<dyn BleAdapter>)
+    }
+
+    async fn adapters(&self) -> Vec<Arc<dyn BleAdapter>> {
+        self.adapters
+            .iter()
+            .filter(|adapter| adapter.is_present())
+            .map(|adapter| Arc::new(adapter.clone()) as Arc<dyn BleAdapter>)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl BleAdapter for SimulatedAdapter {
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn name(&self) -> String {
+        format!("Simulated adapter {}", self.id)
+    }
+
+    async fn wait_available(&self) -> BleResult<()> {
+        Ok(())
+    }
+
+    async fn is_available(&self) -> BleResult<bool> {
+        let state = self.lock();
+        if state.removed {
+            return Err(ErrorKind::AdapterUnavailable.into());
+        }
+        Ok(!state.powered_off)
+    }
+
+    async fn events(&self) -> BleResult<AdapterEventStream<'_>> {
+        let mut state = self.lock();
+        if state.removed {
+            return Err(ErrorKind::AdapterUnavailable.into());
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        state.adapter_subscribers.push(tx);
+        Ok(receiver_stream(rx))
+    }
+
+    async fn connected_devices_with_services(
+        &self,
+        services: &[Uuid],
+    ) -> BleResult<Vec<Arc<dyn BleDevice>>> {
+        let ids: Vec<String> = {
+            #[cfg_attr(not(test), allow(unused_mut))]
+            let mut state = self.lock();
+            #[cfg(test)]
+            {
+                state.connected_queries += 1;
+            }
+            state.available()?;
+            state
+                .keyboards
+                .iter()
+                .filter(|k| k.connected)
+                .filter(|_| services.is_empty() || services.contains(&BATTERY_SERVICE_UUID))
+                .map(|k| k.id.clone())
+                .collect()
+        };
+        Ok(ids.iter().map(|id| self.device_handle(id)).collect())
+    }
+
+    /// Disconnected keyboards advertise once each; the stream then stays open
+    /// like a real scan until it is dropped.
+    async fn scan<'a>(&'a self, services: &'a [Uuid]) -> BleResult<AdvertisementStream<'a>> {
+        let advertising: Vec<(String, String)> = {
+            let state = self.lock();
+            state.available()?;
+            state
+                .keyboards
+                .iter()
+                .filter(|k| !k.connected)
+                .filter(|_| services.is_empty() || services.contains(&BATTERY_SERVICE_UUID))
+                .map(|k| (k.id.clone(), k.name.clone()))
+                .collect()
+        };
+        let advertisements: Vec<Advertisement> = advertising
+            .into_iter()
+            .map(|(id, name)| Advertisement {
+                device: self.device_handle(&id),
+                local_name: Some(name),
+                services: vec![BATTERY_SERVICE_UUID],
+                rssi: Some(SIMULATED_RSSI),
+            })
+            .collect();
+        Ok(futures_util::stream::iter(advertisements)
+            .chain(futures_util::stream::pending())
+            .boxed())
+    }
+}
+
+struct SimulatedDevice {
+    adapter: SimulatedAdapter,
+    id: String,
+}
+
+#[async_trait]
+impl BleDevice for SimulatedDevice {
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn name(&self) -> BleResult<String> {
+        Ok(self.adapter.lock().keyboard(&self.id)?.name.clone())
+    }
+
+    async fn connect(&self) -> BleResult<()> {
+        if self.adapter.lock().keyboard(&self.id)?.connected {
+            Ok(())
+        } else {
+            Err(ErrorKind::ConnectionFailed.into())
+        }
+    }
+
+    async fn disconnect(&self) -> BleResult<()> {
+        Ok(())
+    }
+
+    async fn connection_events(&self) -> BleResult<ConnectionEventStream<'_>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut state = self.adapter.lock();
+        let keyboard = state.keyboard_mut(&self.id).ok_or(ErrorKind::NotFound)?;
+        keyboard.connection_subscribers.push(tx);
+        Ok(receiver_stream(rx))
+    }
+
+    async fn services(&self) -> BleResult<Vec<Arc<dyn BleService>>> {
+        let state = self.adapter.lock();
+        let keyboard = state.connected_keyboard(&self.id)?;
+        let mut uuids = vec![BATTERY_SERVICE_UUID];
+        for characteristic in &keyboard.static_characteristics {
+            if !uuids.contains(&characteristic.definition.service) {
+                uuids.push(characteristic.definition.service);
+            }
+        }
+        Ok(uuids
+            .into_iter()
+            .map(|uuid| {
+                Arc::new(SimulatedService {
+                    adapter: self.adapter.clone(),
+                    device_id: self.id.clone(),
+                    uuid,
+                }) as Arc<dyn BleService>
+            })
+            .collect())
+    }
+}
+
+struct SimulatedService {
+    adapter: SimulatedAdapter,
+    device_id: String,
+    uuid: Uuid,
+}
+
+#[async_trait]
+impl BleService for SimulatedService {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    /// Battery Level characteristics (one per part) come first in the Battery
+    /// Service, followed by the static characteristics of this service.
+    async fn characteristics(&self) -> BleResult<Vec<Arc<dyn BleCharacteristic>>> {
+        let state = self.adapter.lock();
+        let keyboard = state.keyboard(&self.device_id)?;
+        let part_count = if self.uuid == BATTERY_SERVICE_UUID {
+            keyboard.parts.len()
+        } else {
+            0
+        };
+        let levels = (0..part_count).map(|part| {
+            Arc::new(SimulatedBatteryLevel {
+                adapter: self.adapter.clone(),
+                device_id: self.device_id.clone(),
+                part,
+            }) as Arc<dyn BleCharacteristic>
+        });
+        let statics = keyboard
+            .static_characteristics
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.definition.service == self.uuid)
+            .map(|(index, characteristic)| {
+                Arc::new(SimulatedStaticValue {
+                    adapter: self.adapter.clone(),
+                    device_id: self.device_id.clone(),
+                    uuid: characteristic.definition.uuid,
+                    index,
+                }) as Arc<dyn BleCharacteristic>
+            });
+        Ok(levels.chain(statics).collect())
+    }
+}
+
+struct SimulatedBatteryLevel {
+    adapter: SimulatedAdapter,
+    device_id: String,
+    part: usize,
+}
+
+#[async_trait]
+impl BleCharacteristic for SimulatedBatteryLevel {
+    fn uuid(&self) -> Uuid {
+        BATTERY_LEVEL_UUID
+    }
+
+    async fn properties(&self) -> BleResult<CharacteristicProperties> {
+        let state = self.adapter.lock();
+        let part = state.connected_part(&self.device_id, self.part)?;
+        let mut properties = CharacteristicProperties::default();
+        properties.read = true;
+        properties.notify = part.definition.notify;
+        Ok(properties)
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        loop {
+            {
+                let mut state = self.adapter.lock();
+                state.connected_part(&self.device_id, self.part)?;
+                let part = state
+                    .keyboard_mut(&self.device_id)
+                    .and_then(|k| k.parts.get_mut(self.part))
+                    .ok_or(ErrorKind::NotFound)?;
+                if !part.stalled_reads {
+                    if part.failing_reads > 0 {
+                        part.failing_reads -= 1;
+                        return Err(ErrorKind::Other.into());
+                    }
+                    return Ok(vec![part.definition.level]);
+                }
+            }
+            sleep(STALL_RECHECK).await;
+        }
+    }
+
+    async fn notify(&self) -> BleResult<NotifyStream<'_>> {
+        let mut state = self.adapter.lock();
+        let part = state.connected_part(&self.device_id, self.part)?;
+        if !part.definition.notify || part.reject_subscribe {
+            return Err(ErrorKind::NotSupported.into());
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        state
+            .keyboard_mut(&self.device_id)
+            .and_then(|k| k.parts.get_mut(self.part))
+            .ok_or(ErrorKind::NotFound)?
+            .subscribers
+            .push(tx);
+        Ok(receiver_stream(rx))
+    }
+
+    async fn descriptors(&self) -> BleResult<Vec<Arc<dyn BleDescriptor>>> {
+        let state = self.adapter.lock();
+        let part = state.connected_part(&self.device_id, self.part)?;
+        let user_description = part.definition.user_description.iter().map(|description| {
+            Arc::new(SimulatedDescriptor {
+                uuid: CHARACTERISTIC_USER_DESCRIPTION,
+                value: description.as_bytes().to_vec(),
+            }) as Arc<dyn BleDescriptor>
+        });
+        let presentation_format = part.definition.presentation_format.iter().map(|format| {
+            Arc::new(SimulatedDescriptor {
+                uuid: CHARACTERISTIC_PRESENTATION_FORMAT,
+                value: format.to_bytes(),
+            }) as Arc<dyn BleDescriptor>
+        });
+        Ok(user_description.chain(presentation_format).collect())
+    }
+}
+
+struct SimulatedStaticValue {
+    adapter: SimulatedAdapter,
+    device_id: String,
+    uuid: Uuid,
+    index: usize,
+}
+
+impl SimulatedStaticValue {
+    fn definition(&self) -> BleResult<SimulatedStaticCharacteristic> {
+        let state = self.adapter.lock();
+        state
+            .connected_keyboard(&self.device_id)?
+            .static_characteristics
+            .get(self.index)
+            .map(|c| c.definition.clone())
+            .ok_or_else(|| ErrorKind::NotFound.into())
+    }
+}
+
+#[async_trait]
+impl BleCharacteristic for SimulatedStaticValue {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    async fn properties(&self) -> BleResult<CharacteristicProperties> {
+        let definition = self.definition()?;
+        let mut properties = CharacteristicProperties::default();
+        properties.read = true;
+        properties.notify = definition.notify;
+        Ok(properties)
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        Ok(self.definition()?.value)
+    }
+
+    async fn notify(&self) -> BleResult<NotifyStream<'_>> {
+        if !self.definition()?.notify {
+            return Err(ErrorKind::NotSupported.into());
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.adapter
+            .lock()
+            .keyboard_mut(&self.device_id)
+            .and_then(|k| k.static_characteristics.get_mut(self.index))
+            .ok_or(ErrorKind::NotFound)?
+            .subscribers
+            .push(tx);
+        Ok(receiver_stream(rx))
+    }
+
+    async fn descriptors(&self) -> BleResult<Vec<Arc<dyn BleDescriptor>>> {
+        self.definition()?;
+        Ok(Vec::new())
+    }
+}
+
+struct SimulatedDescriptor {
+    uuid: Uuid,
+    value: Vec<u8>,
+}
+
+#[async_trait]
+impl BleDescriptor for SimulatedDescriptor {
+    fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        Ok(self.value.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adapter() -> SimulatedAdapter {
+        SimulatedAdapter::new(vec![
+            SimulatedKeyboard::split("kbd-1", "Corne", 80, 70),
+            SimulatedKeyboard::new("kbd-2", "Single")
+                .part(None, 50)
+                .disconnected(),
+        ])
+    }
+
+    async fn level_characteristic(
+        adapter: &SimulatedAdapter,
+        id: &str,
+        part: usize,
+    ) -> Arc<dyn BleCharacteristic> {
+        let device = adapter.device_handle(id);
+        let services = device.services().await.expect("services");
+        let mut characteristics = services[0]
+            .characteristics()
+            .await
+            .expect("characteristics");
+        characteristics.remove(part)
+    }
+
+    #[tokio::test]
+    async fn simulated_transport_hands_out_the_shared_adapter() {
+        let adapter = adapter();
+        let transport = SimulatedTransport::new(adapter.clone());
+        let from_transport = transport.default_adapter().await.expect("adapter");
+
+        adapter.apply(&SimAction::Disconnect {
+            device: "kbd-1".to_string(),
+        });
+        assert!(from_transport
+            .connected_devices_with_services(&[BATTERY_SERVICE_UUID])
+            .await
+            .expect("list")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn only_connected_keyboards_are_listed() {
+        let adapter = adapter();
+        let devices = adapter
+            .connected_devices_with_services(&[BATTERY_SERVICE_UUID])
+            .await
+            .expect("list");
+        let ids: Vec<String> = devices.iter().map(|d| d.id()).collect();
+        assert_eq!(ids, vec!["kbd-1".to_string()]);
+
+        adapter.apply(&SimAction::Connect {
+            device: "kbd-2".to_string(),
+        });
+        let devices = adapter
+            .connected_devices_with_services(&[BATTERY_SERVICE_UUID])
+            .await
+            .expect("list");
+        assert_eq!(devices.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn parts_expose_levels_and_user_descriptions() {
+        let adapter = adapter();
+        let peripheral = level_characteristic(&adapter, "kbd-1", 1).await;
+        assert_eq!(peripheral.read().await.expect("read"), vec![70]);
+
+        let descriptors = peripheral.descriptors().await.expect("descriptors");
+        assert_eq!(descriptors.len(), 1);
+        assert_eq!(descriptors[0].uuid(), CHARACTERISTIC_USER_DESCRIPTION);
+        assert_eq!(
+            descriptors[0].read().await.expect("read"),
+            b"Peripheral 0".to_vec()
+        );
+
+        let central = level_characteristic(&adapter, "kbd-1", 0).await;
+        assert!(central.descriptors().await.expect("descriptors").is_empty());
+    }
+
+    #[tokio::test]
+    async fn set_level_is_delivered_to_notify_streams() {
+        let adapter = adapter();
+        let central = level_characteristic(&adapter, "kbd-1", 0).await;
+        let mut stream = central.notify().await.expect("notify");
+
+        adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 0,
+            level: 79,
+        });
+
+        assert_eq!(stream.next().await.map(|r| r.ok()), Some(Some(vec![79])));
+        assert_eq!(central.read().await.expect("read"), vec![79]);
+    }
+
+    #[tokio::test]
+    async fn disconnect_ends_notify_streams_and_emits_connection_event() {
+        let adapter = adapter();
+        let device = adapter.device_handle("kbd-1");
+        let mut events = device.connection_events().await.expect("events");
+        let central = level_characteristic(&adapter, "kbd-1", 0).await;
+        let mut stream = central.notify().await.expect("notify");
+
+        adapter.apply(&SimAction::Disconnect {
+            device: "kbd-1".to_string(),
+        });
+
+        assert!(stream.next().await.is_none());
+        assert_eq!(events.next().await, Some(ConnectionEvent::Disconnected));
+        assert_eq!(
+            central.read().await.map_err(|e| e.kind()),
+            Err(ErrorKind::NotConnected)
+        );
+
+        adapter.apply(&SimAction::Connect {
+            device: "kbd-1".to_string(),
+        });
+        assert_eq!(events.next().await, Some(ConnectionEvent::Connected));
+    }
+
+    #[tokio::test]
+    async fn scripted_failures_affect_reads_and_subscriptions() {
+        let adapter = adapter();
+        let central = level_characteristic(&adapter, "kbd-1", 0).await;
+        let mut stream = central.notify().await.expect("notify");
+
+        adapter.apply(&SimAction::FailNotify {
+            device: "kbd-1".to_string(),
+            part: 0,
+        });
+        assert!(matches!(stream.next().await, Some(Err(_))));
+
+        adapter.apply(&SimAction::FailReads {
+            device: "kbd-1".to_string(),
+            part: 0,
+            count: 1,
+        });
+        assert!(central.read().await.is_err());
+        assert_eq!(central.read().await.expect("read"), vec![80]);
+
+        adapter.apply(&SimAction::RejectSubscribe {
+            device: "kbd-1".to_string(),
+            part: 0,
+            reject: true,
+        });
+        assert!(central.notify().await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn run_script_applies_steps_after_their_delays() {
+        let adapter = adapter();
+        let script = vec![
+            SimStep {
+                after: Duration::from_secs(10),
+                action: SimAction::SetLevel {
+                    device: "kbd-1".to_string(),
+                    part: 1,
+                    level: 60,
+                },
+            },
+            SimStep {
+                after: Duration::from_secs(10),
+                action: SimAction::Disconnect {
+                    device: "kbd-1".to_string(),
+                },
+            },
+        ];
+        let start = tokio::time::Instant::now();
+        adapter.run_script(&script).await;
+        assert_eq!(start.elapsed(), Duration::from_secs(20));
+
+        assert!(adapter
+            .connected_devices_with_services(&[])
+            .await
+            .expect("list")
+            .is_empty());
+        adapter.apply(&SimAction::Connect {
+            device: "kbd-1".to_string(),
+        });
+        let peripheral = level_characteristic(&adapter, "kbd-1", 1).await;
+        assert_eq!(peripheral.read().await.expect("read"), vec![60]);
+    }
+}
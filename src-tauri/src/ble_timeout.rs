@@ -0,0 +1,459 @@
+//! Timeouts on every BLE operation.
+//!
+//! A wedged OS stack can leave a `bluest` future pending forever, which used
+//! to hang the command awaiting it, and the frontend's in-flight guard with
+//! it. `bounded` wraps the transport so that opening adapters, connecting,
+//! discovery, reads, descriptor reads and subscriptions each give up after
+//! the limit set in `OperationTimeouts`, failing with `ErrorKind::Timeout`
+//! (reported as `CommandError::Timeout`). An adapter lookup that times out
+//! finds no adapter. The OS future is dropped on timeout, and with the call
+//! whenever its caller is dropped, e.g. a stopped monitor.
+
+use crate::ble_transport::{
+    AdapterEventStream, AdvertisementStream, BleAdapter, BleCharacteristic, BleDescriptor,
+    BleDevice, BleResult, BleService, BleTransport, ConnectionEventStream, NotifyStream,
+};
+use crate::error::CommandError;
+use async_trait::async_trait;
+use bluest::error::ErrorKind;
+use bluest::CharacteristicProperties;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::{Arc, LazyLock, RwLock};
+use tokio::time::{timeout, Duration};
+use uuid::Uuid;
+
+const MIN_TIMEOUT_MS: u64 = 100;
+const MAX_TIMEOUT_MS: u64 = 120_000;
+
+/// Limits of each kind of BLE operation, for every device and adapter.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct OperationTimeouts {
+    /// Connecting and disconnecting.
+    pub connect_ms: u64,
+    /// Opening adapters and listing connected devices, services,
+    /// characteristics and descriptors.
+    pub discover_ms: u64,
+    pub read_ms: u64,
+    pub descriptor_read_ms: u64,
+    /// Subscribing to notifications and connection or adapter events.
+    pub subscribe_ms: u64,
+}
+
+impl Default for OperationTimeouts {
+    fn default() -> Self {
+        Self {
+            connect_ms: 20_000,
+            discover_ms: 10_000,
+            read_ms: 5_000,
+            descriptor_read_ms: 5_000,
+            subscribe_ms: 5_000,
+        }
+    }
+}
+
+impl OperationTimeouts {
+    pub fn validate(&self) -> Result<(), CommandError> {
+        let all = [
+            self.connect_ms,
+            self.discover_ms,
+            self.read_ms,
+            self.descriptor_read_ms,
+            self.subscribe_ms,
+        ];
+        if all
+            .iter()
+            .any(|ms| !(MIN_TIMEOUT_MS..=MAX_TIMEOUT_MS).contains(ms))
+        {
+            return Err(CommandError::InvalidArgument(format!(
+                "Timeouts must be between {MIN_TIMEOUT_MS} and {MAX_TIMEOUT_MS} ms"
+            )));
+        }
+        Ok(())
+    }
+}
+
+static TIMEOUTS: LazyLock<RwLock<OperationTimeouts>> =
+    LazyLock::new(|| RwLock::new(OperationTimeouts::default()));
+
+fn timeouts() -> OperationTimeouts {
+    *TIMEOUTS.read().unwrap_or_else(|p| p.into_inner())
+}
+
+/// Set the BLE operation timeouts. Applies to operations started afterwards.
+#[tauri::command]
+pub async fn set_operation_timeouts(timeouts: OperationTimeouts) -> Result<(), CommandError> {
+    timeouts.validate()?;
+    log::debug!("BLE I/O: operation timeouts set timeouts={timeouts:?}");
+    *TIMEOUTS.write().unwrap_or_else(|p| p.into_inner()) = timeouts;
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Operation {
+    Connect,
+    Discover,
+    Read,
+    DescriptorRead,
+    Subscribe,
+}
+
+impl Operation {
+    fn limit(self, timeouts: &OperationTimeouts) -> Duration {
+        Duration::from_millis(match self {
+            Self::Connect => timeouts.connect_ms,
+            Self::Discover => timeouts.discover_ms,
+            Self::Read => timeouts.read_ms,
+            Self::DescriptorRead => timeouts.descriptor_read_ms,
+            Self::Subscribe => timeouts.subscribe_ms,
+        })
+    }
+}
+
+async fn bounded_op<T>(
+    operation: Operation,
+    target: &str,
+    future: impl Future<Output = BleResult<T>>,
+) -> BleResult<T> {
+    let limit = operation.limit(&timeouts());
+    match timeout(limit, future).await {
+        Ok(result) => result,
+        Err(_) => {
+            log::warn!("BLE I/O: {operation:?} timed out after {limit:?} target={target}");
+            Err(ErrorKind::Timeout.into())
+        }
+    }
+}
+
+/// `transport` with every operation bounded by `OperationTimeouts`.
+pub(crate) fn bounded(transport: Arc<dyn BleTransport>) -> Arc<dyn BleTransport> {
+    Arc::new(BoundedTransport(transport))
+}
+
+struct BoundedTransport(Arc<dyn BleTransport>);
+
+fn bounded_adapter(adapter: Arc<dyn BleAdapter>) -> Arc<dyn BleAdapter> {
+    Arc::new(BoundedAdapter(adapter))
+}
+
+fn bounded_device(device: Arc<dyn BleDevice>) -> Arc<dyn BleDevice> {
+    Arc::new(BoundedDevice(device))
+}
+
+const ADAPTER_LOOKUP: &str = "adapters";
+
+#[async_trait]
+impl BleTransport for BoundedTransport {
+    async fn default_adapter(&self) -> Option<Arc<dyn BleAdapter>> {
+        let lookup = async { Ok(self.0.default_adapter().await) };
+        let adapter = bounded_op(Operation::Discover, ADAPTER_LOOKUP, lookup).await;
+        adapter.ok().flatten().map(bounded_adapter)
+    }
+
+    async fn adapters(&self) -> Vec<Arc<dyn BleAdapter>> {
+        let lookup = async { Ok(self.0.adapters().await) };
+        bounded_op(Operation::Discover, ADAPTER_LOOKUP, lookup)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(bounded_adapter)
+            .collect()
+    }
+}
+
+struct BoundedAdapter(Arc<dyn BleAdapter>);
+
+#[async_trait]
+impl BleAdapter for BoundedAdapter {
+    fn id(&self) -> String {
+        self.0.id()
+    }
+
+    fn name(&self) -> String {
+        self.0.name()
+    }
+
+    /// Waits for the adapter to be switched on, which may take any time.
+    async fn wait_available(&self) -> BleResult<()> {
+        self.0.wait_available().await
+    }
+
+    async fn is_available(&self) -> BleResult<bool> {
+        bounded_op(Operation::Discover, &self.0.id(), self.0.is_available()).await
+    }
+
+    async fn events(&self) -> BleResult<AdapterEventStream<'_>> {
+        bounded_op(Operation::Subscribe, &self.0.id(), self.0.events()).await
+    }
+
+    async fn connected_devices_with_services(
+        &self,
+        services: &[Uuid],
+    ) -> BleResult<Vec<Arc<dyn BleDevice>>> {
+        let devices = bounded_op(
+            Operation::Discover,
+            &self.0.id(),
+            self.0.connected_devices_with_services(services),
+        )
+        .await?;
+        Ok(devices.into_iter().map(bounded_device).collect())
+    }
+
+    /// The scan itself runs until its stream is dropped; only starting it is
+    /// bounded.
+    async fn scan<'a>(&'a self, services: &'a [Uuid]) -> BleResult<AdvertisementStream<'a>> {
+        let advertisements =
+            bounded_op(Operation::Subscribe, &self.0.id(), self.0.scan(services)).await?;
+        Ok(advertisements
+            .map(|mut advertisement| {
+                advertisement.device = bounded_device(advertisement.device);
+                advertisement
+            })
+            .boxed())
+    }
+}
+
+struct BoundedDevice(Arc<dyn BleDevice>);
+
+#[async_trait]
+impl BleDevice for BoundedDevice {
+    fn id(&self) -> String {
+        self.0.id()
+    }
+
+    fn name(&self) -> BleResult<String> {
+        self.0.name()
+    }
+
+    async fn connect(&self) -> BleResult<()> {
+        bounded_op(Operation::Connect, &self.0.id(), self.0.connect()).await
+    }
+
+    async fn disconnect(&self) -> BleResult<()> {
+        bounded_op(Operation::Connect, &self.0.id(), self.0.disconnect()).await
+    }
+
+    async fn connection_events(&self) -> BleResult<ConnectionEventStream<'_>> {
+        bounded_op(
+            Operation::Subscribe,
+            &self.0.id(),
+            self.0.connection_events(),
+        )
+        .await
+    }
+
+    async fn services(&self) -> BleResult<Vec<Arc<dyn BleService>>> {
+        let device = self.0.id();
+        let services = bounded_op(Operation::Discover, &device, self.0.services()).await?;
+        Ok(services
+            .into_iter()
+            .map(|service| {
+                Arc::new(BoundedService {
+                    inner: service,
+                    device: device.clone(),
+                }) as Arc<dyn BleService>
+            })
+            .collect())
+    }
+}
+
+/// Wrappers below a device keep its id for the timeout log.
+struct BoundedService {
+    inner: Arc<dyn BleService>,
+    device: String,
+}
+
+#[async_trait]
+impl BleService for BoundedService {
+    fn uuid(&self) -> Uuid {
+        self.inner.uuid()
+    }
+
+    async fn characteristics(&self) -> BleResult<Vec<Arc<dyn BleCharacteristic>>> {
+        let characteristics = bounded_op(
+            Operation::Discover,
+            &self.device,
+            self.inner.characteristics(),
+        )
+        .await?;
+        Ok(characteristics
+            .into_iter()
+            .map(|characteristic| {
+                Arc::new(BoundedCharacteristic {
+                    inner: characteristic,
+                    device: self.device.clone(),
+                }) as Arc<dyn BleCharacteristic>
+            })
+            .collect())
+    }
+}
+
+struct BoundedCharacteristic {
+    inner: Arc<dyn BleCharacteristic>,
+    device: String,
+}
+
+#[async_trait]
+impl BleCharacteristic for BoundedCharacteristic {
+    fn uuid(&self) -> Uuid {
+        self.inner.uuid()
+    }
+
+    async fn properties(&self) -> BleResult<CharacteristicProperties> {
+        bounded_op(Operation::Discover, &self.device, self.inner.properties()).await
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        bounded_op(Operation::Read, &self.device, self.inner.read()).await
+    }
+
+    async fn notify(&self) -> BleResult<NotifyStream<'_>> {
+        bounded_op(Operation::Subscribe, &self.device, self.inner.notify()).await
+    }
+
+    async fn descriptors(&self) -> BleResult<Vec<Arc<dyn BleDescriptor>>> {
+        let descriptors =
+            bounded_op(Operation::Discover, &self.device, self.inner.descriptors()).await?;
+        Ok(descriptors
+            .into_iter()
+            .map(|descriptor| {
+                Arc::new(BoundedDescriptor {
+                    inner: descriptor,
+                    device: self.device.clone(),
+                }) as Arc<dyn BleDescriptor>
+            })
+            .collect())
+    }
+}
+
+struct BoundedDescriptor {
+    inner: Arc<dyn BleDescriptor>,
+    device: String,
+}
+
+#[async_trait]
+impl BleDescriptor for BoundedDescriptor {
+    fn uuid(&self) -> Uuid {
+        self.inner.uuid()
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        bounded_op(Operation::DescriptorRead, &self.device, self.inner.read()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ble_simulated::{SimulatedAdapter, SimulatedKeyboard, SimulatedTransport};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tokio::time::Instant;
+
+    /// Sets its flag when dropped.
+    struct DropFlag(Arc<AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// A characteristic whose reads never complete, like a wedged stack.
+    struct WedgedCharacteristic {
+        dropped: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl BleCharacteristic for WedgedCharacteristic {
+        fn uuid(&self) -> Uuid {
+            Uuid::nil()
+        }
+
+        async fn properties(&self) -> BleResult<CharacteristicProperties> {
+            Ok(CharacteristicProperties::default())
+        }
+
+        async fn read(&self) -> BleResult<Vec<u8>> {
+            let _flag = DropFlag(self.dropped.clone());
+            std::future::pending().await
+        }
+
+        async fn notify(&self) -> BleResult<NotifyStream<'_>> {
+            std::future::pending().await
+        }
+
+        async fn descriptors(&self) -> BleResult<Vec<Arc<dyn BleDescriptor>>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wedged_read_times_out_and_is_dropped() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let characteristic = BoundedCharacteristic {
+            inner: Arc::new(WedgedCharacteristic {
+                dropped: dropped.clone(),
+            }),
+            device: "kbd-1".to_string(),
+        };
+
+        let started = Instant::now();
+        let error = characteristic.read().await.expect_err("read must time out");
+        assert_eq!(started.elapsed(), Duration::from_secs(5));
+        assert_eq!(CommandError::from(error).code(), "timeout");
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+
+    /// A transport whose OS stack never answers the adapter lookup.
+    struct WedgedTransport;
+
+    #[async_trait]
+    impl BleTransport for WedgedTransport {
+        async fn default_adapter(&self) -> Option<Arc<dyn BleAdapter>> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wedged_adapter_lookup_times_out_with_no_adapter() {
+        let transport = bounded(Arc::new(WedgedTransport));
+
+        let started = Instant::now();
+        assert!(transport.default_adapter().await.is_none());
+        assert!(transport.adapters().await.is_empty());
+        assert_eq!(started.elapsed(), Duration::from_secs(20));
+    }
+
+    #[tokio::test]
+    async fn bounded_transport_passes_results_through() {
+        let transport = bounded(Arc::new(SimulatedTransport::new(SimulatedAdapter::new(
+            vec![SimulatedKeyboard::split("kbd-1", "Corne", 80, 70)],
+        ))));
+        let adapter = transport.default_adapter().await.expect("adapter");
+        let devices = adapter
+            .connected_devices_with_services(&[])
+            .await
+            .expect("connected devices");
+        let services = devices[0].services().await.expect("services");
+        let mut levels = Vec::new();
+        for service in services {
+            for characteristic in service.characteristics().await.expect("characteristics") {
+                levels.push(characteristic.read().await.expect("read"));
+            }
+        }
+        assert!(levels.contains(&vec![80]));
+        assert!(levels.contains(&vec![70]));
+    }
+
+    #[test]
+    fn timeouts_outside_limits_are_refused() {
+        let zero = OperationTimeouts {
+            read_ms: 0,
+            ..OperationTimeouts::default()
+        };
+        assert!(zero.validate().is_err());
+        assert!(OperationTimeouts::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,1441 @@
+//! Structured capture and replay of BLE sessions.
+//!
+//! Recording wraps whichever transport is in use and appends one JSON line per
+//! operation and result (connected-device queries, connects, discovery, reads,
+//! subscriptions, notifications, descriptor reads, connection and adapter
+//! events) to a trace file, timestamped in milliseconds since the recording
+//! started. Enabled with `--record-ble-trace <path>` or the
+//! `ZMK_BATTERY_CENTER_RECORD_BLE_TRACE` environment variable.
+//!
+//! Replay turns a trace back into the simulated keyboards of
+//! `ble_simulated.rs` plus a timed script: the devices are rebuilt from what
+//! was discovered and read, and every recorded level change, disconnect,
+//! failure and adapter power change is applied when it happened. The monitor
+//! and polling code run against that world through the normal transport, so
+//! they make their own calls at their own times rather than repeating the
+//! recorded ones. Enabled with `--replay-ble-trace <path>` or
+//! `ZMK_BATTERY_CENTER_REPLAY_BLE_TRACE`.
+
+use crate::ble::{bytes_to_hex, BATTERY_LEVEL_UUID, BATTERY_SERVICE_UUID};
+use crate::ble_demo::path_from_args;
+use crate::ble_device_info::decode_string;
+use crate::ble_presentation::PresentationFormat;
+use crate::ble_simulated::{
+    SimAction, SimStep, SimulatedAdapter, SimulatedKeyboard, SimulatedTransport,
+};
+use crate::ble_transport::{
+    self, AdapterEventStream, AdvertisementStream, BleAdapter, BleCharacteristic, BleDescriptor,
+    BleDevice, BleResult, BleService, BleTransport, ConnectionEventStream, NotifyStream,
+};
+use async_trait::async_trait;
+use bluest::btuuid::descriptors::{
+    CHARACTERISTIC_PRESENTATION_FORMAT, CHARACTERISTIC_USER_DESCRIPTION,
+};
+use bluest::{AdapterEvent, CharacteristicProperties, ConnectionEvent};
+use futures_util::stream;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
+use uuid::Uuid;
+
+const RECORD_TRACE_ENV: &str = "ZMK_BATTERY_CENTER_RECORD_BLE_TRACE";
+const RECORD_TRACE_FLAG: &str = "--record-ble-trace";
+const REPLAY_TRACE_ENV: &str = "ZMK_BATTERY_CENTER_REPLAY_BLE_TRACE";
+const REPLAY_TRACE_FLAG: &str = "--replay-ble-trace";
+
+/// One line of a trace.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TraceRecord {
+    /// Milliseconds since the recording started.
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub op: TraceOp,
+}
+
+/// A recorded operation and its result. Values are hex like the debug logs;
+/// errors are the transport's error text.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TraceOp {
+    /// First line of every trace.
+    Start {
+        unix_ms: u64,
+        app_version: String,
+    },
+    ConnectedDevices {
+        adapter: String,
+        devices: Vec<TraceDevice>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    AdapterEvent {
+        adapter: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        available: Option<bool>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Connect {
+        device: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Disconnect {
+        device: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    ConnectionEvent {
+        device: String,
+        connected: bool,
+    },
+    Services {
+        device: String,
+        services: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Characteristics {
+        device: String,
+        service: String,
+        characteristics: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Read {
+        device: String,
+        characteristic: CharacteristicRef,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Subscribe {
+        device: String,
+        characteristic: CharacteristicRef,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    Notification {
+        device: String,
+        characteristic: CharacteristicRef,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    NotifyEnded {
+        device: String,
+        characteristic: CharacteristicRef,
+    },
+    DescriptorRead {
+        device: String,
+        characteristic: CharacteristicRef,
+        descriptor: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TraceDevice {
+    pub id: String,
+    pub name: Option<String>,
+}
+
+/// Where a characteristic sits in the GATT tree. `index` is its position in
+/// the service, which tells the Battery Level characteristics of a split
+/// keyboard's parts apart.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CharacteristicRef {
+    pub service: String,
+    pub uuid: String,
+    pub index: usize,
+}
+
+impl CharacteristicRef {
+    fn is_battery_level(&self) -> bool {
+        Uuid::parse_str(&self.service).ok() == Some(BATTERY_SERVICE_UUID)
+            && Uuid::parse_str(&self.uuid).ok() == Some(BATTERY_LEVEL_UUID)
+    }
+}
+
+fn error_text<T>(result: &BleResult<T>) -> Option<String> {
+    result.as_ref().err().map(ToString::to_string)
+}
+
+fn value_hex(result: &BleResult<Vec<u8>>) -> Option<String> {
+    result.as_ref().ok().map(|value| bytes_to_hex(value))
+}
+
+fn hex_to_bytes(hex: &str) -> Option<Vec<u8>> {
+    hex.split_whitespace()
+        .map(|byte| u8::from_str_radix(byte, 16).ok())
+        .collect()
+}
+
+/// Appends records to the trace file, one flushed line each, so a trace
+/// survives the app being killed mid-session.
+pub(crate) struct TraceWriter {
+    out: Mutex<Box<dyn Write + Send>>,
+    started: Instant,
+    failed: AtomicBool,
+}
+
+impl TraceWriter {
+    pub fn new(out: Box<dyn Write + Send>) -> Arc<Self> {
+        let writer = Arc::new(Self {
+            out: Mutex::new(out),
+            started: Instant::now(),
+            failed: AtomicBool::new(false),
+        });
+        let unix_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        writer.record(TraceOp::Start {
+            unix_ms,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        });
+        writer
+    }
+
+    fn record(&self, op: TraceOp) {
+        let record = TraceRecord {
+            at_ms: self.started.elapsed().as_millis() as u64,
+            op,
+        };
+        let line = match serde_json::to_string(&record) {
+            Ok(line) => line,
+            Err(e) => {
+                log::warn!("BLE trace: failed to serialize record: {e}");
+                return;
+            }
+        };
+        let mut out = self.out.lock().unwrap_or_else(|p| p.into_inner());
+        if let Err(e) = writeln!(out, "{line}").and_then(|()| out.flush()) {
+            // Once is enough; a full disk would otherwise flood the log.
+            if !self.failed.swap(true, Ordering::Relaxed) {
+                log::warn!("BLE trace: failed to write record: {e}");
+            }
+        }
+    }
+}
+
+static RECORDER: OnceLock<Arc<TraceWriter>> = OnceLock::new();
+
+/// `transport`, wrapped by the recorder when recording was started.
+pub(crate) fn recording(transport: Arc<dyn BleTransport>) -> Arc<dyn BleTransport> {
+    match RECORDER.get() {
+        Some(trace) => Arc::new(RecordingTransport {
+            inner: transport,
+            trace: trace.clone(),
+        }),
+        None => transport,
+    }
+}
+
+/// Path of the trace to record from the command line or environment, if
+/// recording was requested. The command-line flag wins over the environment.
+pub fn record_trace_path() -> Option<PathBuf> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let env_value = std::env::var(RECORD_TRACE_ENV).ok();
+    path_from_args(&args, RECORD_TRACE_FLAG, env_value.as_deref())
+}
+
+/// Start writing a trace to `path`, replacing any previous one. Must run
+/// before the transport is installed or first used.
+pub fn start_recording(path: &Path) -> Result<(), String> {
+    let file = std::fs::File::create(path)
+        .map_err(|e| format!("Failed to create BLE trace file {}: {e}", path.display()))?;
+    RECORDER
+        .set(TraceWriter::new(Box::new(file)))
+        .map_err(|_| "BLE trace recording is already started".to_string())?;
+    log::info!("BLE trace: recording to {}", path.display());
+    Ok(())
+}
+
+struct RecordingTransport {
+    inner: Arc<dyn BleTransport>,
+    trace: Arc<TraceWriter>,
+}
+
+impl RecordingTransport {
+    fn wrap(&self, adapter: Arc<dyn BleAdapter>) -> Arc<dyn BleAdapter> {
+        Arc::new(RecordingAdapter {
+            inner: adapter,
+            trace: self.trace.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl BleTransport for RecordingTransport {
+    async fn default_adapter(&self) -> Option<Arc<dyn BleAdapter>> {
+        self.inner
+            .default_adapter()
+            .await
+            .map(|adapter| self.wrap(adapter))
+    }
+
+    async fn adapters(&self) -> Vec<Arc<dyn BleAdapter>> {
+        self.inner
+            .adapters()
+            .await
+            .into_iter()
+            .map(|adapter| self.wrap(adapter))
+            .collect()
+    }
+}
+
+struct RecordingAdapter {
+    inner: Arc<dyn BleAdapter>,
+    trace: Arc<TraceWriter>,
+}
+
+fn recording_device(device: Arc<dyn BleDevice>, trace: &Arc<TraceWriter>) -> Arc<dyn BleDevice> {
+    Arc::new(RecordingDevice {
+        inner: device,
+        trace: trace.clone(),
+    })
+}
+
+#[async_trait]
+impl BleAdapter for RecordingAdapter {
+    fn id(&self) -> String {
+        self.inner.id()
+    }
+
+    fn name(&self) -> String {
+        self.inner.name()
+    }
+
+    async fn wait_available(&self) -> BleResult<()> {
+        self.inner.wait_available().await
+    }
+
+    async fn is_available(&self) -> BleResult<bool> {
+        self.inner.is_available().await
+    }
+
+    async fn events(&self) -> BleResult<AdapterEventStream<'_>> {
+        let events = self.inner.events().await?;
+        let adapter = self.inner.id();
+        let trace = self.trace.clone();
+        Ok(events
+            .inspect(move |event| {
+                trace.record(TraceOp::AdapterEvent {
+                    adapter: adapter.clone(),
+                    available: event
+                        .as_ref()
+                        .ok()
+                        .map(|event| matches!(event, AdapterEvent::Available)),
+                    error: error_text(event),
+                })
+            })
+            .boxed())
+    }
+
+    async fn connected_devices_with_services(
+        &self,
+        services: &[Uuid],
+    ) -> BleResult<Vec<Arc<dyn BleDevice>>> {
+        let result = self.inner.connected_devices_with_services(services).await;
+        self.trace.record(TraceOp::ConnectedDevices {
+            adapter: self.inner.id(),
+            devices: result
+                .iter()
+                .flatten()
+                .map(|device| TraceDevice {
+                    id: device.id(),
+                    name: device.name().ok(),
+                })
+                .collect(),
+            error: error_text(&result),
+        });
+        Ok(result?
+            .into_iter()
+            .map(|device| recording_device(device, &self.trace))
+            .collect())
+    }
+
+    async fn scan<'a>(&'a self, services: &'a [Uuid]) -> BleResult<AdvertisementStream<'a>> {
+        let advertisements = self.inner.scan(services).await?;
+        let trace = self.trace.clone();
+        Ok(advertisements
+            .map(move |mut advertisement| {
+                advertisement.device = recording_device(advertisement.device, &trace);
+                advertisement
+            })
+            .boxed())
+    }
+}
+
+struct RecordingDevice {
+    inner: Arc<dyn BleDevice>,
+    trace: Arc<TraceWriter>,
+}
+
+#[async_trait]
+impl BleDevice for RecordingDevice {
+    fn id(&self) -> String {
+        self.inner.id()
+    }
+
+    fn name(&self) -> BleResult<String> {
+        self.inner.name()
+    }
+
+    async fn connect(&self) -> BleResult<()> {
+        let result = self.inner.connect().await;
+        self.trace.record(TraceOp::Connect {
+            device: self.inner.id(),
+            error: error_text(&result),
+        });
+        result
+    }
+
+    async fn disconnect(&self) -> BleResult<()> {
+        let result = self.inner.disconnect().await;
+        self.trace.record(TraceOp::Disconnect {
+            device: self.inner.id(),
+            error: error_text(&result),
+        });
+        result
+    }
+
+    async fn connection_events(&self) -> BleResult<ConnectionEventStream<'_>> {
+        let events = self.inner.connection_events().await?;
+        let device = self.inner.id();
+        let trace = self.trace.clone();
+        Ok(events
+            .inspect(move |event| {
+                trace.record(TraceOp::ConnectionEvent {
+                    device: device.clone(),
+                    connected: matches!(event, ConnectionEvent::Connected),
+                })
+            })
+            .boxed())
+    }
+
+    async fn services(&self) -> BleResult<Vec<Arc<dyn BleService>>> {
+        let result = self.inner.services().await;
+        self.trace.record(TraceOp::Services {
+            device: self.inner.id(),
+            services: result
+                .iter()
+                .flatten()
+                .map(|service| service.uuid().to_string())
+                .collect(),
+            error: error_text(&result),
+        });
+        Ok(result?
+            .into_iter()
+            .map(|service| {
+                Arc::new(RecordingService {
+                    inner: service,
+                    trace: self.trace.clone(),
+                    device: self.inner.id(),
+                }) as Arc<dyn BleService>
+            })
+            .collect())
+    }
+}
+
+struct RecordingService {
+    inner: Arc<dyn BleService>,
+    trace: Arc<TraceWriter>,
+    device: String,
+}
+
+#[async_trait]
+impl BleService for RecordingService {
+    fn uuid(&self) -> Uuid {
+        self.inner.uuid()
+    }
+
+    async fn characteristics(&self) -> BleResult<Vec<Arc<dyn BleCharacteristic>>> {
+        let service = self.inner.uuid().to_string();
+        let result = self.inner.characteristics().await;
+        self.trace.record(TraceOp::Characteristics {
+            device: self.device.clone(),
+            service: service.clone(),
+            characteristics: result
+                .iter()
+                .flatten()
+                .map(|characteristic| characteristic.uuid().to_string())
+                .collect(),
+            error: error_text(&result),
+        });
+        Ok(result?
+            .into_iter()
+            .enumerate()
+            .map(|(index, characteristic)| {
+                let context = CharacteristicContext {
+                    trace: self.trace.clone(),
+                    device: self.device.clone(),
+                    characteristic: CharacteristicRef {
+                        service: service.clone(),
+                        uuid: characteristic.uuid().to_string(),
+                        index,
+                    },
+                };
+                Arc::new(RecordingCharacteristic {
+                    inner: characteristic,
+                    context,
+                }) as Arc<dyn BleCharacteristic>
+            })
+            .collect())
+    }
+}
+
+/// What every record about one characteristic needs.
+#[derive(Clone)]
+struct CharacteristicContext {
+    trace: Arc<TraceWriter>,
+    device: String,
+    characteristic: CharacteristicRef,
+}
+
+impl CharacteristicContext {
+    fn notification(&self, item: Option<&BleResult<Vec<u8>>>) {
+        let device = self.device.clone();
+        let characteristic = self.characteristic.clone();
+        self.trace.record(match item {
+            Some(result) => TraceOp::Notification {
+                device,
+                characteristic,
+                value: value_hex(result),
+                error: error_text(result),
+            },
+            None => TraceOp::NotifyEnded {
+                device,
+                characteristic,
+            },
+        });
+    }
+}
+
+struct RecordingCharacteristic {
+    inner: Arc<dyn BleCharacteristic>,
+    context: CharacteristicContext,
+}
+
+#[async_trait]
+impl BleCharacteristic for RecordingCharacteristic {
+    fn uuid(&self) -> Uuid {
+        self.inner.uuid()
+    }
+
+    async fn properties(&self) -> BleResult<CharacteristicProperties> {
+        self.inner.properties().await
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        let result = self.inner.read().await;
+        self.context.trace.record(TraceOp::Read {
+            device: self.context.device.clone(),
+            characteristic: self.context.characteristic.clone(),
+            value: value_hex(&result),
+            error: error_text(&result),
+        });
+        result
+    }
+
+    async fn notify(&self) -> BleResult<NotifyStream<'_>> {
+        let result = self.inner.notify().await;
+        self.context.trace.record(TraceOp::Subscribe {
+            device: self.context.device.clone(),
+            characteristic: self.context.characteristic.clone(),
+            error: error_text(&result),
+        });
+        let notifications = result?;
+        Ok(stream::unfold(
+            (notifications, self.context.clone()),
+            |(mut notifications, context)| async move {
+                let item = notifications.next().await;
+                context.notification(item.as_ref());
+                item.map(|item| (item, (notifications, context)))
+            },
+        )
+        .boxed())
+    }
+
+    async fn descriptors(&self) -> BleResult<Vec<Arc<dyn BleDescriptor>>> {
+        Ok(self
+            .inner
+            .descriptors()
+            .await?
+            .into_iter()
+            .map(|descriptor| {
+                Arc::new(RecordingDescriptor {
+                    inner: descriptor,
+                    context: self.context.clone(),
+                }) as Arc<dyn BleDescriptor>
+            })
+            .collect())
+    }
+}
+
+struct RecordingDescriptor {
+    inner: Arc<dyn BleDescriptor>,
+    context: CharacteristicContext,
+}
+
+#[async_trait]
+impl BleDescriptor for RecordingDescriptor {
+    fn uuid(&self) -> Uuid {
+        self.inner.uuid()
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        let result = self.inner.read().await;
+        self.context.trace.record(TraceOp::DescriptorRead {
+            device: self.context.device.clone(),
+            characteristic: self.context.characteristic.clone(),
+            descriptor: self.inner.uuid().to_string(),
+            value: value_hex(&result),
+            error: error_text(&result),
+        });
+        result
+    }
+}
+
+/// Parse a trace file. Blank lines are skipped.
+pub(crate) fn parse_trace(text: &str) -> Result<Vec<TraceRecord>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("Invalid BLE trace line {}: {e}", index + 1))
+        })
+        .collect()
+}
+
+/// The simulated world a trace describes.
+pub(crate) struct ReplayWorld {
+    /// Adapter of the recording, so a selected adapter id still matches.
+    pub adapter_id: Option<String>,
+    pub keyboards: Vec<SimulatedKeyboard>,
+    pub script: Vec<SimStep>,
+}
+
+#[derive(Default)]
+struct ReplayPart {
+    /// Position of the Battery Level characteristic in the Battery Service.
+    index: usize,
+    user_description: Option<String>,
+    presentation_format: Option<PresentationFormat>,
+    level: Option<u8>,
+    /// Whether a subscription ever succeeded; `Some(false)` when every one
+    /// failed.
+    notifies: Option<bool>,
+}
+
+struct ReplayDevice {
+    id: String,
+    name: Option<String>,
+    /// First connection state the trace shows.
+    connected: Option<bool>,
+    parts: Vec<ReplayPart>,
+    /// Other characteristics that were read, with their first value.
+    statics: Vec<(Uuid, Uuid, Vec<u8>)>,
+}
+
+impl ReplayDevice {
+    fn part(&mut self, index: usize) -> &mut ReplayPart {
+        let position = match self.parts.iter().position(|part| part.index == index) {
+            Some(position) => position,
+            None => {
+                self.parts.push(ReplayPart {
+                    index,
+                    ..ReplayPart::default()
+                });
+                self.parts.len() - 1
+            }
+        };
+        &mut self.parts[position]
+    }
+}
+
+fn record_device(op: &TraceOp) -> Option<&str> {
+    match op {
+        TraceOp::Connect { device, .. }
+        | TraceOp::Disconnect { device, .. }
+        | TraceOp::ConnectionEvent { device, .. }
+        | TraceOp::Services { device, .. }
+        | TraceOp::Characteristics { device, .. }
+        | TraceOp::Read { device, .. }
+        | TraceOp::Subscribe { device, .. }
+        | TraceOp::Notification { device, .. }
+        | TraceOp::NotifyEnded { device, .. }
+        | TraceOp::DescriptorRead { device, .. } => Some(device),
+        TraceOp::Start { .. } | TraceOp::ConnectedDevices { .. } | TraceOp::AdapterEvent { .. } => {
+            None
+        }
+    }
+}
+
+/// Rebuild each device as it was first seen: name, parts with their user
+/// descriptions and first levels, other characteristics that were read, and
+/// whether it started out connected.
+fn replay_devices(records: &[TraceRecord]) -> Vec<ReplayDevice> {
+    let mut devices: Vec<ReplayDevice> = Vec::new();
+    let mut ids: Vec<String> = Vec::new();
+    for record in records {
+        let listed: Vec<&str> = match &record.op {
+            TraceOp::ConnectedDevices {
+                devices: listed, ..
+            } => listed.iter().map(|d| d.id.as_str()).collect(),
+            op => record_device(op).into_iter().collect(),
+        };
+        for id in listed {
+            if !ids.iter().any(|known| known == id) {
+                ids.push(id.to_string());
+            }
+        }
+    }
+    for id in ids {
+        devices.push(ReplayDevice {
+            id,
+            name: None,
+            connected: None,
+            parts: Vec::new(),
+            statics: Vec::new(),
+        });
+    }
+
+    for record in records {
+        match &record.op {
+            TraceOp::ConnectedDevices {
+                devices: listed,
+                error: None,
+                ..
+            } => {
+                for device in devices.iter_mut() {
+                    let entry = listed.iter().find(|d| d.id == device.id);
+                    if let Some(name) = entry.and_then(|d| d.name.clone()) {
+                        device.name.get_or_insert(name);
+                    }
+                    device.connected.get_or_insert(entry.is_some());
+                }
+            }
+            op => {
+                let Some(device) = record_device(op)
+                    .and_then(|id| devices.iter_mut().find(|device| device.id == id))
+                else {
+                    continue;
+                };
+                observe_device(device, op);
+            }
+        }
+    }
+    for device in devices.iter_mut() {
+        device.parts.sort_by_key(|part| part.index);
+    }
+    devices
+}
+
+fn observe_device(device: &mut ReplayDevice, op: &TraceOp) {
+    match op {
+        // A first "disconnected" event means it was connected until then.
+        TraceOp::ConnectionEvent { connected, .. } => {
+            device.connected.get_or_insert(!connected);
+        }
+        TraceOp::Connect { error, .. } => {
+            device.connected.get_or_insert(error.is_none());
+        }
+        TraceOp::Services { error: None, .. } => {
+            device.connected.get_or_insert(true);
+        }
+        TraceOp::Read {
+            characteristic,
+            value,
+            ..
+        }
+        | TraceOp::Notification {
+            characteristic,
+            value,
+            ..
+        } => {
+            let value = value.as_deref().and_then(hex_to_bytes);
+            if value.is_some() {
+                device.connected.get_or_insert(true);
+            }
+            if characteristic.is_battery_level() {
+                let part = device.part(characteristic.index);
+                if let Some(&level) = value.as_ref().and_then(|value| value.first()) {
+                    part.level.get_or_insert(level);
+                }
+                if matches!(op, TraceOp::Notification { .. }) {
+                    part.notifies = Some(true);
+                }
+            } else if let (Some(value), Ok(service), Ok(uuid)) = (
+                value,
+                Uuid::parse_str(&characteristic.service),
+                Uuid::parse_str(&characteristic.uuid),
+            ) {
+                if !device
+                    .statics
+                    .iter()
+                    .any(|(s, u, _)| *s == service && *u == uuid)
+                {
+                    device.statics.push((service, uuid, value));
+                }
+            }
+        }
+        TraceOp::Subscribe {
+            characteristic,
+            error,
+            ..
+        } if characteristic.is_battery_level() => {
+            let part = device.part(characteristic.index);
+            if error.is_none() {
+                part.notifies = Some(true);
+            } else {
+                part.notifies.get_or_insert(false);
+            }
+        }
+        TraceOp::DescriptorRead {
+            characteristic,
+            descriptor,
+            value,
+            ..
+        } if characteristic.is_battery_level() => {
+            let part = device.part(characteristic.index);
+            let value = value.as_deref().and_then(hex_to_bytes);
+            match Uuid::parse_str(descriptor).ok() {
+                Some(CHARACTERISTIC_USER_DESCRIPTION) => {
+                    if let Some(description) = value.and_then(|value| decode_string(&value)) {
+                        part.user_description.get_or_insert(description);
+                    }
+                }
+                Some(CHARACTERISTIC_PRESENTATION_FORMAT) => {
+                    if let Some(format) = value.and_then(|value| PresentationFormat::parse(&value))
+                    {
+                        part.presentation_format.get_or_insert(format);
+                    }
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Script steps with delays taken from the record times, and the state the
+/// script has put the simulated world in so far.
+struct ScriptBuilder {
+    steps: Vec<SimStep>,
+    last_at_ms: u64,
+    powered: bool,
+    connected: HashMap<String, bool>,
+    levels: HashMap<(String, usize), u8>,
+    values: HashMap<(String, Uuid), Vec<u8>>,
+    rejecting: HashSet<(String, usize)>,
+}
+
+impl ScriptBuilder {
+    fn push(&mut self, at_ms: u64, action: SimAction) {
+        self.steps.push(SimStep {
+            after: Duration::from_millis(at_ms.saturating_sub(self.last_at_ms)),
+            action,
+        });
+        self.last_at_ms = self.last_at_ms.max(at_ms);
+    }
+
+    fn set_connected(&mut self, at_ms: u64, device: &str, connected: bool) {
+        if self.connected.get(device) == Some(&connected) {
+            return;
+        }
+        self.connected.insert(device.to_string(), connected);
+        let device = device.to_string();
+        self.push(
+            at_ms,
+            if connected {
+                SimAction::Connect { device }
+            } else {
+                SimAction::Disconnect { device }
+            },
+        );
+    }
+
+    fn set_level(&mut self, at_ms: u64, device: &str, part: usize, level: u8) {
+        let key = (device.to_string(), part);
+        if self.levels.get(&key) == Some(&level) {
+            return;
+        }
+        self.levels.insert(key, level);
+        self.push(
+            at_ms,
+            SimAction::SetLevel {
+                device: device.to_string(),
+                part,
+                level,
+            },
+        );
+    }
+
+    /// Failures only need injecting while the simulated device would
+    /// otherwise succeed.
+    fn reachable(&self, device: &str) -> bool {
+        self.powered && self.connected.get(device) == Some(&true)
+    }
+}
+
+/// Turn a trace into simulated keyboards and the script that replays it.
+pub(crate) fn replay_world(records: &[TraceRecord]) -> ReplayWorld {
+    let devices = replay_devices(records);
+    let adapter_id = records.iter().find_map(|record| match &record.op {
+        TraceOp::ConnectedDevices { adapter, .. } | TraceOp::AdapterEvent { adapter, .. } => {
+            Some(adapter.clone())
+        }
+        _ => None,
+    });
+
+    // Part number in the simulated keyboard of each Battery Level
+    // characteristic, by device id and position in the service.
+    let parts: HashMap<(String, usize), usize> = devices
+        .iter()
+        .flat_map(|device| {
+            device
+                .parts
+                .iter()
+                .enumerate()
+                .map(|(number, part)| ((device.id.clone(), part.index), number))
+        })
+        .collect();
+    let notifies: HashSet<(String, usize)> = devices
+        .iter()
+        .flat_map(|device| {
+            device
+                .parts
+                .iter()
+                .filter(|part| part.notifies != Some(false))
+                .map(|part| (device.id.clone(), part.index))
+        })
+        .collect();
+
+    let mut script = ScriptBuilder {
+        steps: Vec::new(),
+        last_at_ms: records.first().map(|record| record.at_ms).unwrap_or(0),
+        powered: true,
+        connected: devices
+            .iter()
+            .map(|device| (device.id.clone(), device.connected.unwrap_or(true)))
+            .collect(),
+        levels: devices
+            .iter()
+            .flat_map(|device| {
+                device
+                    .parts
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(number, part)| {
+                        part.level.map(|level| ((device.id.clone(), number), level))
+                    })
+            })
+            .collect(),
+        values: devices
+            .iter()
+            .flat_map(|device| {
+                device
+                    .statics
+                    .iter()
+                    .map(|(_, uuid, value)| ((device.id.clone(), *uuid), value.clone()))
+            })
+            .collect(),
+        rejecting: HashSet::new(),
+    };
+
+    for record in records {
+        let at_ms = record.at_ms;
+        match &record.op {
+            TraceOp::AdapterEvent {
+                available: Some(available),
+                ..
+            } if script.powered != *available => {
+                script.powered = *available;
+                script.push(
+                    at_ms,
+                    SimAction::SetPowered {
+                        powered: *available,
+                    },
+                );
+            }
+            TraceOp::ConnectedDevices {
+                devices: listed,
+                error: None,
+                ..
+            } => {
+                for device in &devices {
+                    let connected = listed.iter().any(|d| d.id == device.id);
+                    script.set_connected(at_ms, &device.id, connected);
+                }
+            }
+            TraceOp::ConnectionEvent { device, connected } => {
+                script.set_connected(at_ms, device, *connected);
+            }
+            TraceOp::Connect { device, error } => {
+                script.set_connected(at_ms, device, error.is_none());
+            }
+            TraceOp::Read {
+                device,
+                characteristic,
+                value,
+                error,
+            } => {
+                let part = parts.get(&(device.clone(), characteristic.index));
+                match (value.as_deref().and_then(hex_to_bytes), part) {
+                    (Some(value), Some(&part)) if characteristic.is_battery_level() => {
+                        script.set_connected(at_ms, device, true);
+                        if let Some(&level) = value.first() {
+                            script.set_level(at_ms, device, part, level);
+                        }
+                    }
+                    (Some(value), _) => {
+                        script.set_connected(at_ms, device, true);
+                        let Ok(uuid) = Uuid::parse_str(&characteristic.uuid) else {
+                            continue;
+                        };
+                        let key = (device.clone(), uuid);
+                        if script.values.get(&key) != Some(&value) {
+                            script.values.insert(key, value.clone());
+                            script.push(
+                                at_ms,
+                                SimAction::SetValue {
+                                    device: device.clone(),
+                                    uuid,
+                                    value,
+                                },
+                            );
+                        }
+                    }
+                    (None, Some(&part))
+                        if error.is_some()
+                            && characteristic.is_battery_level()
+                            && script.reachable(device) =>
+                    {
+                        script.push(
+                            at_ms,
+                            SimAction::FailReads {
+                                device: device.clone(),
+                                part,
+                                count: 1,
+                            },
+                        );
+                    }
+                    _ => {}
+                }
+            }
+            TraceOp::Notification {
+                device,
+                characteristic,
+                value,
+                error,
+            } if characteristic.is_battery_level() => {
+                let Some(&part) = parts.get(&(device.clone(), characteristic.index)) else {
+                    continue;
+                };
+                if let Some(&level) = value
+                    .as_deref()
+                    .and_then(hex_to_bytes)
+                    .as_ref()
+                    .and_then(|value| value.first())
+                {
+                    script.set_level(at_ms, device, part, level);
+                } else if error.is_some() {
+                    script.push(
+                        at_ms,
+                        SimAction::FailNotify {
+                            device: device.clone(),
+                            part,
+                        },
+                    );
+                }
+            }
+            TraceOp::Subscribe {
+                device,
+                characteristic,
+                error,
+            } if characteristic.is_battery_level() => {
+                let key = (device.clone(), characteristic.index);
+                let Some(&part) = parts.get(&key) else {
+                    continue;
+                };
+                let reject = match error {
+                    None => false,
+                    Some(_) if script.reachable(device) => true,
+                    // Failing because the device or adapter was gone says
+                    // nothing about the part.
+                    Some(_) => continue,
+                };
+                // Parts that never accepted a subscription are built without
+                // notify support instead.
+                if notifies.contains(&key) && reject != script.rejecting.contains(&key) {
+                    if reject {
+                        script.rejecting.insert(key);
+                    } else {
+                        script.rejecting.remove(&key);
+                    }
+                    script.push(
+                        at_ms,
+                        SimAction::RejectSubscribe {
+                            device: device.clone(),
+                            part,
+                            reject,
+                        },
+                    );
+                }
+            }
+            TraceOp::Services {
+                device,
+                error: None,
+                ..
+            } => {
+                script.set_connected(at_ms, device, true);
+            }
+            _ => {}
+        }
+    }
+
+    let keyboards = devices
+        .into_iter()
+        .map(|device| {
+            let name = device.name.clone().unwrap_or_else(|| device.id.clone());
+            let mut keyboard = SimulatedKeyboard::new(&device.id, &name);
+            for part in &device.parts {
+                // A part whose level was never read starts empty.
+                keyboard = keyboard.part(part.user_description.as_deref(), part.level.unwrap_or(0));
+                if part.notifies == Some(false) {
+                    keyboard = keyboard.without_notify();
+                }
+                if let Some(format) = part.presentation_format {
+                    keyboard = keyboard.with_presentation_format(format);
+                }
+            }
+            for (service, uuid, value) in &device.statics {
+                keyboard = keyboard.characteristic(*service, *uuid, value);
+            }
+            if device.connected == Some(false) {
+                keyboard = keyboard.disconnected();
+            }
+            keyboard
+        })
+        .collect();
+
+    ReplayWorld {
+        adapter_id,
+        keyboards,
+        script: script.steps,
+    }
+}
+
+/// Path of the trace to replay from the command line or environment, if
+/// replay was requested. The command-line flag wins over the environment.
+pub fn replay_trace_path() -> Option<PathBuf> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let env_value = std::env::var(REPLAY_TRACE_ENV).ok();
+    path_from_args(&args, REPLAY_TRACE_FLAG, env_value.as_deref())
+}
+
+/// Load a trace, route all BLE commands to the world it describes and start
+/// its script. Must run before the first BLE command.
+pub fn start_replay(path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read BLE trace {}: {e}", path.display()))?;
+    let ReplayWorld {
+        adapter_id,
+        keyboards,
+        script,
+    } = replay_world(&parse_trace(&text)?);
+    log::info!(
+        "BLE trace: replaying {} devices and {} steps from {}",
+        keyboards.len(),
+        script.len(),
+        path.display()
+    );
+
+    let mut adapter = SimulatedAdapter::new(keyboards);
+    if let Some(id) = adapter_id {
+        adapter = adapter.with_id(&id);
+    }
+    ble_transport::install_transport(Arc::new(SimulatedTransport::new(adapter.clone())))?;
+    tauri::async_runtime::spawn(async move {
+        adapter.run_script(&script).await;
+        log::info!("BLE trace: replay finished");
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::sleep;
+
+    /// A `Write` the test can read back.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn records(&self) -> Vec<TraceRecord> {
+            parse_trace(&String::from_utf8(self.0.lock().unwrap().clone()).unwrap())
+                .expect("parse recorded trace")
+        }
+    }
+
+    fn level_ref(index: usize) -> CharacteristicRef {
+        CharacteristicRef {
+            service: BATTERY_SERVICE_UUID.to_string(),
+            uuid: BATTERY_LEVEL_UUID.to_string(),
+            index,
+        }
+    }
+
+    fn example() -> Vec<TraceRecord> {
+        parse_trace(include_str!("../../docs/ble-trace.example.jsonl")).expect("parse example")
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn recorder_writes_one_line_per_operation() {
+        let adapter =
+            SimulatedAdapter::new(vec![SimulatedKeyboard::split("kbd-1", "Corne", 80, 70)]);
+        let buffer = SharedBuffer::default();
+        let transport = RecordingTransport {
+            inner: Arc::new(SimulatedTransport::new(adapter.clone())),
+            trace: TraceWriter::new(Box::new(buffer.clone())),
+        };
+
+        let recorded = transport.default_adapter().await.expect("adapter");
+        let device = recorded
+            .connected_devices_with_services(&[BATTERY_SERVICE_UUID])
+            .await
+            .expect("list")
+            .remove(0);
+        let services = device.services().await.expect("services");
+        let peripheral = services[0]
+            .characteristics()
+            .await
+            .expect("characteristics")
+            .remove(1);
+        assert_eq!(peripheral.read().await.expect("read"), vec![70]);
+        let descriptors = peripheral.descriptors().await.expect("descriptors");
+        descriptors[0].read().await.expect("descriptor");
+        let mut notifications = peripheral.notify().await.expect("notify");
+
+        sleep(Duration::from_secs(5)).await;
+        adapter.apply(&SimAction::SetLevel {
+            device: "kbd-1".to_string(),
+            part: 1,
+            level: 60,
+        });
+        assert_eq!(
+            notifications.next().await.map(|r| r.ok()),
+            Some(Some(vec![60]))
+        );
+        adapter.apply(&SimAction::Disconnect {
+            device: "kbd-1".to_string(),
+        });
+        assert!(notifications.next().await.is_none());
+
+        let records = buffer.records();
+        assert!(matches!(records[0].op, TraceOp::Start { .. }));
+        let device = || "kbd-1".to_string();
+        let battery_service = BATTERY_SERVICE_UUID.to_string();
+        let ops: Vec<(u64, TraceOp)> = records[1..]
+            .iter()
+            .map(|record| (record.at_ms, record.op.clone()))
+            .collect();
+        assert_eq!(
+            ops,
+            vec![
+                (
+                    0,
+                    TraceOp::ConnectedDevices {
+                        adapter: "sim0".to_string(),
+                        devices: vec![TraceDevice {
+                            id: device(),
+                            name: Some("Corne".to_string()),
+                        }],
+                        error: None,
+                    }
+                ),
+                (
+                    0,
+                    TraceOp::Services {
+                        device: device(),
+                        services: vec![battery_service.clone()],
+                        error: None,
+                    }
+                ),
+                (
+                    0,
+                    TraceOp::Characteristics {
+                        device: device(),
+                        service: battery_service,
+                        characteristics: vec![BATTERY_LEVEL_UUID.to_string(); 2],
+                        error: None,
+                    }
+                ),
+                (
+                    0,
+                    TraceOp::Read {
+                        device: device(),
+                        characteristic: level_ref(1),
+                        value: Some("46".to_string()),
+                        error: None,
+                    }
+                ),
+                (
+                    0,
+                    TraceOp::DescriptorRead {
+                        device: device(),
+                        characteristic: level_ref(1),
+                        descriptor: CHARACTERISTIC_USER_DESCRIPTION.to_string(),
+                        value: Some(bytes_to_hex(b"Peripheral 0")),
+                        error: None,
+                    }
+                ),
+                (
+                    0,
+                    TraceOp::Subscribe {
+                        device: device(),
+                        characteristic: level_ref(1),
+                        error: None,
+                    }
+                ),
+                (
+                    5000,
+                    TraceOp::Notification {
+                        device: device(),
+                        characteristic: level_ref(1),
+                        value: Some("3C".to_string()),
+                        error: None,
+                    }
+                ),
+                (
+                    5000,
+                    TraceOp::NotifyEnded {
+                        device: device(),
+                        characteristic: level_ref(1),
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_trace_reports_the_bad_line() {
+        let error = parse_trace("{\"at_ms\":0,\"op\":\"connect\",\"device\":\"a\"}\n\nnot json")
+            .unwrap_err();
+        assert!(error.starts_with("Invalid BLE trace line 3"), "{error}");
+    }
+
+    #[test]
+    fn example_trace_rebuilds_the_keyboard() {
+        let world = replay_world(&example());
+        assert_eq!(world.adapter_id.as_deref(), Some("default"));
+        assert_eq!(world.keyboards.len(), 1);
+        let corne = &world.keyboards[0];
+        assert_eq!((corne.id.as_str(), corne.name.as_str()), ("corne", "Corne"));
+        assert!(corne.connected);
+        let parts: Vec<(Option<&str>, u8, bool)> = corne
+            .parts
+            .iter()
+            .map(|part| (part.user_description.as_deref(), part.level, part.notify))
+            .collect();
+        assert_eq!(
+            parts,
+            vec![(None, 80, true), (Some("Peripheral 0"), 70, true)]
+        );
+    }
+
+    #[test]
+    fn example_trace_replays_level_changes_and_the_reconnect() {
+        let world = replay_world(&example());
+        let corne = || "corne".to_string();
+        let steps: Vec<(u64, SimAction)> = world
+            .script
+            .into_iter()
+            .map(|step| (step.after.as_millis() as u64, step.action))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (
+                    30_000,
+                    SimAction::SetLevel {
+                        device: corne(),
+                        part: 1,
+                        level: 69,
+                    }
+                ),
+                (20_000, SimAction::Disconnect { device: corne() }),
+                (20_000, SimAction::Connect { device: corne() }),
+                (
+                    80,
+                    SimAction::SetLevel {
+                        device: corne(),
+                        part: 0,
+                        level: 75,
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn failures_are_injected_only_while_reachable() {
+        let trace = [
+            r#"{"at_ms":0,"op":"connected_devices","adapter":"hci0","devices":[{"id":"a","name":"A"}]}"#,
+            r#"{"at_ms":10,"op":"read","device":"a","characteristic":{"service":"0000180f-0000-1000-8000-00805f9b34fb","uuid":"00002a19-0000-1000-8000-00805f9b34fb","index":0},"value":"32"}"#,
+            r#"{"at_ms":20,"op":"subscribe","device":"a","characteristic":{"service":"0000180f-0000-1000-8000-00805f9b34fb","uuid":"00002a19-0000-1000-8000-00805f9b34fb","index":0}}"#,
+            r#"{"at_ms":30,"op":"read","device":"a","characteristic":{"service":"0000180f-0000-1000-8000-00805f9b34fb","uuid":"00002a19-0000-1000-8000-00805f9b34fb","index":0},"error":"timed out"}"#,
+            r#"{"at_ms":40,"op":"adapter_event","adapter":"hci0","available":false}"#,
+            r#"{"at_ms":50,"op":"read","device":"a","characteristic":{"service":"0000180f-0000-1000-8000-00805f9b34fb","uuid":"00002a19-0000-1000-8000-00805f9b34fb","index":0},"error":"adapter unavailable"}"#,
+            r#"{"at_ms":60,"op":"subscribe","device":"a","characteristic":{"service":"0000180f-0000-1000-8000-00805f9b34fb","uuid":"00002a19-0000-1000-8000-00805f9b34fb","index":0},"error":"adapter unavailable"}"#,
+            r#"{"at_ms":70,"op":"adapter_event","adapter":"hci0","available":true}"#,
+            r#"{"at_ms":80,"op":"subscribe","device":"a","characteristic":{"service":"0000180f-0000-1000-8000-00805f9b34fb","uuid":"00002a19-0000-1000-8000-00805f9b34fb","index":0},"error":"not supported"}"#,
+        ]
+        .join("\n");
+        let world = replay_world(&parse_trace(&trace).expect("parse"));
+        let actions: Vec<SimAction> = world.script.into_iter().map(|step| step.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                SimAction::FailReads {
+                    device: "a".to_string(),
+                    part: 0,
+                    count: 1,
+                },
+                SimAction::SetPowered { powered: false },
+                SimAction::SetPowered { powered: true },
+                SimAction::RejectSubscribe {
+                    device: "a".to_string(),
+                    part: 0,
+                    reject: true,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+//! Transport abstraction between the battery monitor in `ble.rs` and the
+//! Bluetooth stack.
+//!
+//! The traits mirror the small subset of the `bluest` API the monitor needs.
+//! `BluestTransport` forwards to the OS stack; other implementations (such as
+//! the simulated keyboards in `ble_simulated.rs`) let the connection watcher,
+//! notification workers and one-shot reads run without Bluetooth hardware.
+
+#[cfg(target_os = "linux")]
+use crate::ble_bluez;
+use crate::{ble_scheduler, ble_timeout, ble_trace};
+use async_trait::async_trait;
+use bluest::{AdapterEvent, CharacteristicProperties, ConnectionEvent};
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use std::sync::{Arc, OnceLock};
+use tokio::time::{timeout, Duration};
+use uuid::Uuid;
+
+pub type BleResult<T> = bluest::Result<T>;
+pub type NotifyStream<'a> = BoxStream<'a, BleResult<Vec<u8>>>;
+pub type ConnectionEventStream<'a> = BoxStream<'a, ConnectionEvent>;
+pub type AdvertisementStream<'a> = BoxStream<'a, Advertisement>;
+pub type AdapterEventStream<'a> = BoxStream<'a, BleResult<AdapterEvent>>;
+
+/// One received advertisement packet.
+pub struct Advertisement {
+    pub device: Arc<dyn BleDevice>,
+    pub local_name: Option<String>,
+    pub services: Vec<Uuid>,
+    pub rssi: Option<i16>,
+}
+
+#[async_trait]
+pub trait BleTransport: Send + Sync {
+    /// Returns the adapter used when none is selected, if one exists.
+    async fn default_adapter(&self) -> Option<Arc<dyn BleAdapter>>;
+
+    /// Every adapter that can be opened, the default one first.
+    async fn adapters(&self) -> Vec<Arc<dyn BleAdapter>> {
+        self.default_adapter().await.into_iter().collect()
+    }
+}
+
+#[async_trait]
+pub trait BleAdapter: Send + Sync {
+    /// Stable identifier used to select this adapter and bind monitors to it.
+    fn id(&self) -> String;
+    fn name(&self) -> String;
+
+    async fn wait_available(&self) -> BleResult<()>;
+
+    /// Whether the adapter is powered on right now.
+    async fn is_available(&self) -> BleResult<bool>;
+
+    /// Power changes of the adapter. The stream ends when the adapter goes
+    /// away, e.g. a USB dongle being unplugged.
+    async fn events(&self) -> BleResult<AdapterEventStream<'_>>;
+
+    /// Devices currently connected to the host that expose any of `services`.
+    async fn connected_devices_with_services(
+        &self,
+        services: &[Uuid],
+    ) -> BleResult<Vec<Arc<dyn BleDevice>>>;
+
+    /// Active scan for advertisements including any of `services` (all
+    /// advertisements when empty). Scanning stops when the stream is dropped.
+    async fn scan<'a>(&'a self, services: &'a [Uuid]) -> BleResult<AdvertisementStream<'a>>;
+}
+
+/// A device handle. Connection management lives here rather than on the
+/// adapter so implementations can keep whatever adapter state they need.
+#[async_trait]
+pub trait BleDevice: Send + Sync {
+    fn id(&self) -> String;
+    fn name(&self) -> BleResult<String>;
+    async fn connect(&self) -> BleResult<()>;
+    /// Never called on Linux, where it would drop the OS-level connection.
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    async fn disconnect(&self) -> BleResult<()>;
+    async fn connection_events(&self) -> BleResult<ConnectionEventStream<'_>>;
+    async fn services(&self) -> BleResult<Vec<Arc<dyn BleService>>>;
+}
+
+#[async_trait]
+pub trait BleService: Send + Sync {
+    fn uuid(&self) -> Uuid;
+    async fn characteristics(&self) -> BleResult<Vec<Arc<dyn BleCharacteristic>>>;
+}
+
+#[async_trait]
+pub trait BleCharacteristic: Send + Sync {
+    fn uuid(&self) -> Uuid;
+    async fn properties(&self) -> BleResult<CharacteristicProperties>;
+    async fn read(&self) -> BleResult<Vec<u8>>;
+    async fn notify(&self) -> BleResult<NotifyStream<'_>>;
+    async fn descriptors(&self) -> BleResult<Vec<Arc<dyn BleDescriptor>>>;
+}
+
+#[async_trait]
+pub trait BleDescriptor: Send + Sync {
+    fn uuid(&self) -> Uuid;
+    async fn read(&self) -> BleResult<Vec<u8>>;
+}
+
+static TRANSPORT: OnceLock<Arc<dyn BleTransport>> = OnceLock::new();
+
+/// The transport used by the Tauri commands. Defaults to the OS Bluetooth stack.
+/// Either way its GATT operations are queued per adapter and bounded by the
+/// configured timeouts, and it is wrapped by the trace recorder when one was
+/// started.
+pub fn transport() -> Arc<dyn BleTransport> {
+    TRANSPORT
+        .get_or_init(|| wrap(Arc::new(BluestTransport)))
+        .clone()
+}
+
+/// Replace the default transport. Must run before the first BLE command.
+pub fn install_transport(transport: Arc<dyn BleTransport>) -> Result<(), String> {
+    TRANSPORT
+        .set(wrap(transport))
+        .map_err(|_| "BLE transport is already initialized".to_string())
+}
+
+/// The timeout only starts once the operation has its turn.
+fn wrap(transport: Arc<dyn BleTransport>) -> Arc<dyn BleTransport> {
+    ble_trace::recording(ble_scheduler::scheduled(ble_timeout::bounded(transport)))
+}
+
+pub struct BluestTransport;
+
+#[async_trait]
+impl BleTransport for BluestTransport {
+    async fn default_adapter(&self) -> Option<Arc<dyn BleAdapter>> {
+        let adapter = bluest::Adapter::default().await?;
+        Some(Arc::new(BluestAdapter(adapter)))
+    }
+
+    /// bluest only opens the default adapter. On Linux the others come from
+    /// BlueZ; elsewhere the default adapter is the only one.
+    async fn adapters(&self) -> Vec<Arc<dyn BleAdapter>> {
+        #[cfg_attr(not(target_os = "linux"), allow(unused_mut))]
+        let mut adapters: Vec<Arc<dyn BleAdapter>> =
+            self.default_adapter().await.into_iter().collect();
+        #[cfg(target_os = "linux")]
+        adapters.extend(ble_bluez::secondary_adapters().await);
+        adapters
+    }
+}
+
+/// The OS default adapter, which bluest opens. On Linux it is `hci0` when
+/// present.
+struct BluestAdapter(bluest::Adapter);
+
+const BLUEST_ADAPTER_ID: &str = "default";
+/// bluest has no power state getter; `wait_available` returns at once when
+/// the adapter is on, so a short wait stands in for one.
+const AVAILABILITY_PROBE: Duration = Duration::from_secs(1);
+
+#[async_trait]
+impl BleAdapter for BluestAdapter {
+    fn id(&self) -> String {
+        BLUEST_ADAPTER_ID.to_string()
+    }
+
+    fn name(&self) -> String {
+        "System default adapter".to_string()
+    }
+
+    async fn wait_available(&self) -> BleResult<()> {
+        self.0.wait_available().await
+    }
+
+    async fn is_available(&self) -> BleResult<bool> {
+        match timeout(AVAILABILITY_PROBE, self.0.wait_available()).await {
+            Ok(result) => result.map(|()| true),
+            Err(_) => Ok(false),
+        }
+    }
+
+    async fn events(&self) -> BleResult<AdapterEventStream<'_>> {
+        let events = self.0.events().await?;
+        Ok(events.boxed())
+    }
+
+    async fn connected_devices_with_services(
+        &self,
+        services: &[Uuid],
+    ) -> BleResult<Vec<Arc<dyn BleDevice>>> {
+        let devices = self.0.connected_devices_with_services(services).await?;
+        Ok(devices
+            .into_iter()
+            .map(|device| self.device_handle(device))
+            .collect())
+    }
+
+    async fn scan<'a>(&'a self, services: &'a [Uuid]) -> BleResult<AdvertisementStream<'a>> {
+        let advertisements = self.0.scan(services).await?;
+        Ok(advertisements
+            .map(|advertising| Advertisement {
+                device: self.device_handle(advertising.device),
+                local_name: advertising.adv_data.local_name,
+                services: advertising.adv_data.services,
+                rssi: advertising.rssi,
+            })
+            .boxed())
+    }
+}
+
+impl BluestAdapter {
+    fn device_handle(&self, device: bluest::Device) -> Arc<dyn BleDevice> {
+        Arc::new(BluestDevice {
+            adapter: self.0.clone(),
+            device,
+        })
+    }
+}
+
+struct BluestDevice {
+    adapter: bluest::Adapter,
+    device: bluest::Device,
+}
+
+#[async_trait]
+impl BleDevice for BluestDevice {
+    fn id(&self) -> String {
+        self.device.id().to_string()
+    }
+
+    fn name(&self) -> BleResult<String> {
+        self.device.name()
+    }
+
+    async fn connect(&self) -> BleResult<()> {
+        self.adapter.connect_device(&self.device).await
+    }
+
+    async fn disconnect(&self) -> BleResult<()> {
+        self.adapter.disconnect_device(&self.device).await
+    }
+
+    async fn connection_events(&self) -> BleResult<ConnectionEventStream<'_>> {
+        let events = self.adapter.device_connection_events(&self.device).await?;
+        Ok(events.boxed())
+    }
+
+    async fn services(&self) -> BleResult<Vec<Arc<dyn BleService>>> {
+        let services = self.device.services().await?;
+        Ok(services
+            .into_iter()
+            .map(|service| Arc::new(BluestService(service)) as Arc<dyn BleService>)
+            .collect())
+    }
+}
+
+struct BluestService(bluest::Service);
+
+#[async_trait]
+impl BleService for BluestService {
+    fn uuid(&self) -> Uuid {
+        self.0.uuid()
+    }
+
+    async fn characteristics(&self) -> BleResult<Vec<Arc<dyn BleCharacteristic>>> {
+        let characteristics = self.0.characteristics().await?;
+        Ok(characteristics
+            .into_iter()
+            .map(|c| Arc::new(BluestCharacteristic(c)) as Arc<dyn BleCharacteristic>)
+            .collect())
+    }
+}
+
+struct BluestCharacteristic(bluest::Characteristic);
+
+#[async_trait]
+impl BleCharacteristic for BluestCharacteristic {
+    fn uuid(&self) -> Uuid {
+        self.0.uuid()
+    }
+
+    async fn properties(&self) -> BleResult<CharacteristicProperties> {
+        self.0.properties().await
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        self.0.read().await
+    }
+
+    async fn notify(&self) -> BleResult<NotifyStream<'_>> {
+        let stream = self.0.notify().await?;
+        Ok(stream.boxed())
+    }
+
+    async fn descriptors(&self) -> BleResult<Vec<Arc<dyn BleDescriptor>>> {
+        let descriptors = self.0.descriptors().await?;
+        Ok(descriptors
+            .into_iter()
+            .map(|d| Arc::new(BluestDescriptor(d)) as Arc<dyn BleDescriptor>)
+            .collect())
+    }
+}
+
+struct BluestDescriptor(bluest::Descriptor);
+
+#[async_trait]
+impl BleDescriptor for BluestDescriptor {
+    fn uuid(&self) -> Uuid {
+        self.0.uuid()
+    }
+
+    async fn read(&self) -> BleResult<Vec<u8>> {
+        self.0.read().await
+    }
+}
@@ -0,0 +1,77 @@
+//! Liveness watchdog for monitored devices.
+//!
+//! Some stacks keep a notify stream open after the keyboard sleeps and wakes
+//! but deliver nothing on it, and never report a disconnect, so the workers
+//! would wait forever. The workers of a session mark every notification and
+//! successful read on a shared `Liveness`; when it has been quiet for
+//! `stale_after_secs` the connection watcher probes the device with a read
+//! and restarts the session if that fails.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use tokio::time::{sleep_until, Instant};
+
+/// When the workers of one session last heard from their device.
+pub(crate) struct Liveness {
+    last_heard: Mutex<Instant>,
+}
+
+impl Liveness {
+    pub fn new() -> Self {
+        Self {
+            last_heard: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn heard(&self) {
+        *self.lock() = Instant::now();
+    }
+
+    pub fn last_heard(&self) -> Instant {
+        *self.lock()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Instant> {
+        self.last_heard.lock().unwrap_or_else(|p| p.into_inner())
+    }
+
+    /// Resolves once nothing has been heard for `stale_after`.
+    pub async fn wait_until_stale(&self, stale_after: std::time::Duration) {
+        loop {
+            let deadline = self.last_heard() + stale_after;
+            if Instant::now() >= deadline {
+                return;
+            }
+            sleep_until(deadline).await;
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    /// Nothing arrived for the stale window and the probe read failed, so the
+    /// session was torn down and the watcher started over.
+    StaleStreamRestarted,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::sleep;
+
+    #[tokio::test(start_paused = true)]
+    async fn activity_pushes_the_stale_deadline_back() {
+        let liveness = std::sync::Arc::new(Liveness::new());
+        let started = Instant::now();
+        let marker = liveness.clone();
+        tokio::spawn(async move {
+            sleep(Duration::from_secs(40)).await;
+            marker.heard();
+        });
+
+        liveness.wait_until_stale(Duration::from_secs(60)).await;
+        assert_eq!(started.elapsed(), Duration::from_secs(100));
+    }
+}
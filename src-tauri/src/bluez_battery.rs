@@ -0,0 +1,398 @@
+//! Battery levels from BlueZ's battery plugin (`org.bluez.Battery1`).
+//!
+//! When the plugin claims a device's Battery Service, BlueZ republishes the
+//! level as `Battery1.Percentage` on the device object and direct GATT reads
+//! of 0x2A19 may be refused. This source talks to BlueZ over the system bus
+//! instead. It only sees the level BlueZ aggregates, so split peripherals do
+//! not show up here.
+
+use crate::error::CommandError;
+use std::collections::HashMap;
+use tokio::sync::watch;
+use tokio::time::{sleep, Duration};
+use zbus::fdo::{ManagedObjects, ObjectManagerProxy};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::Connection;
+
+const BLUEZ_SERVICE: &str = "org.bluez";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+#[zbus::proxy(interface = "org.bluez.Battery1", default_service = "org.bluez")]
+trait Battery1 {
+    #[zbus(property)]
+    fn percentage(&self) -> zbus::Result<u8>;
+}
+
+fn dbus_error(context: &str, error: zbus::Error) -> CommandError {
+    CommandError::Io(format!("{context}: {error}"))
+}
+
+pub(crate) async fn system_bus() -> Result<Connection, CommandError> {
+    Connection::system()
+        .await
+        .map_err(|e| dbus_error("Failed to connect to the system D-Bus", e))
+}
+
+fn device_address(
+    interfaces: &HashMap<zbus::names::OwnedInterfaceName, HashMap<String, OwnedValue>>,
+) -> Option<String> {
+    let address = interfaces.get(DEVICE_INTERFACE)?.get("Address")?;
+    String::try_from(address.try_clone().ok()?).ok()
+}
+
+/// Object path of the device with `address` if BlueZ currently exposes a
+/// battery for it. Addresses compare case-insensitively, as bluest formats
+/// them in upper case while user-entered ids may not be.
+fn find_battery_path(objects: &ManagedObjects, address: &str) -> Option<OwnedObjectPath> {
+    objects.iter().find_map(|(path, interfaces)| {
+        let matches = device_address(interfaces)
+            .is_some_and(|candidate| candidate.eq_ignore_ascii_case(address));
+        (matches && interfaces.contains_key(BATTERY_INTERFACE)).then(|| path.clone())
+    })
+}
+
+async fn battery_proxy<'a>(
+    connection: &Connection,
+    address: &str,
+) -> Result<Battery1Proxy<'a>, CommandError> {
+    let objects = ObjectManagerProxy::builder(connection)
+        .destination(BLUEZ_SERVICE)
+        .and_then(|b| b.path("/"))
+        .map_err(|e| dbus_error("Failed to query BlueZ objects", e))?
+        .build()
+        .await
+        .map_err(|e| dbus_error("Failed to query BlueZ objects", e))?
+        .get_managed_objects()
+        .await
+        .map_err(|e| dbus_error("Failed to query BlueZ objects", e.into()))?;
+    // BlueZ drops Battery1 when the device disconnects.
+    let path = find_battery_path(&objects, address).ok_or_else(|| {
+        CommandError::DeviceNotConnected(
+            "BlueZ does not expose a battery for this device".to_string(),
+        )
+    })?;
+    Battery1Proxy::builder(connection)
+        .path(path)
+        .map_err(|e| dbus_error("Failed to open the BlueZ battery", e))?
+        .build()
+        .await
+        .map_err(|e| dbus_error("Failed to open the BlueZ battery", e))
+}
+
+pub(crate) async fn read_percentage(
+    connection: &Connection,
+    address: &str,
+) -> Result<u8, CommandError> {
+    log::debug!("BlueZ: read Battery1.Percentage device_id={address}");
+    battery_proxy(connection, address)
+        .await?
+        .percentage()
+        .await
+        .map_err(|e| dbus_error("Failed to read BlueZ battery level", e))
+}
+
+/// Report the device's percentage through `on_change` until stopped: the
+/// current value whenever the battery (re)appears, then every change signalled
+/// by `PropertiesChanged`, and `None` when BlueZ drops the battery (device
+/// disconnected or removed). While it is missing the lookup is retried.
+pub(crate) async fn watch_percentage(
+    connection: &Connection,
+    address: &str,
+    mut stop_rx: watch::Receiver<bool>,
+    mut on_change: impl FnMut(Option<u8>),
+) {
+    use futures_util::StreamExt;
+
+    log::debug!("BlueZ: battery watcher started device_id={address}");
+    let mut last_reported: Option<u8> = None;
+    let mut report = |level: Option<u8>| {
+        if level != last_reported {
+            last_reported = level;
+            on_change(level);
+        }
+    };
+    loop {
+        if *stop_rx.borrow() {
+            return;
+        }
+
+        let proxy = match battery_proxy(connection, address).await {
+            Ok(proxy) => proxy,
+            Err(e) => {
+                log::debug!("BlueZ: battery not available device_id={address}: {e}");
+                report(None);
+                tokio::select! {
+                    _ = sleep(RETRY_INTERVAL) => continue,
+                    changed = stop_rx.changed() => {
+                        if changed.is_err() || *stop_rx.borrow() {
+                            return;
+                        }
+                        continue;
+                    }
+                }
+            }
+        };
+
+        // Subscribe before the initial read so no change falls in between. The
+        // property stream also yields the current value first; `report` drops
+        // the duplicate.
+        let removals = ObjectManagerProxy::builder(connection)
+            .destination(BLUEZ_SERVICE)
+            .and_then(|b| b.path("/"));
+        let mut removals = match removals {
+            Ok(builder) => match builder.build().await {
+                Ok(manager) => manager.receive_interfaces_removed().await.ok(),
+                Err(_) => None,
+            },
+            Err(_) => None,
+        };
+        let mut changes = proxy.receive_percentage_changed().await;
+
+        match proxy.percentage().await {
+            Ok(level) => report(Some(level)),
+            Err(e) => {
+                log::warn!("BlueZ: initial battery read failed device_id={address}: {e}");
+            }
+        }
+
+        loop {
+            tokio::select! {
+                changed = stop_rx.changed() => {
+                    if changed.is_err() || *stop_rx.borrow() {
+                        log::debug!("BlueZ: battery watcher stopped device_id={address}");
+                        return;
+                    }
+                }
+                change = changes.next() => {
+                    let Some(change) = change else { break };
+                    match change.get().await {
+                        Ok(level) => {
+                            log::debug!("BlueZ: PropertiesChanged Percentage={level} device_id={address}");
+                            report(Some(level));
+                        }
+                        Err(e) => log::warn!("BlueZ: bad Percentage update device_id={address}: {e}"),
+                    }
+                }
+                removed = async {
+                    match removals.as_mut() {
+                        Some(stream) => stream.next().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    let Some(removed) = removed else {
+                        removals = None;
+                        continue;
+                    };
+                    let Ok(args) = removed.args() else { continue };
+                    if args.object_path.as_str() == proxy.inner().path().as_str()
+                        && args.interfaces.iter().any(|i| i.as_str() == BATTERY_INTERFACE)
+                    {
+                        log::debug!("BlueZ: battery interface removed device_id={address}");
+                        break;
+                    }
+                }
+            }
+        }
+
+        report(None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+    use tokio::sync::mpsc;
+
+    /// A private `dbus-daemon` for one test, killed on drop.
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        fn start() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--nofork", "--print-address=1"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            BufReader::new(daemon.stdout.take()?)
+                .read_line(&mut address)
+                .ok()?;
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+
+        async fn connect(&self) -> Connection {
+            zbus::connection::Builder::address(self.address.as_str())
+                .expect("address")
+                .build()
+                .await
+                .expect("connect to private bus")
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    struct FakeDevice {
+        address: String,
+    }
+
+    #[zbus::interface(name = "org.bluez.Device1")]
+    impl FakeDevice {
+        #[zbus(property)]
+        fn address(&self) -> String {
+            self.address.clone()
+        }
+    }
+
+    struct FakeBattery {
+        percentage: u8,
+    }
+
+    #[zbus::interface(name = "org.bluez.Battery1")]
+    impl FakeBattery {
+        #[zbus(property)]
+        fn percentage(&self) -> u8 {
+            self.percentage
+        }
+    }
+
+    const DEVICE_PATH: &str = "/org/bluez/hci0/dev_AA_BB_CC_DD_EE_FF";
+    const ADDRESS: &str = "AA:BB:CC:DD:EE:FF";
+
+    /// Serve a BlueZ-like object tree: an ObjectManager at `/` and one device
+    /// with a battery, owning the `org.bluez` name.
+    async fn fake_bluez(bus: &PrivateBus, percentage: u8) -> Connection {
+        let server = bus.connect().await;
+        let objects = server.object_server();
+        objects
+            .at("/", zbus::fdo::ObjectManager)
+            .await
+            .expect("object manager");
+        objects
+            .at(
+                DEVICE_PATH,
+                FakeDevice {
+                    address: ADDRESS.to_string(),
+                },
+            )
+            .await
+            .expect("device");
+        objects
+            .at(DEVICE_PATH, FakeBattery { percentage })
+            .await
+            .expect("battery");
+        server.request_name(BLUEZ_SERVICE).await.expect("name");
+        server
+    }
+
+    async fn set_percentage(server: &Connection, percentage: u8) {
+        let battery = server
+            .object_server()
+            .interface::<_, FakeBattery>(DEVICE_PATH)
+            .await
+            .expect("battery interface");
+        battery.get_mut().await.percentage = percentage;
+        battery
+            .get()
+            .await
+            .percentage_changed(battery.signal_emitter())
+            .await
+            .expect("emit change");
+    }
+
+    async fn next_update(rx: &mut mpsc::UnboundedReceiver<Option<u8>>) -> Option<u8> {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("watcher produced no update")
+            .expect("channel closed")
+    }
+
+    #[tokio::test]
+    async fn reads_percentage_by_address() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let _server = fake_bluez(&bus, 73).await;
+        let client = bus.connect().await;
+
+        assert_eq!(read_percentage(&client, ADDRESS).await, Ok(73));
+        assert_eq!(
+            read_percentage(&client, &ADDRESS.to_lowercase()).await,
+            Ok(73)
+        );
+        assert_eq!(
+            read_percentage(&client, "11:22:33:44:55:66")
+                .await
+                .unwrap_err()
+                .code(),
+            "device_not_connected"
+        );
+    }
+
+    #[tokio::test]
+    async fn watch_reports_changes_and_battery_removal() {
+        let Some(bus) = PrivateBus::start() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        let server = fake_bluez(&bus, 80).await;
+        let client = bus.connect().await;
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (stop_tx, stop_rx) = watch::channel(false);
+
+        let watcher = tokio::spawn(async move {
+            watch_percentage(&client, ADDRESS, stop_rx, |level| {
+                let _ = tx.send(level);
+            })
+            .await;
+        });
+
+        assert_eq!(next_update(&mut rx).await, Some(80));
+        set_percentage(&server, 79).await;
+        assert_eq!(next_update(&mut rx).await, Some(79));
+
+        server
+            .object_server()
+            .remove::<FakeBattery, _>(DEVICE_PATH)
+            .await
+            .expect("remove battery");
+        assert_eq!(next_update(&mut rx).await, None);
+
+        stop_tx.send(true).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), watcher)
+            .await
+            .expect("watcher did not stop")
+            .expect("watcher panicked");
+    }
+
+    #[test]
+    fn battery_path_requires_battery_interface() {
+        let device_only: ManagedObjects = HashMap::from([(
+            OwnedObjectPath::try_from(DEVICE_PATH).unwrap(),
+            HashMap::from([(
+                zbus::names::OwnedInterfaceName::try_from(DEVICE_INTERFACE).unwrap(),
+                HashMap::from([(
+                    "Address".to_string(),
+                    OwnedValue::try_from(zbus::zvariant::Value::from(ADDRESS)).unwrap(),
+                )]),
+            )]),
+        )]);
+        assert_eq!(find_battery_path(&device_only, ADDRESS), None);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::ble;
+use crate::{ble, ble_polling};
 use tauri::AppHandle;
 
 #[tauri::command]
@@ -11,6 +11,7 @@ pub async fn exit_app(app: AppHandle) {
     }
     log::debug!("exit_app: stopping all BLE monitors");
     ble::stop_all_battery_monitors().await;
+    ble_polling::stop_all_battery_polling().await;
     log::debug!("exit_app: all BLE monitors stopped");
     log::debug!("exit_app: exiting");
     std::process::exit(0);
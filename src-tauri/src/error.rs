@@ -0,0 +1,211 @@
+//! Error type returned by the BLE and history commands.
+//!
+//! It reaches the frontend as `{ code, message, retryable, details }`, so the
+//! UI can branch on `code` and `retryable` instead of matching message text.
+
+use bluest::error::ErrorKind;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    AdapterNotFound,
+    /// The adapter exists but is powered off or otherwise not ready.
+    AdapterUnavailable(String),
+    PermissionDenied(String),
+    /// The OS does not list the device: powered off, out of range or unpaired.
+    DeviceNotFound,
+    DeviceNotConnected(String),
+    CharacteristicNotFound(String),
+    Timeout(String),
+    /// Another operation that cannot overlap is running, e.g. a scan.
+    Busy(String),
+    /// Not available on this platform or with this device.
+    Unsupported(String),
+    InvalidArgument(String),
+    Io(String),
+    MalformedHistory(String),
+}
+
+impl CommandError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::AdapterNotFound => "adapter_not_found",
+            Self::AdapterUnavailable(_) => "adapter_unavailable",
+            Self::PermissionDenied(_) => "permission_denied",
+            Self::DeviceNotFound => "device_not_found",
+            Self::DeviceNotConnected(_) => "device_not_connected",
+            Self::CharacteristicNotFound(_) => "characteristic_not_found",
+            Self::Timeout(_) => "timeout",
+            Self::Busy(_) => "busy",
+            Self::Unsupported(_) => "unsupported",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::Io(_) => "io",
+            Self::MalformedHistory(_) => "malformed_history",
+        }
+    }
+
+    /// Whether the same call may succeed later without user action.
+    pub fn retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::AdapterUnavailable(_)
+                | Self::DeviceNotFound
+                | Self::DeviceNotConnected(_)
+                | Self::Timeout(_)
+                | Self::Busy(_)
+                | Self::Io(_)
+        )
+    }
+
+    fn message(&self) -> &'static str {
+        match self {
+            Self::AdapterNotFound => "Bluetooth adapter not found",
+            Self::AdapterUnavailable(_) => "Bluetooth adapter is not available",
+            Self::PermissionDenied(_) => "Bluetooth permission denied",
+            Self::DeviceNotFound => "Device not found",
+            Self::DeviceNotConnected(_) => "Device is not connected",
+            Self::CharacteristicNotFound(_) => "Battery characteristic not found",
+            Self::Timeout(_) => "Bluetooth operation timed out",
+            Self::Busy(_) => "Another Bluetooth operation is in progress",
+            Self::Unsupported(_) => "Not supported",
+            Self::InvalidArgument(_) => "Invalid argument",
+            Self::Io(_) => "I/O error",
+            Self::MalformedHistory(_) => "Malformed battery history",
+        }
+    }
+
+    fn details(&self) -> Option<&str> {
+        match self {
+            Self::AdapterNotFound | Self::DeviceNotFound => None,
+            Self::AdapterUnavailable(details)
+            | Self::PermissionDenied(details)
+            | Self::DeviceNotConnected(details)
+            | Self::CharacteristicNotFound(details)
+            | Self::Timeout(details)
+            | Self::Busy(details)
+            | Self::Unsupported(details)
+            | Self::InvalidArgument(details)
+            | Self::Io(details)
+            | Self::MalformedHistory(details) => Some(details),
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.details() {
+            Some(details) if !details.is_empty() => write!(f, "{}: {details}", self.message()),
+            _ => f.write_str(self.message()),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Serialize for CommandError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Payload<'a> {
+            code: &'a str,
+            message: &'a str,
+            retryable: bool,
+            details: Option<&'a str>,
+        }
+
+        Payload {
+            code: self.code(),
+            message: self.message(),
+            retryable: self.retryable(),
+            details: self.details(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl From<bluest::Error> for CommandError {
+    fn from(error: bluest::Error) -> Self {
+        let details = error.to_string();
+        match error.kind() {
+            ErrorKind::AdapterUnavailable | ErrorKind::NotReady => {
+                Self::AdapterUnavailable(details)
+            }
+            ErrorKind::NotAuthorized => Self::PermissionDenied(details),
+            ErrorKind::NotConnected | ErrorKind::ConnectionFailed => {
+                Self::DeviceNotConnected(details)
+            }
+            ErrorKind::NotSupported => Self::Unsupported(details),
+            ErrorKind::Timeout => Self::Timeout(details),
+            ErrorKind::AlreadyScanning => Self::Busy(details),
+            ErrorKind::InvalidParameter => Self::InvalidArgument(details),
+            _ => Self::Io(details),
+        }
+    }
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::PermissionDenied => Self::PermissionDenied(error.to_string()),
+            std::io::ErrorKind::TimedOut => Self::Timeout(error.to_string()),
+            _ => Self::Io(error.to_string()),
+        }
+    }
+}
+
+impl From<csv::Error> for CommandError {
+    fn from(error: csv::Error) -> Self {
+        if error.is_io_error() {
+            Self::Io(error.to_string())
+        } else {
+            Self::MalformedHistory(error.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_code_retryable_and_details() {
+        let value = serde_json::to_value(CommandError::DeviceNotConnected("link lost".to_string()))
+            .unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "code": "device_not_connected",
+                "message": "Device is not connected",
+                "retryable": true,
+                "details": "link lost",
+            })
+        );
+
+        let value = serde_json::to_value(CommandError::AdapterNotFound).unwrap();
+        assert_eq!(value["retryable"], false);
+        assert_eq!(value["details"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn bluest_errors_map_by_kind() {
+        let error = CommandError::from(bluest::Error::from(ErrorKind::NotAuthorized));
+        assert_eq!(error.code(), "permission_denied");
+        assert!(!error.retryable());
+
+        let error = CommandError::from(bluest::Error::from(ErrorKind::Timeout));
+        assert_eq!(error.code(), "timeout");
+        assert!(error.retryable());
+
+        let error = CommandError::from(bluest::Error::from(ErrorKind::Other));
+        assert_eq!(error.code(), "io");
+    }
+
+    #[test]
+    fn display_includes_details() {
+        assert_eq!(CommandError::DeviceNotFound.to_string(), "Device not found");
+        assert_eq!(
+            CommandError::Busy("scan running".to_string()).to_string(),
+            "Another Bluetooth operation is in progress: scan running"
+        );
+    }
+}
@@ -1,7 +1,9 @@
+use crate::ble_power_state::PowerState;
+use crate::error::CommandError;
 use csv::{ReaderBuilder, WriterBuilder};
 use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::PathBuf;
 use std::sync::{LazyLock, Mutex};
 
@@ -12,6 +14,93 @@ static PRUNED_FILES: LazyLock<Mutex<HashMap<PathBuf, u64>>> =
 
 const HISTORY_RETENTION_DAYS: u64 = 365;
 
+/// Files written before `power_state` or `part_key` existed, or without a
+/// header at all, get this one in place of their first line when the next row
+/// is appended; readers accept every width from three to five columns.
+const HISTORY_HEADER: &str = "timestamp,user_description,battery_level,power_state,part_key";
+
+fn is_history_row_width(len: usize) -> bool {
+    (3..=5).contains(&len)
+}
+
+/// Whether `record` is a history header, of this version or an older one.
+fn is_history_header(record: &csv::StringRecord) -> bool {
+    record.get(0).map(|field| field.trim_start_matches('\u{feff}')) == Some("timestamp")
+}
+
+/// Whether the file at `path` starts with the current history header.
+fn has_current_header(path: &std::path::Path) -> Result<bool, CommandError> {
+    let mut first_line = String::new();
+    BufReader::new(fs::File::open(path)?).read_line(&mut first_line)?;
+    Ok(first_line.trim_end_matches(['\r', '\n']) == HISTORY_HEADER)
+}
+
+/// Whether the history file at `path` with `contents` starts with a header,
+/// of this version or an older one, rather than straight with a row. A file
+/// that starts with neither is not one of ours; refuse it rather than
+/// rewrite it.
+fn starts_with_history_header(
+    path: &std::path::Path,
+    contents: &[u8],
+) -> Result<bool, CommandError> {
+    let first = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(contents)
+        .records()
+        .next();
+    match first {
+        None => Ok(false),
+        Some(Ok(record)) if is_history_header(&record) => Ok(true),
+        Some(Ok(record))
+            if is_history_row_width(record.len())
+                && record
+                    .get(2)
+                    .is_some_and(|level| level.parse::<i32>().is_ok()) =>
+        {
+            Ok(false)
+        }
+        Some(_) => Err(CommandError::MalformedHistory(format!(
+            "{} has no battery history header",
+            path.display()
+        ))),
+    }
+}
+
+/// Give the history file at `path` the current header, replacing an older one
+/// or adding a missing one. Rows are copied byte for byte.
+fn migrate_history_header(path: &std::path::Path) -> Result<(), CommandError> {
+    let contents = fs::read(path)?;
+    let rows = if starts_with_history_header(path, &contents)? {
+        match contents.iter().position(|&byte| byte == b'\n') {
+            Some(end) => &contents[end + 1..],
+            None => &[],
+        }
+    } else {
+        &contents[..]
+    };
+
+    let tmp_path = path.with_extension("csv.tmp");
+    {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&tmp_path)?;
+        writeln!(file, "{HISTORY_HEADER}")?;
+        file.write_all(rows)?;
+    }
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+/// Rows written before `part_key` existed, or by a source without one, leave
+/// it empty.
+fn parse_part_key(field: Option<&str>) -> Option<String> {
+    field.filter(|key| !key.is_empty()).map(str::to_string)
+}
+
 /// Decide whether a prune should run for `path`, and record today's epoch day if so.
 /// `today_epoch_day` = seconds-since-epoch / 86400. Returns true at most once per
 /// path per UTC day.
@@ -50,7 +139,13 @@ fn safe_filename(device_name: &str, ble_id: &str) -> String {
     format!("{}_{}.csv", sanitize(device_name), sanitize(ble_id))
 }
 
-fn csv_record_line(timestamp: &str, user_description: &str, battery_level: i32) -> Result<String, String> {
+fn csv_record_line(
+    timestamp: &str,
+    user_description: &str,
+    battery_level: i32,
+    power_state: PowerState,
+    part_key: Option<&str>,
+) -> Result<String, CommandError> {
     let mut buf = Vec::new();
     {
         let mut wtr = WriterBuilder::new()
@@ -60,11 +155,13 @@ fn csv_record_line(timestamp: &str, user_description: &str, battery_level: i32)
             timestamp,
             user_description,
             &battery_level.to_string(),
-        ])
-        .map_err(|e| e.to_string())?;
-        wtr.flush().map_err(|e| e.to_string())?;
+            power_state.as_str(),
+            part_key.unwrap_or(""),
+        ])?;
+        wtr.flush()?;
     }
-    let mut line = String::from_utf8(buf).map_err(|e| e.to_string())?;
+    let mut line =
+        String::from_utf8(buf).map_err(|e| CommandError::MalformedHistory(e.to_string()))?;
     while line.ends_with('\n') || line.ends_with('\r') {
         line.pop();
     }
@@ -79,7 +176,7 @@ fn parse_history_record_line(line: &str) -> Option<BatteryHistoryRecord> {
         .from_reader(std::io::Cursor::new(line.as_bytes()));
     let mut it = rdr.records();
     let rec = it.next()?.ok()?;
-    if rec.len() != 3 {
+    if !is_history_row_width(rec.len()) {
         return None;
     }
     let battery_level: i32 = rec.get(2)?.parse().unwrap_or(-1);
@@ -87,6 +184,8 @@ fn parse_history_record_line(line: &str) -> Option<BatteryHistoryRecord> {
         timestamp: rec.get(0)?.to_string(),
         user_description: rec.get(1)?.to_string(),
         battery_level,
+        power_state: PowerState::parse(rec.get(3).unwrap_or("")),
+        part_key: parse_part_key(rec.get(4)),
     })
 }
 
@@ -94,29 +193,35 @@ fn append_battery_history_at_dir(
     dir: &std::path::Path,
     device_name: &str,
     ble_id: &str,
-    timestamp: &str,
-    user_description: &str,
-    battery_level: i32,
-) -> Result<(), String> {
-    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    record: &BatteryHistoryRecord,
+) -> Result<(), CommandError> {
+    fs::create_dir_all(dir)?;
 
     let filename = safe_filename(device_name, ble_id);
     let path = dir.join(filename);
 
     let needs_header = !path.exists();
+    if !needs_header && !has_current_header(&path)? {
+        migrate_history_header(&path)?;
+    }
 
     let mut file = OpenOptions::new()
         .create(true)
         .append(true)
-        .open(&path)
-        .map_err(|e| e.to_string())?;
+        .open(&path)?;
 
     if needs_header {
-        writeln!(file, "timestamp,user_description,battery_level").map_err(|e| e.to_string())?;
+        writeln!(file, "{HISTORY_HEADER}")?;
     }
 
-    let line = csv_record_line(timestamp, user_description, battery_level)?;
-    writeln!(file, "{line}").map_err(|e| e.to_string())?;
+    let line = csv_record_line(
+        &record.timestamp,
+        &record.user_description,
+        record.battery_level,
+        record.power_state,
+        record.part_key.as_deref(),
+    )?;
+    writeln!(file, "{line}")?;
 
     Ok(())
 }
@@ -126,7 +231,7 @@ fn prune_battery_history_at_dir(
     device_name: &str,
     ble_id: &str,
     cutoff_timestamp: &str,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     let filename = safe_filename(device_name, ble_id);
     let path = dir.join(filename);
 
@@ -144,25 +249,64 @@ fn prune_battery_history_at_dir(
         return Ok(());
     }
 
+    write_battery_history_file(&path, &surviving)
+}
+
+/// Replace the history file at `path` with `records`, through a temporary file
+/// so a crash never leaves it half written.
+fn write_battery_history_file(
+    path: &std::path::Path,
+    records: &[&BatteryHistoryRecord],
+) -> Result<(), CommandError> {
     let tmp_path = path.with_extension("csv.tmp");
     {
         let mut file = OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open(&tmp_path)
-            .map_err(|e| e.to_string())?;
-        writeln!(file, "timestamp,user_description,battery_level").map_err(|e| e.to_string())?;
-        for record in &surviving {
+            .open(&tmp_path)?;
+        writeln!(file, "{HISTORY_HEADER}")?;
+        for record in records {
             let line = csv_record_line(
                 &record.timestamp,
                 &record.user_description,
                 record.battery_level,
+                record.power_state,
+                record.part_key.as_deref(),
             )?;
-            writeln!(file, "{line}").map_err(|e| e.to_string())?;
+            writeln!(file, "{line}")?;
         }
     }
-    fs::rename(&tmp_path, &path).map_err(|e| e.to_string())?;
+    fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+/// Move the history of a device to its new name and id, merging it with any
+/// history already recorded under the new ones.
+fn migrate_battery_history_at_dir(
+    dir: &std::path::Path,
+    from_name: &str,
+    from_id: &str,
+    to_name: &str,
+    to_id: &str,
+) -> Result<(), CommandError> {
+    let from_path = dir.join(safe_filename(from_name, from_id));
+    let to_path = dir.join(safe_filename(to_name, to_id));
+    if from_path == to_path || !from_path.exists() {
+        return Ok(());
+    }
+    if !to_path.exists() {
+        fs::rename(&from_path, &to_path)?;
+        return Ok(());
+    }
+
+    let mut records = read_battery_history_from_dir(dir, from_name, from_id, None)?;
+    records.extend(read_battery_history_from_dir(dir, to_name, to_id, None)?);
+    // Stable, so rows with equal timestamps keep the old file's first.
+    records.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    write_battery_history_file(&to_path, &records.iter().collect::<Vec<_>>())?;
+    fs::remove_file(&from_path)?;
 
     Ok(())
 }
@@ -191,10 +335,10 @@ fn rfc3339_date_n_days_ago(now_secs: u64, days: u64) -> String {
     format!("{y:04}-{m:02}-{d:02}T00:00:00Z")
 }
 
-fn cutoff_timestamp_n_days_ago(days: u64) -> Result<String, String> {
+fn cutoff_timestamp_n_days_ago(days: u64) -> Result<String, CommandError> {
     let now_secs = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| e.to_string())?
+        .map_err(|e| CommandError::Io(e.to_string()))?
         .as_secs();
     Ok(rfc3339_date_n_days_ago(now_secs, days))
 }
@@ -204,7 +348,7 @@ fn read_battery_history_from_dir(
     device_name: &str,
     ble_id: &str,
     since: Option<&str>,
-) -> Result<Vec<BatteryHistoryRecord>, String> {
+) -> Result<Vec<BatteryHistoryRecord>, CommandError> {
     let filename = safe_filename(device_name, ble_id);
     let path = dir.join(filename);
 
@@ -212,12 +356,14 @@ fn read_battery_history_from_dir(
         return Ok(vec![]);
     }
 
-    let mut file = fs::File::open(&path).map_err(|e| e.to_string())?;
+    let mut file = fs::File::open(&path)?;
     let mut contents = Vec::new();
-    file.read_to_end(&mut contents).map_err(|e| e.to_string())?;
+    file.read_to_end(&mut contents)?;
 
+    // Older files start with a shorter header, or with none at all.
+    let has_header = starts_with_history_header(&path, &contents)?;
     let mut rdr = ReaderBuilder::new()
-        .has_headers(true)
+        .has_headers(has_header)
         .flexible(true)
         .from_reader(std::io::Cursor::new(contents));
 
@@ -227,7 +373,7 @@ fn read_battery_history_from_dir(
             Ok(r) => r,
             Err(_) => continue,
         };
-        if rec.len() != 3 {
+        if !is_history_row_width(rec.len()) {
             continue;
         }
         let timestamp = rec.get(0).unwrap_or("").to_string();
@@ -242,6 +388,8 @@ fn read_battery_history_from_dir(
             timestamp,
             user_description: rec.get(1).unwrap_or("").to_string(),
             battery_level,
+            power_state: PowerState::parse(rec.get(3).unwrap_or("")),
+            part_key: parse_part_key(rec.get(4)),
         });
     }
     Ok(out)
@@ -293,9 +441,17 @@ pub struct BatteryHistoryRecord {
     pub timestamp: String,
     pub user_description: String,
     pub battery_level: i32,
+    #[serde(default)]
+    pub power_state: PowerState,
+    /// The `BatteryInfo::part_key` of the reading; None for rows recorded
+    /// before parts had keys.
+    #[serde(default)]
+    pub part_key: Option<String>,
 }
 
 /// Append battery history to CSV
+// The frontend passes every column as its own argument.
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
 pub fn append_battery_history(
     app: tauri::AppHandle,
@@ -304,7 +460,9 @@ pub fn append_battery_history(
     timestamp: String,
     user_description: String,
     battery_level: i32,
-) -> Result<(), String> {
+    power_state: Option<PowerState>,
+    part_key: Option<String>,
+) -> Result<(), CommandError> {
     let _guard = HISTORY_FILE_LOCK
         .lock()
         .unwrap_or_else(|p| p.into_inner());
@@ -316,7 +474,7 @@ pub fn append_battery_history(
     {
         let today_epoch_day = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| e.to_string())?
+            .map_err(|e| CommandError::Io(e.to_string()))?
             .as_secs()
             / 86400;
         let mut pruned = PRUNED_FILES
@@ -328,14 +486,31 @@ pub fn append_battery_history(
         }
     }
 
-    append_battery_history_at_dir(
-        &dir,
-        &device_name,
-        &ble_id,
-        &timestamp,
-        &user_description,
+    let record = BatteryHistoryRecord {
+        timestamp,
+        user_description,
         battery_level,
-    )
+        power_state: power_state.unwrap_or_default(),
+        part_key,
+    };
+    append_battery_history_at_dir(&dir, &device_name, &ble_id, &record)
+}
+
+/// Move battery history recorded for `from_id` to `to_id` after the device was
+/// linked to its new id.
+pub(crate) fn migrate_battery_history(
+    app: &tauri::AppHandle,
+    from_name: &str,
+    from_id: &str,
+    to_name: &str,
+    to_id: &str,
+) -> Result<(), CommandError> {
+    let _guard = HISTORY_FILE_LOCK
+        .lock()
+        .unwrap_or_else(|p| p.into_inner());
+
+    let dir = history_dir(app);
+    migrate_battery_history_at_dir(&dir, from_name, from_id, to_name, to_id)
 }
 
 /// Read battery history, optionally limited to records at or after `since` (RFC3339 UTC)
@@ -345,7 +520,7 @@ pub fn read_battery_history(
     device_name: String,
     ble_id: String,
     since: Option<String>,
-) -> Result<Vec<BatteryHistoryRecord>, String> {
+) -> Result<Vec<BatteryHistoryRecord>, CommandError> {
     let _guard = HISTORY_FILE_LOCK
         .lock()
         .unwrap_or_else(|p| p.into_inner());
@@ -359,6 +534,16 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
+    fn record(timestamp: &str, user_description: &str, battery_level: i32) -> BatteryHistoryRecord {
+        BatteryHistoryRecord {
+            timestamp: timestamp.to_string(),
+            user_description: user_description.to_string(),
+            battery_level,
+            power_state: PowerState::Unknown,
+            part_key: None,
+        }
+    }
+
     #[test]
     fn safe_filename_sanitizes_non_filename_chars() {
         let filename = safe_filename("My Keyboard / Main", "AA:BB:CC");
@@ -367,7 +552,7 @@ mod tests {
 
     #[test]
     fn csv_record_line_and_parser_roundtrip() {
-        let line = csv_record_line("2026-03-19T12:34:56Z", "desk", 87).expect("serialize row");
+        let line = csv_record_line("2026-03-19T12:34:56Z", "desk", 87, PowerState::Unknown, None).expect("serialize row");
         let parsed = parse_history_record_line(&line).expect("expected valid line");
         assert_eq!(parsed.timestamp, "2026-03-19T12:34:56Z");
         assert_eq!(parsed.user_description, "desk");
@@ -377,7 +562,7 @@ mod tests {
     #[test]
     fn csv_roundtrip_escapes_commas_and_quotes_in_text_fields() {
         let desc = "Left, \"quoted\" side";
-        let line = csv_record_line("2026-03-19T12:34:56Z", desc, 42).expect("serialize row");
+        let line = csv_record_line("2026-03-19T12:34:56Z", desc, 42, PowerState::Unknown, None).expect("serialize row");
         let parsed = parse_history_record_line(&line).expect("parse row");
         assert_eq!(parsed.timestamp, "2026-03-19T12:34:56Z");
         assert_eq!(parsed.user_description, desc);
@@ -402,6 +587,89 @@ mod tests {
         assert_eq!(records[1].battery_level, 75);
     }
 
+    #[test]
+    fn append_battery_history_at_dir_adds_a_missing_header() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join(safe_filename("Keyboard", "dev-1"));
+        fs::write(&path, "2026-03-19T00:00:00Z,office,90\n").expect("write csv");
+
+        append_battery_history_at_dir(
+            dir.path(),
+            "Keyboard",
+            "dev-1",
+            &BatteryHistoryRecord {
+                power_state: PowerState::Discharging,
+                ..record("2026-03-19T01:00:00Z", "office", 85)
+            },
+        )
+        .expect("append should succeed");
+
+        let content = fs::read_to_string(&path).expect("read csv file");
+        let old_part = format!("{HISTORY_HEADER}\n2026-03-19T00:00:00Z,office,90\n");
+        assert!(content.starts_with(&old_part));
+    }
+
+    #[test]
+    fn read_battery_history_accepts_file_without_history_header() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join(safe_filename("Keyboard", "dev-1"));
+        fs::write(&path, "2026-03-19T00:00:00Z,office,90\n").expect("write csv");
+
+        let records = read_battery_history_from_dir(dir.path(), "Keyboard", "dev-1", None)
+            .expect("read headerless file");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].battery_level, 90);
+    }
+
+    #[test]
+    fn read_battery_history_rejects_file_without_history_header() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join(safe_filename("Keyboard", "dev-1"));
+        fs::write(&path, "name,level\nCorne,high\n").expect("write csv");
+
+        let error = read_battery_history_from_dir(dir.path(), "Keyboard", "dev-1", None)
+            .expect_err("file of another kind");
+        assert_eq!(error.code(), "malformed_history");
+        assert!(!error.retryable());
+    }
+
+    #[test]
+    fn append_battery_history_at_dir_migrates_an_older_header() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join(safe_filename("Keyboard", "dev-1"));
+        let rows = concat!(
+            "2026-03-19T00:00:00Z,office,90\n",
+            "malformed-row-without-commas\n",
+        );
+        fs::write(
+            &path,
+            format!("timestamp,user_description,battery_level\n{rows}"),
+        )
+        .expect("write csv");
+
+        append_battery_history_at_dir(
+            dir.path(),
+            "Keyboard",
+            "dev-1",
+            &BatteryHistoryRecord {
+                power_state: PowerState::Discharging,
+                part_key: Some("central".to_string()),
+                ..record("2026-03-19T01:00:00Z", "office", 85)
+            },
+        )
+        .expect("append should succeed");
+
+        // Only the header changes; every old row is kept as it was.
+        let content = fs::read_to_string(&path).expect("read csv file");
+        let old_part = format!("{HISTORY_HEADER}\n{rows}");
+        assert!(content.starts_with(&old_part));
+        let records = read_battery_history_from_dir(dir.path(), "Keyboard", "dev-1", None)
+            .expect("read should succeed");
+        let levels: Vec<i32> = records.iter().map(|r| r.battery_level).collect();
+        assert_eq!(levels, [90, 85]);
+        assert_eq!(records[1].part_key.as_deref(), Some("central"));
+    }
+
     #[test]
     fn read_battery_history_since_filters_older_rows() {
         let dir = tempdir().expect("create temp dir");
@@ -437,9 +705,7 @@ mod tests {
             dir.path(),
             "Keyboard",
             "dev-1",
-            "2026-03-19T12:00:00Z",
-            desc,
-            55,
+            &record("2026-03-19T12:00:00Z", desc, 55),
         )
         .expect("append");
         let records = read_battery_history_from_dir(dir.path(), "Keyboard", "dev-1", None).expect("read");
@@ -471,18 +737,14 @@ mod tests {
             dir.path(),
             "Keyboard",
             "dev-1",
-            "2026-03-19T12:34:56Z",
-            "Central",
-            88,
+            &record("2026-03-19T12:34:56Z", "Central", 88),
         )
         .expect("append should succeed");
         append_battery_history_at_dir(
             dir.path(),
             "Keyboard",
             "dev-1",
-            "2026-03-19T13:34:56Z",
-            "Left",
-            77,
+            &record("2026-03-19T13:34:56Z", "Left", 77),
         )
         .expect("append should succeed");
 
@@ -503,18 +765,14 @@ mod tests {
             dir.path(),
             "Keyboard",
             "dev-1",
-            "2026-03-19T12:34:56Z",
-            "Central",
-            88,
+            &record("2026-03-19T12:34:56Z", "Central", 88),
         )
         .expect("append should succeed");
         append_battery_history_at_dir(
             dir.path(),
             "Keyboard",
             "dev-1",
-            "2026-03-19T13:34:56Z",
-            "Central",
-            80,
+            &record("2026-03-19T13:34:56Z", "Central", 80),
         )
         .expect("append should succeed");
 
@@ -522,7 +780,7 @@ mod tests {
         let content = fs::read_to_string(path).expect("read csv file");
         let header_count = content
             .lines()
-            .filter(|line| *line == "timestamp,user_description,battery_level")
+            .filter(|line| *line == HISTORY_HEADER)
             .count();
         assert_eq!(header_count, 1);
     }
@@ -564,11 +822,11 @@ mod tests {
     #[test]
     fn prune_drops_rows_older_than_cutoff() {
         let dir = tempdir().expect("create temp dir");
-        append_battery_history_at_dir(dir.path(), "Kb", "d1", "2024-01-01T00:00:00Z", "old1", 90)
+        append_battery_history_at_dir(dir.path(), "Kb", "d1", &record("2024-01-01T00:00:00Z", "old1", 90))
             .expect("append");
-        append_battery_history_at_dir(dir.path(), "Kb", "d1", "2025-01-01T00:00:00Z", "mid", 80)
+        append_battery_history_at_dir(dir.path(), "Kb", "d1", &record("2025-01-01T00:00:00Z", "mid", 80))
             .expect("append");
-        append_battery_history_at_dir(dir.path(), "Kb", "d1", "2026-01-01T00:00:00Z", "new", 70)
+        append_battery_history_at_dir(dir.path(), "Kb", "d1", &record("2026-01-01T00:00:00Z", "new", 70))
             .expect("append");
 
         prune_battery_history_at_dir(dir.path(), "Kb", "d1", "2025-06-01T00:00:00Z")
@@ -583,7 +841,7 @@ mod tests {
         let content = fs::read_to_string(path).expect("read csv");
         let header_count = content
             .lines()
-            .filter(|l| *l == "timestamp,user_description,battery_level")
+            .filter(|l| *l == HISTORY_HEADER)
             .count();
         assert_eq!(header_count, 1);
     }
@@ -591,9 +849,9 @@ mod tests {
     #[test]
     fn prune_is_noop_when_nothing_expires() {
         let dir = tempdir().expect("create temp dir");
-        append_battery_history_at_dir(dir.path(), "Kb", "d1", "2026-01-01T00:00:00Z", "a", 90)
+        append_battery_history_at_dir(dir.path(), "Kb", "d1", &record("2026-01-01T00:00:00Z", "a", 90))
             .expect("append");
-        append_battery_history_at_dir(dir.path(), "Kb", "d1", "2026-06-01T00:00:00Z", "b", 80)
+        append_battery_history_at_dir(dir.path(), "Kb", "d1", &record("2026-06-01T00:00:00Z", "b", 80))
             .expect("append");
 
         let path = dir.path().join(safe_filename("Kb", "d1"));
@@ -617,9 +875,9 @@ mod tests {
     fn prune_preserves_quoted_fields() {
         let dir = tempdir().expect("create temp dir");
         let desc = "Left, \"quoted\" side";
-        append_battery_history_at_dir(dir.path(), "Kb", "d1", "2026-01-01T00:00:00Z", desc, 55)
+        append_battery_history_at_dir(dir.path(), "Kb", "d1", &record("2026-01-01T00:00:00Z", desc, 55))
             .expect("append");
-        append_battery_history_at_dir(dir.path(), "Kb", "d1", "2024-01-01T00:00:00Z", "old", 10)
+        append_battery_history_at_dir(dir.path(), "Kb", "d1", &record("2024-01-01T00:00:00Z", "old", 10))
             .expect("append");
 
         prune_battery_history_at_dir(dir.path(), "Kb", "d1", "2025-06-01T00:00:00Z")
@@ -634,15 +892,15 @@ mod tests {
     #[test]
     fn prune_then_append_roundtrip() {
         let dir = tempdir().expect("create temp dir");
-        append_battery_history_at_dir(dir.path(), "Kb", "d1", "2024-01-01T00:00:00Z", "old", 90)
+        append_battery_history_at_dir(dir.path(), "Kb", "d1", &record("2024-01-01T00:00:00Z", "old", 90))
             .expect("append");
-        append_battery_history_at_dir(dir.path(), "Kb", "d1", "2026-01-01T00:00:00Z", "keep", 80)
+        append_battery_history_at_dir(dir.path(), "Kb", "d1", &record("2026-01-01T00:00:00Z", "keep", 80))
             .expect("append");
 
         prune_battery_history_at_dir(dir.path(), "Kb", "d1", "2025-06-01T00:00:00Z")
             .expect("prune");
 
-        append_battery_history_at_dir(dir.path(), "Kb", "d1", "2026-06-01T00:00:00Z", "new", 70)
+        append_battery_history_at_dir(dir.path(), "Kb", "d1", &record("2026-06-01T00:00:00Z", "new", 70))
             .expect("append");
 
         let records = read_battery_history_from_dir(dir.path(), "Kb", "d1", None).expect("read");
@@ -653,6 +911,54 @@ mod tests {
         assert_eq!(records[1].user_description, "new");
     }
 
+    #[test]
+    fn migrate_renames_history_to_new_id() {
+        let dir = tempdir().expect("create temp dir");
+        append_battery_history_at_dir(dir.path(), "Kb", "old", &record("2026-01-01T00:00:00Z", "a", 90))
+            .expect("append");
+
+        migrate_battery_history_at_dir(dir.path(), "Kb", "old", "Kb", "new").expect("migrate");
+
+        assert!(!dir.path().join(safe_filename("Kb", "old")).exists());
+        let records = read_battery_history_from_dir(dir.path(), "Kb", "new", None).expect("read");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].battery_level, 90);
+    }
+
+    #[test]
+    fn migrate_merges_with_history_under_new_id() {
+        let dir = tempdir().expect("create temp dir");
+        append_battery_history_at_dir(dir.path(), "Kb", "old", &record("2026-01-01T00:00:00Z", "a", 90))
+            .expect("append");
+        append_battery_history_at_dir(dir.path(), "Kb", "old", &record("2026-01-03T00:00:00Z", "a", 70))
+            .expect("append");
+        append_battery_history_at_dir(
+            dir.path(),
+            "Kb v2",
+            "new",
+            &BatteryHistoryRecord {
+                power_state: PowerState::Charging,
+                ..record("2026-01-02T00:00:00Z", "a", 80)
+            },
+        )
+        .expect("append");
+
+        migrate_battery_history_at_dir(dir.path(), "Kb", "old", "Kb v2", "new").expect("migrate");
+
+        assert!(!dir.path().join(safe_filename("Kb", "old")).exists());
+        let records = read_battery_history_from_dir(dir.path(), "Kb v2", "new", None).expect("read");
+        let levels: Vec<i32> = records.iter().map(|r| r.battery_level).collect();
+        assert_eq!(levels, vec![90, 80, 70]);
+        assert_eq!(records[1].power_state, PowerState::Charging);
+    }
+
+    #[test]
+    fn migrate_without_old_history_is_noop() {
+        let dir = tempdir().expect("create temp dir");
+        migrate_battery_history_at_dir(dir.path(), "Kb", "old", "Kb", "new").expect("migrate");
+        assert!(!dir.path().join(safe_filename("Kb", "new")).exists());
+    }
+
     #[test]
     fn should_prune_today_first_sighting() {
         let mut last_pruned = HashMap::new();
@@ -694,4 +1000,86 @@ mod tests {
         assert!(rfc3339_date_n_days_ago(epoch_2026_06_13, 365).starts_with("2025-06-13"));
         assert!(rfc3339_date_n_days_ago(epoch_2026_06_13, 1).starts_with("2026-06-12"));
     }
+
+    #[test]
+    fn power_state_roundtrips_through_append_and_read() {
+        let dir = tempdir().expect("create temp dir");
+        append_battery_history_at_dir(
+            dir.path(),
+            "Kb",
+            "d1",
+            &BatteryHistoryRecord {
+                power_state: PowerState::Charging,
+                ..record("2026-01-01T00:00:00Z", "Central", 40)
+            },
+        )
+        .expect("append");
+
+        let records = read_battery_history_from_dir(dir.path(), "Kb", "d1", None).expect("read");
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].power_state, PowerState::Charging);
+    }
+
+    #[test]
+    fn legacy_three_column_rows_read_as_unknown_and_prune_migrates_header() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join(safe_filename("Kb", "d1"));
+        let csv = concat!(
+            "timestamp,user_description,battery_level\n",
+            "2024-01-01T00:00:00Z,Central,90\n",
+            "2026-01-01T00:00:00Z,Central,80\n",
+        );
+        fs::write(&path, csv).expect("write csv");
+        append_battery_history_at_dir(
+            dir.path(),
+            "Kb",
+            "d1",
+            &BatteryHistoryRecord {
+                power_state: PowerState::Discharging,
+                ..record("2026-02-01T00:00:00Z", "Central", 75)
+            },
+        )
+        .expect("append");
+
+        let records = read_battery_history_from_dir(dir.path(), "Kb", "d1", None).expect("read");
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].power_state, PowerState::Unknown);
+        assert_eq!(records[2].power_state, PowerState::Discharging);
+
+        prune_battery_history_at_dir(dir.path(), "Kb", "d1", "2025-01-01T00:00:00Z")
+            .expect("prune");
+        let content = fs::read_to_string(&path).expect("read csv");
+        assert_eq!(content.lines().next(), Some(HISTORY_HEADER));
+        let records = read_battery_history_from_dir(dir.path(), "Kb", "d1", None).expect("read");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].power_state, PowerState::Discharging);
+    }
+
+    #[test]
+    fn part_key_roundtrips_and_four_column_rows_have_none() {
+        let dir = tempdir().expect("create temp dir");
+        let path = dir.path().join(safe_filename("Kb", "d1"));
+        let csv = concat!(
+            "timestamp,user_description,battery_level,power_state\n",
+            "2026-01-01T00:00:00Z,Left,80,discharging\n",
+        );
+        fs::write(&path, csv).expect("write csv");
+        append_battery_history_at_dir(
+            dir.path(),
+            "Kb",
+            "d1",
+            &BatteryHistoryRecord {
+                power_state: PowerState::Discharging,
+                part_key: Some("0:1".to_string()),
+                ..record("2026-02-01T00:00:00Z", "Left half", 75)
+            },
+        )
+        .expect("append");
+
+        let records = read_battery_history_from_dir(dir.path(), "Kb", "d1", None).expect("read");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].part_key, None);
+        assert_eq!(records[1].part_key.as_deref(), Some("0:1"));
+        assert_eq!(records[1].user_description, "Left half");
+    }
 }
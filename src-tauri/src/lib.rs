@@ -3,9 +3,33 @@ use tauri::Manager;
 use tauri_plugin_autostart::MacosLauncher;
 
 mod ble;
+mod ble_batch;
+#[cfg(target_os = "linux")]
+mod ble_bluez;
+mod ble_coalesce;
+mod ble_demo;
+mod ble_device_info;
+mod ble_identity;
+mod ble_inspector;
+mod ble_polling;
+mod ble_power_state;
+mod ble_presence;
+mod ble_presentation;
+mod ble_reading;
+mod ble_reconnect;
+mod ble_scheduler;
+mod ble_simulated;
+mod ble_timeout;
+mod ble_trace;
+mod ble_transport;
+mod ble_watchdog;
+#[cfg(target_os = "linux")]
+mod bluez_battery;
 mod common;
+mod error;
 mod history;
 mod licenses;
+mod power_supply_battery;
 mod storage;
 mod tray;
 mod tray_battery_payload;
@@ -75,9 +99,27 @@ pub fn run() {
             common::exit_app,
             ble::list_battery_devices,
             ble::get_battery_info,
+            ble::get_battery_info_many,
+            ble::get_device_metadata,
+            ble::get_device_fingerprint,
+            ble::find_device_identity_matches,
+            ble::link_device_identity,
+            ble::inspect_gatt_device,
             ble::start_battery_notification_monitor,
             ble::stop_battery_notification_monitor,
             ble::stop_all_battery_monitors,
+            ble::scan_battery_devices,
+            ble::stop_battery_device_scan,
+            ble::set_battery_source,
+            ble::list_hid_batteries,
+            ble::set_monitor_options,
+            ble::set_reconnect_policy,
+            ble::list_bluetooth_adapters,
+            ble::select_bluetooth_adapter,
+            ble_polling::set_battery_polling,
+            ble_polling::stop_all_battery_polling,
+            ble_scheduler::get_gatt_scheduler_metrics,
+            ble_timeout::set_operation_timeouts,
             window::get_windows_text_scale_factor,
             licenses::get_licenses,
             storage::get_dev_store_path,
@@ -93,6 +135,16 @@ pub fn run() {
                 tray_handle: std::sync::Mutex::new(None),
             });
 
+            // The recorder wraps whichever transport is installed next.
+            if let Some(path) = ble_trace::record_trace_path() {
+                ble_trace::start_recording(&path)?;
+            }
+            if let Some(path) = ble_trace::replay_trace_path() {
+                ble_trace::start_replay(&path)?;
+            } else if let Some(path) = ble_demo::demo_devices_path() {
+                ble_demo::start(&path)?;
+            }
+
             tray::init_tray(app.handle().clone());
 
             #[cfg(target_os = "macos")]
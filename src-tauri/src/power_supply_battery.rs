@@ -0,0 +1,269 @@
+//! Battery levels of HID devices from the kernel's power_supply class.
+//!
+//! The HID core registers one `hid-<uniq>-battery` supply per device that
+//! reports a battery in its HID descriptor; for Bluetooth LE keyboards `uniq`
+//! is the device address. UPower republishes these same nodes, so reading
+//! sysfs directly covers both without a D-Bus dependency. This still works
+//! when GATT is unavailable to the app, e.g. behind a ZMK dongle or when
+//! another process holds the Battery Service.
+//!
+//! sysfs has no change notification for `capacity`, so watching polls.
+
+use crate::ble_power_state::PowerState;
+use crate::error::CommandError;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tokio::sync::watch;
+use tokio::time::{sleep, Duration};
+
+const DEFAULT_POWER_SUPPLY_ROOT: &str = "/sys/class/power_supply";
+#[cfg(debug_assertions)]
+const POWER_SUPPLY_ROOT_ENV: &str = "ZMK_BATTERY_CENTER_POWER_SUPPLY_DIR";
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct HidBattery {
+    /// Upper-case device address, matching the ids bluest reports on Linux.
+    pub address: String,
+    pub model_name: Option<String>,
+    pub battery_level: Option<u8>,
+    pub power_state: PowerState,
+}
+
+/// The power_supply class directory; debug builds can point it at a fake tree.
+pub(crate) fn power_supply_root() -> PathBuf {
+    #[cfg(debug_assertions)]
+    if let Ok(dir) = std::env::var(POWER_SUPPLY_ROOT_ENV) {
+        return PathBuf::from(dir);
+    }
+    PathBuf::from(DEFAULT_POWER_SUPPLY_ROOT)
+}
+
+/// `hid-aa:bb:cc:dd:ee:ff-battery` -> `AA:BB:CC:DD:EE:FF`. Supplies whose uniq
+/// is not an address (USB receivers without a serial) are skipped.
+fn address_from_supply_name(name: &str) -> Option<String> {
+    let uniq = name.strip_prefix("hid-")?.strip_suffix("-battery")?;
+    let is_address = uniq.len() == 17
+        && uniq.split(':').count() == 6
+        && uniq
+            .split(':')
+            .all(|octet| octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()));
+    is_address.then(|| uniq.to_ascii_uppercase())
+}
+
+fn read_attribute(dir: &Path, attribute: &str) -> Option<String> {
+    let value = fs::read_to_string(dir.join(attribute)).ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Kernel `status` strings; "Full" and "Not charging" mean it sits on external
+/// power without charging.
+fn power_state_from_status(status: Option<&str>) -> PowerState {
+    match status {
+        Some("Charging") => PowerState::Charging,
+        Some("Discharging") => PowerState::Discharging,
+        Some("Full") | Some("Not charging") => PowerState::ExternalPower,
+        _ => PowerState::Unknown,
+    }
+}
+
+fn read_supply(dir: &Path, address: String) -> HidBattery {
+    HidBattery {
+        address,
+        model_name: read_attribute(dir, "model_name"),
+        battery_level: read_attribute(dir, "capacity")
+            .and_then(|capacity| capacity.parse::<u8>().ok())
+            .filter(|level| *level <= 100),
+        power_state: power_state_from_status(read_attribute(dir, "status").as_deref()),
+    }
+}
+
+/// Every HID battery under `root`, sorted by address.
+pub(crate) fn enumerate(root: &Path) -> Vec<HidBattery> {
+    let Ok(entries) = fs::read_dir(root) else {
+        log::debug!("power_supply: cannot read {}", root.display());
+        return Vec::new();
+    };
+    let mut batteries: Vec<HidBattery> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let address = address_from_supply_name(&entry.file_name().to_string_lossy())?;
+            Some(read_supply(&entry.path(), address))
+        })
+        .collect();
+    batteries.sort_by(|a, b| a.address.cmp(&b.address));
+    batteries
+}
+
+/// The kernel removes the supply on disconnect, so a missing one is reported
+/// as not connected.
+pub(crate) fn read_battery(root: &Path, address: &str) -> Result<HidBattery, CommandError> {
+    enumerate(root)
+        .into_iter()
+        .find(|battery| battery.address.eq_ignore_ascii_case(address))
+        .ok_or_else(|| {
+            CommandError::DeviceNotConnected(
+                "No kernel power_supply battery for this device".to_string(),
+            )
+        })
+}
+
+/// Poll the device's supply every `interval` until stopped, reporting it
+/// through `on_change` when the level or power state changes and `None` when
+/// the supply disappears (the kernel removes it on disconnect).
+pub(crate) async fn watch_battery(
+    root: &Path,
+    address: &str,
+    interval: Duration,
+    mut stop_rx: watch::Receiver<bool>,
+    mut on_change: impl FnMut(Option<&HidBattery>),
+) {
+    log::debug!("power_supply: battery watcher started device_id={address}");
+    let mut last: Option<HidBattery> = None;
+    loop {
+        let current = read_battery(root, address).ok();
+        if current != last {
+            log::debug!("power_supply: battery changed device_id={address} battery={current:?}");
+            on_change(current.as_ref());
+            last = current;
+        }
+
+        tokio::select! {
+            _ = sleep(interval) => {}
+            changed = stop_rx.changed() => {
+                if changed.is_err() || *stop_rx.borrow() {
+                    log::debug!("power_supply: battery watcher stopped device_id={address}");
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use tokio::sync::mpsc;
+
+    fn write_supply(root: &Path, name: &str, attributes: &[(&str, &str)]) {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).expect("create supply dir");
+        for (attribute, value) in attributes {
+            fs::write(dir.join(attribute), format!("{value}\n")).expect("write attribute");
+        }
+    }
+
+    #[test]
+    fn supply_names_map_to_addresses() {
+        assert_eq!(
+            address_from_supply_name("hid-aa:bb:cc:dd:ee:0f-battery"),
+            Some("AA:BB:CC:DD:EE:0F".to_string())
+        );
+        assert_eq!(
+            address_from_supply_name("hid-0003:046D:C52B.0001-battery"),
+            None
+        );
+        assert_eq!(address_from_supply_name("BAT0"), None);
+        assert_eq!(address_from_supply_name("hid--battery"), None);
+    }
+
+    #[test]
+    fn enumerate_reads_hid_batteries_only() {
+        let root = tempdir().expect("create temp dir");
+        write_supply(
+            root.path(),
+            "hid-aa:bb:cc:dd:ee:ff-battery",
+            &[
+                ("capacity", "64"),
+                ("status", "Discharging"),
+                ("model_name", "Corne"),
+            ],
+        );
+        write_supply(
+            root.path(),
+            "hid-11:22:33:44:55:66-battery",
+            &[("capacity", "100"), ("status", "Full")],
+        );
+        write_supply(root.path(), "BAT0", &[("capacity", "50")]);
+
+        let batteries = enumerate(root.path());
+
+        assert_eq!(
+            batteries,
+            vec![
+                HidBattery {
+                    address: "11:22:33:44:55:66".to_string(),
+                    model_name: None,
+                    battery_level: Some(100),
+                    power_state: PowerState::ExternalPower,
+                },
+                HidBattery {
+                    address: "AA:BB:CC:DD:EE:FF".to_string(),
+                    model_name: Some("Corne".to_string()),
+                    battery_level: Some(64),
+                    power_state: PowerState::Discharging,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unreadable_or_invalid_capacity_is_none() {
+        let root = tempdir().expect("create temp dir");
+        write_supply(
+            root.path(),
+            "hid-aa:bb:cc:dd:ee:ff-battery",
+            &[("capacity", "250"), ("status", "Charging")],
+        );
+
+        let battery = read_battery(root.path(), "aa:bb:cc:dd:ee:ff").expect("battery");
+        assert_eq!(battery.battery_level, None);
+        assert_eq!(battery.power_state, PowerState::Charging);
+        assert_eq!(
+            read_battery(root.path(), "11:22:33:44:55:66")
+                .unwrap_err()
+                .code(),
+            "device_not_connected"
+        );
+        assert!(enumerate(&root.path().join("missing")).is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn watch_reports_changes_and_removal() {
+        let root = tempdir().expect("create temp dir");
+        let supply = "hid-aa:bb:cc:dd:ee:ff-battery";
+        write_supply(root.path(), supply, &[("capacity", "80")]);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let root_path = root.path().to_path_buf();
+        let watcher = tokio::spawn(async move {
+            watch_battery(
+                &root_path,
+                "AA:BB:CC:DD:EE:FF",
+                Duration::from_secs(30),
+                stop_rx,
+                |battery| {
+                    let _ = tx.send(battery.and_then(|b| b.battery_level));
+                },
+            )
+            .await;
+        });
+
+        assert_eq!(rx.recv().await, Some(Some(80)));
+
+        // Unchanged polls report nothing; the next change is picked up.
+        sleep(Duration::from_secs(31)).await;
+        assert!(rx.try_recv().is_err());
+        write_supply(root.path(), supply, &[("capacity", "79")]);
+        assert_eq!(rx.recv().await, Some(Some(79)));
+
+        fs::remove_dir_all(root.path().join(supply)).expect("remove supply");
+        assert_eq!(rx.recv().await, Some(None));
+
+        stop_tx.send(true).unwrap();
+        watcher.await.expect("watcher panicked");
+    }
+}
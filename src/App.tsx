@@ -4,7 +4,12 @@ import {
 	getBatteryInfo,
 	startBatteryNotificationMonitor,
 	stopBatteryNotificationMonitor,
+	getDeviceFingerprint,
+	findDeviceIdentityMatches,
+	linkDeviceIdentity,
+	inspectGattDevice,
 	BleDeviceInfo,
+	IdentityMatch,
 	BatteryInfoNotificationEvent,
 	BatteryMonitorStatusEvent,
 } from "./utils/ble";
@@ -28,14 +33,16 @@ import { sendNotification } from "./utils/notification";
 import { FETCH_INTERVAL_AUTO, NotificationType } from "./utils/config";
 import { notifyBatteryEdgeTransitions } from "./utils/batteryEdgeNotification";
 import { fireAndForget, withTimeout } from "./utils/common";
+import { commandErrorGuidance, errorMessage } from "./utils/commandError";
 import { platform } from "@tauri-apps/plugin-os";
 import { useWindowEvents } from "@/hooks/useWindowEvents";
 import { useTrayEvents } from "@/hooks/useTrayEvents";
 import { emit, listen } from '@tauri-apps/api/event';
 import { recordBatteryReadings } from '@/utils/batteryHistory';
 import {
-	upsertBatteryInfo,
+	upsertBatteryInfos,
 	getRegisteredDeviceDisplayName,
+	relinkRegisteredDevice,
 	type RegisteredDevice,
 } from "@/utils/appHelpers";
 import { syncTrayBatteryIcon } from "@/utils/trayBatteryIcon";
@@ -71,6 +78,7 @@ function App() {
 	} = useRegisteredDevices();
 
 	const [devices, setDevices] = useState<BleDeviceInfo[]>([]);
+	const [identityMatches, setIdentityMatches] = useState<IdentityMatch[]>([]);
 	const [error, setError] = useState("");
 	const { config, isConfigLoaded } = useConfigContext();
 
@@ -136,6 +144,18 @@ function App() {
 		return () => clearTimeout(id);
 	}, [registeredDevices, config.trayIconComponents, isConfigLoaded]);
 
+	// Fingerprinting connects to each unregistered device, so matches arrive
+	// after the list is shown.
+	async function refreshIdentityMatches() {
+		const registered = registeredDevicesRef.current.flatMap(d =>
+			d.fingerprint ? [{ id: d.id, fingerprint: d.fingerprint }] : []
+		);
+		if (registered.length === 0) {
+			return;
+		}
+		setIdentityMatches(await findDeviceIdentityMatches(registered));
+	}
+
 	async function fetchDevices() {
 		setState(State.fetchingDevices);
 		setError("");
@@ -156,10 +176,15 @@ function App() {
 				createTimeoutError,
 			);
 			setDevices(result);
+			setIdentityMatches([]);
 			setState(State.addDeviceModal);
+			fireAndForget(refreshIdentityMatches(), "Failed to look for re-paired devices");
 		} catch (e: unknown) {
-			let msg = e instanceof Error ? e.message : String(e);
-			if (isMac && !msg.includes("Bluetooth permission")) {
+			let msg = errorMessage(e);
+			const guidance = commandErrorGuidance(e);
+			if (guidance) {
+				msg = `${msg}. ${guidance}`;
+			} else if (isMac && !msg.includes("Bluetooth permission")) {
 				msg += " If you are using macOS, please make sure Bluetooth permission is granted.";
 			}
 			setError(msg);
@@ -199,7 +224,8 @@ function App() {
 			commitRegisteredDevices(prev => [...prev, newDevice]);
 			handleCloseModal();
 		} catch (e: unknown) {
-			const msg = e instanceof Error ? e.message : String(e);
+			const guidance = commandErrorGuidance(e);
+			const msg = guidance ? `${errorMessage(e)}. ${guidance}` : errorMessage(e);
 			setError(`Failed to add device: ${msg}`);
 			setState(State.addDeviceModal);
 		}
@@ -210,6 +236,7 @@ function App() {
 		isConfigLoaded,
 		isDeviceLoaded,
 		fetchInterval: config.fetchInterval,
+		registeredDeviceIdsKey,
 		registeredDevicesRef,
 		commitRegisteredDevices,
 		pushNotification: config.pushNotification,
@@ -229,6 +256,48 @@ function App() {
 		commitRegisteredDevices,
 	});
 
+	const recordFingerprint = useCallback(async (id: string) => {
+		const fingerprint = await getDeviceFingerprint(id);
+		commitRegisteredDevices(prev => prev.map(d => d.id === id ? { ...d, fingerprint } : d));
+	}, [commitRegisteredDevices]);
+
+	// Fingerprint each registered device once it is seen connected, so it can
+	// be recognized if re-pairing gives it a new id.
+	const fingerprintRequestedRef = useRef<Set<string>>(new Set());
+	useEffect(() => {
+		for (const device of deviceList) {
+			if (device.fingerprint || device.isDisconnected || fingerprintRequestedRef.current.has(device.id)) {
+				continue;
+			}
+			fingerprintRequestedRef.current.add(device.id);
+			fireAndForget(recordFingerprint(device.id), `Failed to fingerprint device ${device.id}`);
+		}
+	}, [deviceList, recordFingerprint]);
+
+	const handleLinkDevice = async (device: BleDeviceInfo, registered: RegisteredDevice) => {
+		setState(State.fetchingBatteryInfo);
+		setError("");
+		try {
+			const info = await linkDeviceIdentity(registered, device);
+			if (info !== null) {
+				// The backend moved the running monitor; keep the sync from restarting it.
+				activeNotificationMonitorsRef.current.delete(registered.id);
+				activeNotificationMonitorsRef.current.add(device.id);
+			}
+			commitRegisteredDevices(prev => prev.map(d => d.id === registered.id
+				? relinkRegisteredDevice(d, device, info)
+				: d
+			));
+			fireAndForget(recordFingerprint(device.id), `Failed to fingerprint device ${device.id}`);
+			handleCloseModal();
+		} catch (e: unknown) {
+			const guidance = commandErrorGuidance(e);
+			const msg = guidance ? `${errorMessage(e)}. ${guidance}` : errorMessage(e);
+			setError(`Failed to link device: ${msg}`);
+			setState(State.addDeviceModal);
+		}
+	};
+
 	const handleCloseModal = () => {
 		setState(State.main);
 		setError("");
@@ -250,12 +319,19 @@ function App() {
 		try {
 			await stopBatteryNotificationMonitor(device.id);
 		} catch (e) {
-			logger.warn(`Failed to stop notification monitor for ${device.id}: ${String(e)}`);
+			logger.warn(`Failed to stop notification monitor for ${device.id}: ${errorMessage(e)}`);
 		} finally {
 			activeNotificationMonitorsRef.current.delete(device.id);
 		}
 	}, [isNotificationMonitorMode, commitRegisteredDevices, activeNotificationMonitorsRef]);
 
+	const handleCopyGattDump = useCallback((device: RegisteredDevice) => {
+		fireAndForget((async () => {
+			const inspection = await inspectGattDevice(device.id);
+			await navigator.clipboard.writeText(JSON.stringify(inspection, null, 2));
+		})(), `Failed to copy GATT dump for ${device.id}`);
+	}, []);
+
 	const handleReload = async () => {
 		if (!isPollingMode || !isDeviceLoaded) {
 			return;
@@ -291,9 +367,9 @@ function App() {
 
 			// Side effects stay outside the state updater: React may invoke
 			// updater recipes more than once (StrictMode, concurrent replays).
-			recordBatteryReadings(device, [payload.battery_info]);
+			recordBatteryReadings(device, payload.battery_infos);
 
-			const newBatteryInfos = upsertBatteryInfo(device.batteryInfos, payload.battery_info);
+			const newBatteryInfos = upsertBatteryInfos(device.batteryInfos, payload.battery_infos);
 			notifyBatteryEdgeTransitions({
 				deviceDisplayName: getRegisteredDeviceDisplayName(device),
 				deviceId: device.id,
@@ -310,7 +386,7 @@ function App() {
 			commitRegisteredDevices(prev => prev.map(d => d.id !== payload.id
 				? d
 				: expandIfConnected(
-					{ ...d, batteryInfos: upsertBatteryInfo(d.batteryInfos, payload.battery_info), isDisconnected: false },
+					{ ...d, batteryInfos: upsertBatteryInfos(d.batteryInfos, payload.battery_infos), isDisconnected: false },
 					autoCollapseDisconnectedDevicesRef.current,
 				)));
 		});
@@ -467,16 +543,28 @@ function App() {
 									{availableDevices.length === 0 ? (
 										<li className="text-muted-foreground">No devices found</li>
 									) : (
-										availableDevices.map((d) => (
-											<li key={d.id}>
-												<Button
-													className="w-full text-left rounded-none bg-card text-card-foreground hover:bg-muted transition-colors duration-300 p-2!"
-													onClick={() => handleAddDevice(d.id)}
-												>
-													{d.name}
-												</Button>
-											</li>
-										))
+										availableDevices.map((d) => {
+											const match = identityMatches.find(m => m.device_id === d.id);
+											const linkTarget = match && deviceList.find(r => r.id === match.registered_id);
+											return (
+												<li key={d.id}>
+													<Button
+														className="w-full text-left rounded-none bg-card text-card-foreground hover:bg-muted transition-colors duration-300 p-2!"
+														onClick={() => handleAddDevice(d.id)}
+													>
+														{d.name}
+													</Button>
+													{linkTarget && (
+														<Button
+															className="w-full text-left text-sm rounded-none bg-card text-muted-foreground hover:bg-muted transition-colors duration-300 pl-6! py-1!"
+															onClick={() => handleLinkDevice(d, linkTarget)}
+														>
+															{`Same device as "${getRegisteredDeviceDisplayName(linkTarget)}"? Link it`}
+														</Button>
+													)}
+												</li>
+											);
+										})
 									)}
 								</ul>
 							)}
@@ -490,6 +578,7 @@ function App() {
 								registeredDevices={deviceList}
 								setRegisteredDevices={setRegisteredDevicesForPanel}
 								onRemoveDevice={handleRemoveDevice}
+								onCopyGattDump={handleCopyGattDump}
 								onChartOpenChange={handleChartOpenChange}
 								onLayoutChange={handlePanelLayoutChange}
 							/>
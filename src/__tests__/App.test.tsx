@@ -2,7 +2,7 @@ import { act, fireEvent, render, screen, waitFor } from "@testing-library/react"
 import { StrictMode, useState, type Dispatch, type SetStateAction } from "react";
 import { afterEach, beforeEach, describe, expect, it, vi } from "vitest";
 import App from "@/App";
-import { getBatteryInfo, startBatteryNotificationMonitor } from "@/utils/ble";
+import { getBatteryInfo, setBatteryPolling, startBatteryNotificationMonitor } from "@/utils/ble";
 import { defaultConfig, FETCH_INTERVAL_AUTO, NotificationType } from "@/utils/config";
 import { sendNotification } from "@/utils/notification";
 
@@ -25,7 +25,7 @@ let batteryInfoNotificationHandler:
 	| ((event: {
 			payload: {
 				id: string;
-				battery_info: { battery_level: number | null; user_description: string | null };
+				battery_infos: { battery_level: number | null; user_description: string | null }[];
 			};
 	  }) => void)
 	| undefined;
@@ -69,6 +69,14 @@ vi.mock("@/utils/ble", () => ({
 	startBatteryNotificationMonitor: vi.fn(async () => [{ battery_level: 87, user_description: "Central" }]),
 	stopBatteryNotificationMonitor: vi.fn(async () => undefined),
 	stopAllBatteryMonitors: vi.fn(async () => undefined),
+	setBatteryPolling: vi.fn(async () => undefined),
+	stopAllBatteryPolling: vi.fn(async () => undefined),
+	getDeviceFingerprint: vi.fn(async () => {
+		throw new Error("fingerprint unavailable");
+	}),
+	findDeviceIdentityMatches: vi.fn(async () => []),
+	linkDeviceIdentity: vi.fn(async () => null),
+	inspectGattDevice: vi.fn(async () => ({ services: [] })),
 }));
 
 vi.mock("@/hooks/useWindowEvents", () => ({
@@ -118,6 +126,7 @@ describe("App", () => {
 		setMockedConfigInApp = undefined;
 		vi.mocked(getBatteryInfo).mockReset();
 		vi.mocked(getBatteryInfo).mockResolvedValue([{ battery_level: 87, user_description: "Central" }]);
+		vi.mocked(setBatteryPolling).mockClear();
 
 		mockListen.mockImplementation(async (event: string, handler: unknown) => {
 			if (event === "battery-info-notification") {
@@ -353,7 +362,7 @@ describe("App", () => {
 			batteryInfoNotificationHandler?.({
 				payload: {
 					id: "kbd-1",
-					battery_info: { battery_level: 87, user_description: "Central" },
+					battery_infos: [{ battery_level: 87, user_description: "Central" }],
 				},
 			});
 		});
@@ -438,7 +447,7 @@ describe("App", () => {
 			batteryInfoNotificationHandler?.({
 				payload: {
 					id: "kbd-1",
-					battery_info: { battery_level: 35, user_description: "Central" },
+					battery_infos: [{ battery_level: 35, user_description: "Central" }],
 				},
 			});
 		});
@@ -527,7 +536,7 @@ describe("App", () => {
 		expect(screen.queryByText("87%")).toBeNull();
 	});
 
-	it("does not refetch battery info when toggling auto collapse in polling mode", async () => {
+	it("does not restart polling when toggling auto collapse in polling mode", async () => {
 		render(<App />);
 
 		await waitFor(() => {
@@ -547,10 +556,10 @@ describe("App", () => {
 		});
 
 		await waitFor(() => {
-			expect(getBatteryInfo).toHaveBeenCalledTimes(1);
+			expect(setBatteryPolling).toHaveBeenCalledTimes(1);
 		});
 
-		vi.mocked(getBatteryInfo).mockClear();
+		vi.mocked(setBatteryPolling).mockClear();
 
 		await act(async () => {
 			setMockedConfigInApp?.((config) => ({
@@ -563,6 +572,7 @@ describe("App", () => {
 			await Promise.resolve();
 		});
 
+		expect(setBatteryPolling).not.toHaveBeenCalled();
 		expect(getBatteryInfo).not.toHaveBeenCalled();
 	});
 
@@ -652,7 +662,7 @@ describe("App", () => {
 			batteryInfoNotificationHandler?.({
 				payload: {
 					id: "kbd-1",
-					battery_info: { battery_level: 42, user_description: "Central" },
+					battery_infos: [{ battery_level: 42, user_description: "Central" }],
 				},
 			});
 		});
@@ -857,7 +867,7 @@ describe("App", () => {
 			batteryInfoNotificationHandler?.({
 				payload: {
 					id: "kbd-1",
-					battery_info: { battery_level: 60, user_description: "Central" },
+					battery_infos: [{ battery_level: 60, user_description: "Central" }],
 				},
 			});
 		});
@@ -873,7 +883,7 @@ describe("App", () => {
 			batteryInfoNotificationHandler?.({
 				payload: {
 					id: "kbd-1",
-					battery_info: { battery_level: 42, user_description: "Central" },
+					battery_infos: [{ battery_level: 42, user_description: "Central" }],
 				},
 			});
 		});
@@ -886,7 +896,7 @@ describe("App", () => {
 		expect(mockMoveWindowToTrayCenter).not.toHaveBeenCalled();
 	});
 
-	describe("polling overlap guard", () => {
+	describe("backend polling", () => {
 		beforeEach(() => {
 			vi.useFakeTimers();
 		});
@@ -895,103 +905,38 @@ describe("App", () => {
 			vi.useRealTimers();
 		});
 
-		it("skips a poll cycle when the previous one is still in flight", async () => {
-			const fetchInterval = 5_000;
-			mockedConfig = { ...defaultConfig, fetchInterval };
-
-			let resolvePoll!: (value: { battery_level: number; user_description: string }[]) => void;
-			vi.mocked(getBatteryInfo).mockImplementation(
-				() => new Promise((resolve) => { resolvePoll = resolve; }),
-			);
-
-			await act(async () => {
-				render(<App />);
-			});
-
-			await act(async () => {
-				resolveDeviceStoreGets([
-					{
-						id: "kbd-1",
-						name: "MockBoard One",
-						isDisconnected: false,
-						isCollapsed: false,
-						batteryInfos: [{ battery_level: 87, user_description: "Central" }],
-					},
-				]);
-			});
-
-			expect(getBatteryInfo).toHaveBeenCalledTimes(1);
-
-			await act(async () => {
-				vi.advanceTimersByTime(fetchInterval);
-			});
-			expect(getBatteryInfo).toHaveBeenCalledTimes(1);
-
-			await act(async () => {
-				vi.advanceTimersByTime(fetchInterval);
-			});
-			expect(getBatteryInfo).toHaveBeenCalledTimes(1);
-
-			await act(async () => {
-				resolvePoll([{ battery_level: 90, user_description: "Central" }]);
-			});
-
-			await act(async () => {
-				vi.advanceTimersByTime(fetchInterval);
-			});
-			expect(getBatteryInfo).toHaveBeenCalledTimes(2);
-		});
+		const savedDevice = {
+			id: "kbd-1",
+			name: "MockBoard One",
+			isDisconnected: false,
+			isCollapsed: false,
+			batteryInfos: [{ battery_level: 87, user_description: "Central" }],
+		};
 
-		it("ignores a manual reload while a poll cycle is in flight", async () => {
+		it("hands registered devices to the backend instead of polling in the webview", async () => {
 			const fetchInterval = 5_000;
 			mockedConfig = { ...defaultConfig, fetchInterval };
 
-			let resolvePoll!: (value: { battery_level: number; user_description: string }[]) => void;
-			vi.mocked(getBatteryInfo).mockImplementation(
-				() => new Promise((resolve) => { resolvePoll = resolve; }),
-			);
-
 			await act(async () => {
 				render(<App />);
 			});
 
 			await act(async () => {
-				resolveDeviceStoreGets([
-					{
-						id: "kbd-1",
-						name: "MockBoard One",
-						isDisconnected: false,
-						isCollapsed: false,
-						batteryInfos: [{ battery_level: 87, user_description: "Central" }],
-					},
-				]);
-			});
-
-			// Initial poll cycle is in flight
-			expect(getBatteryInfo).toHaveBeenCalledTimes(1);
-
-			await act(async () => {
-				fireEvent.click(screen.getByRole("button", { name: "Reload" }));
+				resolveDeviceStoreGets([savedDevice]);
 			});
 
-			// The click must not issue fresh fetches while the cycle holds the guard
-			expect(getBatteryInfo).toHaveBeenCalledTimes(1);
+			expect(setBatteryPolling).toHaveBeenCalledWith(["kbd-1"], fetchInterval);
 
 			await act(async () => {
-				resolvePoll([{ battery_level: 90, user_description: "Central" }]);
+				vi.advanceTimersByTime(fetchInterval * 3);
 			});
-
-			// UI is back on the main screen with the reload button available
-			expect(screen.getByRole("button", { name: "Reload" })).toBeTruthy();
+			expect(getBatteryInfo).not.toHaveBeenCalled();
 		});
 
-		it("skips a poll cycle while a manual reload is in flight", async () => {
-			const fetchInterval = 5_000;
-			mockedConfig = { ...defaultConfig, fetchInterval };
-
-			let resolvePoll!: (value: { battery_level: number; user_description: string }[]) => void;
+		it("ignores a manual reload while a previous reload is in flight", async () => {
+			let resolveReload!: (value: { battery_level: number; user_description: string }[]) => void;
 			vi.mocked(getBatteryInfo).mockImplementation(
-				() => new Promise((resolve) => { resolvePoll = resolve; }),
+				() => new Promise((resolve) => { resolveReload = resolve; }),
 			);
 
 			await act(async () => {
@@ -999,58 +944,36 @@ describe("App", () => {
 			});
 
 			await act(async () => {
-				resolveDeviceStoreGets([
-					{
-						id: "kbd-1",
-						name: "MockBoard One",
-						isDisconnected: false,
-						isCollapsed: false,
-						batteryInfos: [{ battery_level: 87, user_description: "Central" }],
-					},
-				]);
+				resolveDeviceStoreGets([savedDevice]);
 			});
 
-			// Let the initial poll cycle finish so the reload owns the guard
 			await act(async () => {
-				resolvePoll([{ battery_level: 90, user_description: "Central" }]);
+				fireEvent.click(screen.getByRole("button", { name: "Reload" }));
 			});
 			expect(getBatteryInfo).toHaveBeenCalledTimes(1);
 
 			await act(async () => {
 				fireEvent.click(screen.getByRole("button", { name: "Reload" }));
 			});
-			expect(getBatteryInfo).toHaveBeenCalledTimes(2);
-
-			// Interval fires while the reload is still pending: no extra fetch
-			await act(async () => {
-				vi.advanceTimersByTime(fetchInterval);
-			});
-			expect(getBatteryInfo).toHaveBeenCalledTimes(2);
+			expect(getBatteryInfo).toHaveBeenCalledTimes(1);
 
 			await act(async () => {
-				resolvePoll([{ battery_level: 91, user_description: "Central" }]);
+				resolveReload([{ battery_level: 90, user_description: "Central" }]);
 			});
 
-			await act(async () => {
-				vi.advanceTimersByTime(fetchInterval);
-			});
-			expect(getBatteryInfo).toHaveBeenCalledTimes(3);
+			expect(screen.getByRole("button", { name: "Reload" })).toBeTruthy();
 		});
 
-		it("does not cause unhandled rejection when getBatteryInfo and sendNotification both reject", async () => {
-			const fetchInterval = 5_000;
+		it("marks a device disconnected from a poller status event", async () => {
 			mockedConfig = {
 				...defaultConfig,
-				fetchInterval,
+				fetchInterval: 5_000,
 				pushNotification: true,
 				pushNotificationWhen: {
 					...defaultConfig.pushNotificationWhen,
 					[NotificationType.Disconnected]: true,
-					[NotificationType.LowBattery]: true,
 				},
 			};
-
-			vi.mocked(getBatteryInfo).mockRejectedValue(new Error("BLE error"));
 			vi.mocked(sendNotification).mockRejectedValue(new Error("Notification error"));
 
 			await act(async () => {
@@ -1058,23 +981,12 @@ describe("App", () => {
 			});
 
 			await act(async () => {
-				resolveDeviceStoreGets([
-					{
-						id: "kbd-1",
-						name: "MockBoard One",
-						isDisconnected: false,
-						isCollapsed: false,
-						batteryInfos: [{ battery_level: 87, user_description: "Central" }],
-					},
-				]);
+				resolveDeviceStoreGets([savedDevice]);
 			});
 
-			// Advance through retry sleeps (3 attempts × 500ms)
-			for (let i = 0; i < 3; i++) {
-				await act(async () => {
-					vi.advanceTimersByTime(500);
-				});
-			}
+			await act(async () => {
+				monitorStatusHandler?.({ payload: { id: "kbd-1", connected: false } });
+			});
 
 			await act(async () => {
 				await Promise.resolve();
@@ -13,6 +13,7 @@ import type { RegisteredDevice } from "@/utils/appHelpers";
 import DateRangePicker, { type DateRange } from "@/components/DateRangePicker";
 import { useConfigContext } from "@/context/ConfigContext";
 import { getRegisteredDeviceDisplayName } from "@/utils/appHelpers";
+import { batteryPartLabelStorageKey, getBatteryPartDisplayName } from "@/utils/batteryLabels";
 
 // ── Types ──────────────────────────────────────────────
 import {
@@ -74,7 +75,7 @@ const BatteryHistoryChart: React.FC<BatteryHistoryChartProps> = ({ device, onClo
 		setConfig(prev => ({ ...prev, chartSmoothingWindowSize: w }));
 	}, [setConfig]);
 
-	const { recordedData, allKeys, isLoading, error, hasHistory } = useBatteryChartData({
+	const { recordedData, allKeys, descriptionsByKey, isLoading, error, hasHistory } = useBatteryChartData({
 		device,
 		rangeMs,
 		customRange,
@@ -82,8 +83,14 @@ const BatteryHistoryChart: React.FC<BatteryHistoryChartProps> = ({ device, onClo
 	});
 
 	const displayNameForKey = useCallback(
-		(key: string) => device.batteryPartLabels?.[key] ?? key,
-		[device.batteryPartLabels],
+		(key: string) => {
+			const current = device.batteryInfos.find((info) => batteryPartLabelStorageKey(info) === key);
+			return getBatteryPartDisplayName(
+				device.batteryPartLabels,
+				current ?? { user_description: descriptionsByKey.get(key) ?? key, part_key: key },
+			);
+		},
+		[device.batteryPartLabels, device.batteryInfos, descriptionsByKey],
 	);
 
 	const now = useMemo(() => Date.now(), [recordedData]); // eslint-disable-line react-hooks/exhaustive-deps
@@ -12,9 +12,11 @@ import {
 } from "@heroicons/react/24/outline";
 import type { BatteryInfo } from "@/utils/ble";
 import {
+	batteryPartDescriptionKey,
 	batteryPartLabelStorageKey,
 	defaultBatteryPartDisplayName,
 	getBatteryPartDisplayName,
+	type BatteryPart,
 } from "@/utils/batteryLabels";
 import { getRegisteredDeviceDisplayName } from "@/utils/appHelpers";
 import { cn } from "@/lib/utils";
@@ -39,6 +41,8 @@ interface DeviceListProps {
 	registeredDevices: RegisteredDevice[];
 	setRegisteredDevices: React.Dispatch<React.SetStateAction<RegisteredDevice[]>>;
 	onRemoveDevice?: (device: RegisteredDevice) => void | Promise<void>;
+	/** Offered for connected devices: dump the GATT tree for a bug report. */
+	onCopyGattDump?: (device: RegisteredDevice) => void | Promise<void>;
 	onChartOpenChange?: (isOpen: boolean) => void;
 	onLayoutChange?: () => void;
 }
@@ -51,6 +55,7 @@ type DeviceTopBarProps = {
 	onOpenMenu: () => void;
 	onMoveUp: () => void;
 	onMoveDown: () => void;
+	onCopyGattDump?: () => void;
 	onRemove: () => void | Promise<void>;
 };
 
@@ -62,6 +67,7 @@ const DeviceTopBar: React.FC<DeviceTopBarProps> = ({
 	onOpenMenu,
 	onMoveUp,
 	onMoveDown,
+	onCopyGattDump,
 	onRemove,
 }) => {
 	const menuClass =
@@ -105,6 +111,14 @@ const DeviceTopBar: React.FC<DeviceTopBarProps> = ({
 							Move Down
 						</Button>
 					)}
+					{onCopyGattDump && (
+						<Button
+							className="w-full text-left text-sm! px-3! py-2! bg-popover text-popover-foreground hover:bg-muted"
+							onClick={onCopyGattDump}
+						>
+							Copy GATT Dump
+						</Button>
+					)}
 					<Button
 						className="w-full text-left text-sm! px-3! py-2! bg-popover text-destructive hover:bg-muted"
 						onClick={onRemove}
@@ -216,10 +230,10 @@ type BatteryPartRowProps = {
 	setLabelDraft: (v: string) => void;
 	inputRef: RefObject<HTMLInputElement | null>;
 	skipLabelCommitOnBlurRef: MutableRefObject<boolean>;
-	onCommitPartLabel: (deviceId: string, userDescription: string | null, value: string) => void;
+	onCommitPartLabel: (deviceId: string, part: BatteryPart, value: string) => void;
 	setLabelEdit: React.Dispatch<React.SetStateAction<{ deviceId: string; partKey: string } | null>>;
-	startLabelEdit: (device: RegisteredDevice, userDescription: string | null) => void;
-	resetLabelAndCloseEdit: (deviceId: string, userDescription: string | null) => void;
+	startLabelEdit: (device: RegisteredDevice, part: BatteryPart) => void;
+	resetLabelAndCloseEdit: (deviceId: string, part: BatteryPart) => void;
 };
 
 const BatteryPartRow: React.FC<BatteryPartRowProps> = ({
@@ -236,7 +250,7 @@ const BatteryPartRow: React.FC<BatteryPartRowProps> = ({
 	startLabelEdit,
 	resetLabelAndCloseEdit,
 }) => {
-	const defaultTitle = defaultBatteryPartDisplayName(b.user_description);
+	const defaultTitle = defaultBatteryPartDisplayName(b.user_description, b.presentation);
 	const testIdPart = b.user_description ?? "Central";
 
 	const commitBlur = () => {
@@ -244,7 +258,7 @@ const BatteryPartRow: React.FC<BatteryPartRowProps> = ({
 			skipLabelCommitOnBlurRef.current = false;
 			return;
 		}
-		onCommitPartLabel(device.id, b.user_description, labelDraft);
+		onCommitPartLabel(device.id, b, labelDraft);
 		setLabelEdit(null);
 	};
 
@@ -264,7 +278,7 @@ const BatteryPartRow: React.FC<BatteryPartRowProps> = ({
 							skipLabelCommitOnBlurRef.current = true;
 							setLabelEdit(null);
 						}}
-						onReset={() => resetLabelAndCloseEdit(device.id, b.user_description)}
+						onReset={() => resetLabelAndCloseEdit(device.id, b)}
 						inputRef={inputRef}
 						skipLabelCommitOnBlurRef={skipLabelCommitOnBlurRef}
 					/>
@@ -272,7 +286,7 @@ const BatteryPartRow: React.FC<BatteryPartRowProps> = ({
 					<PartLabelView
 						displayName={displayName}
 						defaultNameTitle={defaultTitle}
-						onStartEdit={() => startLabelEdit(device, b.user_description)}
+						onStartEdit={() => startLabelEdit(device, b)}
 					/>
 				)}
 			</div>
@@ -304,6 +318,7 @@ const RegisteredDevicesPanel: React.FC<DeviceListProps> = ({
 	registeredDevices,
 	setRegisteredDevices,
 	onRemoveDevice,
+	onCopyGattDump,
 	onChartOpenChange,
 	onLayoutChange,
 }) => {
@@ -345,9 +360,10 @@ const RegisteredDevicesPanel: React.FC<DeviceListProps> = ({
 		setMenuOpen(null);
 	};
 
-	const commitPartLabel = (deviceId: string, userDescription: string | null, value: string) => {
-		const partKey = batteryPartLabelStorageKey(userDescription);
-		const defaultName = defaultBatteryPartDisplayName(userDescription);
+	const commitPartLabel = (deviceId: string, part: BatteryPart, value: string) => {
+		const partKey = batteryPartLabelStorageKey(part);
+		const descriptionKey = batteryPartDescriptionKey(part.user_description);
+		const defaultName = defaultBatteryPartDisplayName(part.user_description, part.presentation);
 		const trimmed = value.trim();
 		setRegisteredDevices((prev) =>
 			prev.map((d) => {
@@ -355,6 +371,10 @@ const RegisteredDevicesPanel: React.FC<DeviceListProps> = ({
 					return d;
 				}
 				const nextLabels: Record<string, string> = { ...d.batteryPartLabels };
+				// A label saved before the part had a key is superseded either way.
+				if (descriptionKey !== partKey) {
+					delete nextLabels[descriptionKey];
+				}
 				if (trimmed === "" || trimmed === defaultName) {
 					delete nextLabels[partKey];
 				} else {
@@ -396,10 +416,10 @@ const RegisteredDevicesPanel: React.FC<DeviceListProps> = ({
 		setDeviceNameEditId(null);
 	};
 
-	const startLabelEdit = (device: RegisteredDevice, userDescription: string | null) => {
+	const startLabelEdit = (device: RegisteredDevice, part: BatteryPart) => {
 		setDeviceNameEditId(null);
-		const partKey = batteryPartLabelStorageKey(userDescription);
-		setLabelDraft(getBatteryPartDisplayName(device.batteryPartLabels, userDescription));
+		const partKey = batteryPartLabelStorageKey(part);
+		setLabelDraft(getBatteryPartDisplayName(device.batteryPartLabels, part));
 		setLabelEdit({ deviceId: device.id, partKey });
 	};
 
@@ -417,9 +437,9 @@ const RegisteredDevicesPanel: React.FC<DeviceListProps> = ({
 		onLayoutChange?.();
 	};
 
-	const resetLabelAndCloseEdit = (deviceId: string, userDescription: string | null) => {
-		const def = defaultBatteryPartDisplayName(userDescription);
-		commitPartLabel(deviceId, userDescription, def);
+	const resetLabelAndCloseEdit = (deviceId: string, part: BatteryPart) => {
+		const def = defaultBatteryPartDisplayName(part.user_description, part.presentation);
+		commitPartLabel(deviceId, part, def);
 		setLabelEdit(null);
 	};
 
@@ -532,6 +552,12 @@ const RegisteredDevicesPanel: React.FC<DeviceListProps> = ({
 											}
 											handleMenuClose();
 										}}
+										onCopyGattDump={onCopyGattDump && !device.isDisconnected
+											? () => {
+												handleMenuClose();
+												void onCopyGattDump(device);
+											}
+											: undefined}
 										onRemove={async () => {
 											if (onRemoveDevice) {
 												await onRemoveDevice(device);
@@ -548,7 +574,7 @@ const RegisteredDevicesPanel: React.FC<DeviceListProps> = ({
 								) : (
 									<div className="space-y-1 ml-7">
 										{device.batteryInfos.map((b, batteryIndex) => {
-											const partKey = batteryPartLabelStorageKey(b.user_description);
+											const partKey = batteryPartLabelStorageKey(b);
 											const isEditing =
 												labelEdit?.deviceId === device.id && labelEdit.partKey === partKey;
 											return (
@@ -557,10 +583,7 @@ const RegisteredDevicesPanel: React.FC<DeviceListProps> = ({
 													device={device}
 													b={b}
 													isEditing={isEditing}
-													displayName={getBatteryPartDisplayName(
-														device.batteryPartLabels,
-														b.user_description,
-													)}
+													displayName={getBatteryPartDisplayName(device.batteryPartLabels, b)}
 													labelDraft={labelDraft}
 													setLabelDraft={setLabelDraft}
 													inputRef={labelInputRef}
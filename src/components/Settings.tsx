@@ -1,4 +1,4 @@
-import React from "react";
+import React, { useEffect, useState } from "react";
 import Button from "./Button";
 import {
 	FETCH_INTERVAL_AUTO,
@@ -16,6 +16,9 @@ import { XMarkIcon, MoonIcon, SunIcon } from "@heroicons/react/24/outline";
 import { useConfigContext } from "@/context/ConfigContext";
 import TopRightButtons from "./TopRightButtons";
 import { platform } from "@tauri-apps/plugin-os";
+import { listBluetoothAdapters, type BluetoothAdapterInfo } from "@/utils/ble";
+import { errorMessage } from "@/utils/commandError";
+import { logger } from "@/utils/log";
 
 import { cn } from "@/lib/utils";
 
@@ -128,12 +131,22 @@ const trayIconComponentOptions = [
 	{ label: "Battery percentage", value: TrayIconComponent.BatteryPercent },
 ];
 
+// Select item values cannot be empty, so the default adapter gets a sentinel.
+const DEFAULT_ADAPTER_VALUE = "system-default";
+
 const Settings: React.FC<SettingsScreenProps> = ({
 	onExit
 }) => {
 	const { setTheme, theme } = useTheme();
 	const { config, setConfig } = useConfigContext();
 	const isMac = platform() === "macos";
+	const [adapters, setAdapters] = useState<BluetoothAdapterInfo[]>([]);
+
+	useEffect(() => {
+		listBluetoothAdapters()
+			.then(setAdapters)
+			.catch(e => logger.warn(`Failed to list Bluetooth adapters: ${errorMessage(e)}`));
+	}, []);
 
 	const handleTrayIconComponentChange = (component: TrayIconComponent, checked: boolean) => {
 		setConfig(c => {
@@ -255,6 +268,33 @@ const Settings: React.FC<SettingsScreenProps> = ({
 						</div>
 					</SettingsGroup>
 
+					{/* Bluetooth adapter, shown when there is a choice or a saved one to undo */}
+					{(adapters.length > 1 || config.bluetoothAdapterId !== null) && (
+						<SettingsGroup>
+							<div className="flex min-w-0 items-center justify-between gap-3">
+								<span className="shrink-0">Bluetooth adapter</span>
+								<div className="flex min-w-0 max-w-full flex-1 basis-0 justify-end">
+									<Select
+										value={config.bluetoothAdapterId ?? DEFAULT_ADAPTER_VALUE}
+										onValueChange={value => setConfig(c => ({ ...c, bluetoothAdapterId: value === DEFAULT_ADAPTER_VALUE ? null : value }))}
+									>
+										<SelectTrigger size="sm" className="w-fit min-w-0 max-w-full">
+											<SelectValue placeholder="Select" />
+										</SelectTrigger>
+										<SelectContent>
+											<SelectItem value={DEFAULT_ADAPTER_VALUE}>System default</SelectItem>
+											{adapters.map(adapter => (
+												<SelectItem key={adapter.id} value={adapter.id}>
+													{adapter.name}
+												</SelectItem>
+											))}
+										</SelectContent>
+									</Select>
+								</div>
+							</div>
+						</SettingsGroup>
+					)}
+
 					{/* Auto collapse disconnected devices */}
 					<SettingsGroup>
 						<div className="flex items-center justify-between gap-3">
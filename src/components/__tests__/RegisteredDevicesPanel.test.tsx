@@ -91,6 +91,32 @@ describe("RegisteredDevicesPanel", () => {
 		expect(onRemoveDevice).toHaveBeenCalledWith(sampleDevice);
 	});
 
+	it("offers Copy GATT Dump only for connected devices", async () => {
+		const user = userEvent.setup();
+		const onCopyGattDump = vi.fn();
+		const { rerender } = render(
+			<RegisteredDevicesPanel
+				registeredDevices={[sampleDevice]}
+				setRegisteredDevices={vi.fn()}
+				onCopyGattDump={onCopyGattDump}
+			/>,
+		);
+
+		await openDeviceMenu(user, "MockBoard");
+		await user.click(screen.getByRole("button", { name: "Copy GATT Dump" }));
+		expect(onCopyGattDump).toHaveBeenCalledWith(sampleDevice);
+
+		rerender(
+			<RegisteredDevicesPanel
+				registeredDevices={[{ ...sampleDevice, isDisconnected: true }]}
+				setRegisteredDevices={vi.fn()}
+				onCopyGattDump={onCopyGattDump}
+			/>,
+		);
+		await openDeviceMenu(user, "MockBoard");
+		expect(screen.queryByRole("button", { name: "Copy GATT Dump" })).toBeNull();
+	});
+
 	it("renders disconnected badge when device is marked disconnected", () => {
 		render(
 			<RegisteredDevicesPanel
@@ -2,6 +2,8 @@ import { createContext, useContext, Dispatch, SetStateAction, ReactNode, useStat
 import { defaultConfig, loadSavedConfig, setConfig as storeSetConfig, type Config } from '../utils/config';
 import { useTheme, type Theme } from '@/context/theme-provider';
 import { logger } from '@/utils/log';
+import { selectBluetoothAdapter } from '@/utils/ble';
+import { errorMessage } from '@/utils/commandError';
 import { listen, emit } from '@tauri-apps/api/event';
 
 type ConfigContextType = {
@@ -49,6 +51,9 @@ export const ConfigProvider = ({ children }: { children: ReactNode }) => {
 		let isMounted = true;
 		(async () => {
 			const loaded = await loadSavedConfig();
+			// Select the adapter before monitors can start on the default one.
+			await selectBluetoothAdapter(loaded.bluetoothAdapterId)
+				.catch(e => logger.warn(`Failed to select Bluetooth adapter: ${errorMessage(e)}`));
 			if (isMounted) {
 				setConfig(loaded);
 				setIsConfigLoaded(true);
@@ -61,6 +66,12 @@ export const ConfigProvider = ({ children }: { children: ReactNode }) => {
 		return () => { isMounted = false; };
 	}, []);
 
+	useEffect(() => {
+		if (!isConfigLoaded) return;
+		selectBluetoothAdapter(config.bluetoothAdapterId)
+			.catch(e => logger.warn(`Failed to select Bluetooth adapter: ${errorMessage(e)}`));
+	}, [isConfigLoaded, config.bluetoothAdapterId]);
+
 	useEffect(() => {
 		const unlistenPromise = listen<Partial<Config>>('update-config', (event) => {
 			const updates = event.payload;
@@ -1,7 +1,15 @@
 import { act, renderHook } from "@testing-library/react";
 import { afterEach, beforeEach, describe, expect, it, vi } from "vitest";
 import { useBatteryPolling } from "@/hooks/useBatteryPolling";
-import { getBatteryInfo, type BatteryInfo } from "@/utils/ble";
+import {
+	getBatteryInfo,
+	getBatteryInfoMany,
+	setBatteryPolling,
+	stopAllBatteryPolling,
+	type BatteryInfo,
+	type BatteryReadResult,
+} from "@/utils/ble";
+import type { CommandError } from "@/utils/commandError";
 import { recordBatteryReadings } from "@/utils/batteryHistory";
 import { notifyBatteryEdgeTransitions } from "@/utils/batteryEdgeNotification";
 import { sendNotification } from "@/utils/notification";
@@ -10,6 +18,9 @@ import type { RegisteredDevice } from "@/utils/appHelpers";
 
 vi.mock("@/utils/ble", () => ({
 	getBatteryInfo: vi.fn(),
+	getBatteryInfoMany: vi.fn(),
+	setBatteryPolling: vi.fn(async () => undefined),
+	stopAllBatteryPolling: vi.fn(async () => undefined),
 }));
 
 vi.mock("@/utils/batteryHistory", () => ({
@@ -71,6 +82,7 @@ function renderPolling(overrides: RenderOverrides = {}) {
 		isConfigLoaded: true,
 		isDeviceLoaded: true,
 		fetchInterval: 60_000,
+		registeredDeviceIdsKey: device.id,
 		registeredDevicesRef,
 		commitRegisteredDevices,
 		pushNotification: true,
@@ -100,6 +112,11 @@ describe("useBatteryPolling", () => {
 		vi.useFakeTimers();
 		vi.clearAllMocks();
 		vi.mocked(getBatteryInfo).mockResolvedValue(connectedBatteryInfos);
+		vi.mocked(getBatteryInfoMany).mockImplementation(async ids => ids.map(id => ({
+			id,
+			battery_infos: connectedBatteryInfos,
+			error: null,
+		})));
 		vi.mocked(sendNotification).mockResolvedValue(true);
 	});
 
@@ -223,77 +240,74 @@ describe("useBatteryPolling", () => {
 		});
 	});
 
-	it("polls immediately and then on each interval", async () => {
+	it("hands the registered devices and interval to the backend poller", async () => {
 		const view = renderPolling();
+		await act(flushPromises);
 
-		expect(getBatteryInfo).toHaveBeenCalledTimes(1);
-
-		await act(async () => {
-			await vi.advanceTimersByTimeAsync(60_000);
-		});
-		expect(getBatteryInfo).toHaveBeenCalledTimes(2);
-
-		await act(async () => {
-			await vi.advanceTimersByTimeAsync(60_000);
-		});
-		expect(getBatteryInfo).toHaveBeenCalledTimes(3);
+		expect(setBatteryPolling).toHaveBeenCalledOnce();
+		expect(setBatteryPolling).toHaveBeenCalledWith(["kbd-1"], 60_000);
+		expect(getBatteryInfo).not.toHaveBeenCalled();
 		view.unmount();
 	});
 
-	it("skips a cycle while one is in flight", async () => {
-		const resolvers: Array<(infos: BatteryInfo[]) => void> = [];
-		vi.mocked(getBatteryInfo).mockImplementation(
-			() => new Promise(resolve => resolvers.push(resolve)),
+	it("updates the backend poller when devices or the interval change", async () => {
+		const { rerender, unmount } = renderHook(
+			(props: { key: string; interval: number }) => useBatteryPolling({
+				isPollingMode: true,
+				isConfigLoaded: true,
+				isDeviceLoaded: true,
+				fetchInterval: props.interval,
+				registeredDeviceIdsKey: props.key,
+				registeredDevicesRef: { current: [] },
+				commitRegisteredDevices: vi.fn(),
+				pushNotification: false,
+				pushNotificationWhen: notificationFlags,
+				lowBatteryThreshold: 20,
+				ignoreZeroPercent: true,
+				highBatteryThreshold: 80,
+				autoCollapseDisconnectedDevices: false,
+			}),
+			{ initialProps: { key: "kbd-1", interval: 60_000 } },
 		);
-		const view = renderPolling();
-
-		expect(getBatteryInfo).toHaveBeenCalledTimes(1);
-		await act(async () => {
-			await vi.advanceTimersByTimeAsync(60_000);
-		});
-		expect(getBatteryInfo).toHaveBeenCalledTimes(1);
-
-		await act(async () => {
-			resolvers.shift()!(connectedBatteryInfos);
-			await flushPromises();
-		});
-		await act(async () => {
-			await vi.advanceTimersByTimeAsync(60_000);
-		});
-		expect(getBatteryInfo).toHaveBeenCalledTimes(2);
-
-		await act(async () => {
-			resolvers.shift()!(connectedBatteryInfos);
-			await flushPromises();
-		});
-		view.unmount();
+		await act(flushPromises);
+		rerender({ key: "kbd-1", interval: 60_000 });
+		await act(flushPromises);
+		rerender({ key: "kbd-1,kbd-2", interval: 60_000 });
+		await act(flushPromises);
+		rerender({ key: "kbd-1,kbd-2", interval: 300_000 });
+		await act(flushPromises);
+
+		// Re-rendering with the same devices and interval does not re-send.
+		expect(vi.mocked(setBatteryPolling).mock.calls).toEqual([
+			[["kbd-1"], 60_000],
+			[["kbd-1", "kbd-2"], 60_000],
+			[["kbd-1", "kbd-2"], 300_000],
+		]);
+		unmount();
 	});
 
-	it("stops polling on unmount", async () => {
+	it("stops backend polling on unmount", async () => {
 		const view = renderPolling();
+		await act(flushPromises);
 		view.unmount();
+		await act(flushPromises);
 
-		await act(async () => {
-			await vi.advanceTimersByTimeAsync(120_000);
-		});
-
-		expect(getBatteryInfo).toHaveBeenCalledTimes(1);
+		expect(stopAllBatteryPolling).toHaveBeenCalledOnce();
 	});
 
 	it("does not poll when isPollingMode is false", async () => {
 		const view = renderPolling({ isPollingMode: false });
+		await act(flushPromises);
 
-		await act(async () => {
-			await vi.advanceTimersByTimeAsync(120_000);
-		});
-
+		expect(setBatteryPolling).not.toHaveBeenCalled();
+		expect(stopAllBatteryPolling).toHaveBeenCalledOnce();
 		expect(getBatteryInfo).not.toHaveBeenCalled();
 		view.unmount();
 	});
 
 	it("reloadAll returns false while a cycle is in flight, true otherwise", async () => {
-		let resolveReload!: (infos: BatteryInfo[]) => void;
-		vi.mocked(getBatteryInfo).mockImplementation(
+		let resolveReload!: (results: BatteryReadResult[]) => void;
+		vi.mocked(getBatteryInfoMany).mockImplementationOnce(
 			() => new Promise(resolve => {
 				resolveReload = resolve;
 			}),
@@ -304,21 +318,50 @@ describe("useBatteryPolling", () => {
 		await act(async () => {
 			firstReload = view.result.current.reloadAll();
 		});
-		expect(getBatteryInfo).toHaveBeenCalledTimes(1);
+		expect(getBatteryInfoMany).toHaveBeenCalledTimes(1);
 
 		await act(async () => {
 			expect(await view.result.current.reloadAll()).toBe(false);
 		});
 
 		await act(async () => {
-			resolveReload(connectedBatteryInfos);
+			resolveReload([{ id: "kbd-1", battery_infos: connectedBatteryInfos, error: null }]);
 			expect(await firstReload).toBe(true);
 		});
 
-		vi.mocked(getBatteryInfo).mockResolvedValue(connectedBatteryInfos);
 		await act(async () => {
 			expect(await view.result.current.reloadAll()).toBe(true);
 		});
+		expect(getBatteryInfo).not.toHaveBeenCalled();
+		view.unmount();
+	});
+
+	it("reloadAll retries only devices whose batch read failed with a retryable error", async () => {
+		const timeout: CommandError = { code: "timeout", message: "Timed out", retryable: true, details: null };
+		vi.mocked(getBatteryInfoMany).mockResolvedValue([
+			{ id: "kbd-1", battery_infos: null, error: timeout },
+		]);
+		const view = renderPolling({ isPollingMode: false });
+
+		await act(async () => {
+			expect(await view.result.current.reloadAll()).toBe(true);
+		});
+
+		// The batch counts as the first of three attempts.
+		expect(getBatteryInfo).toHaveBeenCalledTimes(1);
+		expect(view.getDevices()[0]).toMatchObject({ isDisconnected: false });
+
+		const unsupported: CommandError = { code: "unsupported", message: "Unsupported", retryable: false, details: null };
+		vi.mocked(getBatteryInfoMany).mockResolvedValue([
+			{ id: "kbd-1", battery_infos: null, error: unsupported },
+		]);
+		vi.mocked(getBatteryInfo).mockClear();
+		await act(async () => {
+			await view.result.current.reloadAll();
+		});
+
+		expect(getBatteryInfo).not.toHaveBeenCalled();
+		expect(view.getDevices()[0]).toMatchObject({ isDisconnected: true });
 		view.unmount();
 	});
 });
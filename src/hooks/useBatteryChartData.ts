@@ -1,7 +1,8 @@
 import { useCallback, useEffect, useMemo, useState } from "react";
 import { listen } from "@tauri-apps/api/event";
-import type { RegisteredDevice } from "@/utils/appHelpers";
+import { batteryHistorySeriesKey, type RegisteredDevice } from "@/utils/appHelpers";
 import { logger } from "@/utils/log";
+import { errorMessage } from "@/utils/commandError";
 import { readBatteryHistory, type BatteryHistoryRecord } from "@/utils/batteryHistory";
 import { smooth, type ChartRow } from "@/utils/batteryChartMath";
 import type { DateRange } from "@/components/DateRangePicker";
@@ -16,11 +17,14 @@ export function useBatteryChartData(options: {
 }): {
 	recordedData: ChartRow[];
 	allKeys: string[];
+	/** Latest user description recorded for each series key. */
+	descriptionsByKey: Map<string, string>;
 	isLoading: boolean;
 	error: string | null;
 	hasHistory: boolean;
 } {
 	const { device, rangeMs, customRange, smoothingWindow } = options;
+	const aliases = device.batteryPartAliases;
 	const [grouped, setGrouped] = useState<GroupedHistory>(new Map());
 	const [isLoading, setIsLoading] = useState(true);
 	const [error, setError] = useState<string | null>(null);
@@ -41,19 +45,19 @@ export function useBatteryChartData(options: {
 			const map = new Map<string, BatteryHistoryRecord[]>();
 			for (const r of records) {
 				if (r.battery_level === 0) continue; // Ignore 0%
-				const key = r.user_description || "Central";
+				const key = batteryHistorySeriesKey(r, aliases);
 				if (!map.has(key)) map.set(key, []);
 				map.get(key)!.push(r);
 			}
 			setGrouped(map);
 		} catch (e) {
-			const msg = e instanceof Error ? e.message : String(e);
+			const msg = errorMessage(e);
 			setError(msg);
 			logger.warn(`Failed to load battery history: ${msg}`);
 		} finally {
 			setIsLoading(false);
 		}
-	}, [device.name, device.id, rangeMs, customRange, smoothingWindow]);
+	}, [device.name, device.id, aliases, rangeMs, customRange, smoothingWindow]);
 
 	useEffect(() => {
 		load();
@@ -72,7 +76,7 @@ export function useBatteryChartData(options: {
 						const next = new Map(prev);
 						for (const r of records) {
 							if (r.battery_level === 0) continue; // match load()'s 0% skip
-							const key = r.user_description || "Central";
+							const key = batteryHistorySeriesKey(r, aliases);
 							next.set(key, [...(next.get(key) ?? []), r]);
 						}
 						return next;
@@ -85,11 +89,20 @@ export function useBatteryChartData(options: {
 		return () => {
 			unlistenPromise.then(unlisten => unlisten());
 		};
-	}, [device.id, load]);
+	}, [device.id, aliases, load]);
 
 	// ── Derived data ───────────────────────────────────
 	const allKeys = useMemo(() => [...grouped.keys()], [grouped]);
 
+	const descriptionsByKey = useMemo(() => {
+		const out = new Map<string, string>();
+		for (const [key, records] of grouped) {
+			const latest = records[records.length - 1];
+			out.set(key, latest?.user_description || "Central");
+		}
+		return out;
+	}, [grouped]);
+
 	const smoothedByKey = useMemo<Map<string, BatteryHistoryRecord[]>>(() => {
 		const out = new Map<string, BatteryHistoryRecord[]>();
 		for (const key of allKeys) {
@@ -136,6 +149,7 @@ export function useBatteryChartData(options: {
 	return {
 		recordedData,
 		allKeys,
+		descriptionsByKey,
 		isLoading,
 		error,
 		hasHistory: grouped.size > 0,
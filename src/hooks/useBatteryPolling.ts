@@ -1,7 +1,15 @@
 import { useEffect, useCallback, useRef } from "react";
-import { getBatteryInfo } from "@/utils/ble";
+import {
+	getBatteryInfo,
+	getBatteryInfoMany,
+	setBatteryPolling,
+	stopAllBatteryPolling,
+	type BatteryInfo,
+	type BatteryReadResult,
+} from "@/utils/ble";
 import { logger } from "@/utils/log";
 import { fireAndForget, sleep } from "@/utils/common";
+import { errorMessage, isRetryableError } from "@/utils/commandError";
 import { recordBatteryReadings } from "@/utils/batteryHistory";
 import { sendNotification } from "@/utils/notification";
 import { NotificationType } from "@/utils/config";
@@ -13,11 +21,15 @@ import {
 } from "@/utils/appHelpers";
 import { collapseIfDisconnected, expandIfConnected } from "@/hooks/useRegisteredDevices";
 
+// Reads of a connected device; a disconnected one is tried once.
+const MAX_READ_ATTEMPTS = 3;
+
 interface UseBatteryPollingOptions {
 	isPollingMode: boolean;
 	isConfigLoaded: boolean;
 	isDeviceLoaded: boolean;
 	fetchInterval: number | "auto";
+	registeredDeviceIdsKey: string;
 	registeredDevicesRef: React.RefObject<RegisteredDevice[]>;
 	commitRegisteredDevices: (recipe: (current: RegisteredDevice[]) => RegisteredDevice[]) => void;
 	pushNotification: boolean;
@@ -33,6 +45,7 @@ export function useBatteryPolling({
 	isConfigLoaded,
 	isDeviceLoaded,
 	fetchInterval,
+	registeredDeviceIdsKey,
 	registeredDevicesRef,
 	commitRegisteredDevices,
 	pushNotification,
@@ -48,9 +61,10 @@ export function useBatteryPolling({
 	const ignoreZeroPercentRef = useRef(ignoreZeroPercent);
 	const highBatteryThresholdRef = useRef(highBatteryThreshold);
 	const autoCollapseDisconnectedDevicesRef = useRef(autoCollapseDisconnectedDevices);
-	// Shared by the interval cycle and the manual reload: concurrent
-	// get_battery_info calls for one device can tear each other down.
+	// The backend joins overlapping reads of a device, but a second reload
+	// would still repeat the retries and notifications of the first.
 	const isCycleInFlightRef = useRef(false);
+	const pollingSyncChainRef = useRef<Promise<void>>(Promise.resolve());
 	useEffect(() => {
 		pushNotificationRef.current = pushNotification;
 		pushNotificationWhenRef.current = pushNotificationWhen;
@@ -60,102 +74,103 @@ export function useBatteryPolling({
 		autoCollapseDisconnectedDevicesRef.current = autoCollapseDisconnectedDevices;
 	}, [pushNotification, pushNotificationWhen, lowBatteryThreshold, ignoreZeroPercent, highBatteryThreshold, autoCollapseDisconnectedDevices]);
 
-	const updateBatteryInfo = useCallback(async (device: RegisteredDevice) => {
-		const isDisconnectedPrev = device.isDisconnected;
+	const commitReadSuccess = useCallback((device: RegisteredDevice, infoArray: BatteryInfo[]) => {
+		commitRegisteredDevices(prev => prev.map(d => {
+			if (d.id !== device.id) return d;
+			return expandIfConnected(
+				{ ...d, batteryInfos: mergeBatteryInfos(d.batteryInfos, infoArray), isDisconnected: false },
+				autoCollapseDisconnectedDevicesRef.current,
+			);
+		}));
+
+		recordBatteryReadings(device, infoArray);
+
+		if(device.isDisconnected && pushNotificationRef.current && pushNotificationWhenRef.current[NotificationType.Connected]){
+			fireAndForget(
+				sendNotification(`${getRegisteredDeviceDisplayName(device)} has been connected.`),
+				`Failed to send connected notification for ${device.id}`,
+			);
+		}
+
+		notifyBatteryEdgeTransitions({
+			deviceDisplayName: getRegisteredDeviceDisplayName(device),
+			deviceId: device.id,
+			prevBatteryInfos: device.batteryInfos,
+			newBatteryInfos: infoArray,
+			batteryPartLabels: device.batteryPartLabels,
+			lowBatteryThreshold: lowBatteryThresholdRef.current,
+			ignoreZeroPercent: ignoreZeroPercentRef.current,
+			highBatteryThreshold: highBatteryThresholdRef.current,
+			pushNotification: pushNotificationRef.current,
+			pushNotificationWhen: pushNotificationWhenRef.current,
+		});
+	}, [commitRegisteredDevices]);
+
+	const commitReadFailure = useCallback((device: RegisteredDevice) => {
+		commitRegisteredDevices(prev => prev.map(d => {
+			if (d.id !== device.id) {
+				return d;
+			}
+			return collapseIfDisconnected(
+				{ ...d, isDisconnected: true },
+				autoCollapseDisconnectedDevicesRef.current,
+			);
+		}));
+
+		if(!device.isDisconnected && pushNotificationRef.current && pushNotificationWhenRef.current[NotificationType.Disconnected]){
+			fireAndForget(
+				sendNotification(`${getRegisteredDeviceDisplayName(device)} has been disconnected.`),
+				`Failed to send disconnected notification for ${device.id}`,
+			);
+		}
+	}, [commitRegisteredDevices]);
 
-		let attempts = 0;
-		const maxAttempts = isDisconnectedPrev ? 1 : 3;
+	// `attemptsMade` counts reads that already failed, e.g. in a batch.
+	const updateBatteryInfo = useCallback(async (device: RegisteredDevice, attemptsMade = 0) => {
+		let attempts = attemptsMade;
+		const maxAttempts = device.isDisconnected ? 1 : MAX_READ_ATTEMPTS;
 
 		while (attempts < maxAttempts) {
+			if (attempts > 0) {
+				await sleep(500);
+			}
 			logger.info(`Updating battery info for: ${device.id} (attempt ${attempts + 1} of ${maxAttempts})`);
 			try {
 				const info = await getBatteryInfo(device.id);
-				const infoArray = Array.isArray(info) ? info : [info];
-				commitRegisteredDevices(prev => prev.map(d => {
-					if (d.id !== device.id) return d;
-					return expandIfConnected(
-						{ ...d, batteryInfos: mergeBatteryInfos(d.batteryInfos, infoArray), isDisconnected: false },
-						autoCollapseDisconnectedDevicesRef.current,
-					);
-				}));
-
-				recordBatteryReadings(device, infoArray);
-
-				if(isDisconnectedPrev && pushNotificationRef.current && pushNotificationWhenRef.current[NotificationType.Connected]){
-					fireAndForget(
-						sendNotification(`${getRegisteredDeviceDisplayName(device)} has been connected.`),
-						`Failed to send connected notification for ${device.id}`,
-					);
-				}
-
-				notifyBatteryEdgeTransitions({
-					deviceDisplayName: getRegisteredDeviceDisplayName(device),
-					deviceId: device.id,
-					prevBatteryInfos: device.batteryInfos,
-					newBatteryInfos: infoArray,
-					batteryPartLabels: device.batteryPartLabels,
-					lowBatteryThreshold: lowBatteryThresholdRef.current,
-					ignoreZeroPercent: ignoreZeroPercentRef.current,
-					highBatteryThreshold: highBatteryThresholdRef.current,
-					pushNotification: pushNotificationRef.current,
-					pushNotificationWhen: pushNotificationWhenRef.current,
-				});
-
+				commitReadSuccess(device, Array.isArray(info) ? info : [info]);
 				return;
-			} catch {
-				attempts++;
-				if (attempts >= maxAttempts) {
-					commitRegisteredDevices(prev => prev.map(d => {
-						if (d.id !== device.id) {
-							return d;
-						}
-						return collapseIfDisconnected(
-							{ ...d, isDisconnected: true },
-							autoCollapseDisconnectedDevicesRef.current,
-						);
-					}));
-
-					if(!isDisconnectedPrev && pushNotificationRef.current && pushNotificationWhenRef.current[NotificationType.Disconnected]){
-						fireAndForget(
-							sendNotification(`${getRegisteredDeviceDisplayName(device)} has been disconnected.`),
-							`Failed to send disconnected notification for ${device.id}`,
-						);
-						return;
-					}
-				}
+			} catch (e) {
+				// Errors that cannot clear up by themselves are not retried.
+				attempts = isRetryableError(e) ? attempts + 1 : maxAttempts;
 			}
-			await sleep(500);
 		}
-	}, [commitRegisteredDevices]);
+		commitReadFailure(device);
+	}, [commitReadSuccess, commitReadFailure]);
 
-	// Polling: use registeredDevicesRef so this effect doesn't re-run on every
-	// device update (which would cause an infinite loop).
+	// The backend owns the poll timers and reports through the same events as
+	// the notification monitors; this only tells it which devices to poll.
+	// Calls are chained so they reach the backend in order.
 	useEffect(() => {
-		if (!isPollingMode || !isConfigLoaded || !isDeviceLoaded) {
+		if (!isConfigLoaded || !isDeviceLoaded) {
 			return;
 		}
 
-		let isUnmounted = false;
-
-		const runPollCycle = () => {
-			if (isUnmounted || isCycleInFlightRef.current) return;
-			isCycleInFlightRef.current = true;
-			fireAndForget(
-				Promise.all(registeredDevicesRef.current.map(updateBatteryInfo))
-					.finally(() => { isCycleInFlightRef.current = false; }),
-				"Polling cycle failed",
-			);
-		};
-
-		runPollCycle();
-
-		const interval = setInterval(runPollCycle, fetchInterval as number);
+		const ids = registeredDeviceIdsKey ? registeredDeviceIdsKey.split(',') : [];
+		pollingSyncChainRef.current = pollingSyncChainRef.current
+			.then(() => isPollingMode
+				? setBatteryPolling(ids, fetchInterval as number)
+				: stopAllBatteryPolling())
+			.catch(e => logger.warn(`Failed to synchronize battery polling: ${errorMessage(e)}`));
+	}, [isPollingMode, isConfigLoaded, isDeviceLoaded, fetchInterval, registeredDeviceIdsKey]);
 
+	useEffect(() => {
+		const syncChain = pollingSyncChainRef;
 		return () => {
-			isUnmounted = true;
-			clearInterval(interval);
+			syncChain.current = syncChain.current
+				.then(() => stopAllBatteryPolling())
+				.catch(e => logger.warn(`Failed to stop battery polling: ${errorMessage(e)}`));
 		};
-	}, [isPollingMode, isConfigLoaded, isDeviceLoaded, fetchInterval, updateBatteryInfo, registeredDevicesRef]);
+	}, []);
 
 	const reloadAll = useCallback(async () => {
 		if (isCycleInFlightRef.current) {
@@ -163,12 +178,30 @@ export function useBatteryPolling({
 		}
 		isCycleInFlightRef.current = true;
 		try {
-			await Promise.all(registeredDevicesRef.current.map(updateBatteryInfo));
+			const devices = registeredDevicesRef.current;
+			// One round trip for every device; only failed reads are retried,
+			// one by one.
+			let results: BatteryReadResult[] = [];
+			try {
+				results = await getBatteryInfoMany(devices.map(d => d.id));
+			} catch (e) {
+				logger.warn(`Batch battery read failed: ${errorMessage(e)}`);
+			}
+			const resultsById = new Map(results.map(result => [result.id, result]));
+			await Promise.all(devices.map(async device => {
+				const result = resultsById.get(device.id);
+				if (result?.battery_infos) {
+					commitReadSuccess(device, result.battery_infos);
+					return;
+				}
+				const retryable = isRetryableError(result?.error);
+				await updateBatteryInfo(device, retryable ? 1 : MAX_READ_ATTEMPTS);
+			}));
 		} finally {
 			isCycleInFlightRef.current = false;
 		}
 		return true;
-	}, [registeredDevicesRef, updateBatteryInfo]);
+	}, [registeredDevicesRef, commitReadSuccess, updateBatteryInfo]);
 
 	return { updateBatteryInfo, reloadAll, autoCollapseDisconnectedDevicesRef };
 }
@@ -6,6 +6,7 @@ import {
 } from "@/utils/ble";
 import { logger } from "@/utils/log";
 import { fireAndForget } from "@/utils/common";
+import { errorMessage } from "@/utils/commandError";
 import { mergeBatteryInfos, type RegisteredDevice } from "@/utils/appHelpers";
 import { collapseIfDisconnected, expandIfConnected } from "@/hooks/useRegisteredDevices";
 
@@ -54,7 +55,7 @@ export function useNotificationMonitors({
 				try {
 					await stopBatteryNotificationMonitor(id);
 				} catch (e) {
-					logger.warn(`Failed to stop notification monitor for ${id}: ${String(e)}`);
+					logger.warn(`Failed to stop notification monitor for ${id}: ${errorMessage(e)}`);
 				}
 				active.delete(id);
 			}
@@ -110,7 +111,7 @@ export function useNotificationMonitors({
 
 		syncChainRef.current = syncChainRef.current
 			.then(syncNotificationMonitors)
-			.catch(e => logger.warn(`Failed to synchronize battery notification monitors: ${String(e)}`));
+			.catch(e => logger.warn(`Failed to synchronize battery notification monitors: ${errorMessage(e)}`));
 
 		return () => {
 			// Bump the generation so an in-flight run stops mutating state.
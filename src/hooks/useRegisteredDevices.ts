@@ -11,6 +11,7 @@ import { logger } from "@/utils/log";
 import { load, getStorePath } from "@/utils/storage";
 import { fireAndForget } from "@/utils/common";
 import {
+	learnBatteryPartAliases,
 	normalizeLoadedDevices,
 	type RegisteredDevice,
 } from "@/utils/appHelpers";
@@ -64,7 +65,14 @@ export function useRegisteredDevices() {
 				if (prev === undefined) {
 					return prev;
 				}
-				const next = recipe(prev);
+				// Every battery update passes through here, so this is where a
+				// part's descriptions get tied to its key.
+				const next = recipe(prev).map(learnBatteryPartAliases);
+				// Repeated status events (e.g. reconnection attempt reports) map
+				// every device to itself; skip the write and the re-render.
+				if (next.length === prev.length && next.every((device, i) => device === prev[i])) {
+					return prev;
+				}
 				fireAndForget(persistRegisteredDevices(next), "Failed to persist registered devices");
 				return next;
 			});
@@ -1,5 +1,5 @@
 import { describe, expect, it } from "vitest";
-import { mapIsHighBattery, mapIsLowBattery, mergeBatteryInfos, normalizeLoadedDevices, upsertBatteryInfo, getRegisteredDeviceDisplayName } from "../appHelpers";
+import { mapIsHighBattery, mapIsLowBattery, mergeBatteryInfos, normalizeLoadedDevices, upsertBatteryInfo, upsertBatteryInfos, getRegisteredDeviceDisplayName, relinkRegisteredDevice, learnBatteryPartAliases, batteryHistorySeriesKey } from "../appHelpers";
 
 describe("App helpers", () => {
 	describe("getRegisteredDeviceDisplayName", () => {
@@ -76,6 +76,93 @@ describe("App helpers", () => {
 
 			expect(upsertBatteryInfo(prev, nextInfo)).toEqual([{ battery_level: 55, user_description: null }]);
 		});
+
+		it("matches entries by part_key, not description", () => {
+			const prev = [
+				{ battery_level: 80, user_description: null, part_key: "0:0" },
+				{ battery_level: 70, user_description: null, part_key: "0:1" },
+			];
+			const result = upsertBatteryInfo(prev, { battery_level: 65, user_description: null, part_key: "0:1" });
+			expect(result).toEqual([
+				{ battery_level: 80, user_description: null, part_key: "0:0" },
+				{ battery_level: 65, user_description: null, part_key: "0:1" },
+			]);
+		});
+
+		it("upsertBatteryInfos applies a batch that covers only some parts", () => {
+			const prev = [
+				{ battery_level: 80, user_description: null, part_key: "0:0" },
+				{ battery_level: 70, user_description: null, part_key: "0:1" },
+			];
+			const result = upsertBatteryInfos(prev, [{ battery_level: 69, user_description: null, part_key: "0:1" }]);
+			expect(result).toEqual([
+				{ battery_level: 80, user_description: null, part_key: "0:0" },
+				{ battery_level: 69, user_description: null, part_key: "0:1" },
+			]);
+			expect(upsertBatteryInfos(prev, [])).toBe(prev);
+		});
+	});
+
+	describe("learnBatteryPartAliases", () => {
+		const base = {
+			id: "kbd",
+			name: "Corne",
+			isDisconnected: false,
+			isCollapsed: false,
+		};
+
+		it("ties descriptions to part keys and moves their labels", () => {
+			const device = {
+				...base,
+				batteryInfos: [
+					{ battery_level: 80, user_description: null, part_key: "0:0" },
+					{ battery_level: 70, user_description: "Left", part_key: "0:1" },
+				],
+				batteryPartLabels: { Left: "Left half" },
+			};
+			const learned = learnBatteryPartAliases(device);
+			expect(learned.batteryPartAliases).toEqual({ Central: "0:0", Left: "0:1" });
+			expect(learned.batteryPartLabels).toEqual({ "0:1": "Left half" });
+			expect(learnBatteryPartAliases(learned)).toBe(learned);
+		});
+
+		it("keeps old descriptions when the firmware renames a part", () => {
+			const device = {
+				...base,
+				batteryInfos: [{ battery_level: 70, user_description: "Left side", part_key: "0:1" }],
+				batteryPartAliases: { Left: "0:1" },
+			};
+			expect(learnBatteryPartAliases(device).batteryPartAliases).toEqual({ Left: "0:1", "Left side": "0:1" });
+		});
+
+		it("skips descriptions shared by several parts", () => {
+			const device = {
+				...base,
+				batteryInfos: [
+					{ battery_level: 80, user_description: null, part_key: "0:0" },
+					{ battery_level: 70, user_description: null, part_key: "0:1" },
+				],
+			};
+			expect(learnBatteryPartAliases(device)).toBe(device);
+		});
+
+		it("returns the device unchanged when infos have no part keys", () => {
+			const device = { ...base, batteryInfos: [{ battery_level: 80, user_description: null }] };
+			expect(learnBatteryPartAliases(device)).toBe(device);
+		});
+	});
+
+	describe("batteryHistorySeriesKey", () => {
+		it("uses the row's part key when present", () => {
+			expect(batteryHistorySeriesKey({ user_description: "Left", part_key: "0:1" }, undefined)).toBe("0:1");
+		});
+
+		it("maps rows without a part key through the aliases", () => {
+			const aliases = { Left: "0:1" };
+			expect(batteryHistorySeriesKey({ user_description: "Left" }, aliases)).toBe("0:1");
+			expect(batteryHistorySeriesKey({ user_description: "Right", part_key: null }, aliases)).toBe("Right");
+			expect(batteryHistorySeriesKey({ user_description: "" }, undefined)).toBe("Central");
+		});
 	});
 
 	describe("mergeBatteryInfos", () => {
@@ -302,5 +389,57 @@ describe("App helpers", () => {
 				},
 			]);
 		});
+
+		it("loads a fingerprint and drops malformed ones", () => {
+			const raw = [
+				{
+					id: "dev-1",
+					name: "Keyboard",
+					batteryInfos: [],
+					fingerprint: { name: "Corne", serial_number: 42, battery_layout: [null, "Peripheral 0", 7] },
+				},
+				{ id: "dev-2", name: "Keyboard", batteryInfos: [], fingerprint: { name: "Corne" } },
+			];
+
+			const [withFingerprint, malformed] = normalizeLoadedDevices(raw);
+			expect(withFingerprint.fingerprint).toEqual({
+				name: "Corne",
+				manufacturer: null,
+				model_number: null,
+				serial_number: null,
+				battery_layout: [null, "Peripheral 0", null],
+			});
+			expect(malformed.fingerprint).toBeUndefined();
+		});
+	});
+
+	describe("relinkRegisteredDevice", () => {
+		const device = {
+			id: "old",
+			name: "Corne",
+			displayName: "Desk",
+			batteryInfos: [{ battery_level: 80, user_description: null }],
+			isDisconnected: true,
+			isCollapsed: false,
+			batteryPartLabels: { Central: "Left" },
+		};
+
+		it("moves the device to the new id and name, keeping its labels", () => {
+			expect(relinkRegisteredDevice(device, { id: "new", name: "Corne v2" }, null)).toEqual({
+				...device,
+				id: "new",
+				name: "Corne v2",
+			});
+		});
+
+		it("takes the moved monitor's snapshot as connected", () => {
+			const relinked = relinkRegisteredDevice(
+				device,
+				{ id: "new", name: "Corne" },
+				[{ battery_level: null, user_description: null }],
+			);
+			expect(relinked.isDisconnected).toBe(false);
+			expect(relinked.batteryInfos).toEqual([{ battery_level: 80, user_description: null }]);
+		});
 	});
 });
@@ -26,6 +26,22 @@ describe("batteryHistory utils", () => {
 		vi.useRealTimers();
 	});
 
+	it("appendBatteryHistory passes the part key through", async () => {
+		mockedInvoke.mockResolvedValue(undefined);
+
+		await appendBatteryHistory("Keyboard", "dev-1", "Left", 77, "2026-02-03T04:05:06.000Z", "discharging", "0:1");
+
+		expect(invoke).toHaveBeenCalledWith("append_battery_history", {
+			deviceName: "Keyboard",
+			bleId: "dev-1",
+			timestamp: "2026-02-03T04:05:06.000Z",
+			userDescription: "Left",
+			batteryLevel: 77,
+			powerState: "discharging",
+			partKey: "0:1",
+		});
+	});
+
 	it("readBatteryHistory invokes command with requested ids", async () => {
 		const mockedHistory = [
 			{
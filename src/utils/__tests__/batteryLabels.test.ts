@@ -1,29 +1,54 @@
 import { describe, expect, it } from "vitest";
 import {
+	batteryPartDescriptionKey,
 	batteryPartLabelStorageKey,
 	defaultBatteryPartDisplayName,
 	getBatteryPartDisplayName,
 } from "../batteryLabels";
 
 describe("batteryLabels", () => {
-	it("uses Central as the storage key for null user_description", () => {
-		expect(batteryPartLabelStorageKey(null)).toBe("Central");
-		expect(batteryPartLabelStorageKey(undefined)).toBe("Central");
+	it("uses Central as the description key for null user_description", () => {
+		expect(batteryPartDescriptionKey(null)).toBe("Central");
+		expect(batteryPartDescriptionKey(undefined)).toBe("Central");
+		expect(batteryPartDescriptionKey("Peripheral")).toBe("Peripheral");
 	});
 
-	it("preserves explicit user_description in the storage key", () => {
-		expect(batteryPartLabelStorageKey("Peripheral")).toBe("Peripheral");
+	it("prefers part_key as the storage key", () => {
+		expect(batteryPartLabelStorageKey({ user_description: null, part_key: "0:1" })).toBe("0:1");
+	});
+
+	it("falls back to the description key for parts without part_key", () => {
+		expect(batteryPartLabelStorageKey({ user_description: null })).toBe("Central");
+		expect(batteryPartLabelStorageKey({ user_description: "Peripheral" })).toBe("Peripheral");
 	});
 
 	it("getBatteryPartDisplayName uses custom label when set", () => {
 		const labels = { Central: "Left half", Peripheral: "Right" };
-		expect(getBatteryPartDisplayName(labels, null)).toBe("Left half");
-		expect(getBatteryPartDisplayName(labels, "Peripheral")).toBe("Right");
+		expect(getBatteryPartDisplayName(labels, { user_description: null })).toBe("Left half");
+		expect(getBatteryPartDisplayName(labels, { user_description: "Peripheral" })).toBe("Right");
+	});
+
+	it("getBatteryPartDisplayName prefers the label saved under part_key", () => {
+		const labels = { "0:1": "Right", Peripheral: "Old right" };
+		expect(
+			getBatteryPartDisplayName(labels, { user_description: "Peripheral", part_key: "0:1" }),
+		).toBe("Right");
+		expect(
+			getBatteryPartDisplayName({ Peripheral: "Old right" }, { user_description: "Peripheral", part_key: "0:1" }),
+		).toBe("Old right");
 	});
 
 	it("getBatteryPartDisplayName falls back to the default name", () => {
-		expect(getBatteryPartDisplayName(undefined, null)).toBe("Central");
-		expect(getBatteryPartDisplayName({}, "Peripheral")).toBe("Peripheral");
+		expect(getBatteryPartDisplayName(undefined, { user_description: null })).toBe("Central");
+		expect(getBatteryPartDisplayName({}, { user_description: "Peripheral", part_key: "0:1" })).toBe("Peripheral");
+	});
+
+	it("defaultBatteryPartDisplayName falls back to the Presentation Format name", () => {
+		const presentation = { namespace: 1, description: 0x010d, description_name: "left" };
+		expect(defaultBatteryPartDisplayName(null, presentation)).toBe("Left");
+		expect(defaultBatteryPartDisplayName("Peripheral 0", presentation)).toBe("Peripheral 0");
+		expect(defaultBatteryPartDisplayName(null, { ...presentation, description_name: null })).toBe("Central");
+		expect(getBatteryPartDisplayName(undefined, { user_description: null, presentation })).toBe("Left");
 	});
 
 	it("defaultBatteryPartDisplayName matches prior UI fallback", () => {
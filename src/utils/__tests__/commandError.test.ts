@@ -0,0 +1,50 @@
+import { describe, expect, it } from "vitest";
+import {
+	commandErrorGuidance,
+	errorMessage,
+	isCommandError,
+	isRetryableError,
+	type CommandError,
+} from "../commandError";
+
+const notConnected: CommandError = {
+	code: "device_not_connected",
+	message: "Device is not connected",
+	retryable: true,
+	details: "link lost",
+};
+
+const permissionDenied: CommandError = {
+	code: "permission_denied",
+	message: "Bluetooth permission denied",
+	retryable: false,
+	details: null,
+};
+
+describe("commandError utils", () => {
+	it("recognizes command errors only", () => {
+		expect(isCommandError(notConnected)).toBe(true);
+		expect(isCommandError(new Error("boom"))).toBe(false);
+		expect(isCommandError("Device not found")).toBe(false);
+		expect(isCommandError(null)).toBe(false);
+	});
+
+	it("formats message and details", () => {
+		expect(errorMessage(notConnected)).toBe("Device is not connected: link lost");
+		expect(errorMessage(permissionDenied)).toBe("Bluetooth permission denied");
+		expect(errorMessage(new Error("boom"))).toBe("boom");
+		expect(errorMessage("plain")).toBe("plain");
+	});
+
+	it("retries unless the backend says otherwise", () => {
+		expect(isRetryableError(notConnected)).toBe(true);
+		expect(isRetryableError(permissionDenied)).toBe(false);
+		expect(isRetryableError(new Error("timeout"))).toBe(true);
+	});
+
+	it("offers guidance for errors the user can fix", () => {
+		expect(commandErrorGuidance(permissionDenied)).toContain("Bluetooth permission");
+		expect(commandErrorGuidance({ ...notConnected, code: "busy" })).toBeNull();
+		expect(commandErrorGuidance(new Error("boom"))).toBeNull();
+	});
+});
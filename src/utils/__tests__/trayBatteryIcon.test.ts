@@ -79,6 +79,20 @@ describe("trayBatteryPayloadFromPrimaryDevice", () => {
 		expect(payload.peripheralLabel).toBe("P");
 	});
 
+	it("falls back to the Presentation Format name without a description", () => {
+		const presentation = (description_name: string) => ({ namespace: 1, description: 0, description_name });
+		const payload = trayBatteryPayloadFromPrimaryDevice([
+			device({
+				batteryInfos: [
+					{ ...info(90, null), presentation: presentation("left") },
+					{ ...info(72, null), presentation: presentation("right") },
+				],
+			}),
+		]);
+		expect(payload.centralLabel).toBe("L");
+		expect(payload.peripheralLabel).toBe("R");
+	});
+
 	it("uses first-char labels for non-special descriptions", () => {
 		const payload = trayBatteryPayloadFromPrimaryDevice([
 			device({ batteryInfos: [info(90, "left"), info(72, "right")] }),
@@ -1,4 +1,5 @@
-import type { BatteryInfo } from "./ble";
+import type { BatteryInfo, DeviceFingerprint } from "./ble";
+import { batteryPartDescriptionKey, batteryPartLabelStorageKey } from "./batteryLabels";
 
 export type RegisteredDevice = {
 	id: string;
@@ -9,8 +10,16 @@ export type RegisteredDevice = {
 	batteryInfos: BatteryInfo[];
 	isDisconnected: boolean;
 	isCollapsed: boolean;
-	/** Custom display names per part; keys match battery history user_description (null → "Central"). */
+	/** Custom display names per part, keyed by {@link batteryPartLabelStorageKey}. */
 	batteryPartLabels?: Record<string, string>;
+	/**
+	 * Description key → part key for every description a part has reported, so
+	 * history rows recorded before parts had keys, or under a description the
+	 * firmware has since renamed, continue the part's series.
+	 */
+	batteryPartAliases?: Record<string, string>;
+	/** Taken when the device was last seen connected; recognizes it after re-pairing. */
+	fingerprint?: DeviceFingerprint;
 };
 
 function normalizeBatteryPartLabels(raw: unknown): Record<string, string> | undefined {
@@ -26,6 +35,31 @@ function normalizeBatteryPartLabels(raw: unknown): Record<string, string> | unde
 	return Object.keys(out).length > 0 ? out : undefined;
 }
 
+function normalizeBatteryPartAliases(raw: unknown): Record<string, string> | undefined {
+	if (typeof raw !== "object" || raw === null || Array.isArray(raw)) return undefined;
+	const out: Record<string, string> = {};
+	for (const [k, v] of Object.entries(raw as Record<string, unknown>)) {
+		if (typeof v === "string" && v !== "") {
+			out[k] = v;
+		}
+	}
+	return Object.keys(out).length > 0 ? out : undefined;
+}
+
+function normalizeFingerprint(raw: unknown): DeviceFingerprint | undefined {
+	if (typeof raw !== "object" || raw === null || Array.isArray(raw)) return undefined;
+	const o = raw as Record<string, unknown>;
+	if (typeof o.name !== "string" || !Array.isArray(o.battery_layout)) return undefined;
+	const stringOrNull = (v: unknown) => (typeof v === "string" ? v : null);
+	return {
+		name: o.name,
+		manufacturer: stringOrNull(o.manufacturer),
+		model_number: stringOrNull(o.model_number),
+		serial_number: stringOrNull(o.serial_number),
+		battery_layout: o.battery_layout.map(stringOrNull),
+	};
+}
+
 function normalizeDeviceDisplayName(raw: unknown): string | undefined {
 	if (typeof raw !== "string") return undefined;
 	const t = raw.trim();
@@ -51,8 +85,8 @@ export function mapIsHighBattery(batteryInfos: BatteryInfo[], threshold: number)
 }
 
 export function upsertBatteryInfo(batteryInfos: BatteryInfo[], nextInfo: BatteryInfo): BatteryInfo[] {
-	const key = nextInfo.user_description ?? null;
-	const idx = batteryInfos.findIndex((info) => (info.user_description ?? null) === key);
+	const key = batteryPartLabelStorageKey(nextInfo);
+	const idx = batteryInfos.findIndex((info) => batteryPartLabelStorageKey(info) === key);
 	if (idx === -1) {
 		return [...batteryInfos, nextInfo];
 	}
@@ -65,17 +99,90 @@ export function upsertBatteryInfo(batteryInfos: BatteryInfo[], nextInfo: Battery
 	return next;
 }
 
+/** Apply the parts of one battery info event, which need not cover them all. */
+export function upsertBatteryInfos(batteryInfos: BatteryInfo[], nextInfos: BatteryInfo[]): BatteryInfo[] {
+	return nextInfos.reduce(upsertBatteryInfo, batteryInfos);
+}
+
 export function mergeBatteryInfos(prev: BatteryInfo[], next: BatteryInfo[]): BatteryInfo[] {
 	return next.map((info) => {
 		if (info.battery_level !== null) {
 			return info;
 		}
-		const key = info.user_description ?? null;
-		const existing = prev.find((p) => (p.user_description ?? null) === key);
+		const key = batteryPartLabelStorageKey(info);
+		const existing = prev.find((p) => batteryPartLabelStorageKey(p) === key);
 		return existing ? { ...info, battery_level: existing.battery_level } : info;
 	});
 }
 
+/**
+ * Record the part key behind each description in `device.batteryInfos`, and
+ * move labels saved under a description to its part key. Descriptions shared
+ * by several parts (e.g. two without one) are left out, as they cannot say
+ * which part a row belongs to. Returns `device` itself when nothing is new.
+ */
+export function learnBatteryPartAliases(device: RegisteredDevice): RegisteredDevice {
+	const keyed = device.batteryInfos.filter((info) => info.part_key !== undefined);
+	if (keyed.length === 0) return device;
+	const descriptionCounts = new Map<string, number>();
+	for (const info of device.batteryInfos) {
+		const descKey = batteryPartDescriptionKey(info.user_description);
+		descriptionCounts.set(descKey, (descriptionCounts.get(descKey) ?? 0) + 1);
+	}
+
+	let aliases = device.batteryPartAliases;
+	let labels = device.batteryPartLabels;
+	for (const info of keyed) {
+		const partKey = info.part_key as string;
+		const descKey = batteryPartDescriptionKey(info.user_description);
+		if (descriptionCounts.get(descKey) !== 1 || aliases?.[descKey] === partKey) continue;
+		aliases = { ...aliases, [descKey]: partKey };
+		const legacyLabel = labels?.[descKey];
+		if (legacyLabel !== undefined && labels?.[partKey] === undefined) {
+			const { [descKey]: _moved, ...rest } = labels ?? {};
+			labels = { ...rest, [partKey]: legacyLabel };
+		}
+	}
+	if (aliases === device.batteryPartAliases) return device;
+	return labels === undefined
+		? { ...device, batteryPartAliases: aliases }
+		: { ...device, batteryPartAliases: aliases, batteryPartLabels: labels };
+}
+
+/**
+ * Series key of a battery history row: its part key, or for rows without one
+ * the part its description was last seen on.
+ */
+export function batteryHistorySeriesKey(
+	record: { user_description: string; part_key?: string | null },
+	aliases: Record<string, string> | undefined,
+): string {
+	if (record.part_key) return record.part_key;
+	const descKey = batteryPartDescriptionKey(record.user_description || null);
+	return aliases?.[descKey] ?? descKey;
+}
+
+/**
+ * Point a registered device at the id (and name) it came back under after
+ * re-pairing. Labels and display name stay; `batteryInfos` is the moved
+ * monitor's snapshot, if any.
+ */
+export function relinkRegisteredDevice(
+	device: RegisteredDevice,
+	next: { id: string; name: string },
+	batteryInfos: BatteryInfo[] | null,
+): RegisteredDevice {
+	const relinked = { ...device, id: next.id, name: next.name };
+	if (batteryInfos === null || batteryInfos.length === 0) {
+		return relinked;
+	}
+	return {
+		...relinked,
+		batteryInfos: mergeBatteryInfos(device.batteryInfos, batteryInfos),
+		isDisconnected: false,
+	};
+}
+
 export function normalizeLoadedDevices(raw: unknown): RegisteredDevice[] {
 	const devices = Array.isArray(raw) ? raw : [];
 	return devices.map((device): RegisteredDevice => {
@@ -85,9 +192,11 @@ export function normalizeLoadedDevices(raw: unknown): RegisteredDevice[] {
 				const rawUserDesc = info.user_description ?? (info as { user_descriptor?: unknown }).user_descriptor;
 				const userDesc = typeof rawUserDesc === "string" || rawUserDesc === null ? rawUserDesc : null;
 				const level = info.battery_level;
+				const partKey = typeof info.part_key === "string" && info.part_key !== "" ? info.part_key : undefined;
 				return {
 					battery_level: typeof level === "number" ? level : null,
 					user_description: userDesc ?? null,
+					...(partKey !== undefined ? { part_key: partKey } : {}),
 				};
 			})
 			: [];
@@ -105,6 +214,8 @@ export function normalizeLoadedDevices(raw: unknown): RegisteredDevice[] {
 			isDisconnected: d.isDisconnected === true,
 			isCollapsed: d.isCollapsed === true,
 			batteryPartLabels: normalizeBatteryPartLabels(d.batteryPartLabels),
+			batteryPartAliases: normalizeBatteryPartAliases(d.batteryPartAliases),
+			fingerprint: normalizeFingerprint(d.fingerprint),
 		};
 		return displayName !== undefined ? { ...base, displayName } : base;
 	});
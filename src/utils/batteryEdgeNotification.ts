@@ -47,8 +47,9 @@ export function notifyBatteryEdgeTransitions({
 		for (let i = 0; i < curr.length && i < prev.length; i++) {
 			if (prev[i] || !curr[i]) continue;
 			const part = newBatteryInfos[i];
-			const partDisplayName = getBatteryPartDisplayName(batteryPartLabels, part.user_description);
-			const hasCustomPartLabel = partDisplayName !== defaultBatteryPartDisplayName(part.user_description);
+			const partDisplayName = getBatteryPartDisplayName(batteryPartLabels, part);
+			const hasCustomPartLabel =
+				partDisplayName !== defaultBatteryPartDisplayName(part.user_description, part.presentation);
 			const suffix = newBatteryInfos.length >= 2 || hasCustomPartLabel
 				? ' ' + partDisplayName
 				: '';
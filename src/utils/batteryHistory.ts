@@ -1,12 +1,15 @@
 import { invoke } from "@tauri-apps/api/core";
 import { emit } from "@tauri-apps/api/event";
 import { fireAndForget } from "@/utils/common";
-import type { BatteryInfo } from "@/utils/ble";
+import type { BatteryInfo, PowerState } from "@/utils/ble";
 
 export type BatteryHistoryRecord = {
 	timestamp: string;
 	user_description: string;
 	battery_level: number;
+	power_state?: PowerState;
+	/** Null on rows recorded before parts had keys. */
+	part_key?: string | null;
 };
 
 export async function appendBatteryHistory(
@@ -15,6 +18,8 @@ export async function appendBatteryHistory(
 	userDescription: string,
 	batteryLevel: number,
 	timestamp: string = new Date().toISOString(),
+	powerState?: PowerState,
+	partKey?: string,
 ): Promise<void> {
 	await invoke("append_battery_history", {
 		deviceName,
@@ -22,6 +27,8 @@ export async function appendBatteryHistory(
 		timestamp,
 		userDescription,
 		batteryLevel,
+		powerState,
+		partKey,
 	});
 }
 
@@ -36,6 +43,8 @@ export function recordBatteryReadings(
 			timestamp: new Date().toISOString(),
 			user_description: info.user_description ?? 'Central',
 			battery_level: info.battery_level as number,
+			power_state: info.power_state,
+			part_key: info.part_key ?? null,
 		}));
 	if (records.length === 0) return;
 	fireAndForget((async () => {
@@ -46,6 +55,8 @@ export function recordBatteryReadings(
 				record.user_description,
 				record.battery_level,
 				record.timestamp,
+				record.power_state,
+				record.part_key ?? undefined,
 			);
 		}
 		await emit('battery-history-updated', { deviceId: device.id, records });
@@ -1,23 +1,55 @@
+import type { BatteryInfo } from "@/utils/ble";
+
+/** What identifies a battery part: its stable key, and how it describes itself. */
+export type BatteryPart = Pick<BatteryInfo, "user_description" | "part_key" | "presentation">;
+
 /**
- * Keys in {@link batteryPartLabels} match the canonical part id used in battery history
- * (see appendBatteryHistory: null → "Central") so storage stays aligned with CSV user_description.
+ * Key a part had before parts carried a `part_key`; battery history rows of that
+ * time name the part by it (see appendBatteryHistory: null → "Central").
  */
-export function batteryPartLabelStorageKey(userDescription: string | null | undefined): string {
+export function batteryPartDescriptionKey(userDescription: string | null | undefined): string {
 	return userDescription ?? "Central";
 }
 
-export function defaultBatteryPartDisplayName(userDescription: string | null | undefined): string {
-	return userDescription ?? "Central";
+/**
+ * Keys in {@link batteryPartLabels} and battery history series: the part's stable
+ * `part_key`, or its description key for readings that have none.
+ */
+export function batteryPartLabelStorageKey(part: BatteryPart): string {
+	return part.part_key ?? batteryPartDescriptionKey(part.user_description);
+}
+
+/**
+ * The part's user description; failing that, its Presentation Format name
+ * ("left" → "Left"); failing that, "Central".
+ */
+export function defaultBatteryPartDisplayName(
+	userDescription: string | null | undefined,
+	presentation?: BatteryInfo["presentation"],
+): string {
+	if (userDescription != null) return userDescription;
+	const name = presentation?.description_name;
+	if (name) return name.charAt(0).toUpperCase() + name.slice(1);
+	return "Central";
+}
+
+/** The user's label for `part`, including one saved under its description before it had a key. */
+export function getBatteryPartLabel(
+	batteryPartLabels: Record<string, string> | undefined | null,
+	part: BatteryPart,
+): string | undefined {
+	const custom =
+		batteryPartLabels?.[batteryPartLabelStorageKey(part)] ??
+		batteryPartLabels?.[batteryPartDescriptionKey(part.user_description)];
+	return custom != null && custom.trim() !== "" ? custom.trim() : undefined;
 }
 
 export function getBatteryPartDisplayName(
 	batteryPartLabels: Record<string, string> | undefined | null,
-	userDescription: string | null | undefined,
+	part: BatteryPart,
 ): string {
-	const key = batteryPartLabelStorageKey(userDescription);
-	const custom = batteryPartLabels?.[key];
-	if (custom != null && custom.trim() !== "") {
-		return custom.trim();
-	}
-	return defaultBatteryPartDisplayName(userDescription);
+	return (
+		getBatteryPartLabel(batteryPartLabels, part) ??
+		defaultBatteryPartDisplayName(part.user_description, part.presentation)
+	);
 }
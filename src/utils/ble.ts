@@ -1,35 +1,337 @@
 import { invoke } from "@tauri-apps/api/core";
+import type { CommandError } from "@/utils/commandError";
+
+export type DeviceKind =
+	| "keyboard"
+	| "mouse"
+	| "joystick"
+	| "gamepad"
+	| "digitizer_tablet"
+	| "touchpad"
+	| "presentation_remote"
+	| "other_hid"
+	| "other";
+
+/**
+ * Device Information Service strings and GAP Appearance.
+ * Fields the device does not publish are null.
+ */
+export type DeviceMetadata = {
+	manufacturer: string | null;
+	model_number: string | null;
+	serial_number: string | null;
+	hardware_revision: string | null;
+	firmware_revision: string | null;
+	software_revision: string | null;
+	appearance: number | null;
+	device_kind: DeviceKind | null;
+};
 
 /**
  * @typedef {Object} BleDeviceInfo
  * @property {string} name Device name
  * @property {string} id Device ID
+ * @property {DeviceMetadata|null} metadata Cached metadata, if already read
  */
 /** @export */
 export type BleDeviceInfo = {
 	name: string;
 	id: string;
+	metadata?: DeviceMetadata | null;
+};
+
+/**
+ * What a device says about itself, kept with its registration so it can be
+ * recognized after re-pairing gives it a new id. `battery_layout` lists the
+ * user descriptions of its battery parts in order.
+ */
+export type DeviceFingerprint = {
+	name: string;
+	manufacturer: string | null;
+	model_number: string | null;
+	serial_number: string | null;
+	battery_layout: (string | null)[];
+};
+
+/**
+ * "serial_number" matches are near certain; "name_and_layout" ones could be
+ * two identical boards and should be confirmed by the user.
+ */
+export type IdentityConfidence = "name_and_layout" | "serial_number";
+
+/** A connected, unregistered device that looks like a registered one. */
+export type IdentityMatch = {
+	device_id: string;
+	registered_id: string;
+	confidence: IdentityConfidence;
+};
+
+/** A raw GATT value with its decoded form when the UUID is known. */
+export type GattValue = {
+	hex: string;
+	decoded: string | null;
+};
+
+/** Characteristic Presentation Format (0x2904) descriptor fields. */
+export type PresentationFormat = {
+	format: number;
+	exponent: number;
+	unit: number;
+	namespace: number;
+	description: number;
+};
+
+export type GattDescriptorInfo = {
+	uuid: string;
+	name: string | null;
+	value: GattValue | null;
+	error: string | null;
+};
+
+export type GattCharacteristicInfo = {
+	uuid: string;
+	name: string | null;
+	properties: string[];
+	value: GattValue | null;
+	error: string | null;
+	user_description: string | null;
+	presentation_format: PresentationFormat | null;
+	descriptors: GattDescriptorInfo[];
+};
+
+export type GattServiceInfo = {
+	uuid: string;
+	name: string | null;
+	characteristics: GattCharacteristicInfo[];
+	error: string | null;
+};
+
+/** Full GATT tree of a device, for attaching to bug reports. */
+export type GattInspection = {
+	app_version: string;
+	device_id: string;
+	name: string | null;
+	services: GattServiceInfo[];
 };
 
+/**
+ * Charging state from Battery Level Status / Battery Power State.
+ * "external_power" means plugged in but not charging (usually full).
+ */
+export type PowerState = "charging" | "discharging" | "external_power" | "unknown";
+
 /**
  * @typedef {Object} BatteryInfo
  * @property {number|null} battery_level Battery level (0-100)
  * @property {string|null} user_description User description
+ * @property {PowerState} power_state Charging state ("unknown" when not published)
+ * @property {string} part_key Stable part identity ("<service>:<level index>"), independent of the description
+ * @property {PartPresentation|null} presentation Presentation Format namespace/description, if published
+ * @property {number|null} raw_battery_level Level as the device sent it, before filtering
+ * @property {ReadingFlag|null} reading_flag Why the reading was rejected or looks suspicious
  */
 /** @export */
 export type BatteryInfo = {
 	battery_level: number | null;
 	user_description: string | null;
+	power_state?: PowerState;
+	/** Missing on infos persisted before parts had keys. */
+	part_key?: string;
+	presentation?: PartPresentation | null;
+	raw_battery_level?: number | null;
+	reading_flag?: ReadingFlag | null;
+};
+
+/**
+ * "empty" and "out_of_range" readings were rejected and "held" ones wait for
+ * the next reading to confirm them; all three have a null battery_level.
+ * "suspicious_jump" readings were accepted.
+ */
+export type ReadingFlag = "empty" | "out_of_range" | "suspicious_jump" | "held";
+
+/**
+ * What a part's Presentation Format descriptor says about it. `description_name`
+ * is the Bluetooth SIG name of the description ("left", "second", ...), if any.
+ */
+export type PartPresentation = {
+	namespace: number;
+	description: number;
+	description_name: string | null;
 };
 
+/**
+ * Where a device's battery level is read from. "bluez" reads
+ * org.bluez.Battery1 over D-Bus and is only available on Linux.
+ */
+export type BatterySource = "gatt" | "bluez" | "power_supply";
+
+/**
+ * Notification monitor timing, reading filter and event coalescing. Parts
+ * that cannot notify are read every `poll_interval_secs`; notifying parts are
+ * re-read after `verify_after_secs` without a notification (0 disables).
+ * After `stale_after_secs` without hearing from the device at all (0
+ * disables), a probe read decides whether the session is restarted.
+ */
+export type MonitorOptions = {
+	poll_interval_secs: number;
+	verify_after_secs: number;
+	stale_after_secs?: number;
+	reading_filter?: ReadingFilter;
+	coalesce?: CoalesceOptions;
+};
+
+/**
+ * Battery info events of a device wait up to `batch_window_ms` (at most
+ * 1000) for its other parts, and come at most once per `min_interval_ms`
+ * (0 disables, at most 60000).
+ */
+export type CoalesceOptions = {
+	batch_window_ms: number;
+	min_interval_ms: number;
+};
+
+/**
+ * Levels that move more than `jump_threshold` points (0 disables) are
+ * flagged, and with `debounce` held back until the next reading confirms
+ * them. The reported level is the median of the last `median_window` (1 to 9)
+ * readings.
+ */
+export type ReadingFilter = {
+	jump_threshold: number;
+	debounce: boolean;
+	median_window: number;
+};
+
+/**
+ * Limits of BLE operations for all devices, each 100 to 120000 ms. An
+ * operation that runs longer fails with the "timeout" error code.
+ */
+export type OperationTimeouts = {
+	connect_ms: number;
+	discover_ms: number;
+	read_ms: number;
+	descriptor_read_ms: number;
+	subscribe_ms: number;
+};
+
+/** Who a queued GATT operation is for, highest priority first. */
+export type GattPriority = "user_refresh" | "notification_setup" | "background_poll";
+
+export type GattWaitStats = {
+	operations: number;
+	total_wait_ms: number;
+	max_wait_ms: number;
+};
+
+/**
+ * GATT operation queue of one adapter: how many operations wait right now,
+ * and how long those that got their turn waited, by priority.
+ */
+export type AdapterQueueMetrics = {
+	adapter_id: string;
+	queued: number;
+	waits: Partial<Record<GattPriority, GattWaitStats>>;
+};
+
+/**
+ * How a monitor retries while its device is away: the delay starts at
+ * `initial_delay_ms`, grows by `factor` per attempt up to `max_delay_ms`,
+ * varies by `jitter` (0 to 1), and retrying stops after
+ * `give_up_after_secs` (0 never gives up).
+ */
+export type ReconnectPolicy = {
+	initial_delay_ms: number;
+	factor: number;
+	jitter: number;
+	max_delay_ms: number;
+	give_up_after_secs: number;
+};
+
+/** A battery the kernel exposes under the power_supply class (Linux). */
+export type HidBattery = {
+	address: string;
+	model_name: string | null;
+	battery_level: number | null;
+	power_state: PowerState;
+};
+
+/** A Bluetooth adapter; `selected` is the one used for listing and new monitors. */
+export type BluetoothAdapterInfo = {
+	id: string;
+	name: string;
+	selected: boolean;
+};
+
+export type AdapterState = "available" | "powered_off" | "removed";
+
+/**
+ * Delivered as `bluetooth-adapter-status` events when an adapter in use is
+ * switched off, unplugged or comes back. Monitors on it pause meanwhile.
+ */
+export type BluetoothAdapterStatusEvent = {
+	adapter_id: string;
+	state: AdapterState;
+};
+
+/**
+ * Parts of one device that changed together. The monitor drops unchanged
+ * parts and limits how often a device emits, so not every part is included.
+ */
 export type BatteryInfoNotificationEvent = {
 	id: string;
-	battery_info: BatteryInfo;
+	battery_infos: BatteryInfo[];
 };
 
+/**
+ * `reconnect_attempt` counts failed reconnection attempts since the device
+ * was last connected; `next_retry_at` (ms since the epoch) is null while
+ * connected or once the monitor gave up.
+ */
 export type BatteryMonitorStatusEvent = {
 	id: string;
 	connected: boolean;
+	reconnect_attempt: number;
+	next_retry_at: number | null;
+};
+
+/**
+ * "stale_stream_restarted": nothing arrived for the watchdog window and the
+ * probe read failed, so the monitor restarted its session.
+ */
+export type DiagnosticKind = "stale_stream_restarted";
+
+/**
+ * Delivered as `battery-monitor-diagnostic` events when a monitor recovers
+ * from a problem on its own. `silent_secs` is how long the device had been
+ * quiet; `detail` is for logs and bug reports.
+ */
+export type BatteryMonitorDiagnosticEvent = {
+	id: string;
+	kind: DiagnosticKind;
+	silent_secs: number;
+	detail: string;
+};
+
+/**
+ * A device seen advertising during an active scan.
+ * Also delivered incrementally as `battery-device-scan-result` events.
+ */
+export type ScannedDeviceInfo = {
+	id: string;
+	name: string | null;
+	rssi: number | null;
+	services: string[];
+	has_battery_service: boolean;
+};
+
+/**
+ * One device of a getBatteryInfoMany call: `battery_infos` on success,
+ * otherwise the `error` its own getBatteryInfo call would have rejected with.
+ */
+export type BatteryReadResult = {
+	id: string;
+	battery_infos: BatteryInfo[] | null;
+	error: CommandError | null;
 };
 
 /**
@@ -49,6 +351,140 @@ export async function getBatteryInfo(id: string): Promise<BatteryInfo[]> {
 	return await invoke("get_battery_info", { id });
 }
 
+/**
+ * Get battery info for several devices in one call. Each id is read once,
+ * results come in request order, and a device already being read shares
+ * that read.
+ */
+export async function getBatteryInfoMany(ids: string[]): Promise<BatteryReadResult[]> {
+	return await invoke("get_battery_info_many", { ids });
+}
+
+/**
+ * Get Device Information / Appearance metadata for a connected device.
+ * Cached per device; pass `refresh` to read it again.
+ */
+export async function getDeviceMetadata(
+	id: string,
+	refresh?: boolean
+): Promise<DeviceMetadata> {
+	return await invoke("get_device_metadata", { id, refresh });
+}
+
+/**
+ * Read the fingerprint of a connected device.
+ */
+export async function getDeviceFingerprint(id: string): Promise<DeviceFingerprint> {
+	return await invoke("get_device_fingerprint", { id });
+}
+
+/**
+ * Find connected devices that are not registered but match the fingerprint
+ * of a registered one.
+ */
+export async function findDeviceIdentityMatches(
+	registered: { id: string; fingerprint: DeviceFingerprint }[]
+): Promise<IdentityMatch[]> {
+	return await invoke("find_device_identity_matches", { registered });
+}
+
+/**
+ * Move a registered device's settings, history and running monitor to the
+ * new id it came back under. Returns the moved monitor's initial snapshot,
+ * or null when no monitor was running.
+ */
+export async function linkDeviceIdentity(
+	from: { id: string; name: string },
+	to: { id: string; name: string }
+): Promise<BatteryInfo[] | null> {
+	return await invoke("link_device_identity", {
+		fromId: from.id,
+		fromName: from.name,
+		toId: to.id,
+		toName: to.name,
+	});
+}
+
+/**
+ * Dump every service, characteristic and descriptor of a connected device,
+ * reading whatever is readable.
+ */
+export async function inspectGattDevice(id: string): Promise<GattInspection> {
+	return await invoke("inspect_gatt_device", { id });
+}
+
+/**
+ * Select the battery source for a device. Applies to the next
+ * getBatteryInfo call or monitor start.
+ */
+export async function setBatterySource(
+	id: string,
+	source: BatterySource
+): Promise<void> {
+	await invoke("set_battery_source", { id, source });
+}
+
+/**
+ * Set the notification monitor timing, reading filter and event coalescing
+ * for a device. Applies to the next monitor start.
+ */
+export async function setMonitorOptions(
+	id: string,
+	options: MonitorOptions
+): Promise<void> {
+	await invoke("set_monitor_options", { id, options });
+}
+
+/**
+ * Set the reconnection policy for a device. Applies to the next monitor
+ * start.
+ */
+export async function setReconnectPolicy(
+	id: string,
+	policy: ReconnectPolicy
+): Promise<void> {
+	await invoke("set_reconnect_policy", { id, policy });
+}
+
+/**
+ * Set the BLE operation timeouts. Applies to operations started afterwards.
+ */
+export async function setOperationTimeouts(
+	timeouts: OperationTimeouts
+): Promise<void> {
+	await invoke("set_operation_timeouts", { timeouts });
+}
+
+/**
+ * Queue wait times of the GATT operation scheduler, per adapter used so far.
+ */
+export async function getGattSchedulerMetrics(): Promise<AdapterQueueMetrics[]> {
+	return await invoke("get_gatt_scheduler_metrics");
+}
+
+/**
+ * List the Bluetooth adapters the app can use, the default one first.
+ */
+export async function listBluetoothAdapters(): Promise<BluetoothAdapterInfo[]> {
+	return await invoke("list_bluetooth_adapters");
+}
+
+/**
+ * Use an adapter (null for the system default) for listing, scans and
+ * monitors started afterwards. Running monitors keep their adapter.
+ */
+export async function selectBluetoothAdapter(id: string | null): Promise<void> {
+	await invoke("select_bluetooth_adapter", { id });
+}
+
+/**
+ * List HID batteries reported by the kernel, keyed by device address.
+ * Empty on platforms without the power_supply class.
+ */
+export async function listHidBatteries(): Promise<HidBattery[]> {
+	return await invoke("list_hid_batteries");
+}
+
 /**
  * Start notification-based monitoring for a specified device.
  * Returns the latest battery info snapshot available at monitor start.
@@ -72,3 +508,39 @@ export async function stopBatteryNotificationMonitor(id: string): Promise<void>
 export async function stopAllBatteryMonitors(): Promise<void> {
 	await invoke("stop_all_battery_monitors");
 }
+
+/**
+ * Let the backend poll exactly these devices. `intervalMs` is the shortest
+ * interval; it stretches while levels hold and backs off on errors. Results
+ * arrive as "battery-info-notification" and "battery-monitor-status" events.
+ */
+export async function setBatteryPolling(
+	ids: string[],
+	intervalMs: number
+): Promise<void> {
+	await invoke("set_battery_polling", { ids, intervalMs });
+}
+
+/**
+ * Stop every backend poller.
+ */
+export async function stopAllBatteryPolling(): Promise<void> {
+	await invoke("stop_all_battery_polling");
+}
+
+/**
+ * Actively scan for advertising devices, including keyboards connected to
+ * another host. Runs for `timeoutSecs` (default 10, max 60) unless stopped.
+ */
+export async function scanBatteryDevices(
+	timeoutSecs?: number
+): Promise<ScannedDeviceInfo[]> {
+	return await invoke("scan_battery_devices", { timeoutSecs });
+}
+
+/**
+ * Stop the running scan early. Its results are still returned.
+ */
+export async function stopBatteryDeviceScan(): Promise<void> {
+	await invoke("stop_battery_device_scan");
+}
@@ -0,0 +1,63 @@
+export type CommandErrorCode =
+	| "adapter_not_found"
+	| "adapter_unavailable"
+	| "permission_denied"
+	| "device_not_found"
+	| "device_not_connected"
+	| "characteristic_not_found"
+	| "timeout"
+	| "busy"
+	| "unsupported"
+	| "invalid_argument"
+	| "io"
+	| "malformed_history";
+
+/**
+ * Rejection value of the BLE and battery history commands.
+ * `retryable` is true when the same call may succeed later without user action.
+ */
+export type CommandError = {
+	code: CommandErrorCode;
+	message: string;
+	retryable: boolean;
+	details: string | null;
+};
+
+export function isCommandError(error: unknown): error is CommandError {
+	return typeof error === "object"
+		&& error !== null
+		&& typeof (error as CommandError).code === "string"
+		&& typeof (error as CommandError).message === "string";
+}
+
+/** Readable text for a caught value: an Error, a CommandError or anything else. */
+export function errorMessage(error: unknown): string {
+	if (error instanceof Error) return error.message;
+	if (isCommandError(error)) {
+		return error.details ? `${error.message}: ${error.details}` : error.message;
+	}
+	return String(error);
+}
+
+/** Errors other than a CommandError (e.g. client-side timeouts) count as retryable. */
+export function isRetryableError(error: unknown): boolean {
+	return isCommandError(error) ? error.retryable : true;
+}
+
+/** What the user can do about an error, if there is something specific. */
+export function commandErrorGuidance(error: unknown): string | null {
+	if (!isCommandError(error)) return null;
+	switch (error.code) {
+		case "adapter_not_found":
+			return "Make sure a Bluetooth adapter is connected.";
+		case "adapter_unavailable":
+			return "Turn Bluetooth on and try again.";
+		case "permission_denied":
+			return "Please make sure Bluetooth permission is granted.";
+		case "device_not_found":
+		case "device_not_connected":
+			return "Make sure the device is on and connected to this computer.";
+		default:
+			return null;
+	}
+}
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { errorMessage } from "./commandError";
 import { logger } from "./log";
 
 export async function sleep(ms: number) {
@@ -6,8 +7,7 @@ export async function sleep(ms: number) {
 }
 
 export function logAsyncWarning(context: string, error: unknown) {
-	const message = error instanceof Error ? error.message : String(error);
-	logger.warn(`${context}: ${message}`);
+	logger.warn(`${context}: ${errorMessage(error)}`);
 }
 
 export function fireAndForget(promise: Promise<unknown>, context: string) {
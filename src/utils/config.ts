@@ -43,6 +43,8 @@ export type Config = {
 	chartSmoothingWindowSize: number;
 	chartCustomRange: { start: string; end: string } | null;
 	trayIconComponents: TrayIconComponent[];
+	/** Adapter used for listing and monitoring; null for the system default. */
+	bluetoothAdapterId: string | null;
 }
 
 export const defaultConfig: Config = {
@@ -73,6 +75,7 @@ export const defaultConfig: Config = {
 		TrayIconComponent.BatteryIcon,
 		TrayIconComponent.BatteryPercent,
 	],
+	bluetoothAdapterId: null,
 };
 
 let configStoreInstance: Store | null = null;
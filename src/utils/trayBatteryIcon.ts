@@ -1,7 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
 import type { RegisteredDevice } from "@/utils/appHelpers";
 import type { BatteryInfo } from "@/utils/ble";
-import { batteryPartLabelStorageKey } from "@/utils/batteryLabels";
+import { getBatteryPartLabel } from "@/utils/batteryLabels";
 import { defaultConfig, type TrayIconComponent } from "@/utils/config";
 
 export type TrayBatteryIconPayload = {
@@ -18,10 +18,11 @@ export type TrayBatteryIconPayload = {
 // Labels sent to src-tauri/src/tray_native_macos.rs are pre-derived single-character glyphs.
 // TypeScript owns semantic rules; Rust's one_char only defends against malformed payloads.
 function labelForInfo(info: BatteryInfo | undefined, fallback: "Central" | "Peripheral"): string {
-	if (!info?.user_description) {
+	const description = info?.user_description || info?.presentation?.description_name;
+	if (!description) {
 		return fallback === "Central" ? "C" : "P";
 	}
-	const raw = info.user_description.trim();
+	const raw = description.trim();
 	if (raw.length === 1) return raw.toUpperCase();
 	const lower = raw.toLowerCase();
 	if (lower === "central") return "C";
@@ -64,14 +65,14 @@ export function trayBatteryPayloadFromPrimaryDevice(devices: RegisteredDevice[])
 			rowCount: 1,
 			centralPercent: null,
 			peripheralPercent: null,
-			centralLabel: trayGlyphFromCustomOrInfo(undefined, "Central", d.batteryPartLabels?.[batteryPartLabelStorageKey(null)]),
+			centralLabel: trayGlyphFromCustomOrInfo(undefined, "Central", getBatteryPartLabel(d.batteryPartLabels, { user_description: null })),
 			peripheralLabel: null,
 			disconnected: d.isDisconnected,
 		};
 	}
 	if (infos.length === 1) {
 		const b = infos[0];
-		const custom = d.batteryPartLabels?.[batteryPartLabelStorageKey(b.user_description)];
+		const custom = getBatteryPartLabel(d.batteryPartLabels, b);
 		return {
 			enabled: true,
 			components: defaultConfig.trayIconComponents,
@@ -94,12 +95,12 @@ export function trayBatteryPayloadFromPrimaryDevice(devices: RegisteredDevice[])
 		centralLabel: trayGlyphFromCustomOrInfo(
 			first,
 			"Central",
-			d.batteryPartLabels?.[batteryPartLabelStorageKey(first.user_description)],
+			getBatteryPartLabel(d.batteryPartLabels, first),
 		),
 		peripheralLabel: trayGlyphFromCustomOrInfo(
 			second,
 			"Peripheral",
-			d.batteryPartLabels?.[batteryPartLabelStorageKey(second.user_description)],
+			getBatteryPartLabel(d.batteryPartLabels, second),
 		),
 		disconnected: d.isDisconnected,
 	};